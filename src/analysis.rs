@@ -0,0 +1,784 @@
+//! Spec analyses that report diagnostics without stopping generation
+
+use crate::blox::actor::{Actor, HEALTH_RECEIVER};
+use crate::blox::message_handlers::{MessageHandle, MessageReceiver};
+use crate::create::RenderCtx;
+use crate::diagnostics::Diagnostic;
+use crate::link::type_names;
+
+/// Types that implement `Clone` and `Debug` whenever their parameters do
+const BUILTIN_TYPES: &[&str] = &[
+    "u8", "u16", "u128", "usize", "i8", "i16", "i128", "isize", "f32", "f64", "char", "str",
+    "HashMap", "HashSet", "BTreeMap", "BTreeSet", "VecDeque", "Duration", "Instant",
+];
+
+/// Framework payload types known to implement `Clone` and `Debug`
+const FRAMEWORK_PAYLOADS: &[&str] = &["StandardPayload", "StandardMessage"];
+
+/// Traits the bounds analysis knows how to check on payload types
+const CHECKED_TRAITS: &[&str] = &["Clone", "Debug"];
+
+/// Types that are not `Send`, which the Tokio runtime needs the extended state to be
+const NON_SEND_TYPES: &[&str] = &["Rc", "NonNull"];
+
+/// Types that are `Send` but not `Sync`, so the extended state can't be shared
+const NON_SYNC_TYPES: &[&str] = &["RefCell", "Cell", "UnsafeCell"];
+
+/// Types keeping their contents on the heap, so a payload behind one is already small
+const HEAP_TYPES: &[&str] = &["Box", "Vec", "String", "Arc", "Rc", "HashMap", "BTreeMap"];
+
+/// Arrays at least this long make a payload large enough to be worth boxing
+const LARGE_ARRAY_LEN: usize = 64;
+
+/// Runs every analysis over the actor being rendered
+pub fn analyze(ctx: &RenderCtx) -> Vec<Diagnostic> {
+    let mut diagnostics = component_bounds(ctx);
+    diagnostics.extend(payload_bounds(ctx));
+    diagnostics.extend(ext_state_send(ctx));
+    diagnostics.extend(large_variants(ctx));
+    diagnostics.extend(malformed_expressions(ctx));
+    diagnostics.extend(free_form_code(ctx));
+    diagnostics.extend(topology_connections(ctx));
+    diagnostics
+}
+
+/// Reports associated types of the generated `impl Components` that cannot satisfy the
+/// framework traits they are bound by
+pub fn component_bounds(ctx: &RenderCtx) -> Vec<Diagnostic> {
+    let component = &ctx.actor.component;
+    let component_ident = &component.ident;
+    let mut diagnostics = Vec::new();
+    let mut report = |message: String| {
+        diagnostics.push(Diagnostic::error("component-bounds", message));
+    };
+
+    if component.message_set.is_none() {
+        report(format!(
+            "'{component_ident}::MessageSet' must implement MessageSet, but the spec declares no message set to generate it from"
+        ));
+    }
+
+    for (associated, ident) in [
+        ("States", component.states.state_enum.get().ident.as_str()),
+        ("ExtendedState", component.ext_state.ident()),
+        ("Receivers", component.message_receivers.ident.as_str()),
+        ("Handles", component.message_handles.ident.as_str()),
+    ] {
+        if ident.is_empty() {
+            report(format!(
+                "'{component_ident}::{associated}' has no type, because the spec leaves its ident empty"
+            ));
+        }
+    }
+    if component.states.states.is_empty() {
+        report(format!(
+            "'{component_ident}::States' must implement StateEnum, but the spec declares no states"
+        ));
+    }
+
+    let ext_state = &component.ext_state;
+    let init_args = ext_state.init_args();
+    if init_args.ident.is_empty() && !init_args.fields.is_empty() {
+        report(format!(
+            "'{}' must name its ExtendedState::InitArgs type, since it is initialized from {}",
+            ext_state.ident(),
+            init_args
+                .fields
+                .iter()
+                .map(|f| format!("'{}'", f.ident()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    for arg in &init_args.fields {
+        if ext_state.fields().iter().all(|f| f.ident() != arg.ident()) {
+            report(format!(
+                "Init arg '{}' is not a field of '{}', which ExtendedState::new initializes from it",
+                arg.ident(),
+                ext_state.ident()
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Reports extended state fields whose types are known not to be `Send` or `Sync`, as errors
+/// when the options deny them
+pub fn ext_state_send(ctx: &RenderCtx) -> Vec<Diagnostic> {
+    let ext_state = &ctx.actor.component.ext_state;
+    let report = if ctx.options.deny_non_send {
+        Diagnostic::error
+    } else {
+        Diagnostic::warning
+    };
+
+    let mut diagnostics = Vec::new();
+    for field in ext_state.fields() {
+        let ty = field.ty().as_ref();
+        let names = ty
+            .split(['<', '>', ',', ' ', '(', ')', '[', ']', '&', ';'])
+            .filter_map(|part| part.rsplit("::").next())
+            .collect::<Vec<_>>();
+        let raw_pointer = (ty.contains("*const") || ty.contains("*mut")).then_some("raw pointer");
+        let non_send = raw_pointer
+            .or_else(|| names.iter().copied().find(|n| NON_SEND_TYPES.contains(n)))
+            .map(|name| (name, "Send"));
+        let non_sync = || {
+            names
+                .iter()
+                .copied()
+                .find(|n| NON_SYNC_TYPES.contains(n))
+                .map(|name| (name, "Sync"))
+        };
+        if let Some((name, bound)) = non_send.or_else(non_sync) {
+            diagnostics.push(report(
+                "non-send-ext-state",
+                format!(
+                    "Field '{}' of '{}' has type '{ty}', whose {name} is not {bound}; actors run on Tokio, which needs the extended state to be Send and Sync",
+                    field.ident(),
+                    ext_state.ident()
+                ),
+            ));
+        }
+    }
+    diagnostics
+}
+
+/// Warns about payload types that may not satisfy the traits derived on the message set
+pub fn payload_bounds(ctx: &RenderCtx) -> Vec<Diagnostic> {
+    let Some(message_set) = &ctx.actor.component.message_set else {
+        return Vec::new();
+    };
+    let derives = ctx.message_set_derives();
+    let message_set_ident = &message_set.get().ident;
+
+    let mut diagnostics = Vec::new();
+    for trait_name in CHECKED_TRAITS
+        .iter()
+        .filter(|t| derives.iter().any(|d| d == *t))
+    {
+        for variant in &message_set.get().variants {
+            for type_name in variant.args.iter().flat_map(|arg| type_names(arg.as_ref())) {
+                let known = BUILTIN_TYPES.contains(&type_name.as_str())
+                    || FRAMEWORK_PAYLOADS.contains(&type_name.as_str())
+                    || message_set
+                        .custom_types
+                        .iter()
+                        .any(|c| c.ident == type_name)
+                    || message_set.payload_implements(&type_name, trait_name);
+
+                if !known {
+                    diagnostics.push(Diagnostic::warning(
+                        "payload-bounds",
+                        format!(
+                            "Payload type '{type_name}' of variant '{message_set_ident}::{}' is not declared to implement {trait_name}, which the message set derives; list it under 'payload_traits' or derive it on the type",
+                            variant.ident
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Suggests boxing variants whose payload is likely to make every message of the set large:
+/// long arrays, or custom types nesting other custom types, stored inline
+pub fn large_variants(ctx: &RenderCtx) -> Vec<Diagnostic> {
+    let Some(message_set) = &ctx.actor.component.message_set else {
+        return Vec::new();
+    };
+    let custom_type = |name: &str| message_set.custom_types.iter().find(|c| c.ident == name);
+
+    let mut diagnostics = Vec::new();
+    for variant in message_set.get().variants.iter().filter(|v| !v.boxed) {
+        for payload in &variant.args {
+            let ty = payload.as_ref().trim();
+            if HEAP_TYPES
+                .iter()
+                .any(|heap| ty.rsplit("::").next().is_some_and(|t| t.starts_with(heap)))
+            {
+                continue;
+            }
+            let long_array = ty
+                .rsplit_once(';')
+                .and_then(|(_, len)| len.trim_end_matches(']').trim().parse::<usize>().ok())
+                .filter(|len| *len >= LARGE_ARRAY_LEN)
+                .map(|len| format!("inlines a {len}-element array"));
+            let nested = || {
+                let outer = custom_type(ty)?;
+                outer
+                    .variants
+                    .iter()
+                    .flat_map(|v| &v.args)
+                    .flat_map(|arg| type_names(arg.as_ref()))
+                    .find(|name| custom_type(name).is_some())
+                    .map(|inner| format!("nests '{inner}' inside '{}'", outer.ident))
+            };
+            if let Some(reason) = long_array.or_else(nested) {
+                diagnostics.push(Diagnostic::warning(
+                    "large-variant",
+                    format!(
+                        "Payload '{ty}' of variant '{}::{}' {reason}, making every message of the set as large; consider 'boxed: true' on the variant",
+                        message_set.get().ident,
+                        variant.ident
+                    ),
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Reports Rust expressions in the spec that don't parse, naming where they appear, so a
+/// typo fails validation instead of the generated code's build
+pub fn malformed_expressions(ctx: &RenderCtx) -> Vec<Diagnostic> {
+    expressions(ctx)
+        .into_iter()
+        .filter_map(|(location, expr)| {
+            let error = syn::parse_str::<syn::Expr>(expr).err()?;
+            Some(Diagnostic::error(
+                "malformed-expression",
+                format!("`{expr}` in {location} is not a Rust expression: {error}"),
+            ))
+        })
+        .collect()
+}
+
+/// Rejects free-form Rust code in restricted mode: method bodies, lifecycle and sampling
+/// hooks and any expression other than a literal, so specs stay declarative and auditable
+pub fn free_form_code(ctx: &RenderCtx) -> Vec<Diagnostic> {
+    if !ctx.options.restricted {
+        return Vec::new();
+    }
+    let actor = ctx.actor;
+    let mut code = expressions(ctx)
+        .into_iter()
+        .filter(|(_, expr)| !is_literal(expr))
+        .collect::<Vec<_>>();
+    for method in actor.component.ext_state.methods() {
+        code.push((
+            format!("the body of method '{}'", method.ident()),
+            method.body(),
+        ));
+    }
+    for state in &actor.component.states.states {
+        for method in &state.methods {
+            code.push((
+                format!(
+                    "the body of method '{}' of state '{}'",
+                    method.ident(),
+                    state.ident
+                ),
+                method.body(),
+            ));
+        }
+    }
+    if let Some(pre_start) = &actor.lifecycle.pre_start {
+        code.push(("the `pre_start` hook".to_string(), pre_start));
+    }
+    if let Some(post_stop) = &actor.lifecycle.post_stop {
+        code.push(("the `post_stop` hook".to_string(), post_stop));
+    }
+    if let Some(sequence_gap) = &actor.sequence_gap {
+        code.push(("the `sequence_gap` hook".to_string(), sequence_gap));
+    }
+    if let Some(observe) = actor
+        .queue_sampling
+        .as_ref()
+        .and_then(|s| s.observe.as_ref())
+    {
+        code.push(("the queue sampling `observe` hook".to_string(), observe));
+    }
+    if let Some(key) = actor.pool.as_ref().and_then(|pool| pool.key.as_ref()) {
+        code.push(("the pool key".to_string(), key));
+    }
+
+    code.into_iter()
+        .map(|(location, _)| {
+            Diagnostic::error(
+                "free-form-code",
+                format!("Restricted specs can't contain Rust code, found in {location}"),
+            )
+        })
+        .collect()
+}
+
+/// Whether `expr` is a literal, possibly negated, like `42`, `-1.5` or `"name"`
+fn is_literal(expr: &str) -> bool {
+    match syn::parse_str::<syn::Expr>(expr) {
+        Ok(syn::Expr::Lit(_)) => true,
+        Ok(syn::Expr::Unary(unary)) => {
+            matches!(unary.op, syn::UnOp::Neg(_)) && matches!(*unary.expr, syn::Expr::Lit(_))
+        }
+        _ => false,
+    }
+}
+
+/// Rust expressions in the spec, with where they appear
+fn expressions<'a>(ctx: &RenderCtx<'a>) -> Vec<(String, &'a str)> {
+    let actor = ctx.actor;
+    let component = &actor.component;
+    let mut expressions = Vec::new();
+
+    let ext_state = &component.ext_state;
+    let states = &component.states.states;
+    let fields = ext_state
+        .fields()
+        .iter()
+        .map(|field| (field, ext_state.ident()))
+        .chain(states.iter().flat_map(|state| {
+            state
+                .fields
+                .iter()
+                .map(|field| (field, state.ident.as_str()))
+        }));
+    for (field, owner) in fields {
+        // Secret references are replaced by the loader, not pasted as code
+        if let Some(value) = field.value().filter(|_| field.secret().is_none()) {
+            expressions.push((
+                format!("the value of field '{}' of '{owner}'", field.ident()),
+                value,
+            ));
+        }
+    }
+    if let Some(config) = ext_state.config() {
+        for (arg, expr) in &config.map {
+            expressions.push((
+                format!("the mapping of init arg '{arg}' from '{}'", config.ident),
+                expr,
+            ));
+        }
+    }
+    for state in states {
+        for transition in &state.transitions {
+            for emit in &transition.emit {
+                expressions.push((
+                    format!(
+                        "the message state '{}' emits through '{}' on '{}'",
+                        state.ident, emit.handle, transition.on
+                    ),
+                    &emit.message,
+                ));
+            }
+        }
+    }
+    for state in states {
+        for invariant in &state.invariants {
+            expressions.push((
+                format!("an invariant of state '{}'", state.ident),
+                invariant,
+            ));
+        }
+    }
+    for schedule in &actor.schedules {
+        expressions.push((
+            format!("the payload of schedule '{}'", schedule.cron),
+            &schedule.payload,
+        ));
+    }
+    for relay in &actor.relays {
+        if let Some(transform) = &relay.transform {
+            expressions.push((
+                format!("the transform relaying '{}'", relay.variant),
+                transform,
+            ));
+        }
+    }
+    for constant in &actor.constants {
+        expressions.push((
+            format!("the value of constant '{}'", constant.ident),
+            &constant.value,
+        ));
+    }
+    if let Some(shard) = &actor.shard {
+        expressions.push(("the shard key".to_string(), &shard.key));
+    }
+    expressions
+}
+
+/// Warns, for an actor composed with children, about handles sending a type no receiver in
+/// the topology takes and about children's receivers that neither a forward nor any handle
+/// in the topology feeds
+pub fn topology_connections(ctx: &RenderCtx) -> Vec<Diagnostic> {
+    let root = ctx.actor;
+    if root.children.is_empty() {
+        return Vec::new();
+    }
+    // Every actor in the topology, with the channels its parent forwards to it
+    let mut actors = Vec::new();
+    let mut pending = vec![(root, Vec::new())];
+    while let Some((actor, forwarded)) = pending.pop() {
+        for child in &actor.children {
+            if let Ok(child_actor) = child.actor() {
+                let channels = child.forward.iter().map(|f| f.channel.as_str()).collect();
+                pending.push((child_actor, channels));
+            }
+        }
+        actors.push((actor, forwarded));
+    }
+    let mut diagnostics = Vec::new();
+    for (actor, forwarded) in &actors {
+        for handle in handles(actor) {
+            if !actors.iter().any(|(other, _)| {
+                receivers(other).any(|receiver| receiver.message_type == handle.message_type)
+            }) {
+                diagnostics.push(Diagnostic::warning(
+                    "orphan-handle",
+                    format!(
+                        "Handle '{}' of '{}' sends '{}', which no receiver in the topology of '{}' takes",
+                        handle.ident, actor.ident, handle.message_type, root.ident
+                    ),
+                ));
+            }
+        }
+        // The root's receivers are fed from outside the topology
+        if core::ptr::eq(*actor, root) {
+            continue;
+        }
+        for receiver in receivers(actor) {
+            let fed = forwarded.contains(&receiver.channel_name())
+                || actors.iter().any(|(other, _)| {
+                    handles(other).any(|handle| handle.message_type == receiver.message_type)
+                });
+            if !fed {
+                diagnostics.push(Diagnostic::warning(
+                    "orphan-receiver",
+                    format!(
+                        "Receiver '{}' of '{}' takes '{}', which nothing in the topology of '{}' forwards or sends",
+                        receiver.ident, actor.ident, receiver.message_type, root.ident
+                    ),
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Receivers of an actor that are fed by messages, leaving out health probes
+fn receivers(actor: &Actor) -> impl Iterator<Item = &MessageReceiver> {
+    actor
+        .component
+        .message_receivers
+        .receivers
+        .iter()
+        .filter(|receiver| receiver.ident != HEALTH_RECEIVER)
+}
+
+fn handles(actor: &Actor) -> impl Iterator<Item = &MessageHandle> {
+    actor.component.message_handles.handles.iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blox::constant::Constant;
+    use crate::blox::enums::{EnumDef, EnumVariant};
+    use crate::blox::ext_state::{ExtState, InitArgs};
+    use crate::blox::message_set::MessageSet;
+    use crate::blox::state::{Emit, Transition};
+    use crate::create::{ActorGenerator, GeneratorOptions};
+    use crate::tests::create_test_actor;
+    use crate::{Field, Link};
+
+    #[test]
+    fn test_component_bounds() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(component_bounds(&generator.ctx()).is_empty());
+
+        let mut actor = create_test_actor();
+        actor.component.message_set = None;
+        actor.component.ext_state = ExtState::new(
+            "ActorExtState",
+            Vec::new(),
+            Vec::new(),
+            InitArgs::new("", vec![Field::new("peers", "Vec<String>")]),
+        );
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let messages = component_bounds(&generator.ctx())
+            .into_iter()
+            .inspect(|d| assert!(d.is_error() && d.code == "component-bounds"))
+            .map(|d| d.message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "'ActorComponents::MessageSet' must implement MessageSet, but the spec declares no message set to generate it from",
+                "'ActorExtState' must name its ExtendedState::InitArgs type, since it is initialized from 'peers'",
+                "Init arg 'peers' is not a field of 'ActorExtState', which ExtendedState::new initializes from it",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_non_send_ext_state_fields() {
+        let actor = || {
+            let mut actor = create_test_actor();
+            actor.component.ext_state = ExtState::new(
+                "ActorExtState",
+                vec![
+                    Field::new("shared", "std::rc::Rc<String>"),
+                    Field::new("cache", "RefCell<Vec<u8>>"),
+                    Field::new("raw", "*const u8"),
+                    Field::new("count", "Arc<Mutex<u32>>"),
+                ],
+                Vec::new(),
+                InitArgs::default(),
+            );
+            actor
+        };
+        let generator = ActorGenerator::new(actor()).expect("Generator creation should succeed");
+        let diagnostics = ext_state_send(&generator.ctx());
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics.iter().all(|d| !d.is_error()));
+        assert_eq!(
+            diagnostics[0].message,
+            "Field 'shared' of 'ActorExtState' has type 'std::rc::Rc<String>', whose Rc is not Send; actors run on Tokio, which needs the extended state to be Send and Sync"
+        );
+        assert!(diagnostics[1].message.contains("whose RefCell is not Sync"));
+        assert!(
+            diagnostics[2]
+                .message
+                .contains("whose raw pointer is not Send")
+        );
+
+        let options = GeneratorOptions::default().with_deny_non_send(true);
+        let generator = ActorGenerator::with_options(actor(), options)
+            .expect("Generator creation should succeed");
+        assert!(
+            ext_state_send(&generator.ctx())
+                .iter()
+                .all(Diagnostic::is_error)
+        );
+        let error = generator.generate().unwrap_err().to_string();
+        assert!(error.starts_with("Field 'shared' of 'ActorExtState'"));
+    }
+
+    #[test]
+    fn test_large_variants() {
+        let actor = |boxed: bool| {
+            let mut actor = create_test_actor();
+            let mut large = EnumVariant::new("Frame", vec![Link::new("[u8; 4096]")]);
+            if boxed {
+                large = large.boxed();
+            }
+            let def = EnumDef::new(
+                "ActorMessageSet",
+                vec![
+                    large,
+                    EnumVariant::new("Buffered", vec![Link::new("Vec<[u8; 4096]>")]),
+                    EnumVariant::new("Order", vec![Link::new("OrderEvent")]),
+                ],
+            );
+            let custom_types = vec![
+                EnumDef::new(
+                    "OrderEvent",
+                    vec![EnumVariant::new("Placed", vec![Link::new("LineItems")])],
+                ),
+                EnumDef::new("LineItems", vec![EnumVariant::new("Empty", vec![])]),
+            ];
+            actor.component.message_set = Some(MessageSet::with_custom_types(def, custom_types));
+            actor
+        };
+
+        let generator =
+            ActorGenerator::new(actor(false)).expect("Generator creation should succeed");
+        let messages = large_variants(&generator.ctx())
+            .into_iter()
+            .inspect(|d| assert!(!d.is_error() && d.code == "large-variant"))
+            .map(|d| d.message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "Payload '[u8; 4096]' of variant 'ActorMessageSet::Frame' inlines a 4096-element array, making every message of the set as large; consider 'boxed: true' on the variant",
+                "Payload 'OrderEvent' of variant 'ActorMessageSet::Order' nests 'LineItems' inside 'OrderEvent', making every message of the set as large; consider 'boxed: true' on the variant",
+            ]
+        );
+
+        let generator =
+            ActorGenerator::new(actor(true)).expect("Generator creation should succeed");
+        assert_eq!(large_variants(&generator.ctx()).len(), 1);
+    }
+
+    #[test]
+    fn test_no_bounds_checked_without_derives() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(payload_bounds(&generator.ctx()).is_empty());
+    }
+
+    #[test]
+    fn test_undeclared_payload_is_reported() {
+        let options = GeneratorOptions::default().with_derive_message_traits(true);
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        let diagnostics = payload_bounds(&generator.ctx());
+
+        // CustomArgs is neither a custom type nor declared, once per checked trait
+        assert_eq!(diagnostics.len(), 2);
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| d.message.contains("'CustomArgs'") && d.code == "payload-bounds")
+        );
+
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains("#[derive(Debug, Clone)]\npub enum ActorMessageSet"));
+    }
+
+    #[test]
+    fn test_declared_payload_traits_satisfy_bounds() {
+        let mut actor = create_test_actor();
+        let message_set = actor.component.message_set.as_mut().unwrap();
+        message_set.def = message_set.def.clone().with_derives(["Clone"]);
+        message_set
+            .payload_traits
+            .insert("CustomArgs".to_string(), vec!["Clone".to_string()]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        assert!(analyze(&generator.ctx()).is_empty());
+    }
+
+    #[test]
+    fn test_malformed_expressions() {
+        let mut actor = create_test_actor();
+        actor.component.ext_state = ExtState::new(
+            "ActorExtState",
+            vec![
+                Field::new("limit", "u64").with_value("10_000u64"),
+                Field::new("ratio", "f32").with_value("0.5f32"),
+                Field::new("separator", "char").with_value("'/'"),
+                Field::new("bounds", "(u8, i16)").with_value("(0, -1)"),
+                Field::new("token", "String").with_value("${secret:TOKEN}"),
+            ],
+            vec![],
+            InitArgs::default(),
+        );
+        let actor = actor.with_constant(Constant::new("LIMITS", "[u8; 2]", "[1, 2]"));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert!(malformed_expressions(&generator.ctx()).is_empty());
+
+        let mut actor = create_test_actor();
+        actor.component.states.states[0].transitions = vec![
+            Transition::new("CustomValue1", "Update")
+                .with_emit(vec![Emit::new("standard_handle", "Message::new(0,")]),
+        ];
+        actor.component.states.states[1].invariants = vec!["ext.limit >".to_string()];
+        let actor = actor.with_constant(Constant::new("LIMIT", "u8", "2 +"));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let diagnostics = malformed_expressions(&generator.ctx());
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics[0].message.starts_with(
+            "`Message::new(0,` in the message state 'Create' emits through 'standard_handle' on 'CustomValue1' is not a Rust expression: "
+        ));
+        assert!(
+            diagnostics[1]
+                .message
+                .starts_with("`ext.limit >` in an invariant of state 'Update'")
+        );
+        assert!(
+            diagnostics[2]
+                .message
+                .starts_with("`2 +` in the value of constant 'LIMIT'")
+        );
+        assert!(diagnostics.iter().all(Diagnostic::is_error));
+    }
+
+    #[test]
+    fn test_free_form_code() {
+        let actor = || {
+            let mut actor = create_test_actor();
+            actor.component.ext_state = ExtState::new(
+                "ActorExtState",
+                vec![
+                    Field::new("limit", "u64").with_value("10_000u64"),
+                    Field::new("offset", "i32").with_value("-1"),
+                    Field::new("started", "Instant").with_value("Instant::now()"),
+                ],
+                vec![crate::Method::new("reset", &[], "()", "self.limit = 0;")],
+                InitArgs::default(),
+            );
+            actor.component.states.states[0].transitions = vec![
+                Transition::new("CustomValue1", "Update")
+                    .with_emit(vec![Emit::new("standard_handle", "message.clone()")]),
+            ];
+            actor.component.states.states[1].methods = vec![crate::Method::new(
+                "retry",
+                &[],
+                "()",
+                "std::process::exit(1)",
+            )];
+            actor.with_constant(Constant::new("NAME", "&str", "\"actor\""))
+        };
+        let generator = ActorGenerator::new(actor()).expect("Generator creation should succeed");
+        assert!(free_form_code(&generator.ctx()).is_empty());
+
+        let options = GeneratorOptions::default().with_restricted(true);
+        let generator = ActorGenerator::with_options(actor(), options)
+            .expect("Generator creation should succeed");
+        let messages = free_form_code(&generator.ctx())
+            .into_iter()
+            .inspect(|d| assert!(d.is_error() && d.code == "free-form-code"))
+            .map(|d| d.message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "Restricted specs can't contain Rust code, found in the value of field 'started' of 'ActorExtState'",
+                "Restricted specs can't contain Rust code, found in the message state 'Create' emits through 'standard_handle' on 'CustomValue1'",
+                "Restricted specs can't contain Rust code, found in the body of method 'reset'",
+                "Restricted specs can't contain Rust code, found in the body of method 'retry' of state 'Update'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_topology_connections() {
+        use crate::blox::child::{Child, Forward};
+
+        let gateway = Actor::from_json_file(&"tests/children/gateway.json".into())
+            .expect("Gateway spec should load");
+        let generator = ActorGenerator::new(gateway).expect("Generator creation should succeed");
+        assert_eq!(topology_connections(&generator.ctx()), Vec::new());
+
+        let mut worker = create_test_actor();
+        worker.ident = "Worker".to_string();
+        worker
+            .component
+            .message_handles
+            .add_handle(MessageHandle::new("alerts_handle", "Alert"));
+        worker
+            .component
+            .message_receivers
+            .add_receiver(MessageReceiver::new("commands_rx", "Command"));
+        let mut actor = create_test_actor();
+        actor.children = vec![
+            Child::new("worker", "worker.json")
+                .with_forward(Forward::new("CustomValue2", "customargs"))
+                .with_actor(worker),
+        ];
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let messages = topology_connections(&generator.ctx())
+            .into_iter()
+            .inspect(|d| assert!(!d.is_error()))
+            .map(|d| format!("{}: {}", d.code, d.message))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "orphan-handle: Handle 'alerts_handle' of 'Worker' sends 'Alert', which no receiver in the topology of 'Actor' takes",
+                "orphan-receiver: Receiver 'commands_rx' of 'Worker' takes 'Command', which nothing in the topology of 'Actor' forwards or sends",
+            ]
+        );
+        assert!(
+            generator
+                .diagnostics()
+                .iter()
+                .any(|d| d.code == "orphan-handle")
+        );
+    }
+}
@@ -1,20 +1,1058 @@
-use bloxml::actor::Actor;
-use bloxml::create;
-use clap::Parser;
+use anstyle::{AnsiColor, Style};
+use bloxml::create::emit::FieldNumbering;
+use bloxml::create::{
+    DispatchStrategy, EmitConfig, GenerateConfig, GeneratorOptionsBuilder, LicenseHeader,
+    LineEndings, ModuleDependencies, Phase, ProjectConfig, SpecElement, emit, find_actor_specs,
+    render_message_shims, render_migration,
+};
+use bloxml::lint::fix;
+use bloxml::prelude::*;
+use bloxml::rename::{RenameTarget, rename};
+use clap::{CommandFactory, Parser, Subcommand, error::ErrorKind};
+use clap_complete::Shell;
+use indicatif::{ProgressBar, ProgressStyle};
 use std::error::Error;
-use std::path::PathBuf;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+const EXIT_STATUS: &str = "\
+Exit status:
+  0  Success
+  1  Any other failure
+  2  Invalid arguments
+  3  The spec, an overlay, mixin or child of it, or bloxml.toml could not be parsed
+  4  The spec is invalid, or has more warnings than allowed
+  5  Rendering the generated code or another output failed
+  6  A file could not be read or written";
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_long_help = EXIT_STATUS)]
 struct Args {
     /// Path to the JSON file
     #[arg(value_name = "JSON_FILE", short, long)]
-    json_file: PathBuf,
+    json_file: Option<PathBuf>,
+
+    /// Print nothing but errors: no progress, warnings or summaries
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Print without colors; also set by the NO_COLOR environment variable
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate the actor module described by a spec
+    ///
+    /// Flags override the `[generate]` section of the closest `bloxml.toml` above the spec.
+    Generate {
+        /// Path to the JSON spec
+        spec: PathBuf,
+
+        /// Merge the spec's overlay for this environment, like `actor.prod.json` for `prod`
+        #[arg(long, value_name = "NAME")]
+        env: Option<String>,
+
+        /// Keep a `.bak` copy of every existing file whose contents change
+        #[arg(long)]
+        backup: bool,
+
+        /// Fail instead of replacing existing files whose contents differ
+        #[arg(long)]
+        no_overwrite: bool,
+
+        /// Generate `to_wire`/`from_wire` codecs for the message set: json, cbor or postcard
+        #[arg(long, value_name = "FORMAT")]
+        wire: Option<WireFormat>,
+
+        /// Edition the generated code is written for: 2018, 2021 or 2024 (default 2024)
+        #[arg(long)]
+        edition: Option<Edition>,
+
+        /// Oldest Rust release the generated code must compile with, such as 1.70
+        #[arg(long, value_name = "VERSION")]
+        msrv: Option<RustVersion>,
+
+        /// Allow the clippy lints generated files are known to trigger
+        #[arg(long)]
+        clippy_allows: bool,
+
+        /// How the state enum dispatches to its states: match (default) or trait-object, which
+        /// generates less code for actors with many states. Messages are matched the same way
+        /// under both
+        #[arg(long, value_name = "STRATEGY")]
+        dispatch: Option<DispatchStrategy>,
+
+        /// Assert the framework bounds of generated types at compile time; the crate using the
+        /// generated code needs the `static_assertions` dependency
+        #[arg(long)]
+        static_assertions: bool,
+
+        /// Fail when an extended state field's type is known not to be Send or Sync
+        #[arg(long)]
+        deny_non_send: bool,
+
+        /// Split `states/mod.rs` into submodules grouping the states by first letter once it
+        /// would exceed this many lines
+        #[arg(long, value_name = "LINES")]
+        max_file_lines: Option<usize>,
+
+        /// Write `examples/<actor>_demo.rs` into the crate the actor is generated into, which
+        /// sends the actor a message of each kind; an existing demo is left as it is
+        #[arg(long)]
+        demo_example: bool,
+
+        /// Generate loom tests for the locks of shared extended state fields, run with
+        /// RUSTFLAGS="--cfg loom"; the crate needs `loom` as a `cfg(loom)` dev-dependency
+        #[arg(long)]
+        loom_tests: bool,
+
+        /// Also assert state invariants after every message a state handles, not only when
+        /// it is entered
+        #[arg(long)]
+        invariants_after_messages: bool,
+
+        /// Reject specs with free-form Rust code, like method bodies, hooks and emitted
+        /// messages, allowing only declarative constructs and literal values
+        #[arg(long)]
+        restricted: bool,
+
+        /// Also generate `cargo-fuzz` targets decoding the wire format into the `fuzz/`
+        /// directory of the crate the actor is generated into; needs --wire
+        #[arg(long)]
+        fuzz_targets: bool,
+
+        /// Start every generated file with this SPDX license expression, like
+        /// "MIT OR Apache-2.0"
+        #[arg(long, value_name = "EXPR")]
+        spdx: Option<String>,
+
+        /// Copyright holder named in the SPDX header, with the year the file was first
+        /// generated
+        #[arg(long, value_name = "NAME", requires = "spdx")]
+        copyright_holder: Option<String>,
+
+        /// Line endings of the generated files: lf (default), crlf or native, which uses
+        /// crlf on Windows
+        #[arg(long, value_name = "STYLE")]
+        line_endings: Option<LineEndings>,
+
+        /// Generate under this directory instead of the spec's `path`
+        #[arg(long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+
+        /// Look for `${secret:NAME}` references missing from the environment in this file of
+        /// `NAME=value` lines; the generated code reads the file named by BLOXML_SECRETS_FILE
+        #[arg(long, value_name = "FILE")]
+        secrets_file: Option<PathBuf>,
+
+        /// Write a `README.md` describing the module next to the generated code
+        #[arg(long)]
+        readme: bool,
+
+        /// Render the README from this template instead of the default one
+        #[arg(long, value_name = "FILE")]
+        readme_template: Option<PathBuf>,
+
+        #[command(flatten)]
+        warnings: WarningArgs,
+
+        #[command(flatten)]
+        output: ReportArgs,
+    },
+    /// Print the modules that would be generated, without writing any files
+    Plan {
+        /// Path to the JSON spec
+        spec: PathBuf,
+
+        /// Merge the spec's overlay for this environment, like `actor.prod.json` for `prod`
+        #[arg(long, value_name = "NAME")]
+        env: Option<String>,
+    },
+    /// Render the spec in another language or schema, like TypeScript definitions or an
+    /// AsyncAPI document
+    Emit {
+        /// Emitter to render with: typescript, protobuf or asyncapi
+        emitter: String,
+
+        /// Path to the JSON spec
+        spec: PathBuf,
+
+        /// Merge the spec's overlay for this environment, like `actor.prod.json` for `prod`
+        #[arg(long, value_name = "NAME")]
+        env: Option<String>,
+
+        /// Write the output into this directory instead of printing it
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+
+        /// Package of a protobuf schema; defaults to the actor's module path
+        #[arg(long, value_name = "NAME")]
+        package: Option<String>,
+
+        /// How protobuf fields are numbered: sequential (default) or hashed
+        #[arg(long, value_name = "STRATEGY")]
+        numbering: Option<FieldNumbering>,
+
+        /// Version of the actor's API in an AsyncAPI document; defaults to 1.0.0
+        #[arg(long, value_name = "VERSION")]
+        api_version: Option<String>,
+    },
+    /// Report the generated modules and other actors affected by changing parts of a spec
+    Impact {
+        /// Path to the JSON spec
+        spec: PathBuf,
+
+        /// Element of the spec being changed, like `states.Create` or `variants.Ping`
+        #[arg(long, value_name = "ELEMENT", required = true)]
+        changed: Vec<SpecElement>,
+
+        /// Directory whose actor specs are checked for use of the changed types; defaults to
+        /// the directory of the closest `bloxml.toml`
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+    },
+    /// Generate the skeleton migrating persisted extended state from the previous version of
+    /// a spec, if its extended state fields changed
+    Migrate {
+        /// Path to the JSON spec
+        spec: PathBuf,
+
+        /// Path to the previous version of the spec
+        #[arg(long, value_name = "SPEC")]
+        from: PathBuf,
+
+        /// Write the migration into this directory instead of printing it
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+    /// Generate the shims converting messages between the previous version of a spec's
+    /// message set and the current one, if its variants changed
+    Compat {
+        /// Path to the JSON spec
+        spec: PathBuf,
+
+        /// Path to the spec with the previous version of the message set
+        #[arg(long, value_name = "SPEC")]
+        from: PathBuf,
+
+        /// Write the shims into this directory instead of printing them
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+    /// Print the module and type graph of a spec as JSON, for dependency viewers and other
+    /// tooling; the schema is documented on `CodeGenGraph::to_json`
+    Graph {
+        /// Path to the JSON spec
+        spec: PathBuf,
+
+        /// Merge the spec's overlay for this environment, like `actor.prod.json` for `prod`
+        #[arg(long, value_name = "NAME")]
+        env: Option<String>,
+    },
+    /// Report how the generated modules of specs depend on each other, as an adjacency list
+    /// with the number of items used, marking dependencies against the generated layering
+    Deps {
+        /// Specs to report on; defaults to every actor spec under the closest `bloxml.toml`
+        specs: Vec<PathBuf>,
+
+        /// Print an N×N matrix of the modules instead of an adjacency list
+        #[arg(long)]
+        matrix: bool,
+    },
+    /// Search the type graphs and channels of specs, e.g. for every module importing a type
+    Search {
+        /// Case-insensitive part of the name to look for
+        pattern: String,
+
+        /// Specs to search; defaults to every actor spec under the closest `bloxml.toml`
+        specs: Vec<PathBuf>,
+
+        /// Match whole names, case-sensitively
+        #[arg(long)]
+        exact: bool,
+
+        /// Print the hits as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Rename a state, variant, field or handle along with every reference to it in the spec
+    #[command(group(clap::ArgGroup::new("target").required(true)))]
+    Rename {
+        /// Rename a state, with the parents and transition targets naming it
+        #[arg(long, num_args = 2, value_names = ["OLD", "NEW"], group = "target")]
+        state: Option<Vec<String>>,
+
+        /// Rename a message set variant, with everything sending or handling it
+        #[arg(long, num_args = 2, value_names = ["OLD", "NEW"], group = "target")]
+        variant: Option<Vec<String>>,
+
+        /// Rename an extended state field, with its init arg and method accesses
+        #[arg(long, num_args = 2, value_names = ["OLD", "NEW"], group = "target")]
+        field: Option<Vec<String>>,
+
+        /// Rename a message handle, with the transitions emitting through it
+        #[arg(long, num_args = 2, value_names = ["OLD", "NEW"], group = "target")]
+        handle: Option<Vec<String>>,
+
+        /// Path to the JSON spec, rewritten in place
+        spec: PathBuf,
+    },
+    /// Rewrite the spec to resolve the lint findings that can be fixed automatically, like
+    /// misnamed states and unused handles
+    Fix {
+        /// Path to the JSON spec, rewritten in place
+        spec: PathBuf,
+
+        /// Print the fixes without writing the spec
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Lint and analyze a spec, failing if any lint is denied
+    Check {
+        /// Path to the JSON spec
+        spec: PathBuf,
+
+        /// Merge the spec's overlay for this environment, like `actor.prod.json` for `prod`
+        #[arg(long, value_name = "NAME")]
+        env: Option<String>,
+
+        /// Reject free-form Rust code in the spec, as `generate --restricted` does
+        #[arg(long)]
+        restricted: bool,
+
+        #[command(flatten)]
+        warnings: WarningArgs,
+    },
+    /// Print the completion script of a shell, for packagers to install where the shell
+    /// loads completions from
+    Completions {
+        /// Shell to complete in: bash, elvish, fish, powershell or zsh
+        shell: Shell,
+    },
+    /// Print the man page of bloxml in roff
+    Man {
+        /// Write `bloxml.1` and a page per subcommand, like `bloxml-generate.1`, into this
+        /// directory instead of printing the main page
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+/// How many warnings a spec may have before the command fails
+#[derive(clap::Args, Default)]
+struct WarningArgs {
+    /// Fail when the spec has more than this many warnings
+    #[arg(long, value_name = "N")]
+    max_warnings: Option<usize>,
+
+    /// Fail on any warning, like `--max-warnings 0`
+    #[arg(long, conflicts_with = "max_warnings")]
+    deny_warnings: bool,
+}
+
+impl WarningArgs {
+    fn limit(&self) -> Option<usize> {
+        if self.deny_warnings {
+            Some(0)
+        } else {
+            self.max_warnings
+        }
+    }
+
+    /// Fails as invalid if `diagnostics` hold more warnings than allowed
+    fn check(&self, spec: &Path, diagnostics: &[Diagnostic]) -> Result<(), Failed> {
+        let warnings = diagnostics.iter().filter(|d| !d.is_error()).count();
+        match self.limit() {
+            Some(limit) if warnings > limit => Err(Failed::new(
+                Failure::Validation,
+                format!(
+                    "{} has {warnings} warning(s), more than the {limit} allowed",
+                    display_path(spec)
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What a command failed at, told apart by its exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    Parse,
+    Validation,
+    Codegen,
+    Io,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+impl Failure {
+    fn exit_code(self) -> ExitCode {
+        ExitCode::from(match self {
+            Failure::Parse => 3,
+            Failure::Validation => 4,
+            Failure::Codegen => 5,
+            Failure::Io => 6,
+        })
+    }
+
+    /// What `error` failed at, if it says; failing to read or write a file is an IO failure
+    /// at any stage
+    fn of(error: &(dyn Error + 'static)) -> Option<Self> {
+        if let Some(failed) = error.downcast_ref::<Failed>() {
+            Some(failed.failure)
+        } else if error.is::<io::Error>()
+            || error
+                .downcast_ref::<serde_json::Error>()
+                .is_some_and(serde_json::Error::is_io)
+        {
+            Some(Failure::Io)
+        } else {
+            None
+        }
+    }
+}
+
+/// An error along with what the command failed at
+#[derive(Debug)]
+struct Failed {
+    failure: Failure,
+    error: Box<dyn Error>,
+}
+
+impl Failed {
+    fn new(failure: Failure, error: impl Into<Box<dyn Error>>) -> Self {
+        let error = error.into();
+        let failure = Failure::of(error.as_ref()).unwrap_or(failure);
+        Self { failure, error }
+    }
+}
+
+impl fmt::Display for Failed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl Error for Failed {}
+
+trait FailAt<T> {
+    /// Tags the error with what the command failed at
+    fn fail_at(self, failure: Failure) -> Result<T, Failed>;
+}
+
+impl<T, E: Into<Box<dyn Error>>> FailAt<T> for Result<T, E> {
+    fn fail_at(self, failure: Failure) -> Result<T, Failed> {
+        self.map_err(|error| Failed::new(failure, error))
+    }
+}
+
+/// What to print once generation has finished
+#[derive(clap::Args, Default)]
+struct ReportArgs {
+    /// Print the files written and the size of the generated module
+    #[arg(long)]
+    report: bool,
+
+    /// Print the time spent in each generation phase
+    #[arg(long)]
+    timings: bool,
+}
+
+/// How progress and diagnostics are shown on stderr
+struct Ui {
+    quiet: bool,
+    color: bool,
+}
+
+impl Ui {
+    const ERROR: Style = AnsiColor::Red.on_default().bold();
+    const WARNING: Style = AnsiColor::Yellow.on_default().bold();
+    const DONE: Style = AnsiColor::Green.on_default().bold();
+    const LOCATION: Style = AnsiColor::Blue.on_default().bold();
+
+    fn new(quiet: bool, no_color: bool) -> Self {
+        let color =
+            !no_color && std::env::var_os("NO_COLOR").is_none() && io::stderr().is_terminal();
+        Self { quiet, color }
+    }
+
+    fn paint(&self, style: Style, text: impl fmt::Display) -> String {
+        if self.color {
+            format!("{style}{text}{style:#}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// A spinner naming the actor and the phase it is in, hidden when quiet or not on a
+    /// terminal
+    fn progress(&self, actor: &str) -> ProgressBar {
+        if self.quiet {
+            return ProgressBar::hidden();
+        }
+        let progress = ProgressBar::new_spinner().with_prefix(actor.to_string());
+        progress.set_style(
+            ProgressStyle::with_template("{spinner} {prefix}: {msg}")
+                .expect("the progress template is valid"),
+        );
+        progress.enable_steady_tick(Duration::from_millis(100));
+        progress
+    }
+
+    /// Prints a diagnostic about `spec`, dropping warnings when quiet
+    fn diagnostic(&self, spec: &Path, diagnostic: &Diagnostic) {
+        let style = match diagnostic.severity {
+            Severity::Error => Self::ERROR,
+            Severity::Warning if self.quiet => return,
+            Severity::Warning => Self::WARNING,
+        };
+        eprintln!(
+            "{}: {}\n  {} {}",
+            self.paint(
+                style,
+                format_args!("{}[{}]", diagnostic.severity, diagnostic.code)
+            ),
+            diagnostic.message,
+            self.paint(Self::LOCATION, "-->"),
+            display_path(spec)
+        );
+    }
+
+    /// Prints a line of progress like `Generated Session in 12ms`, unless quiet
+    fn status(&self, action: &str, message: impl fmt::Display) {
+        if !self.quiet {
+            eprintln!(
+                "{} {message}",
+                self.paint(Self::DONE, format_args!("{action:>12}"))
+            );
+        }
+    }
+
+    fn error(&self, error: &dyn Error) {
+        eprintln!("{}: {error}", self.paint(Self::ERROR, "error"));
+    }
+}
+
+fn main() -> ExitCode {
     let args = Args::parse();
+    let ui = Ui::new(args.quiet, args.no_color);
+    match run(args, &ui) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            ui.error(error.as_ref());
+            Failure::of(error.as_ref()).map_or(ExitCode::FAILURE, Failure::exit_code)
+        }
+    }
+}
+
+fn run(args: Args, ui: &Ui) -> Result<(), Box<dyn Error>> {
+    match (args.command, args.json_file) {
+        (
+            Some(Command::Generate {
+                spec,
+                env,
+                backup,
+                no_overwrite,
+                wire,
+                edition,
+                msrv,
+                clippy_allows,
+                dispatch,
+                static_assertions,
+                deny_non_send,
+                max_file_lines,
+                demo_example,
+                loom_tests,
+                invariants_after_messages,
+                restricted,
+                fuzz_targets,
+                spdx,
+                copyright_holder,
+                line_endings,
+                output_dir,
+                secrets_file,
+                readme,
+                readme_template,
+                warnings,
+                output,
+            }),
+            _,
+        ) => {
+            let flags = GenerateConfig {
+                backup: backup.then_some(true),
+                no_overwrite: no_overwrite.then_some(true),
+                wire,
+                edition,
+                msrv,
+                clippy_allows: clippy_allows.then_some(true),
+                dispatch,
+                static_assertions: static_assertions.then_some(true),
+                deny_non_send: deny_non_send.then_some(true),
+                max_file_lines,
+                demo_example: demo_example.then_some(true),
+                loom_tests: loom_tests.then_some(true),
+                invariants_after_messages: invariants_after_messages.then_some(true),
+                restricted: restricted.then_some(true),
+                fuzz_targets: fuzz_targets.then_some(true),
+                license_header: spdx
+                    .map(|spdx| LicenseHeader::new(spdx).with_holder(copyright_holder)),
+                line_endings,
+                output_dir,
+                secrets_file,
+                readme: readme.then_some(true),
+                readme_template,
+                ..Default::default()
+            };
+            generate(ui, &spec, env.as_deref(), &flags, &warnings, &output)
+        }
+        (None, Some(spec)) => generate(
+            ui,
+            &spec,
+            None,
+            &GenerateConfig::default(),
+            &WarningArgs::default(),
+            &ReportArgs::default(),
+        ),
+        (Some(Command::Plan { spec, env }), _) => {
+            let (actor, options) = load(&spec, env.as_deref())?;
+            let generator = analyze(actor, options)?;
+            report_diagnostics(ui, &spec, &generator);
+            for module in generator.plan()? {
+                println!("{module}");
+            }
+            Ok(())
+        }
+        (
+            Some(Command::Emit {
+                emitter,
+                spec,
+                env,
+                out_dir,
+                package,
+                numbering,
+                api_version,
+            }),
+            _,
+        ) => {
+            let flags = EmitConfig {
+                package,
+                numbering,
+                version: api_version,
+            };
+            emit(&emitter, &spec, env.as_deref(), out_dir, flags)
+        }
+        (
+            Some(Command::Impact {
+                spec,
+                changed,
+                project,
+            }),
+            _,
+        ) => impact(&spec, &changed, project),
+        (
+            Some(Command::Migrate {
+                spec,
+                from,
+                out_dir,
+            }),
+            _,
+        ) => migrate(&spec, &from, out_dir),
+        (
+            Some(Command::Compat {
+                spec,
+                from,
+                out_dir,
+            }),
+            _,
+        ) => compat(&spec, &from, out_dir),
+        (Some(Command::Graph { spec, env }), _) => {
+            let (actor, options) = load(&spec, env.as_deref())?;
+            println!("{}", analyze(actor, options)?.graph().to_json());
+            Ok(())
+        }
+        (Some(Command::Deps { specs, matrix }), _) => deps(specs, matrix),
+        (
+            Some(Command::Search {
+                pattern,
+                specs,
+                exact,
+                json,
+            }),
+            _,
+        ) => search(&pattern, specs, exact, json),
+        (
+            Some(Command::Rename {
+                state,
+                variant,
+                field,
+                handle,
+                spec,
+            }),
+            _,
+        ) => {
+            let (target, names) = [
+                (RenameTarget::State, state),
+                (RenameTarget::Variant, variant),
+                (RenameTarget::Field, field),
+                (RenameTarget::Handle, handle),
+            ]
+            .into_iter()
+            .find_map(|(target, names)| Some((target, names?)))
+            .expect("clap requires one rename target");
+            rename_in_spec(&spec, target, &names[0], &names[1])
+        }
+        (Some(Command::Fix { spec, dry_run }), _) => fix_spec(ui, &spec, dry_run),
+        (
+            Some(Command::Check {
+                spec,
+                env,
+                restricted,
+                warnings,
+            }),
+            _,
+        ) => check(ui, &spec, env.as_deref(), restricted, &warnings),
+        (Some(Command::Completions { shell }), _) => {
+            // `generate` panics on a failed write, so render first and report a closed pipe
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut Args::command(), "bloxml", &mut script);
+            io::stdout().write_all(&script)?;
+            Ok(())
+        }
+        (Some(Command::Man { out_dir }), _) => man(out_dir),
+        (None, None) => Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "either --json-file or a subcommand is required",
+            )
+            .exit(),
+    }
+}
+
+/// Loads the spec, completed by the closest `bloxml.toml` above it, and the options it sets
+fn load(
+    spec: &PathBuf,
+    env: Option<&str>,
+) -> Result<(Actor, GeneratorOptionsBuilder), Box<dyn Error>> {
+    let mut actor = match env {
+        Some(env) => Actor::from_json_file_for_env(spec, env),
+        None => Actor::from_json_file(spec),
+    }
+    .fail_at(Failure::Parse)?;
+    let builder = GeneratorOptions::builder().spec_file(spec);
+    let builder = match ProjectConfig::discover(spec).fail_at(Failure::Parse)? {
+        Some(config) => {
+            config.apply_to_actor(&mut actor);
+            config.generate.apply(builder).layering(config.layering)
+        }
+        None => builder,
+    };
+    Ok((actor, builder))
+}
+
+/// Builds the options and analyzes the spec with them, failing as invalid if either does
+fn analyze(actor: Actor, options: GeneratorOptionsBuilder) -> Result<ActorGenerator, Failed> {
+    let options = options.build().fail_at(Failure::Validation)?;
+    ActorGenerator::with_options(actor, options).fail_at(Failure::Validation)
+}
+
+fn generate(
+    ui: &Ui,
+    spec: &PathBuf,
+    env: Option<&str>,
+    flags: &GenerateConfig,
+    warnings: &WarningArgs,
+    output: &ReportArgs,
+) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let (mut actor, options) = load(spec, env)?;
+    let parse = start.elapsed();
+
+    if let Some(dir) = &flags.output_dir {
+        actor.path = dir.clone();
+    }
+    let ident = actor.ident.clone();
+    let generator = analyze(actor, flags.apply(options))?;
+    warnings.check(spec, &report_diagnostics(ui, spec, &generator))?;
+    let progress = ui.progress(&ident);
+    let mut failed_in = Phase::Validate;
+    let report = generator.generate_with_progress(|phase| {
+        failed_in = phase;
+        progress.set_message(phase.name());
+    });
+    progress.finish_and_clear();
+    let failure = match failed_in {
+        Phase::Validate => Failure::Validation,
+        Phase::Render => Failure::Codegen,
+        Phase::Write => Failure::Io,
+    };
+    let mut report = report
+        .map_err(|e| format!("{e}\n  --> {}", display_path(spec)))
+        .fail_at(failure)?;
+    report.timings.parse = parse;
+    ui.status(
+        "Generated",
+        format_args!("{ident} in {:.0?}", report.timings.total()),
+    );
+
+    if output.report {
+        println!("{report}");
+    }
+    if output.timings {
+        println!("{}", report.timings);
+    }
+    Ok(())
+}
+
+fn emit(
+    name: &str,
+    spec: &PathBuf,
+    env: Option<&str>,
+    out_dir: Option<PathBuf>,
+    flags: EmitConfig,
+) -> Result<(), Box<dyn Error>> {
+    let config = ProjectConfig::discover(spec)?
+        .map(|config| config.emit)
+        .unwrap_or_default();
+    let emitter = emit::emitter(name, &config.merge(flags))?;
+    let (actor, options) = load(spec, env)?;
+    let generator = analyze(actor, options)?;
+    let (file, output) = generator.emit(emitter.as_ref()).fail_at(Failure::Codegen)?;
+    write_output(out_dir, &file, &output, generator.options().line_endings)
+}
+
+fn impact(
+    spec: &PathBuf,
+    changed: &[SpecElement],
+    project: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let (actor, options) = load(spec, None)?;
+    let generator = analyze(actor, options)?;
+    let project = project.or_else(|| {
+        ProjectConfig::find(spec).and_then(|config| config.parent().map(PathBuf::from))
+    });
+    let others = project
+        .map(|dir| find_actor_specs(&dir, Some(spec)))
+        .unwrap_or_default();
+
+    for element in changed {
+        print!("{}", generator.impact(element)?.with_actors(&others));
+    }
+    Ok(())
+}
+
+fn migrate(spec: &PathBuf, from: &PathBuf, out_dir: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let (previous, _) = load(from, None)?;
+    let (current, options) = load(spec, None)?;
+    let options = options.build().fail_at(Failure::Validation)?;
+    let Some((file, migration)) =
+        render_migration(&previous, &current).fail_at(Failure::Codegen)?
+    else {
+        println!("The extended state of {} did not change", current.ident);
+        return Ok(());
+    };
+    write_output(out_dir, &file, &migration, options.line_endings)
+}
+
+fn compat(spec: &PathBuf, from: &PathBuf, out_dir: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let (previous, _) = load(from, None)?;
+    let (current, options) = load(spec, None)?;
+    let options = options.build().fail_at(Failure::Validation)?;
+    let Some((file, shims)) =
+        render_message_shims(&previous, &current).fail_at(Failure::Codegen)?
+    else {
+        println!("The message set of {} did not change", current.ident);
+        return Ok(());
+    };
+    write_output(out_dir, &file, &shims, options.line_endings)
+}
+
+/// Writes `output` as `file` into `out_dir` with the configured line endings, or prints it
+/// without one
+fn write_output(
+    out_dir: Option<PathBuf>,
+    file: impl AsRef<Path>,
+    output: &str,
+    line_endings: LineEndings,
+) -> Result<(), Box<dyn Error>> {
+    match out_dir {
+        Some(dir) => {
+            fs::create_dir_all(&dir)?;
+            let path = dir.join(file);
+            fs::write(&path, line_endings.apply(output).as_bytes()).map_err(|e| {
+                io::Error::new(e.kind(), format!("Error writing {}: {e}", path.display()))
+            })?;
+        }
+        None => print!("{output}"),
+    }
+    Ok(())
+}
+
+fn deps(specs: Vec<PathBuf>, matrix: bool) -> Result<(), Box<dyn Error>> {
+    let mut deps = ModuleDependencies::default();
+    for spec in project_specs(specs) {
+        let (actor, options) = load(&spec, None)?;
+        deps.merge(analyze(actor, options)?.module_dependencies()?);
+    }
+    if matrix {
+        print!("{}", deps.matrix());
+    } else {
+        print!("{deps}");
+    }
+    let unexpected = deps.unexpected().count();
+    if unexpected > 0 {
+        println!("{unexpected} unexpected module dependencies");
+    }
+    Ok(())
+}
+
+/// The given specs, or every actor spec under the closest `bloxml.toml` if there are none
+fn project_specs(specs: Vec<PathBuf>) -> Vec<PathBuf> {
+    if !specs.is_empty() {
+        return specs;
+    }
+    let root = ProjectConfig::find_from(Path::new("."))
+        .and_then(|config| config.parent().map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("."));
+    find_actor_specs(&root, None)
+        .into_iter()
+        .map(|(spec, _)| spec)
+        .collect()
+}
+
+fn search(
+    pattern: &str,
+    specs: Vec<PathBuf>,
+    exact: bool,
+    json: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut hits = Vec::new();
+    for spec in &project_specs(specs) {
+        let (actor, options) = load(spec, None)?;
+        let generator = analyze(actor, options)?;
+        hits.extend(generator.search(pattern, exact)?);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+    } else {
+        hits.iter().for_each(|hit| println!("{hit}"));
+    }
+    Ok(())
+}
+
+/// Renames an item in the spec file as written, leaving its mixins unresolved
+fn rename_in_spec(
+    spec: &PathBuf,
+    target: RenameTarget,
+    old: &str,
+    new: &str,
+) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read_to_string(spec)
+        .map_err(|e| io::Error::new(e.kind(), format!("Error reading {}: {e}", spec.display())))?;
+    let mut actor: Actor = serde_json::from_str(&contents).fail_at(Failure::Parse)?;
+    let renamed = rename(&mut actor, target, old, new).fail_at(Failure::Validation)?;
+    fs::write(spec, serde_json::to_string_pretty(&actor)? + "\n")?;
+    println!("Renamed {target} '{old}' to '{new}' in {renamed} place(s)");
+    Ok(())
+}
+
+/// Fixes the lint findings in the spec file as written, leaving its mixins unresolved
+fn fix_spec(ui: &Ui, spec: &PathBuf, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read_to_string(spec)
+        .map_err(|e| io::Error::new(e.kind(), format!("Error reading {}: {e}", spec.display())))?;
+    let mut actor: Actor = serde_json::from_str(&contents).fail_at(Failure::Parse)?;
+    let fixes = fix(&mut actor);
+    for fix in &fixes {
+        println!("{}: {} [{}]", display_path(spec), fix.description, fix.lint);
+    }
+    if fixes.is_empty() {
+        ui.status(
+            "Checked",
+            format_args!("{}, nothing to fix", spec.display()),
+        );
+    } else if dry_run {
+        ui.status(
+            "Would fix",
+            format_args!("{} finding(s) in {}", fixes.len(), spec.display()),
+        );
+    } else {
+        fs::write(spec, serde_json::to_string_pretty(&actor)? + "\n")?;
+        ui.status(
+            "Fixed",
+            format_args!("{} finding(s) in {}", fixes.len(), spec.display()),
+        );
+    }
+    Ok(())
+}
+
+fn check(
+    ui: &Ui,
+    spec: &PathBuf,
+    env: Option<&str>,
+    restricted: bool,
+    warnings: &WarningArgs,
+) -> Result<(), Box<dyn Error>> {
+    let (actor, mut options) = load(spec, env)?;
+    if restricted {
+        options = options.restricted(true);
+    }
+    // Lints read the spec as written, before the generator adds its own fields
+    let mut diagnostics = lint(&actor);
+    diagnostics.extend(analyze(actor, options)?.diagnostics());
+
+    for diagnostic in &diagnostics {
+        ui.diagnostic(spec, diagnostic);
+    }
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    if errors > 0 {
+        return Err(Failed::new(
+            Failure::Validation,
+            format!("{} failed with {errors} error(s)", display_path(spec)),
+        )
+        .into());
+    }
+    Ok(warnings.check(spec, &diagnostics)?)
+}
+
+fn man(out_dir: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    match out_dir {
+        Some(dir) => {
+            fs::create_dir_all(&dir)?;
+            clap_mangen::generate_to(Args::command(), &dir).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Error writing man pages into {}: {e}", dir.display()),
+                )
+            })?;
+        }
+        None => clap_mangen::Man::new(Args::command()).render(&mut io::stdout())?,
+    }
+    Ok(())
+}
+
+/// Displays a path the same way on every platform, with `/` between components, so output
+/// quoting specs is stable across Windows and Unix
+fn display_path(path: &Path) -> String {
+    let display = path.display().to_string();
+    if cfg!(windows) {
+        display.replace('\\', "/")
+    } else {
+        display
+    }
+}
 
-    let actor = Actor::from_json_file(&args.json_file)?;
-    create::create_module(actor)
+/// Prints the generator's diagnostics about `spec` and returns them
+fn report_diagnostics(ui: &Ui, spec: &Path, generator: &ActorGenerator) -> Vec<Diagnostic> {
+    let diagnostics = generator.diagnostics();
+    for diagnostic in &diagnostics {
+        ui.diagnostic(spec, diagnostic);
+    }
+    diagnostics
 }
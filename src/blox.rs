@@ -1,7 +1,16 @@
 pub mod actor;
+pub mod bridge;
+pub mod child;
 pub mod component;
+pub mod constant;
 pub mod enums;
 pub mod ext_state;
+pub mod lifecycle;
 pub mod message_handlers;
 pub mod message_set;
+pub mod mixin;
+#[cfg(feature = "std")]
+pub mod overlay;
+pub mod pool;
+pub mod schedule;
 pub mod state;
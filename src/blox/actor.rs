@@ -1,32 +1,208 @@
-use std::{error::Error, fs::OpenOptions, path::PathBuf};
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::{
+    error::Error,
+    fs::{self, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 
 use super::{
+    bridge::Bridge,
+    child::Child,
     component::Component,
-    ext_state::ExtState,
-    message_handlers::{MessageHandle, MessageHandles, MessageReceiver, MessageReceivers},
+    constant::Constant,
+    ext_state::{ExtState, History, Stash},
+    lifecycle::Lifecycle,
+    message_handlers::{Broker, HandleNaming, MessageHandles, QueueSampling, Relay, Sink},
     message_set::MessageSet,
+    pool::{Pool, Shard},
+    schedule::Schedule,
     state::States,
 };
-use serde_json;
+
+use crate::alloc_prelude::*;
+use crate::ident::{is_ident, is_module_ident};
+use crate::lint::LintLevel;
+#[cfg(feature = "codegen")]
+use crate::{
+    Link,
+    blox::{enums::EnumVariant, message_handlers::MessageReceiver},
+    field::Field,
+};
+#[cfg(feature = "std")]
+use crate::{mixin::Mixin, overlay};
+
+/// Characters rejected in generated file names on at least one supported platform
+const INVALID_PATH_CHARS: &[char] = &['/', '\\', '<', '>', ':', '"', '|', '?', '*'];
+
+/// Device names Windows reserves regardless of extension
+const RESERVED_WINDOWS_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Receiver the generated runtime answers health probes on
+pub const HEALTH_RECEIVER: &str = "health_rx";
+
+/// Payload type of health probes
+pub const HEALTH_CHECK_TYPE: &str = "HealthCheck";
+
+/// Message set variant the runtime notifies the states of an overloaded queue with
+pub const OVERLOADED_VARIANT: &str = "Overloaded";
+
+/// Payload of the overload notification
+pub const QUEUE_OVERLOAD_TYPE: &str = "QueueOverload";
+
+/// Extended state field holding the sender of the actor's own queue
+pub const SELF_HANDLE: &str = "self_handle";
+
+/// Receiver the runtime drains the actor's own queue from
+pub const SELF_RECEIVER: &str = "self_rx";
+
+/// Payload wrapper carrying the sequence number of sequenced receivers
+pub const SEQUENCED_TYPE: &str = "Sequenced";
+
+/// Payload wrapper carrying the correlation ID of correlated actors
+pub const CORRELATED_TYPE: &str = "Correlated";
+
+/// Extended state field buffering stashed messages
+pub const STASH_FIELD: &str = "stashed";
+
+/// Extended state field holding the stashed messages handed back to the state just entered,
+/// which the runtime dispatches before reading its channels again
+pub const UNSTASHED_FIELD: &str = "unstashed";
+
+/// Extended state field holding the recorded history of handled messages
+pub const HISTORY_FIELD: &str = "history";
+
+/// Extended state field holding the store the current state is persisted to
+pub const STATE_STORE_FIELD: &str = "state_store";
+
+/// Extended state field the runtime saves the extended state through after every message,
+/// for the supervisor to restart the actor from
+pub const RESTART_SNAPSHOT_FIELD: &str = "restart_snapshot";
+
+/// Directory the actor module is generated under
+#[cfg(feature = "std")]
+pub type OutputPath = PathBuf;
+
+/// Directory the actor module is generated under, as the spec spells it
+#[cfg(not(feature = "std"))]
+pub type OutputPath = String;
+
+/// Checks that an ident can be used as a single file or directory name on every platform
+pub fn validate_path_component(name: &str) -> Result<(), String> {
+    let reason = if name.is_empty() {
+        Some("is empty")
+    } else if name == "." || name == ".." {
+        Some("refers to a relative directory")
+    } else if name.contains(INVALID_PATH_CHARS) || name.chars().any(char::is_control) {
+        Some("contains a path separator or invalid character")
+    } else if name.ends_with(['.', ' ']) {
+        Some("ends with a dot or space")
+    } else if RESERVED_WINDOWS_NAMES.contains(&name.split('.').next().unwrap_or_default()) {
+        Some("is a reserved name on Windows")
+    } else {
+        None
+    };
+
+    reason.map_or(Ok(()), |reason| {
+        Err(format!(
+            "'{name}' cannot be used as a file name: it {reason}"
+        ))
+    })
+}
+
+fn is_unset(path: &OutputPath) -> bool {
+    *path == OutputPath::default()
+}
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
 #[serde(rename = "actor")]
 pub struct Actor {
     pub ident: String,
-    pub path: PathBuf,
+    /// Version of the spec, which extended state migrations are named after
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    /// Directory the actor is generated under; may be left to the project's `bloxml.toml`
+    #[serde(default, skip_serializing_if = "is_unset")]
+    pub path: OutputPath,
+    /// Domain the actor is nested under, e.g. `payments` or `billing::refunds`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Type wrapped by the generated `{ident}Id`, `u16` unless overridden
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_type: Option<String>,
+    /// Generates a `pool` module routing messages across several instances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool: Option<Pool>,
+    /// Generates a `shard` module dispatching messages to one instance per key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shard: Option<Shard>,
+    /// Code run before the actor starts and after it stops
+    #[serde(default, skip_serializing_if = "Lifecycle::is_empty")]
+    pub lifecycle: Lifecycle,
+    /// Generates a `health` module and answers health probes in the runtime
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub health_check: bool,
+    /// Gives the actor a queue of its own, which states post to through `defer`
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub self_messages: bool,
+    /// Lets states stash messages they can't handle yet, replayed on the next state entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stash: Option<Stash>,
+    /// Records the last messages handled and the states they arrived in, dumped when
+    /// handling one panics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<History>,
+    /// Samples queue depths and the message loop's lag on an interval
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_sampling: Option<QueueSampling>,
+    /// Tags every payload with a correlation ID, propagated to emits and tracing spans
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub correlation: bool,
+    /// Code run when a sequenced receiver skips ahead, with `expected` and `received` in scope
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence_gap: Option<String>,
+    /// Generates a `bridge` module exposing the actor through a service protocol
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bridge: Option<Bridge>,
+    /// Kafka topics variant payloads are published to, generating a `sinks` module
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sinks: Vec<Sink>,
+    /// Messages sent to the actor on cron schedules, generating a `scheduler` module
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<Schedule>,
+    /// Variants whose payloads are sent on through a handle instead of being dispatched
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relays: Vec<Relay>,
+    /// `const` and `static` items declared in the generated modules
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constants: Vec<Constant>,
+    /// Actors this one spawns an instance of when it starts, generating a `children` module
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Child>,
+    /// Levels lints are reported at by `bloxml check`, keyed by lint name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lints: BTreeMap<String, LintLevel>,
+    /// Infers the handles and receivers from the message set and the transitions' emits,
+    /// naming them with these templates; see [`Actor::infer_channels`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub infer_channels: Option<HandleNaming>,
     pub component: Component,
 }
 
 impl Actor {
     pub fn new<P, S>(ident: S, path: P, states: States, message_set: Option<MessageSet>) -> Self
     where
-        P: Into<PathBuf>,
+        P: Into<OutputPath>,
         S: Into<String>,
     {
         let ident: String = ident.into();
-        let (handles, receivers) = Self::create_handles(&ident, &message_set);
+        let (handles, receivers) = HandleNaming::default().derive(&ident, message_set.as_ref());
         let component = Component::new(
             format!("{ident}Components"),
             handles,
@@ -38,57 +214,826 @@ impl Actor {
 
         Self {
             ident,
+            version: None,
             path: path.into(),
+            group: None,
+            id_type: None,
+            pool: None,
+            shard: None,
+            lifecycle: Lifecycle::default(),
+            health_check: false,
+            self_messages: false,
+            stash: None,
+            history: None,
+            queue_sampling: None,
+            correlation: false,
+            sequence_gap: None,
+            bridge: None,
+            sinks: Vec::new(),
+            schedules: Vec::new(),
+            relays: Vec::new(),
+            constants: Vec::new(),
+            children: Vec::new(),
+            lints: BTreeMap::new(),
+            infer_channels: None,
             component,
         }
     }
 
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Nests the generated module under a domain group
+    pub fn with_group<S: Into<String>>(mut self, group: S) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    /// Overrides the type wrapped by the generated ID newtype
+    pub fn with_id_type<S: Into<String>>(mut self, id_type: S) -> Self {
+        self.id_type = Some(id_type.into());
+        self
+    }
+
+    /// Generates a pool router for the actor
+    pub fn with_pool(mut self, pool: Pool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Generates a key-sharded dispatcher for the actor
+    pub fn with_shard(mut self, shard: Shard) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    /// Sets the code run before the actor starts and after it stops
+    pub fn with_lifecycle(mut self, lifecycle: Lifecycle) -> Self {
+        self.lifecycle = lifecycle;
+        self
+    }
+
+    /// Generates a health probe for the actor
+    pub fn with_health_check(mut self) -> Self {
+        self.health_check = true;
+        self
+    }
+
+    /// Gives the actor a queue of its own for deferred work
+    pub fn with_self_messages(mut self) -> Self {
+        self.self_messages = true;
+        self
+    }
+
+    /// Lets states stash messages until the next state entry
+    pub fn with_stash(mut self, stash: Stash) -> Self {
+        self.stash = Some(stash);
+        self
+    }
+
+    /// Records the last messages handled, for postmortem debugging
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Derives the handles and receivers from the message set again, named with `naming`
+    /// instead of the default templates
+    pub fn with_handle_naming(mut self, naming: &HandleNaming) -> Self {
+        let (handles, receivers) = naming.derive(&self.ident, self.component.message_set.as_ref());
+        self.component.message_handles = handles;
+        self.component.message_receivers = receivers;
+        self
+    }
+
+    /// Infers the handles and receivers when generating, instead of reading them from the spec
+    pub fn with_inferred_channels(mut self, naming: HandleNaming) -> Self {
+        self.infer_channels = Some(naming);
+        self
+    }
+
+    /// Replaces the handles and receivers with those inferred from the spec, if it asks for it
+    ///
+    /// Every variant of the message set gets a receiver, since the runtime pairs them by
+    /// position, and every handle a transition emits through is one of the handles derived
+    /// from the message set, named after the variant whose payload it sends. The handles and
+    /// receivers the spec declares override the inferred ones of the same name, and declared
+    /// handles nothing emits through are kept for relays and hand-written code.
+    pub fn infer_channels(&mut self) -> Result<(), String> {
+        let Some(naming) = &self.infer_channels else {
+            return Ok(());
+        };
+        let component = &mut self.component;
+        let (derived, mut receivers) = naming.derive(&self.ident, component.message_set.as_ref());
+        let declared_handles = core::mem::take(&mut component.message_handles);
+        let declared_receivers = core::mem::take(&mut component.message_receivers);
+
+        for declared in declared_receivers.receivers {
+            let Some(receiver) = receivers
+                .receivers
+                .iter_mut()
+                .find(|receiver| receiver.ident == declared.ident)
+            else {
+                return Err(format!(
+                    "Receiver '{}' is not one inferred from the message set",
+                    declared.ident
+                ));
+            };
+            *receiver = declared;
+        }
+        if !declared_receivers.ident.is_empty() {
+            receivers.ident = declared_receivers.ident;
+        }
+
+        let mut handles = MessageHandles::new(if declared_handles.ident.is_empty() {
+            derived.ident.clone()
+        } else {
+            declared_handles.ident.clone()
+        });
+        for state in &component.states.states {
+            for emit in state.transitions.iter().flat_map(|t| &t.emit) {
+                if handles.get_handle(&emit.handle).is_some() {
+                    continue;
+                }
+                let handle = declared_handles
+                    .get_handle(&emit.handle)
+                    .or_else(|| derived.get_handle(&emit.handle))
+                    .ok_or_else(|| {
+                        format!(
+                            "Handle '{}' emitted through in state '{}' sends no message set payload, so it must be declared",
+                            emit.handle, state.ident
+                        )
+                    })?;
+                handles.add_handle(handle.clone());
+            }
+        }
+        for handle in declared_handles.handles {
+            if handles.get_handle(&handle.ident).is_none() {
+                handles.add_handle(handle);
+            }
+        }
+
+        component.message_handles = handles;
+        component.message_receivers = receivers;
+        Ok(())
+    }
+
+    /// Samples queue depths and lag, notifying the states when a queue is overloaded
+    pub fn with_queue_sampling(mut self, queue_sampling: QueueSampling) -> Self {
+        self.queue_sampling = Some(queue_sampling);
+        self
+    }
+
+    /// Whether the runtime sends the states `Overloaded` messages
+    pub fn notifies_overload(&self) -> bool {
+        self.component.message_set.is_some()
+            && self
+                .queue_sampling
+                .as_ref()
+                .is_some_and(|sampling| sampling.overload_threshold.is_some())
+    }
+
+    /// Whether the actor has a queue of its own, which deferred messages go through
+    pub fn has_self_queue(&self) -> bool {
+        self.self_messages
+    }
+
+    /// Whether the bridge, a source or a sink propagates OpenTelemetry trace context
+    pub fn traces(&self) -> bool {
+        self.bridge.as_ref().is_some_and(Bridge::trace_context)
+            || self.sinks.iter().any(|sink| sink.trace_context)
+            || self
+                .component
+                .message_receivers
+                .receivers
+                .iter()
+                .any(|receiver| receiver.source.as_ref().is_some_and(|s| s.trace_context))
+    }
+
+    /// Whether any state, message variant or field of the spec is deprecated
+    pub fn has_deprecations(&self) -> bool {
+        let component = &self.component;
+        let states = &component.states.states;
+        states.iter().any(|state| {
+            state.deprecated.is_some()
+                || state.fields.iter().any(|f| f.deprecation().is_some())
+                || state
+                    .variants
+                    .iter()
+                    .flatten()
+                    .any(|v| v.deprecated.is_some())
+        }) || component
+            .message_set
+            .iter()
+            .flat_map(|set| &set.get().variants)
+            .any(|v| v.deprecated.is_some())
+            || component
+                .ext_state
+                .fields()
+                .iter()
+                .any(|f| f.deprecation().is_some())
+    }
+
+    /// Tags every payload with a correlation ID
+    pub fn with_correlation(mut self) -> Self {
+        self.correlation = true;
+        self
+    }
+
+    /// Sets the code run when a sequenced receiver skips ahead
+    pub fn with_sequence_gap<S: Into<String>>(mut self, code: S) -> Self {
+        self.sequence_gap = Some(code.into());
+        self
+    }
+
+    /// Exposes the actor through a service protocol
+    pub fn with_bridge(mut self, bridge: Bridge) -> Self {
+        self.bridge = Some(bridge);
+        self
+    }
+
+    /// Publishes variant payloads to Kafka topics
+    pub fn with_sinks(mut self, sinks: Vec<Sink>) -> Self {
+        self.sinks = sinks;
+        self
+    }
+
+    /// Sends variant payloads on through handles instead of dispatching them
+    pub fn with_relays(mut self, relays: Vec<Relay>) -> Self {
+        self.relays = relays;
+        self
+    }
+
+    /// Declares a `const` or `static` item in one of the generated modules
+    pub fn with_constant(mut self, constant: Constant) -> Self {
+        self.constants.push(constant);
+        self
+    }
+
+    /// Spawns an instance of another actor when this one starts
+    pub fn with_child(mut self, child: Child) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Sends messages to the actor on cron schedules
+    pub fn with_schedules(mut self, schedules: Vec<Schedule>) -> Self {
+        self.schedules = schedules;
+        self
+    }
+
+    /// Overrides the level a lint is reported at
+    pub fn with_lint<S: Into<String>>(mut self, lint: S, level: LintLevel) -> Self {
+        self.lints.insert(lint.into(), level);
+        self
+    }
+
+    /// Checks that every sink publishes a known variant carrying one payload, and no variant
+    /// is published twice, as its publish function is named after it
+    pub fn validate_sinks(&self) -> Result<(), String> {
+        let variants = self
+            .component
+            .message_set
+            .as_ref()
+            .map(|ms| ms.get().variants.as_slice())
+            .unwrap_or_default();
+        for (i, sink) in self.sinks.iter().enumerate() {
+            if let Some(other) = self.sinks[..i]
+                .iter()
+                .find(|s| s.variant.to_lowercase() == sink.variant.to_lowercase())
+            {
+                return Err(format!(
+                    "Variant '{}' is published to both '{}' and '{}'",
+                    sink.variant, other.topic, sink.topic
+                ));
+            }
+            match variants.iter().find(|v| v.ident == sink.variant) {
+                None => {
+                    return Err(format!(
+                        "Sink for topic '{}' publishes unknown variant '{}'",
+                        sink.topic, sink.variant
+                    ));
+                }
+                Some(variant) if variant.args.len() != 1 => {
+                    return Err(format!(
+                        "Variant '{}' published to topic '{}' must carry exactly one payload",
+                        sink.variant, sink.topic
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that no subject or topic feeds two receivers, and that MQTT topics are valid
+    /// filters
+    ///
+    /// In a filter, `+` stands for one whole level and `#`, ending it, for any number of them.
+    pub fn validate_sources(&self) -> Result<(), String> {
+        let sources = self
+            .component
+            .message_receivers
+            .receivers
+            .iter()
+            .filter_map(|receiver| receiver.source.as_ref())
+            .collect::<Vec<_>>();
+        for (i, source) in sources.iter().enumerate() {
+            let broker = source.broker.name();
+            if sources[..i]
+                .iter()
+                .any(|s| s.broker == source.broker && s.subject == source.subject)
+            {
+                return Err(format!(
+                    "'{}' on {broker} feeds more than one receiver",
+                    source.subject
+                ));
+            }
+            let levels = source.subject.split('/').collect::<Vec<_>>();
+            let valid_filter = levels.iter().enumerate().all(|(i, level)| {
+                (!level.contains(['+', '#']) || *level == "+" || *level == "#")
+                    && (*level != "#" || i == levels.len() - 1)
+            });
+            if source.broker == Broker::Mqtt && !valid_filter {
+                return Err(format!(
+                    "{broker} topic '{}' is not a valid topic filter",
+                    source.subject
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that every relay sends a received variant through a known handle, as a payload
+    /// the handle takes unless it is transformed
+    pub fn validate_relays(&self) -> Result<(), String> {
+        for (i, relay) in self.relays.iter().enumerate() {
+            if self.relays[..i].iter().any(|r| r.variant == relay.variant)
+                || self
+                    .children
+                    .iter()
+                    .any(|child| child.forward_for(&relay.variant).is_some())
+            {
+                return Err(format!(
+                    "Variant '{}' is handed on more than once",
+                    relay.variant
+                ));
+            }
+            let Some(receiver) = self.component.receiver_for_variant(&relay.variant) else {
+                return Err(format!(
+                    "Relay of variant '{}' has no receiver to relay from",
+                    relay.variant
+                ));
+            };
+            let Some(handle) = self.component.message_handles.get_handle(&relay.handle) else {
+                return Err(format!(
+                    "Variant '{}' is relayed through unknown handle '{}'",
+                    relay.variant, relay.handle
+                ));
+            };
+            if relay.transform.is_none() && handle.message_type != receiver.message_type {
+                return Err(format!(
+                    "Variant '{}' carries '{}', but handle '{}' sends '{}'; relay it with a `transform`",
+                    relay.variant, receiver.message_type, relay.handle, handle.message_type
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks every constant and that no two of them share a name
+    pub fn validate_constants(&self) -> Result<(), String> {
+        for (i, constant) in self.constants.iter().enumerate() {
+            if self.constants[..i]
+                .iter()
+                .any(|c| c.ident == constant.ident)
+            {
+                return Err(format!("Constant '{}' is declared twice", constant.ident));
+            }
+            constant.validate(&self.component)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that every child's spec is loaded, its ident unique and its forwarding rules
+    /// lead to channels taking the forwarded payload
+    pub fn validate_children(&self) -> Result<(), String> {
+        for (i, child) in self.children.iter().enumerate() {
+            if self.children[..i].iter().any(|c| c.ident == child.ident) {
+                return Err(format!("Child '{}' is declared twice", child.ident));
+            }
+            child.validate(&self.component)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that trace context has correlated payloads to travel in and headers to travel on
+    pub fn validate_trace_context(&self) -> Result<(), String> {
+        if !self.traces() {
+            return Ok(());
+        }
+        if !self.correlation {
+            return Err(
+                "Propagating trace context requires `correlation`, whose payloads carry it"
+                    .to_string(),
+            );
+        }
+        for receiver in &self.component.message_receivers.receivers {
+            let Some(source) = receiver.source.as_ref().filter(|s| s.trace_context) else {
+                continue;
+            };
+            if source.broker == Broker::Mqtt {
+                return Err(format!(
+                    "MQTT topic '{}' has no headers to read trace context from",
+                    source.subject
+                ));
+            }
+            if receiver.sequenced {
+                return Err(format!(
+                    "Receiver '{}' is sequenced, so trace context can't be read from its \
+                     source's headers",
+                    receiver.ident
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Adds the receiver health probes arrive on, if the actor has a health probe
+    pub(crate) fn add_health_receiver(&mut self) {
+        let receivers = &mut self.component.message_receivers;
+        if self.health_check && receivers.get_receiver(HEALTH_RECEIVER).is_none() {
+            receivers.add_receiver(MessageReceiver::new(HEALTH_RECEIVER, HEALTH_CHECK_TYPE));
+        }
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Adds the sender of the actor's own queue and the stash buffers to its extended state
+    pub(crate) fn add_self_handle(&mut self) {
+        let Some(message_set) = &self.component.message_set else {
+            return;
+        };
+        let message_set = &message_set.get().ident;
+        let has_self_queue = self.has_self_queue();
+        let ext_state = &mut self.component.ext_state;
+        let has_field =
+            |ext_state: &ExtState, ident| ext_state.fields().iter().any(|f| f.ident() == ident);
+
+        if has_self_queue && !has_field(ext_state, SELF_HANDLE) {
+            let field = Field::new(SELF_HANDLE, format!("UnboundedSender<{message_set}>"));
+            ext_state.add_field(field.clone());
+            ext_state.add_init_arg(field);
+        }
+        // The stash starts out empty, so it is not an init arg
+        for ident in [STASH_FIELD, UNSTASHED_FIELD] {
+            if self.stash.is_some() && !has_field(ext_state, ident) {
+                ext_state.add_field(Field::new(ident, format!("VecDeque<{message_set}>")));
+            }
+        }
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Adds the fields holding the inboxes of the children, set when they are spawned
+    pub(crate) fn add_child_inboxes(&mut self) {
+        let ext_state = &mut self.component.ext_state;
+        for child in &self.children {
+            let Ok(inbox_type) = child.inbox_type() else {
+                continue;
+            };
+            if ext_state.fields().iter().all(|f| f.ident() != child.ident) {
+                ext_state.add_field(Field::new(&child.ident, inbox_type).with_value("None"));
+            }
+        }
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Adds the ring buffer of recorded messages to the extended state
+    pub(crate) fn add_history(&mut self) {
+        let ext_state = &mut self.component.ext_state;
+        // The history starts out empty, so it is not an init arg
+        if self.history.is_some()
+            && ext_state
+                .fields()
+                .iter()
+                .all(|f| f.ident() != HISTORY_FIELD)
+        {
+            ext_state.add_field(Field::new(
+                HISTORY_FIELD,
+                "VecDeque<(SystemTime, String, String)>",
+            ));
+        }
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Adds the handle to the supervisor's snapshot of the extended state, if restarts keep it
+    pub(crate) fn add_restart_snapshot(&mut self) {
+        let ext_state = &mut self.component.ext_state;
+        // The supervisor points it at its snapshot, so it is not an init arg
+        if self.lifecycle.persists_ext_state()
+            && ext_state
+                .fields()
+                .iter()
+                .all(|f| f.ident() != RESTART_SNAPSHOT_FIELD)
+        {
+            let ty = format!("Weak<Mutex<Option<{}>>>", ext_state.ident());
+            ext_state.add_field(Field::new(RESTART_SNAPSHOT_FIELD, ty));
+        }
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Adds the `Overloaded` variant to the message set, if the runtime sends it
+    ///
+    /// The runtime dispatches it directly, so it has no receiver and is added after the
+    /// receivers are paired with the variants.
+    pub(crate) fn add_overloaded_variant(&mut self) {
+        if !self.notifies_overload() {
+            return;
+        }
+        let Some(message_set) = &mut self.component.message_set else {
+            return;
+        };
+        let variants = &mut message_set.def.variants;
+        if variants.iter().all(|v| v.ident != OVERLOADED_VARIANT) {
+            variants.push(
+                EnumVariant::new(OVERLOADED_VARIANT, vec![Link::new(QUEUE_OVERLOAD_TYPE)])
+                    .with_doc("A receiver's queue grew past the overload threshold"),
+            );
+        }
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Adds the store the current state is persisted to as an extended state init arg
+    pub(crate) fn add_state_store(&mut self) {
+        let ext_state = &mut self.component.ext_state;
+        if !self.lifecycle.persist_state
+            || ext_state
+                .fields()
+                .iter()
+                .any(|f| f.ident() == STATE_STORE_FIELD)
+        {
+            return;
+        }
+        let field = Field::new(STATE_STORE_FIELD, format!("{}StateStoreRef", self.ident));
+        ext_state.add_field(field.clone());
+        ext_state.add_init_arg(field);
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Wraps payloads in `Correlated` and those of sequenced receivers in `Sequenced`
+    ///
+    /// Receivers, the handles sending the same type and the message-set variants are all
+    /// rewritten, so every generated module agrees on the channel types.
+    pub(crate) fn wrap_message_payloads(&mut self) {
+        // Correlation IDs belong to the payload, sequence numbers to the channel carrying it
+        if self.correlation {
+            self.wrap_payloads(CORRELATED_TYPE, |_| true);
+        }
+        self.wrap_payloads(SEQUENCED_TYPE, |receiver| receiver.sequenced);
+    }
+
+    #[cfg(feature = "codegen")]
+    fn wrap_payloads(&mut self, wrapper: &str, wrap: impl Fn(&MessageReceiver) -> bool) {
+        let component = &mut self.component;
+        let Some(message_set) = &mut component.message_set else {
+            return;
+        };
+        let wrapper = format!("{wrapper}<");
+
+        for (receiver, variant) in component
+            .message_receivers
+            .receivers
+            .iter_mut()
+            .zip(message_set.def.variants.iter_mut())
+        {
+            if !wrap(receiver) || receiver.message_type.contains(&wrapper) {
+                continue;
+            }
+            let payload = core::mem::take(&mut receiver.message_type);
+            for handle in &mut component.message_handles.handles {
+                if handle.message_type == payload {
+                    handle.message_type = format!("{wrapper}{payload}>");
+                }
+            }
+            if let Some(arg) = variant.args.first_mut() {
+                *arg = Link::new(format!("{wrapper}{arg}>"));
+            }
+            receiver.message_type = format!("{wrapper}{payload}>");
+        }
+    }
+
+    /// Name of the generated ID newtype, e.g. `RefundId`
+    pub fn id_ident(&self) -> String {
+        format!("{}Id", self.ident)
+    }
+
+    /// Type wrapped by the generated ID newtype
+    pub fn id_type(&self) -> &str {
+        self.id_type.as_deref().unwrap_or("u16")
+    }
+
+    /// Module segments of the group the actor is nested under, outermost first
+    pub fn group_segments(&self) -> Vec<&str> {
+        self.group
+            .as_deref()
+            .map(|group| group.split("::").collect())
+            .unwrap_or_default()
+    }
+
+    /// Module path of the actor relative to the crate root, e.g. `payments::refund`
+    pub fn module_path(&self) -> String {
+        self.group_segments()
+            .into_iter()
+            .map(str::to_string)
+            .chain(core::iter::once(self.ident.to_lowercase()))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    /// Checks that every name the spec gives a generated item is a Rust identifier
+    pub fn validate_idents(&self) -> Result<(), String> {
+        let component = &self.component;
+        let ext_state = &component.ext_state;
+        let states = &component.states;
+        let mut idents = Vec::from([
+            ("actor", self.ident.as_str()),
+            ("state enum", states.state_enum.get().ident.as_str()),
+        ]);
+        idents.extend(states.states.iter().map(|s| ("state", s.ident.as_str())));
+        if let Some(message_set) = &component.message_set {
+            let def = message_set.get();
+            idents.push(("message set", def.ident.as_str()));
+            idents.extend(def.variants.iter().map(|v| ("variant", v.ident.as_str())));
+        }
+        idents.push(("extended state", ext_state.ident()));
+        idents.extend(ext_state.fields().iter().map(|f| ("field", f.ident())));
+        idents.extend(ext_state.methods().iter().map(|m| ("method", m.ident())));
+        let init_args = ext_state.init_args();
+        if !init_args.ident.is_empty() {
+            idents.push(("init args", init_args.ident.as_str()));
+        }
+        idents.extend(init_args.fields.iter().map(|f| ("init arg", f.ident())));
+        idents.extend(
+            component
+                .message_receivers
+                .receivers
+                .iter()
+                .map(|r| ("receiver", r.ident.as_str())),
+        );
+        idents.extend(
+            component
+                .message_handles
+                .handles
+                .iter()
+                .map(|h| ("handle", h.ident.as_str())),
+        );
+
+        if let Some((kind, ident)) = idents.into_iter().find(|(_, ident)| !is_ident(ident)) {
+            return Err(format!(
+                "The {kind} name '{ident}' is not a valid Rust identifier"
+            ));
+        }
+
+        // The group, the actor and its states name generated modules too
+        let mut modules = self
+            .group_segments()
+            .into_iter()
+            .map(|segment| ("group", segment))
+            .collect::<Vec<_>>();
+        modules.push(("actor", self.ident.as_str()));
+        modules.extend(states.states.iter().map(|s| ("state", s.ident.as_str())));
+        match modules
+            .into_iter()
+            .find(|(_, ident)| !is_module_ident(&ident.to_lowercase()))
+        {
+            Some((kind, ident)) => Err(format!(
+                "The {kind} name '{ident}' names a generated module, so it must be an ASCII identifier"
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks every ident that becomes a generated file or directory name
+    pub fn validate_paths(&self) -> Result<(), String> {
+        self.group_segments()
+            .into_iter()
+            .try_for_each(validate_path_component)?;
+        validate_path_component(&self.ident.to_lowercase())?;
+        self.component
+            .states
+            .states
+            .iter()
+            .try_for_each(|state| validate_path_component(&state.ident.to_lowercase()))
+    }
+
+    #[cfg(feature = "std")]
     pub fn create_mod_path(&self) -> PathBuf {
-        self.path.join(self.ident.to_lowercase())
+        self.group_segments()
+            .into_iter()
+            .fold(self.path.clone(), |path, segment| path.join(segment))
+            .join(self.ident.to_lowercase())
     }
 
+    #[cfg(feature = "std")]
     pub fn create_states_path(&self) -> PathBuf {
         self.create_mod_path().join("states")
     }
 
+    #[cfg(feature = "std")]
     pub fn from_json_file(path: &PathBuf) -> Result<Self, Box<dyn Error>> {
         let file = OpenOptions::new()
             .read(true)
             .write(false)
             .create(false)
-            .open(path)?;
-        serde_json::from_reader(file).map_err(From::from)
+            .open(path)
+            .map_err(|e| {
+                io::Error::new(e.kind(), format!("Error reading {}: {e}", path.display()))
+            })?;
+        Self::from_json_value(path, serde_json::from_reader(file)?)
     }
 
-    fn create_handles(
-        ident: &str,
-        message_set: &Option<MessageSet>,
-    ) -> (MessageHandles, MessageReceivers) {
-        let mut handles = MessageHandles::new(format!("{ident}Handles"));
-        let mut receivers = MessageReceivers::new(format!("{ident}Receivers"));
-
-        let Some(message_set) = message_set else {
-            return (handles, receivers);
+    /// Loads a spec with the overlay for `env` merged in, like `actor.prod.json` for `actor.json`
+    ///
+    /// A missing overlay is an error, so a misspelt `env` doesn't go unnoticed.
+    #[cfg(feature = "std")]
+    pub fn from_json_file_for_env(path: &Path, env: &str) -> Result<Self, Box<dyn Error>> {
+        let read = |path: &Path| -> Result<serde_json::Value, Box<dyn Error>> {
+            let contents = fs::read_to_string(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("Error reading {}: {e}", path.display()))
+            })?;
+            Ok(serde_json::from_str(&contents)?)
         };
+        let mut spec = read(path)?;
+        let overlay_path = overlay::overlay_path(path, env);
+        if !overlay_path.is_file() {
+            return Err(format!(
+                "{} has no overlay for '{env}': {} doesn't exist",
+                path.display(),
+                overlay_path.display()
+            )
+            .into());
+        }
+        overlay::merge(&mut spec, read(&overlay_path)?)
+            .map_err(|e| format!("Error in {}: {e}", overlay_path.display()))?;
+        Self::from_json_value(path, spec)
+    }
 
-        for variant in &message_set.get().variants {
-            let message_type = &variant
-                .args
-                .first()
-                .unwrap()
-                .as_ref()
-                .split("::")
-                .last()
-                .unwrap()
-                .to_string();
-            let handle_name = format!("{}_handle", message_type.to_lowercase());
-            handles.add_handle(MessageHandle::new(handle_name, message_type));
+    #[cfg(feature = "std")]
+    fn from_json_value(path: &Path, spec: serde_json::Value) -> Result<Self, Box<dyn Error>> {
+        let mut actor: Self = serde_json::from_value(spec)?;
+        actor.resolve_mixins(path.parent().unwrap_or(Path::new("")))?;
+        actor.resolve_children(path, &mut Vec::new())?;
+        Ok(actor)
+    }
 
-            let receiver_name = format!("{}_rx", message_type.to_lowercase());
-            receivers.add_receiver(MessageReceiver::new(receiver_name, message_type));
+    /// Loads the specs of the children relative to `path`, the spec of this actor, and theirs
+    /// in turn; `parents` are the specs above it, which no child may be
+    #[cfg(feature = "std")]
+    fn resolve_children(
+        &mut self,
+        path: &Path,
+        parents: &mut Vec<PathBuf>,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.children.is_empty() {
+            return Ok(());
+        }
+        let path = fs::canonicalize(path)?;
+        if parents.contains(&path) {
+            return Err(format!("{} is its own descendant", path.display()).into());
         }
+        parents.push(path);
+        let base_dir = parents[parents.len() - 1]
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        for child in &mut self.children {
+            let spec = base_dir.join(&child.spec);
+            let contents = fs::read_to_string(&spec).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Error reading child {}: {e}", spec.display()),
+                )
+            })?;
+            let mut actor: Self = serde_json::from_str(&contents)
+                .map_err(|e| format!("Error in child {}: {e}", spec.display()))?;
+            actor.resolve_mixins(spec.parent().unwrap_or(Path::new("")))?;
+            actor.resolve_children(&spec, parents)?;
+            child.actor = Some(Box::new(actor));
+        }
+        parents.pop();
+        Ok(())
+    }
 
-        (handles, receivers)
+    /// Loads the ext state's mixins relative to `base_dir` and merges them in
+    #[cfg(feature = "std")]
+    pub fn resolve_mixins(&mut self, base_dir: &Path) -> Result<(), Box<dyn Error>> {
+        for mixin_path in self.component.ext_state.take_mixins() {
+            let mixin = Mixin::from_json_file(&base_dir.join(&mixin_path))?;
+            self.component.ext_state.apply_mixin(&mixin)?;
+        }
+        Ok(())
     }
 
     pub fn message_set_ident(&self) -> String {
@@ -99,3 +1044,289 @@ impl Actor {
             .unwrap_or_else(|| format!("{}MessageSet", self.ident))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Link,
+        blox::{
+            enums::{EnumDef, EnumVariant},
+            state::State,
+        },
+        tests::{create_test_actor, create_test_states},
+    };
+
+    #[test]
+    fn test_validate_path_component() {
+        assert!(validate_path_component("actor").is_ok());
+        assert!(validate_path_component("console").is_ok());
+
+        for name in [
+            "",
+            ".",
+            "..",
+            "a/b",
+            "a\\b",
+            "c:",
+            "con",
+            "lpt1.rs",
+            "trailing.",
+            "tab\t",
+        ] {
+            assert!(
+                validate_path_component(name).is_err(),
+                "'{name}' should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_paths() {
+        let mut actor = create_test_actor();
+        assert!(actor.validate_paths().is_ok());
+
+        actor.component.states.states.push(State::from("../Escape"));
+        assert!(actor.validate_paths().unwrap_err().contains("'../escape'"));
+
+        let mut actor = create_test_actor();
+        actor.ident = "Nul".to_string();
+        assert!(actor.validate_paths().is_err());
+    }
+
+    #[test]
+    fn test_validate_idents() {
+        // Names that stay names in the generated code may use Unicode
+        let mut actor = create_test_actor();
+        actor.component.message_handles.handles[0].ident = "größe_handle".to_string();
+        assert!(actor.validate_idents().is_ok());
+
+        // Those naming modules and files must be ASCII
+        actor.ident = "Zähler".to_string();
+        assert_eq!(
+            actor.validate_idents(),
+            Err("The actor name 'Zähler' names a generated module, so it must be an ASCII identifier".to_string())
+        );
+        actor.ident = "Counter".to_string();
+        actor.component.states.states.push(State::from("Überlauf"));
+        assert!(
+            actor
+                .validate_idents()
+                .unwrap_err()
+                .contains("state name 'Überlauf'")
+        );
+        actor.component.states.states.pop();
+        actor.component.states.states.push(State::from("Match"));
+        assert!(
+            actor
+                .validate_idents()
+                .unwrap_err()
+                .contains("state name 'Match'")
+        );
+        actor.component.states.states.pop();
+
+        actor.component.states.states.push(State::from("2Fast"));
+        assert_eq!(
+            actor.validate_idents(),
+            Err("The state name '2Fast' is not a valid Rust identifier".to_string())
+        );
+
+        let mut actor = create_test_actor();
+        actor.component.message_handles.handles[0].ident = "type".to_string();
+        assert!(
+            actor
+                .validate_idents()
+                .unwrap_err()
+                .contains("handle name 'type'")
+        );
+    }
+
+    #[test]
+    fn test_handle_naming() {
+        let message_set = MessageSet::new(EnumDef::new(
+            "ActorMessageSet",
+            vec![
+                EnumVariant::new("Tick", vec![]),
+                EnumVariant::new("Pair", vec![Link::new("a::Foo"), Link::new("Bar")]),
+                EnumVariant::new(
+                    "Runtime",
+                    vec![Link::new("TokioMessageHandle<TokioRuntime>")],
+                ),
+                EnumVariant::new("Single", vec![Link::new("Foo")]),
+                EnumVariant::new("Again", vec![Link::new("Foo")]),
+            ],
+        ));
+        let actor = Actor::new("Actor", "out", create_test_states(), Some(message_set));
+        let names = |actor: &Actor| {
+            actor
+                .component
+                .message_receivers
+                .receivers
+                .iter()
+                .map(|r| (r.ident.clone(), r.message_type.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&actor),
+            vec![
+                ("unit_rx".to_string(), "()".to_string()),
+                ("foo_bar_rx".to_string(), "(a::Foo, Bar)".to_string()),
+                (
+                    "tokiomessagehandle_rx".to_string(),
+                    "TokioMessageHandle<TokioRuntime>".to_string()
+                ),
+                ("foo_rx".to_string(), "Foo".to_string()),
+                ("foo_rx_4".to_string(), "Foo".to_string()),
+            ]
+        );
+        assert_eq!(
+            actor.component.message_handles.handles[4].ident,
+            "foo_handle_4"
+        );
+
+        let actor =
+            actor.with_handle_naming(&HandleNaming::new("{variant}_tx", "{index}_{variant}_rx"));
+        assert_eq!(
+            names(&actor)
+                .into_iter()
+                .map(|(ident, _)| ident)
+                .collect::<Vec<_>>(),
+            vec![
+                "_0_tick_rx",
+                "_1_pair_rx",
+                "_2_runtime_rx",
+                "_3_single_rx",
+                "_4_again_rx"
+            ]
+        );
+        assert_eq!(actor.component.message_handles.handles[0].ident, "tick_tx");
+
+        // Keywords are escaped the same way
+        let actor = actor.with_handle_naming(&HandleNaming::new("{variant}_tx", "type"));
+        assert_eq!(
+            names(&actor)[..2],
+            [
+                ("_type".to_string(), "()".to_string()),
+                ("_type_1".to_string(), "(a::Foo, Bar)".to_string())
+            ]
+        );
+        assert_eq!(actor.component.message_handles.ident, "ActorHandles");
+    }
+
+    #[test]
+    #[cfg(feature = "codegen")]
+    fn test_infer_channels() {
+        use crate::blox::message_handlers::MessageHandle;
+        use crate::blox::state::{Emit, Transition};
+
+        let actor = || {
+            let mut actor = create_test_actor().with_inferred_channels(HandleNaming::default());
+            actor.component.message_handles = MessageHandles::default();
+            actor.component.message_receivers.receivers = vec![
+                MessageReceiver::new("standardpayload_rx", "StandardPayload").with_priority(3),
+            ];
+            actor.component.states.states[0] = State::from("Create").with_transitions(vec![
+                Transition::new("CustomValue1", "Update").with_emit(vec![Emit::new(
+                    "customargs_handle",
+                    "CustomArgs::default()",
+                )]),
+            ]);
+            actor
+        };
+
+        let mut inferred = actor();
+        inferred.infer_channels().unwrap();
+        let receivers = &inferred.component.message_receivers;
+        assert_eq!(receivers.ident, "ActorReceivers");
+        assert_eq!(receivers.receivers.len(), 2);
+        assert_eq!(receivers.receivers[0].priority, 3);
+        assert_eq!(receivers.receivers[1].ident, "customargs_rx");
+        let handles = &inferred.component.message_handles;
+        assert_eq!(handles.ident, "ActorHandles");
+        assert_eq!(
+            handles.handles,
+            vec![MessageHandle::new("customargs_handle", "CustomArgs")]
+        );
+        assert!(inferred.component.validate().is_ok());
+        assert!(crate::create::ActorGenerator::new(actor()).is_ok());
+
+        let mut audited = actor();
+        audited.component.states.states[0].transitions[0]
+            .emit
+            .push(Emit::new("audit", "message.clone()"));
+        assert_eq!(
+            audited.infer_channels(),
+            Err("Handle 'audit' emitted through in state 'Create' sends no message set payload, so it must be declared".to_string())
+        );
+        audited
+            .component
+            .message_handles
+            .add_handle(MessageHandle::new("audit", "AuditEvent"));
+        audited.infer_channels().unwrap();
+        assert_eq!(audited.component.message_handles.handles.len(), 2);
+
+        let mut actor = actor();
+        actor
+            .component
+            .message_receivers
+            .add_receiver(MessageReceiver::new("audit_rx", "AuditEvent"));
+        assert_eq!(
+            actor.infer_channels(),
+            Err("Receiver 'audit_rx' is not one inferred from the message set".to_string())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "codegen")]
+    fn test_infer_channels_from_generic_payloads() {
+        let mut actor = create_test_actor().with_inferred_channels(HandleNaming::default());
+        actor.component.message_set = Some(MessageSet::new(EnumDef::new(
+            "ActorMessageSet",
+            vec![
+                EnumVariant::new("Batch", vec![Link::new("Vec<CustomArgs>")]),
+                EnumVariant::new(
+                    "Lookup",
+                    vec![Link::new("std::collections::HashMap<String, u64>")],
+                ),
+            ],
+        )));
+        actor.component.message_handles = MessageHandles::default();
+        actor.component.message_receivers = Default::default();
+        let generator =
+            crate::create::ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let receivers = &generator.actor().component.message_receivers.receivers;
+        assert_eq!(receivers[0].ident, "vec_rx");
+        assert_eq!(receivers[1].ident, "hashmap_rx");
+        assert_eq!(
+            receivers[1].message_type,
+            "std::collections::HashMap<String, u64>"
+        );
+        for code in [
+            generator.generate_component().unwrap(),
+            generator.generate_runtime().unwrap(),
+            generator.generate_messaging().unwrap().unwrap(),
+        ] {
+            syn::parse_file(&code).unwrap_or_else(|e| panic!("{e}:\n{code}"));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_resolve_children() {
+        let actor = Actor::from_json_file(&PathBuf::from("tests/children/gateway.json")).unwrap();
+        let child = actor.children[0].actor().unwrap();
+        assert_eq!(child.ident, "Session");
+        assert_eq!(
+            actor.children[0].inbox_type().unwrap(),
+            "Option<SessionInbox>"
+        );
+        assert!(actor.validate_children().is_ok());
+
+        let err = Actor::from_json_file(&PathBuf::from("tests/children/cycle.json")).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("cycle.json is its own descendant")
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use serde::{Deserialize, Serialize};
+
+use super::component::Component;
+use crate::alloc_prelude::*;
+
+/// Generates an adapter exposing the actor through a service protocol
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Bridge {
+    /// A tonic service forwarding RPCs to the actor
+    Grpc(GrpcBridge),
+    /// axum handlers forwarding routes to the actor
+    Http(HttpBridge),
+}
+
+impl Bridge {
+    pub fn validate(&self, component: &Component) -> Result<(), String> {
+        match self {
+            Bridge::Grpc(grpc) => grpc.validate(component),
+            Bridge::Http(http) => http.validate(component),
+        }
+    }
+
+    /// Whether requests carry OpenTelemetry trace context into the actor
+    pub fn trace_context(&self) -> bool {
+        match self {
+            Bridge::Grpc(grpc) => grpc.trace_context,
+            Bridge::Http(http) => http.trace_context,
+        }
+    }
+}
+
+/// Checks that a bridged call is forwarded as a known variant with one payload and a receiver
+fn validate_forwarded_variant(
+    component: &Component,
+    call: &str,
+    variant: &str,
+    envelope: &str,
+) -> Result<(), String> {
+    let variants = component
+        .message_set
+        .as_ref()
+        .map(|ms| ms.get().variants.as_slice())
+        .unwrap_or_default();
+
+    let Some(index) = variants.iter().position(|v| v.ident == variant) else {
+        return Err(format!(
+            "{call} is forwarded as unknown variant '{variant}'"
+        ));
+    };
+    if variants[index].args.len() != 1 {
+        return Err(format!(
+            "Variant '{variant}' of {call} must carry exactly one {envelope} payload"
+        ));
+    }
+    if component.message_receivers.receivers.get(index).is_none() {
+        return Err(format!("Variant '{variant}' of {call} has no receiver"));
+    }
+    Ok(())
+}
+
+/// A tonic service implementation backed by the actor
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct GrpcBridge {
+    /// Path of the module tonic generated for the proto package, such as `crate::proto::billing`
+    pub proto: String,
+    /// Name of the service in the `.proto` file
+    pub service: String,
+    pub rpcs: Vec<Rpc>,
+    /// Extracts OpenTelemetry trace context from request metadata
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub trace_context: bool,
+}
+
+/// One RPC of a gRPC bridge, forwarded as a message-set variant
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct Rpc {
+    /// Name of the method in the tonic service trait
+    pub method: String,
+    /// Request message type, defined in the proto module
+    pub request: String,
+    /// Response message type, defined in the proto module
+    pub response: String,
+    /// Variant the request is sent as; its payload must be `RpcRequest<request, response>`
+    pub variant: String,
+}
+
+impl GrpcBridge {
+    pub fn new<P, S>(proto: P, service: S, rpcs: Vec<Rpc>) -> Self
+    where
+        P: Into<String>,
+        S: Into<String>,
+    {
+        Self {
+            proto: proto.into(),
+            service: service.into(),
+            rpcs,
+            trace_context: false,
+        }
+    }
+
+    /// Continues the caller's trace inside the actor
+    pub fn with_trace_context(mut self) -> Self {
+        self.trace_context = true;
+        self
+    }
+
+    /// Module tonic generates the service trait in
+    pub fn server_module(&self) -> String {
+        let mut module = String::new();
+        for (i, c) in self.service.chars().enumerate() {
+            if c.is_uppercase() {
+                if i > 0 {
+                    module.push('_');
+                }
+                module.extend(c.to_lowercase());
+            } else {
+                module.push(c);
+            }
+        }
+        format!("{}::{module}_server", self.proto)
+    }
+
+    pub fn validate(&self, component: &Component) -> Result<(), String> {
+        self.rpcs.iter().try_for_each(|rpc| {
+            let call = format!("RPC '{}'", rpc.method);
+            validate_forwarded_variant(component, &call, &rpc.variant, "RpcRequest")
+        })
+    }
+}
+
+impl Rpc {
+    pub fn new<M, Q, R, V>(method: M, request: Q, response: R, variant: V) -> Self
+    where
+        M: Into<String>,
+        Q: Into<String>,
+        R: Into<String>,
+        V: Into<String>,
+    {
+        Self {
+            method: method.into(),
+            request: request.into(),
+            response: response.into(),
+            variant: variant.into(),
+        }
+    }
+}
+
+/// axum handlers serving HTTP routes backed by the actor
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct HttpBridge {
+    pub routes: Vec<Route>,
+    /// Extracts OpenTelemetry trace context from request headers
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub trace_context: bool,
+}
+
+/// HTTP method a route answers
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpMethod {
+    Get,
+    #[default]
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+impl HttpMethod {
+    /// Name of the `axum::routing` function registering the method
+    pub fn routing_fn(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "get",
+            HttpMethod::Post => "post",
+            HttpMethod::Put => "put",
+            HttpMethod::Patch => "patch",
+            HttpMethod::Delete => "delete",
+        }
+    }
+}
+
+/// Where a route reads the request payload from
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadSource {
+    /// The JSON request body
+    #[default]
+    Json,
+    /// The query string
+    Query,
+    /// The route's path parameters
+    Path,
+}
+
+impl PayloadSource {
+    /// Name of the axum extractor reading the payload
+    pub fn extractor(&self) -> &'static str {
+        match self {
+            PayloadSource::Json => "Json",
+            PayloadSource::Query => "Query",
+            PayloadSource::Path => "Path",
+        }
+    }
+}
+
+/// One route of an HTTP bridge, forwarded as a message-set variant
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct Route {
+    #[serde(default)]
+    pub method: HttpMethod,
+    /// Route path in axum syntax, such as `/charges/{id}`
+    pub path: String,
+    /// Type the payload is deserialized into
+    pub request: String,
+    /// Type returned to the caller as JSON
+    pub response: String,
+    #[serde(default)]
+    pub payload: PayloadSource,
+    /// Variant the request is sent as; its payload must be `HttpRequest<request, response>`
+    pub variant: String,
+}
+
+impl HttpBridge {
+    pub fn new(routes: Vec<Route>) -> Self {
+        Self {
+            routes,
+            trace_context: false,
+        }
+    }
+
+    /// Continues the caller's trace inside the actor
+    pub fn with_trace_context(mut self) -> Self {
+        self.trace_context = true;
+        self
+    }
+
+    pub fn validate(&self, component: &Component) -> Result<(), String> {
+        for (i, route) in self.routes.iter().enumerate() {
+            let call = format!("Route '{}'", route.describe());
+            if self.routes[..i]
+                .iter()
+                .any(|r| r.method == route.method && r.path == route.path)
+            {
+                return Err(format!("{call} is declared more than once"));
+            }
+            // Handlers are named after the variant, so each route needs its own
+            if let Some(other) = self.routes[..i]
+                .iter()
+                .find(|r| r.handler_name() == route.handler_name())
+            {
+                return Err(format!(
+                    "{call} and route '{}' would share the handler '{}'; forward them as different variants",
+                    other.describe(),
+                    route.handler_name()
+                ));
+            }
+            validate_forwarded_variant(component, &call, &route.variant, "HttpRequest")?;
+        }
+        Ok(())
+    }
+}
+
+impl Route {
+    pub fn new<P, Q, R, V>(method: HttpMethod, path: P, request: Q, response: R, variant: V) -> Self
+    where
+        P: Into<String>,
+        Q: Into<String>,
+        R: Into<String>,
+        V: Into<String>,
+    {
+        Self {
+            method,
+            path: path.into(),
+            request: request.into(),
+            response: response.into(),
+            payload: PayloadSource::default(),
+            variant: variant.into(),
+        }
+    }
+
+    /// Reads the payload from somewhere other than the JSON body
+    pub fn with_payload(mut self, payload: PayloadSource) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Method and path, as in `POST /charges`
+    pub fn describe(&self) -> String {
+        format!("{} {}", self.method.routing_fn().to_uppercase(), self.path)
+    }
+
+    /// Name of the generated handler function
+    pub fn handler_name(&self) -> String {
+        self.variant.to_lowercase()
+    }
+}
@@ -0,0 +1,185 @@
+use alloc::boxed::Box;
+
+use serde::{Deserialize, Serialize};
+
+use super::actor::Actor;
+use super::component::Component;
+use crate::alloc_prelude::*;
+
+/// Another actor this one owns an instance of, spawned when it starts
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
+pub struct Child {
+    /// Name of the extended state field holding the child's inbox
+    pub ident: String,
+    /// Path of the child's spec, relative to this one's
+    pub spec: String,
+    /// Messages handed on to the child instead of being dispatched
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forward: Vec<Forward>,
+    /// The child's spec, loaded along with this one
+    #[serde(skip)]
+    pub actor: Option<Box<Actor>>,
+}
+
+/// Sends the payload of a variant on to a channel of the child's inbox
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct Forward {
+    /// Variant of this actor's message set
+    pub variant: String,
+    /// Channel of the child's inbox the payload is sent on
+    pub channel: String,
+}
+
+impl Forward {
+    pub fn new<V, C>(variant: V, channel: C) -> Self
+    where
+        V: Into<String>,
+        C: Into<String>,
+    {
+        Self {
+            variant: variant.into(),
+            channel: channel.into(),
+        }
+    }
+}
+
+impl Child {
+    pub fn new<I, S>(ident: I, spec: S) -> Self
+    where
+        I: Into<String>,
+        S: Into<String>,
+    {
+        Self {
+            ident: ident.into(),
+            spec: spec.into(),
+            forward: Vec::new(),
+            actor: None,
+        }
+    }
+
+    /// Forwards a variant's payload to a channel of the child
+    pub fn with_forward(mut self, forward: Forward) -> Self {
+        self.forward.push(forward);
+        self
+    }
+
+    /// Sets the child's spec, as loading this one does
+    pub fn with_actor(mut self, actor: Actor) -> Self {
+        self.actor = Some(Box::new(actor));
+        self
+    }
+
+    /// The child's spec, or an error naming the child if it was not loaded
+    pub fn actor(&self) -> Result<&Actor, String> {
+        self.actor
+            .as_deref()
+            .ok_or_else(|| format!("Child '{}' has no loaded spec '{}'", self.ident, self.spec))
+    }
+
+    /// Type of the extended state field holding the child's inbox, empty until it is spawned
+    pub fn inbox_type(&self) -> Result<String, String> {
+        Ok(format!("Option<{}Inbox>", self.actor()?.ident))
+    }
+
+    /// The forwarding rule of a variant, if the child takes it over
+    pub fn forward_for(&self, variant: &str) -> Option<&Forward> {
+        self.forward.iter().find(|f| f.variant == variant)
+    }
+
+    pub fn validate(&self, component: &Component) -> Result<(), String> {
+        let child = self.actor()?;
+        if child.has_self_queue() || child.component.message_handles.has_circuit_breakers() {
+            return Err(format!(
+                "Child '{}' can't be spawned by its parent with self messages or circuit breakers",
+                self.ident
+            ));
+        }
+        let inbox_type = self.inbox_type()?;
+        if component
+            .ext_state
+            .fields()
+            .iter()
+            .any(|f| f.ident() == self.ident && f.ty().as_ref() != inbox_type)
+        {
+            return Err(format!(
+                "Child '{}' conflicts with a field of '{}'",
+                self.ident,
+                component.ext_state.ident()
+            ));
+        }
+
+        for forward in &self.forward {
+            let Some(receiver) = component.receiver_for_variant(&forward.variant) else {
+                return Err(format!(
+                    "Child '{}' is forwarded variant '{}', which has no receiver",
+                    self.ident, forward.variant
+                ));
+            };
+            let Some(target) = child
+                .component
+                .message_receivers
+                .receivers
+                .iter()
+                .find(|r| r.channel_name() == forward.channel)
+            else {
+                return Err(format!(
+                    "Child '{}' has no channel '{}' to forward '{}' to",
+                    self.ident, forward.channel, forward.variant
+                ));
+            };
+            if target.message_type != receiver.message_type {
+                return Err(format!(
+                    "Variant '{}' carries '{}', but channel '{}' of child '{}' takes '{}'",
+                    forward.variant,
+                    receiver.message_type,
+                    forward.channel,
+                    self.ident,
+                    target.message_type
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_actor;
+
+    fn worker(forward: Forward) -> Child {
+        let mut actor = create_test_actor();
+        actor.ident = "Worker".to_string();
+        Child::new("worker", "worker.json")
+            .with_forward(forward)
+            .with_actor(actor)
+    }
+
+    #[test]
+    fn test_validate_child() {
+        let component = create_test_actor().component;
+        assert!(
+            worker(Forward::new("CustomValue2", "customargs"))
+                .validate(&component)
+                .is_ok()
+        );
+        assert_eq!(
+            worker(Forward::new("Missing", "customargs")).validate(&component),
+            Err("Child 'worker' is forwarded variant 'Missing', which has no receiver".to_string())
+        );
+        assert_eq!(
+            worker(Forward::new("CustomValue2", "missing")).validate(&component),
+            Err("Child 'worker' has no channel 'missing' to forward 'CustomValue2' to".to_string())
+        );
+        assert!(
+            worker(Forward::new("CustomValue2", "standard"))
+                .validate(&component)
+                .unwrap_err()
+                .starts_with("Variant 'CustomValue2' carries 'CustomArgs'")
+        );
+        assert_eq!(
+            Child::new("worker", "worker.json").validate(&component),
+            Err("Child 'worker' has no loaded spec 'worker.json'".to_string())
+        );
+    }
+}
@@ -1,10 +1,12 @@
 use super::{
     ext_state::ExtState,
-    message_handlers::{MessageHandles, MessageReceivers},
+    message_handlers::{MessageHandles, MessageReceiver, MessageReceivers},
     message_set::MessageSet,
     state::States,
 };
-use crate::create::{ActorGenerator, ToRust};
+use crate::alloc_prelude::*;
+#[cfg(feature = "codegen")]
+use crate::create::{RenderCtx, RenderError, RustVersion, ToRust};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
@@ -40,8 +42,57 @@ impl Component {
     }
 }
 
+impl Component {
+    /// Gets the receiver messages of a variant arrive on, paired by position with the message set
+    pub fn receiver_for_variant(&self, variant: &str) -> Option<&MessageReceiver> {
+        let index = self
+            .message_set
+            .as_ref()?
+            .get()
+            .variants
+            .iter()
+            .position(|v| v.ident == variant)?;
+        self.message_receivers.receivers.get(index)
+    }
+
+    /// Validates the states, the types defined next to the message set and the handles
+    /// transitions emit through
+    pub fn validate(&self) -> Result<(), String> {
+        self.states.validate()?;
+        if let Some(message_set) = &self.message_set {
+            message_set.validate()?;
+        }
+        for receiver in &self.message_receivers.receivers {
+            if let Some(rate_limit) = &receiver.rate_limit {
+                rate_limit.validate(&receiver.ident)?;
+            }
+        }
+        for handle in &self.message_handles.handles {
+            if let Some(circuit_breaker) = &handle.circuit_breaker {
+                circuit_breaker.validate(&handle.ident)?;
+            }
+        }
+
+        for state in &self.states.states {
+            if let Some(emit) = state
+                .transitions
+                .iter()
+                .flat_map(|t| &t.emit)
+                .find(|emit| self.message_handles.get_handle(&emit.handle).is_none())
+            {
+                return Err(format!(
+                    "State '{}' emits through unknown handle '{}'",
+                    state.ident, emit.handle
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "codegen")]
 impl ToRust for Component {
-    fn to_rust(&self, generator: &ActorGenerator) -> String {
+    fn to_source(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
         let actor_name = &self.ident.split("Components").next().unwrap();
         let component_name = &self.ident;
         let ext_state_name = &self.ext_state.ident();
@@ -55,10 +106,33 @@ impl ToRust for Component {
         let handles_ident = &self.message_handles.ident;
         let receivers_ident = &self.message_receivers.ident;
 
-        let handles = self.message_handles.to_rust(generator);
-        let receivers = self.message_receivers.to_rust(generator);
+        let handles = self.message_handles.to_source(ctx)?;
+        let receivers = self.message_receivers.to_source(ctx)?;
+        let id_ident = ctx.actor.id_ident();
+        let id_type = ctx.actor.id_type();
+        let inbox_fields = self
+            .message_receivers
+            .receivers
+            .iter()
+            .map(|receiver| {
+                format!(
+                    "    pub {}: TokioMessageHandle<{}>,",
+                    receiver.channel_name(),
+                    receiver.message_type
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let api = if ctx
+            .options
+            .supports(RustVersion::RETURN_POSITION_IMPL_TRAIT)
+        {
+            self.render_api(actor_name, id_ident.as_str())
+        } else {
+            String::new()
+        };
 
-        format!(
+        Ok(format!(
             r#"
 /// Defines the structure of the {actor_name} Blox component
 pub struct {component_name};
@@ -76,17 +150,143 @@ impl Components for {component_name} {{
 
 /// Message handles for sending messages from the {actor_name} component
 {handles}
+
+/// Identifies one instance of the {actor_name} actor when addressing or logging it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct {id_ident}(pub {id_type});
+
+impl From<{id_type}> for {id_ident} {{
+    fn from(id: {id_type}) -> Self {{
+        Self(id)
+    }}
+}}
+
+impl From<{id_ident}> for {id_type} {{
+    fn from(id: {id_ident}) -> Self {{
+        id.0
+    }}
+}}
+
+impl std::fmt::Display for {id_ident} {{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{
+        write!(f, "{actor_name}#{{}}", self.0)
+    }}
+}}
+
+/// Inbound channels for sending to one {actor_name} instance
+#[derive(Clone)]
+pub struct {actor_name}Inbox {{
+    pub id: {id_ident},
+{inbox_fields}
+}}
+{api}"#
+        ))
+    }
+}
+
+#[cfg(feature = "codegen")]
+impl Component {
+    /// Renders the `{Actor}Api` trait over the inbox, so callers can be written against it, and
+    /// the `Mock{Actor}` recording what they send in tests, in place of Tokio channels
+    fn render_api(&self, actor_name: &str, id_ident: &str) -> String {
+        let receivers = &self.message_receivers.receivers;
+        let signature = |receiver: &MessageReceiver| {
+            let message = format!("Message<{}>", receiver.message_type);
+            format!(
+                "fn send_{}(&self, message: {message}) -> impl Future<Output = Result<(), SendError<{message}>>> + Send",
+                receiver.channel_name()
+            )
+        };
+        let methods = receivers
+            .iter()
+            .map(|receiver| format!("\n    {};", signature(receiver)))
+            .collect::<String>();
+        let impls = receivers
+            .iter()
+            .map(|receiver| {
+                let message = format!("Message<{}>", receiver.message_type);
+                format!(
+                    "\n\n    async fn send_{name}(&self, message: {message}) -> Result<(), SendError<{message}>> {{\n        self.{name}.send(message).await\n    }}",
+                    name = receiver.channel_name()
+                )
+            })
+            .collect::<String>();
+        let mock_fields = receivers
+            .iter()
+            .map(|receiver| {
+                format!(
+                    "\n    pub {}: std::sync::Mutex<Vec<Message<{}>>>,",
+                    receiver.channel_name(),
+                    receiver.message_type
+                )
+            })
+            .collect::<String>();
+        let mock_init = receivers
+            .iter()
+            .map(|receiver| {
+                format!(
+                    "\n            {}: Default::default(),",
+                    receiver.channel_name()
+                )
+            })
+            .collect::<String>();
+        let mock_impls = receivers
+            .iter()
+            .map(|receiver| {
+                let message = format!("Message<{}>", receiver.message_type);
+                format!(
+                    "\n\n    async fn send_{name}(&self, message: {message}) -> Result<(), SendError<{message}>> {{\n        self.{name}\n            .lock()\n            .unwrap_or_else(std::sync::PoisonError::into_inner)\n            .push(message);\n        Ok(())\n    }}",
+                    name = receiver.channel_name()
+                )
+            })
+            .collect::<String>();
+
+        format!(
+            r#"
+/// Operations on one {actor_name} instance, implemented by its inbox and by `Mock{actor_name}`
+pub trait {actor_name}Api {{
+    fn id(&self) -> {id_ident};{methods}
+}}
+
+impl {actor_name}Api for {actor_name}Inbox {{
+    fn id(&self) -> {id_ident} {{
+        self.id
+    }}{impls}
+}}
+
+/// Stand-in for a {actor_name} instance in tests, recording every message sent to it by channel
+#[cfg(test)]
+pub struct Mock{actor_name} {{
+    pub id: {id_ident},{mock_fields}
+}}
+
+#[cfg(test)]
+impl Mock{actor_name} {{
+    pub fn new(id: {id_ident}) -> Self {{
+        Self {{
+            id,{mock_init}
+        }}
+    }}
+}}
+
+#[cfg(test)]
+impl {actor_name}Api for Mock{actor_name} {{
+    fn id(&self) -> {id_ident} {{
+        self.id
+    }}{mock_impls}
+}}
 "#
         )
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "codegen"))]
 mod tests {
     use super::*;
     use crate::{
-        blox::message_handlers::{MessageHandle, MessageReceiver},
-        tests::create_test_states,
+        blox::message_handlers::{MessageHandle, MessageReceiver, OverflowPolicy},
+        blox::state::{Emit, State, Transition},
+        tests::{create_test_component, create_test_states},
     };
 
     #[test]
@@ -114,11 +314,136 @@ mod tests {
         );
         let generator =
             crate::create::ActorGenerator::new(actor).expect("Generator creation should work");
-        let rust_code = component.to_rust(&generator);
+        let rust_code = component
+            .to_source(&generator.ctx())
+            .expect("Component should render");
 
         assert!(rust_code.contains("pub struct ActorHandles"));
         assert!(rust_code.contains("pub struct ActorReceivers"));
         assert!(rust_code.contains("pub test_handle: TokioMessageHandle<TestMessage>"));
         assert!(rust_code.contains("pub test_rx: <<TokioRuntime as Runtime>::MessageHandle<TestMessage> as MessageSender>::ReceiverType"));
+
+        // The same code as tokens, which `syn` can check
+        let tokens = create_test_component()
+            .to_rust(&generator.ctx())
+            .expect("Component should render");
+        let file = syn::parse2::<syn::File>(tokens).expect("Component should be valid Rust");
+        assert!(
+            file.items.iter().any(
+                |item| matches!(item, syn::Item::Struct(item) if item.ident == "ActorHandles")
+            )
+        );
+
+        let method = crate::method::Method::new("broken", &[], "", "if ready {");
+        assert!(matches!(
+            method.to_rust(&generator.ctx()),
+            Err(crate::create::RenderError::InvalidRust(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_emit_handles() {
+        let mut component = create_test_component();
+        component.states.states[0] = State::from("Create").with_transitions(vec![
+            Transition::new("CustomValue1", "Update")
+                .with_emit(vec![Emit::new("standard_handle", "message.clone()")]),
+        ]);
+        assert!(component.validate().is_ok());
+
+        component.states.states[0].transitions[0].emit = vec![Emit::new("missing", "message")];
+        assert_eq!(
+            component.validate().unwrap_err(),
+            "State 'Create' emits through unknown handle 'missing'"
+        );
+    }
+
+    #[test]
+    fn test_handle_send_helpers() {
+        let mut actor = crate::tests::create_test_actor();
+        let handles = &mut actor.component.message_handles.handles;
+        handles[1] = handles[1].clone().with_overflow(OverflowPolicy::DropNew);
+        let generator =
+            crate::create::ActorGenerator::new(actor).expect("Generator creation should work");
+        let rust_code = generator.generate_component().unwrap();
+
+        assert!(rust_code.contains("impl ActorHandles {"));
+        assert!(rust_code.contains(
+            "pub async fn send_standard(&self, message: Message<StandardPayload>) -> Result<(), SendError<Message<StandardPayload>>> {\n        self.standard_handle.send(message).await"
+        ));
+        assert!(rust_code.contains(
+            "pub fn try_send_customargs(&self, message: Message<CustomArgs>) -> Result<(), TrySendError<Message<CustomArgs>>>"
+        ));
+        assert!(rust_code.contains("Ok(()) | Err(TrySendError::Full(_)) => Ok(()),"));
+        assert!(rust_code.contains("use tokio::sync::mpsc::error::TrySendError;"));
+        assert!(rust_code.contains("use bloxide_tokio::messaging::Message;"));
+    }
+
+    #[test]
+    fn test_actor_id_newtype() {
+        let generator = crate::create::ActorGenerator::new(crate::tests::create_test_actor())
+            .expect("Generator creation should work");
+        let rust_code = generator.generate_component().unwrap();
+        assert!(rust_code.contains("pub struct ActorId(pub u16);"));
+        assert!(rust_code.contains("write!(f, \"Actor#{}\", self.0)"));
+        assert!(rust_code.contains(
+            "pub struct ActorInbox {\n    pub id: ActorId,\n    pub standard: TokioMessageHandle<StandardPayload>,\n    pub customargs: TokioMessageHandle<CustomArgs>,\n}"
+        ));
+
+        let actor = crate::tests::create_test_actor().with_id_type("uuid::Uuid");
+        let generator =
+            crate::create::ActorGenerator::new(actor).expect("Generator creation should work");
+        let rust_code = generator.generate_component().unwrap();
+        assert!(rust_code.contains("pub struct ActorId(pub uuid::Uuid);"));
+        assert!(rust_code.contains("impl From<ActorId> for uuid::Uuid {"));
+    }
+
+    #[test]
+    fn test_api_trait() {
+        use crate::create::{Edition, GeneratorOptions, RustVersion};
+
+        let generator = crate::create::ActorGenerator::new(crate::tests::create_test_actor())
+            .expect("Generator creation should work");
+        let rust_code = generator.generate_component().unwrap();
+        assert!(rust_code.contains("pub trait ActorApi {\n    fn id(&self) -> ActorId;"));
+        assert!(rust_code.contains(
+            "fn send_standard(&self, message: Message<StandardPayload>) -> impl Future<Output = Result<(), SendError<Message<StandardPayload>>>> + Send;"
+        ));
+        assert!(rust_code.contains("impl ActorApi for ActorInbox {"));
+        assert!(rust_code.contains(
+            "async fn send_customargs(&self, message: Message<CustomArgs>) -> Result<(), SendError<Message<CustomArgs>>> {\n        self.customargs.send(message).await"
+        ));
+        assert!(rust_code.contains("use tokio::sync::mpsc::error::SendError;"));
+        assert!(!rust_code.contains("use std::future::Future;"));
+
+        // Tests get a mock recording what is sent through the trait
+        assert!(rust_code.contains(
+            "#[cfg(test)]\npub struct MockActor {\n    pub id: ActorId,\n    pub standard: std::sync::Mutex<Vec<Message<StandardPayload>>>,\n    pub customargs: std::sync::Mutex<Vec<Message<CustomArgs>>>,\n}"
+        ));
+        assert!(rust_code.contains(
+            "    pub fn new(id: ActorId) -> Self {\n        Self {\n            id,\n            standard: Default::default(),\n            customargs: Default::default(),\n        }\n    }"
+        ));
+        assert!(rust_code.contains("#[cfg(test)]\nimpl ActorApi for MockActor {"));
+        assert!(rust_code.contains(
+            "    async fn send_standard(&self, message: Message<StandardPayload>) -> Result<(), SendError<Message<StandardPayload>>> {\n        self.standard\n            .lock()\n            .unwrap_or_else(std::sync::PoisonError::into_inner)\n            .push(message);\n        Ok(())\n    }"
+        ));
+
+        let options = GeneratorOptions::default()
+            .with_edition(Edition::E2021)
+            .with_msrv(Some(RustVersion::new(1, 75)));
+        let generator =
+            crate::create::ActorGenerator::with_options(crate::tests::create_test_actor(), options)
+                .expect("Generator creation should work");
+        let rust_code = generator.generate_component().unwrap();
+        assert!(rust_code.contains("use std::future::Future;"));
+
+        let options = GeneratorOptions::default()
+            .with_edition(Edition::E2021)
+            .with_msrv(Some(RustVersion::new(1, 74)));
+        let generator =
+            crate::create::ActorGenerator::with_options(crate::tests::create_test_actor(), options)
+                .expect("Generator creation should work");
+        let rust_code = generator.generate_component().unwrap();
+        assert!(!rust_code.contains("ActorApi"));
+        assert!(!rust_code.contains("MockActor"));
     }
 }
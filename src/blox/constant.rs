@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use super::component::Component;
+use crate::Link;
+use crate::alloc_prelude::*;
+
+/// A `pub const` or `pub static` item emitted into one of the generated modules, so values
+/// handlers share live in the spec
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct Constant {
+    /// Name of the item, in `SCREAMING_SNAKE_CASE`
+    pub ident: String,
+    pub ty: Link,
+    /// Expression the item is initialized with, which must be const-evaluable
+    pub value: String,
+    /// Generated module the item is declared in
+    #[serde(default)]
+    pub module: ConstantModule,
+    /// Declares a `static`, which has one address, instead of a `const`
+    #[serde(
+        default,
+        rename = "static",
+        skip_serializing_if = "core::ops::Not::not"
+    )]
+    pub is_static: bool,
+}
+
+/// Generated module a constant can be declared in
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstantModule {
+    Messaging,
+    Component,
+    #[default]
+    ExtState,
+    States,
+}
+
+impl ConstantModule {
+    /// Name of the module under the actor's
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConstantModule::Messaging => "messaging",
+            ConstantModule::Component => "component",
+            ConstantModule::ExtState => "ext_state",
+            ConstantModule::States => "states",
+        }
+    }
+}
+
+impl Constant {
+    pub fn new<I, T, V>(ident: I, ty: T, value: V) -> Self
+    where
+        I: Into<String>,
+        T: Into<Link>,
+        V: Into<String>,
+    {
+        Self {
+            ident: ident.into(),
+            ty: ty.into(),
+            value: value.into(),
+            module: ConstantModule::default(),
+            is_static: false,
+        }
+    }
+
+    /// Declares the constant in `module` instead of the extended state's
+    pub fn in_module(mut self, module: ConstantModule) -> Self {
+        self.module = module;
+        self
+    }
+
+    /// Declares a `static` instead of a `const`
+    pub fn as_static(mut self) -> Self {
+        self.is_static = true;
+        self
+    }
+
+    pub fn validate(&self, component: &Component) -> Result<(), String> {
+        let is_upper_snake = self
+            .ident
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        if !is_upper_snake || !self.ident.starts_with(|c: char| c.is_ascii_uppercase()) {
+            return Err(format!(
+                "Constant '{}' should be named in SCREAMING_SNAKE_CASE",
+                self.ident
+            ));
+        }
+        if self.module == ConstantModule::Messaging && component.message_set.is_none() {
+            return Err(format!(
+                "Constant '{}' is declared in the messaging module, but there is no message set",
+                self.ident
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "codegen")]
+    /// The item's declaration
+    pub(crate) fn render(&self) -> String {
+        let keyword = if self.is_static { "static" } else { "const" };
+        format!(
+            "pub {keyword} {}: {} = {};",
+            self.ident, self.ty, self.value
+        )
+    }
+}
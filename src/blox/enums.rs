@@ -1,4 +1,5 @@
 use crate::Link;
+use crate::alloc_prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
@@ -7,6 +8,9 @@ pub struct EnumDef {
     pub ident: String,
     #[serde(rename = "enumvariant", default)]
     pub variants: Vec<EnumVariant>,
+    /// Traits derived on the generated enum
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derives: Vec<String>,
 }
 
 impl EnumDef {
@@ -17,8 +21,14 @@ impl EnumDef {
         Self {
             ident: ident.into(),
             variants,
+            derives: Vec::new(),
         }
     }
+
+    pub fn with_derives<S: Into<String>>(mut self, derives: impl IntoIterator<Item = S>) -> Self {
+        self.derives = derives.into_iter().map(Into::into).collect();
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
@@ -26,6 +36,15 @@ impl EnumDef {
 pub struct EnumVariant {
     pub ident: String,
     pub args: Vec<Link>,
+    /// Documentation rendered on the generated variant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// Why the variant is deprecated, rendered as `#[deprecated(note = ...)]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+    /// Carry the payload in a `Box`, so a large one doesn't grow every message of the set
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub boxed: bool,
 }
 
 impl EnumVariant {
@@ -36,11 +55,32 @@ impl EnumVariant {
         Self {
             ident: ident.into(),
             args,
+            doc: None,
+            deprecated: None,
+            boxed: false,
         }
     }
+
+    /// Documents the generated variant
+    pub fn with_doc<S: Into<String>>(mut self, doc: S) -> Self {
+        self.doc = Some(doc.into());
+        self
+    }
+
+    /// Deprecates the generated variant
+    pub fn deprecated<S: Into<String>>(mut self, note: S) -> Self {
+        self.deprecated = Some(note.into());
+        self
+    }
+
+    /// Boxes the variant's payload in the generated enum
+    pub fn boxed(mut self) -> Self {
+        self.boxed = true;
+        self
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use crate::Link;
 
@@ -1,11 +1,32 @@
+use alloc::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::alloc_prelude::*;
+use crate::rename::replace_word;
+use crate::{Method, field::Field, mixin::Mixin};
+#[cfg(feature = "codegen")]
 use crate::{
-    Method,
-    create::{ActorGenerator, ToRust},
-    field::Field,
+    blox::actor::{SELF_HANDLE, STASH_FIELD, UNSTASHED_FIELD},
+    create::{RenderCtx, RenderError, ToRust, render_history_methods},
 };
 
+/// Traits an extended state implementing them derives rather than getting an empty impl for
+#[cfg(feature = "codegen")]
+const DERIVABLE_TRAITS: &[&str] = &[
+    "Clone",
+    "Copy",
+    "Debug",
+    "Default",
+    "PartialEq",
+    "Eq",
+    "Hash",
+    "PartialOrd",
+    "Ord",
+    "Serialize",
+    "Deserialize",
+];
+
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Default, Clone)]
 pub struct InitArgs {
     pub ident: String,
@@ -24,6 +45,178 @@ impl InitArgs {
     }
 }
 
+/// Deployment configuration the init args are converted from, so it flows into the actor
+/// without hand-written glue
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub struct DeploymentConfig {
+    pub ident: String,
+    #[serde(default)]
+    pub fields: Vec<Field>,
+    /// Expressions reading `config` for init args the config has no same-named field of the
+    /// same type for, by init arg
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub map: BTreeMap<String, String>,
+    /// Traits derived on the generated config, e.g. `serde::Deserialize` to load it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derives: Vec<String>,
+}
+
+impl DeploymentConfig {
+    pub fn new<S: Into<String>>(ident: S, fields: Vec<Field>) -> Self {
+        Self {
+            ident: ident.into(),
+            fields,
+            map: BTreeMap::new(),
+            derives: Vec::new(),
+        }
+    }
+
+    /// Sets the init arg `arg` to `expr`, which reads the config as `config`
+    pub fn with_mapping<A: Into<String>, E: Into<String>>(mut self, arg: A, expr: E) -> Self {
+        self.map.insert(arg.into(), expr.into());
+        self
+    }
+
+    pub fn with_derives<D: Into<String>>(mut self, derives: impl IntoIterator<Item = D>) -> Self {
+        self.derives.extend(derives.into_iter().map(Into::into));
+        self
+    }
+
+    /// The config field an init arg is moved from, when it has the same name and type
+    fn same_field(&self, arg: &Field) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|f| f.ident() == arg.ident() && f.ty() == arg.ty())
+    }
+
+    /// Checks that every init arg is set from the config, and only init args are mapped
+    pub fn validate(&self, init_args: &InitArgs) -> Result<(), String> {
+        if init_args.ident.is_empty() {
+            return Err(format!(
+                "Config '{}' converts into the init args, which have no type name",
+                self.ident
+            ));
+        }
+        if let Some(arg) = self
+            .map
+            .keys()
+            .find(|arg| init_args.fields.iter().all(|f| f.ident() != arg.as_str()))
+        {
+            return Err(format!(
+                "Config '{}' maps '{arg}', which is not a field of '{}'",
+                self.ident, init_args.ident
+            ));
+        }
+        if let Some(arg) = init_args
+            .fields
+            .iter()
+            .find(|arg| !self.map.contains_key(arg.ident()) && self.same_field(arg).is_none())
+        {
+            return Err(format!(
+                "Config '{}' has no field '{}: {}', so the init arg needs a mapping",
+                self.ident,
+                arg.ident(),
+                arg.ty()
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "codegen")]
+    /// The config struct and its conversion into the init args
+    fn to_rust(&self, init_args: &InitArgs) -> String {
+        let fields = self
+            .fields
+            .iter()
+            .map(|f| format!("    pub {}: {},", f.ident(), f.ty()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mapping = init_args
+            .fields
+            .iter()
+            .map(|arg| {
+                let value = self
+                    .map
+                    .get(arg.ident())
+                    .cloned()
+                    .unwrap_or_else(|| format!("config.{}", arg.ident()));
+                format!("            {}: {value},", arg.ident())
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let derives = ["Debug", "Clone"]
+            .into_iter()
+            .chain(self.derives.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(", ");
+        // Nothing is read from the config when the init args carry no fields
+        let param = if init_args.fields.is_empty() {
+            "_config"
+        } else {
+            "config"
+        };
+        format!(
+            r#"
+/// Deployment configuration the actor is initialized from
+#[derive({derives})]
+pub struct {ident} {{
+{fields}
+}}
+
+impl From<{ident}> for {init_args} {{
+    fn from({param}: {ident}) -> Self {{
+        Self {{
+{mapping}
+        }}
+    }}
+}}
+"#,
+            ident = self.ident,
+            init_args = init_args.ident,
+        )
+    }
+}
+
+/// Bounded buffer states park messages in until another state is entered
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy)]
+pub struct Stash {
+    /// Most messages held at once; stashing beyond it hands the message back
+    pub capacity: usize,
+}
+
+impl Stash {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.capacity == 0 {
+            return Err("Stash capacity must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Ring buffer of the last messages the actor handled, for postmortem debugging
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy)]
+pub struct History {
+    /// Most messages remembered; recording beyond it forgets the oldest
+    pub capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.capacity == 0 {
+            return Err("History capacity must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Default)]
 pub struct ExtState {
     ident: String,
@@ -33,6 +226,15 @@ pub struct ExtState {
     methods: Vec<Method>,
     #[serde(default)]
     init_args: InitArgs,
+    /// Paths of mixin files, relative to the spec, merged in at load time
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    mixins: Vec<String>,
+    /// Paths of traits the extended state implements, derived when the trait can be
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    implements: Vec<String>,
+    /// Deployment configuration the init args are converted from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    config: Option<DeploymentConfig>,
 }
 
 impl ExtState {
@@ -45,9 +247,28 @@ impl ExtState {
             fields,
             methods,
             init_args,
+            mixins: Vec::new(),
+            implements: Vec::new(),
+            config: None,
         }
     }
 
+    /// Converts the init args from a deployment config
+    pub fn with_config(mut self, config: DeploymentConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn config(&self) -> Option<&DeploymentConfig> {
+        self.config.as_ref()
+    }
+
+    /// Declares that the extended state implements the trait at `trait_path`
+    pub fn with_implements<S: Into<String>>(mut self, trait_path: S) -> Self {
+        self.implements.push(trait_path.into());
+        self
+    }
+
     pub fn ident(&self) -> &str {
         &self.ident
     }
@@ -56,6 +277,11 @@ impl ExtState {
         self.fields.push(field);
     }
 
+    /// Adds a field the extended state is initialized from
+    pub fn add_init_arg(&mut self, field: Field) {
+        self.init_args.fields.push(field);
+    }
+
     pub fn fields(&self) -> &[Field] {
         &self.fields
     }
@@ -63,15 +289,87 @@ impl ExtState {
     pub fn methods(&self) -> &[Method] {
         &self.methods
     }
+
+    pub fn init_args(&self) -> &InitArgs {
+        &self.init_args
+    }
+
+    pub fn implements(&self) -> &[String] {
+        &self.implements
+    }
+
+    pub fn mixins(&self) -> &[String] {
+        &self.mixins
+    }
+
+    pub fn add_mixin<S: Into<String>>(&mut self, path: S) {
+        self.mixins.push(path.into());
+    }
+
+    /// Renames a field, its init arg and the `self.` accesses to it in methods, returning
+    /// how many places changed
+    pub fn rename_field(&mut self, old: &str, new: &str) -> usize {
+        let mut renamed = 0;
+        for field in self.fields.iter_mut().chain(&mut self.init_args.fields) {
+            if field.ident() == old {
+                field.set_ident(new);
+                renamed += 1;
+            }
+        }
+        let (old, new) = (format!("self.{old}"), format!("self.{new}"));
+        for method in &mut self.methods {
+            renamed += replace_word(method.body_mut(), &old, &new);
+        }
+        renamed
+    }
+
+    /// Takes the unresolved mixin paths, leaving none behind
+    pub fn take_mixins(&mut self) -> Vec<String> {
+        core::mem::take(&mut self.mixins)
+    }
+
+    /// Merges a mixin's fields and methods, rejecting name conflicts
+    pub fn apply_mixin(&mut self, mixin: &Mixin) -> Result<(), String> {
+        if let Some(field) = mixin.fields.iter().find(|f| {
+            self.fields
+                .iter()
+                .any(|existing| existing.ident() == f.ident())
+        }) {
+            return Err(format!(
+                "Mixin '{}' field '{}' conflicts with a field of '{}'",
+                mixin.ident,
+                field.ident(),
+                self.ident
+            ));
+        }
+
+        if let Some(method) = mixin.methods.iter().find(|m| {
+            self.methods
+                .iter()
+                .any(|existing| existing.ident() == m.ident())
+        }) {
+            return Err(format!(
+                "Mixin '{}' method '{}' conflicts with a method of '{}'",
+                mixin.ident,
+                method.ident(),
+                self.ident
+            ));
+        }
+
+        self.fields.extend(mixin.fields.iter().cloned());
+        self.methods.extend(mixin.methods.iter().cloned());
+        Ok(())
+    }
 }
 
+#[cfg(feature = "codegen")]
 impl ToRust for ExtState {
-    fn to_rust(&self, generator: &ActorGenerator) -> String {
+    fn to_source(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
         let fields = self
             .fields
             .iter()
-            .map(|f| f.to_rust(generator))
-            .collect::<Vec<_>>()
+            .map(|f| f.to_source(ctx))
+            .collect::<Result<Vec<_>, _>>()?
             .join(",\n\t");
 
         let params = self
@@ -82,12 +380,52 @@ impl ToRust for ExtState {
             .join(", ");
 
         let methods = self
-            .methods
+            .fields
             .iter()
-            .map(|m| m.to_rust(generator))
-            .collect::<Vec<_>>()
+            .flat_map(Field::accessors)
+            .map(Ok)
+            .chain(self.methods.iter().map(|m| m.to_source(ctx)))
+            .collect::<Result<Vec<_>, _>>()?
             .join("\n\t");
 
+        let defer = match (&ctx.actor.component.message_set, ctx.actor.has_self_queue()) {
+            (Some(message_set), true) => format!(
+                r#"
+	/// Queues a message for this actor to handle once the current one is done
+	pub fn defer(&self, message: {message_set}) {{
+	    // Sending only fails once the runtime has stopped and nothing is left to handle it
+	    let _ = self.{SELF_HANDLE}.send(message);
+	}}
+"#,
+                message_set = message_set.get().ident
+            ),
+            _ => String::new(),
+        };
+
+        let stash = match (&ctx.actor.component.message_set, &ctx.actor.stash) {
+            (Some(message_set), Some(stash)) => format!(
+                r#"
+	/// Parks a message until another state is entered, handing it back when the stash is full
+	pub fn stash(&mut self, message: {message_set}) -> Result<(), {message_set}> {{
+	    if self.{STASH_FIELD}.len() >= {capacity} {{
+	        return Err(message);
+	    }}
+	    self.{STASH_FIELD}.push_back(message);
+	    Ok(())
+	}}
+
+	/// Hands every stashed message back, oldest first, to the state just entered, which
+	/// handles them before any message that arrives later
+	pub fn unstash_all(&mut self) {{
+	    self.{UNSTASHED_FIELD}.extend(self.{STASH_FIELD}.drain(..));
+	}}
+"#,
+                message_set = message_set.get().ident,
+                capacity = stash.capacity
+            ),
+            _ => String::new(),
+        };
+
         let init_args_ident = if self.init_args.ident.is_empty() {
             "()"
         } else {
@@ -96,27 +434,106 @@ impl ToRust for ExtState {
         let init_from_params = self
             .fields
             .iter()
-            .map(|f| f.ident())
+            .map(|f| {
+                if f.is_shared() {
+                    format!("{}: {}", f.ident(), f.wrap_value(f.ident()))
+                } else {
+                    f.ident().to_string()
+                }
+            })
             .collect::<Vec<_>>()
             .join(",\n\t");
         let init_fields = self
             .init_args
             .fields
             .iter()
-            .map(|f| format!("{ident}: args.{ident}", ident = f.ident()))
+            .map(|arg| {
+                let value = format!("args.{}", arg.ident());
+                let value = self
+                    .fields
+                    .iter()
+                    .find(|f| f.ident() == arg.ident())
+                    .map_or_else(|| value.clone(), |f| f.wrap_value(&value));
+                format!("{}: {value}", arg.ident())
+            })
             .collect::<Vec<_>>()
             .join(",\n\t");
         let default_fields = self
             .fields
             .iter()
-            .filter(|f| !self.init_args.fields.contains(f))
-            .map(|f| format!("{ident}: Default::default()", ident = f.ident()))
+            .filter(|f| {
+                !self
+                    .init_args
+                    .fields
+                    .iter()
+                    .any(|arg| arg.ident() == f.ident())
+            })
+            .map(|f| {
+                let value = match (f.secret(), f.value()) {
+                    (Some(name), _) => f.wrap_value(&format!("load_secret({name:?})?")),
+                    (None, Some(expr)) => f.wrap_value(expr),
+                    (None, None) => "Default::default()".to_string(),
+                };
+                format!("{}: {value}", f.ident())
+            })
             .collect::<Vec<_>>()
             .join(",\n\t");
-        format!(
-            r#"
-        use bloxide_tokio::state_machine::ExtendedState;
-        pub struct {ident} {{
+        let trait_init_fields = [init_fields, default_fields]
+            .into_iter()
+            .filter(|fields| !fields.is_empty())
+            .collect::<Vec<_>>()
+            .join(",\n\t");
+        // Nothing is read from the init args when they carry no fields
+        let args = if self.init_args.fields.is_empty() {
+            "_args"
+        } else {
+            "args"
+        };
+        // Loading secrets can fail, so the fallible constructor builds the state and the
+        // trait's constructor, which can't report errors, panics on them
+        let has_secrets = self.fields.iter().any(|f| f.secret().is_some());
+        let (try_new, trait_new) = if has_secrets {
+            (
+                format!(
+                    r#"
+
+    /// Builds the state from its init args, failing when a secret is missing or invalid
+    pub fn try_new({args}: {init_args_ident}) -> Result<Self, SecretError> {{
+        Ok(Self {{
+            {trait_init_fields}
+        }})
+    }}"#
+                ),
+                format!("Self::try_new({args}).unwrap_or_else(|e| panic!(\"{{e}}\"))"),
+            )
+        } else {
+            (
+                String::new(),
+                format!("Self {{\n            {trait_init_fields}\n        }}"),
+            )
+        };
+        // A supervisor restarting with persisted state hands out clones of it
+        let mut derives = Vec::new();
+        if ctx.actor.lifecycle.persists_ext_state() {
+            derives.push("Clone");
+        }
+        let mut impls = String::new();
+        for trait_path in &self.implements {
+            let name = trait_path.rsplit("::").next().unwrap_or(trait_path);
+            if !DERIVABLE_TRAITS.contains(&name) {
+                // Anything beyond the trait's provided methods is left to the user
+                impls.push_str(&format!("\nimpl {name} for {} {{}}\n", self.ident));
+            } else if !derives.contains(&name) {
+                derives.push(name);
+            }
+        }
+        let derive = if derives.is_empty() {
+            String::new()
+        } else {
+            format!("#[derive({})]\n", derives.join(", "))
+        };
+        Ok(format!(
+            r#"{derive}pub struct {ident} {{
     {fields}
 }}
 
@@ -125,27 +542,30 @@ impl {ident} {{
         Self {{
             {init_from_params}
         }}
-    }}
+    }}{try_new}
 
-    {methods}
+    {methods}{defer}{stash}{history}
 }}
     
 impl ExtendedState for {ident} {{
     type InitArgs = {init_args_ident};
-    fn new(args: Self::InitArgs) -> Self {{
-        Self {{
-            {init_fields}
-            {default_fields}
-        }}
+    fn new({args}: Self::InitArgs) -> Self {{
+        {trait_new}
     }}
 }}
-    "#,
+{impls}{config}"#,
             ident = self.ident,
-        )
+            history = render_history_methods(ctx),
+            config = self
+                .config
+                .as_ref()
+                .map(|config| config.to_rust(&self.init_args))
+                .unwrap_or_default(),
+        ))
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "codegen"))]
 pub(crate) mod tests {
     use super::*;
     use std::fs;
@@ -172,6 +592,98 @@ pub(crate) mod tests {
         fs::write(EXT_STATE_JSON, json_str).unwrap();
     }
 
+    #[test]
+    fn test_field_accessors() {
+        let ext_state = ExtState::new(
+            "ActorExtState",
+            vec![
+                Field::new("count", "u32")
+                    .private()
+                    .with_getter()
+                    .with_setter(),
+                Field::new("peers", "Vec<String>").with_getter_mut(),
+            ],
+            vec![],
+            InitArgs::default(),
+        );
+        let generator = crate::create::ActorGenerator::new(crate::tests::create_test_actor())
+            .expect("Generator creation should succeed");
+        let rust_code = ext_state
+            .to_source(&generator.ctx())
+            .expect("Ext state should render");
+
+        assert!(rust_code.contains("    count: u32"));
+        assert!(!rust_code.contains("pub count: u32"));
+        assert!(rust_code.contains("pub peers: Vec<String>"));
+        assert!(rust_code.contains("pub fn count(&self) -> &u32"));
+        assert!(rust_code.contains("pub fn set_count(&mut self, count: u32)"));
+        assert!(rust_code.contains("pub fn peers_mut(&mut self) -> &mut Vec<String>"));
+        assert!(!rust_code.contains("pub fn peers(&self)"));
+
+        let json = serde_json::to_string(&Field::new("count", "u32").with_getter()).unwrap();
+        assert_eq!(json, r#"{"ident":"count","ty":"u32","get":true}"#);
+    }
+
+    #[test]
+    fn test_shared_fields() {
+        let mut actor = crate::tests::create_test_actor();
+        actor.component.ext_state = ExtState::new(
+            "ActorExtState",
+            vec![
+                Field::new("peers", "Vec<String>").shared(crate::field::LockKind::RwLock),
+                Field::new("stats", "u64").shared(crate::field::LockKind::Mutex),
+            ],
+            vec![],
+            InitArgs::new("ActorInitArgs", vec![Field::new("peers", "Vec<String>")]),
+        );
+        let generator =
+            crate::create::ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let rust_code = generator
+            .generate_ext_state()
+            .expect("Ext state should render");
+
+        assert!(rust_code.contains("pub peers: Arc<RwLock<Vec<String>>>"));
+        assert!(rust_code.contains("pub stats: Arc<Mutex<u64>>"));
+        assert!(rust_code.contains("pub fn new(peers: Vec<String>, stats: u64)"));
+        assert!(rust_code.contains("peers: Arc::new(RwLock::new(peers))"));
+        assert!(rust_code.contains("peers: Arc::new(RwLock::new(args.peers))"));
+        assert!(rust_code.contains("pub fn read_peers(&self) -> RwLockReadGuard<'_, Vec<String>>"));
+        assert!(rust_code.contains("pub fn write_peers(&self)"));
+        assert!(rust_code.contains("pub fn lock_stats(&self) -> MutexGuard<'_, u64>"));
+        assert!(rust_code.contains("pub fn stats_handle(&self) -> Arc<Mutex<u64>>"));
+
+        let imports = generator
+            .ctx()
+            .imports_for("actor::ext_state")
+            .expect("Ext state module should exist");
+        for import in ["Arc", "RwLock", "RwLockReadGuard", "Mutex", "MutexGuard"] {
+            assert!(
+                imports.contains(&format!("use std::sync::{import};")),
+                "Missing import for {import}: {imports:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_implemented_traits() {
+        let mut actor = crate::tests::create_test_actor();
+        actor.component.ext_state = create_ext_state()
+            .with_implements("serde::Serialize")
+            .with_implements("Clone")
+            .with_implements("crate::metrics::Observable");
+        let generator =
+            crate::create::ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let rust_code = generator
+            .generate_ext_state()
+            .expect("Ext state should render");
+
+        assert!(rust_code.contains("#[derive(Serialize, Clone)]\npub struct ActorExtState {"));
+        assert!(rust_code.contains("\nimpl Observable for ActorExtState {}\n"));
+        assert!(rust_code.contains(
+            "use bloxide_tokio::state_machine::ExtendedState;\nuse crate::metrics::Observable;\nuse serde::Serialize;\n"
+        ));
+    }
+
     #[test]
     fn test_deserialize_from_json() {
         let json_str = fs::read_to_string(EXT_STATE_JSON).unwrap();
@@ -185,4 +697,51 @@ pub(crate) mod tests {
 
         assert_eq!(fields, &expected_fields);
     }
+
+    #[test]
+    fn test_deployment_config() {
+        let init_args = InitArgs::new(
+            "ActorInitArgs",
+            vec![
+                Field::new("peers", "Vec<String>"),
+                Field::new("timeout_ms", "u64"),
+            ],
+        );
+        let config = DeploymentConfig::new(
+            "ActorConfig",
+            vec![
+                Field::new("peers", "Vec<String>"),
+                Field::new("timeout", "std::time::Duration"),
+            ],
+        )
+        .with_derives(["serde::Deserialize"]);
+        assert_eq!(
+            config.validate(&init_args),
+            Err("Config 'ActorConfig' has no field 'timeout_ms: u64', so the init arg needs a mapping".to_string())
+        );
+        let config = config.with_mapping("timeout_ms", "config.timeout.as_millis() as u64");
+        assert_eq!(config.validate(&init_args), Ok(()));
+        assert!(
+            config
+                .clone()
+                .with_mapping("retries", "3")
+                .validate(&init_args)
+                .is_err()
+        );
+
+        let mut actor = crate::tests::create_test_actor();
+        actor.component.ext_state =
+            ExtState::new("ActorExtState", Vec::new(), Vec::new(), init_args).with_config(config);
+        let generator =
+            crate::create::ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let rust_code = generator
+            .generate_ext_state()
+            .expect("Ext state should render");
+        assert!(rust_code.contains(
+            "#[derive(Debug, Clone, serde::Deserialize)]\npub struct ActorConfig {\n    pub peers: Vec<String>,\n    pub timeout: std::time::Duration,\n}"
+        ));
+        assert!(rust_code.contains(
+            "impl From<ActorConfig> for ActorInitArgs {\n    fn from(config: ActorConfig) -> Self {\n        Self {\n            peers: config.peers,\n            timeout_ms: config.timeout.as_millis() as u64,\n        }\n    }\n}"
+        ));
+    }
 }
@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+use super::state::States;
+use crate::alloc_prelude::*;
+
+/// Code run around the actor's message loop
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+#[serde(rename = "lifecycle")]
+pub struct Lifecycle {
+    /// Runs in `Runnable::run` before the state machine is initialized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_start: Option<String>,
+    /// Runs inside the spawned future once the message loop exits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_stop: Option<String>,
+    /// When the generated supervisor restarts the actor
+    #[serde(default, skip_serializing_if = "RestartPolicy::is_never")]
+    pub restart: RestartPolicy,
+    /// Which extended state a restarted actor starts from
+    #[serde(default, skip_serializing_if = "RestartState::is_fresh")]
+    pub restart_state: RestartState,
+    /// Saves the current state after every transition and resumes from it on start
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub persist_state: bool,
+    /// What the runtime does when handling a message panics
+    #[serde(default, skip_serializing_if = "PanicPolicy::is_propagate")]
+    pub on_panic: PanicPolicy,
+}
+
+/// When a supervised actor is restarted
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartPolicy {
+    /// Never restart; no supervisor is generated
+    #[default]
+    Never,
+    /// Restart whenever the actor panics, without limit
+    OnPanic,
+    /// Restart after a panic, at most `max` times, doubling the delay each time; a normal exit
+    /// ends supervision
+    MaxRestarts { max: u32, backoff_ms: u64 },
+}
+
+impl RestartPolicy {
+    pub fn is_never(&self) -> bool {
+        matches!(self, Self::Never)
+    }
+}
+
+/// Extended state a restarted actor is given
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartState {
+    /// The actor is rebuilt from scratch
+    #[default]
+    Fresh,
+    /// The actor is rebuilt from the extended state it had after the last message it handled
+    /// in full, or the one it was first started with if it handled none
+    Persisted,
+}
+
+impl RestartState {
+    pub fn is_fresh(&self) -> bool {
+        matches!(self, Self::Fresh)
+    }
+}
+
+/// How the runtime treats a panic while handling a message
+///
+/// Catching relies on the panic unwinding, so it has no effect in builds with
+/// `panic = "abort"`. The state machine is treated as unwind safe: a caught panic may leave
+/// the extended state half updated.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PanicPolicy {
+    /// Let the panic unwind out of the actor's task, ending it unless it is supervised
+    #[default]
+    Propagate,
+    /// Report the panic and drop the message, staying in the current state
+    Discard,
+    /// Report the panic and re-enter the state machine in `state`
+    Recover { state: String },
+}
+
+impl PanicPolicy {
+    pub fn is_propagate(&self) -> bool {
+        matches!(self, Self::Propagate)
+    }
+
+    /// Whether the runtime catches panics at all
+    pub fn catches(&self) -> bool {
+        !self.is_propagate()
+    }
+}
+
+impl Lifecycle {
+    pub fn with_pre_start<S: Into<String>>(mut self, code: S) -> Self {
+        self.pre_start = Some(code.into());
+        self
+    }
+
+    pub fn with_post_stop<S: Into<String>>(mut self, code: S) -> Self {
+        self.post_stop = Some(code.into());
+        self
+    }
+
+    pub fn with_restart(mut self, restart: RestartPolicy, restart_state: RestartState) -> Self {
+        self.restart = restart;
+        self.restart_state = restart_state;
+        self
+    }
+
+    pub fn with_persisted_state(mut self) -> Self {
+        self.persist_state = true;
+        self
+    }
+
+    pub fn with_panic_policy(mut self, on_panic: PanicPolicy) -> Self {
+        self.on_panic = on_panic;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pre_start.is_none()
+            && self.post_stop.is_none()
+            && self.restart.is_never()
+            && self.restart_state.is_fresh()
+            && !self.persist_state
+            && self.on_panic.is_propagate()
+    }
+
+    /// Checks that panics recover to a declared state
+    pub fn validate(&self, states: &States) -> Result<(), String> {
+        match &self.on_panic {
+            PanicPolicy::Recover { state } if states.get_state(state).is_none() => {
+                Err(format!("Panics recover to unknown state '{state}'"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the extended state must be cloned to restart the actor
+    pub fn persists_ext_state(&self) -> bool {
+        !self.restart.is_never() && !self.restart_state.is_fresh()
+    }
+
+    #[cfg(feature = "codegen")]
+    /// The `pre_start` block indented for the body of `run`
+    pub(crate) fn render_pre_start(&self) -> String {
+        self.pre_start
+            .as_deref()
+            .map(|code| format!("{}\n\n", indent(code, 8)))
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "codegen")]
+    /// The `post_stop` block indented for the spawned future
+    pub(crate) fn render_post_stop(&self) -> String {
+        self.post_stop
+            .as_deref()
+            .map(|code| format!("\n\n{}", indent(code, 12)))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "codegen")]
+/// Indents every non-empty line of `code` by `width` spaces
+fn indent(code: &str, width: usize) -> String {
+    code.trim()
+        .lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                String::new()
+            } else {
+                format!("{:width$}{line}", "")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -1,6 +1,70 @@
+use core::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 
-use crate::create::{ActorGenerator, ToRust};
+use crate::alloc_prelude::*;
+use crate::blox::message_set::MessageSet;
+use crate::ident::{is_ident, is_ident_char, snake_case};
+#[cfg(feature = "codegen")]
+use crate::{
+    blox::actor::SELF_RECEIVER,
+    create::{RenderCtx, RenderError, ToRust},
+};
+
+/// Serialization format of the generated `to_wire`/`from_wire` codecs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireFormat {
+    /// JSON through `serde_json`
+    Json,
+    /// CBOR through `ciborium`
+    Cbor,
+    /// postcard through `postcard`
+    Postcard,
+}
+
+impl WireFormat {
+    /// Name of the format in doc comments
+    pub fn name(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "JSON",
+            WireFormat::Cbor => "CBOR",
+            WireFormat::Postcard => "postcard",
+        }
+    }
+}
+
+impl FromStr for WireFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(WireFormat::Json),
+            "cbor" => Ok(WireFormat::Cbor),
+            "postcard" => Ok(WireFormat::Postcard),
+            _ => Err(format!(
+                "unknown wire format '{s}', expected json, cbor or postcard"
+            )),
+        }
+    }
+}
+
+/// What the generated `send_*` helper does when a handle's channel is full
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Wait until the channel has capacity
+    #[default]
+    Block,
+    /// Discard the message being sent
+    DropNew,
+}
+
+impl OverflowPolicy {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
 
 /// Defines a message handle for sending messages
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
@@ -9,6 +73,56 @@ pub struct MessageHandle {
     pub ident: String,
     /// Type of message this handle sends
     pub message_type: String,
+    /// Behavior of the generated `send_*` helper on a full channel
+    #[serde(default, skip_serializing_if = "OverflowPolicy::is_default")]
+    pub overflow: OverflowPolicy,
+    /// Stops sending to a failing downstream until it had time to recover
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreaker>,
+}
+
+/// Opens after consecutive failed sends through a handle, rejecting sends while open
+///
+/// Once `open_ms` has passed the circuit is half-open: sends go through again, and it closes
+/// if none fails for `half_open_ms`, or opens again on the first failure.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct CircuitBreaker {
+    /// Consecutive failed sends that open the circuit
+    pub failure_threshold: u32,
+    pub open_ms: u64,
+    /// How long the circuit stays half-open, `open_ms` if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub half_open_ms: Option<u64>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_ms: u64) -> Self {
+        Self {
+            failure_threshold,
+            open_ms,
+            half_open_ms: None,
+        }
+    }
+
+    /// Sets how long the circuit stays half-open before closing
+    pub fn with_half_open_ms(mut self, half_open_ms: u64) -> Self {
+        self.half_open_ms = Some(half_open_ms);
+        self
+    }
+
+    pub fn half_open_ms(&self) -> u64 {
+        self.half_open_ms.unwrap_or(self.open_ms)
+    }
+
+    pub fn validate(&self, handle: &str) -> Result<(), String> {
+        if self.failure_threshold == 0 || self.open_ms == 0 || self.half_open_ms() == 0 {
+            return Err(format!(
+                "Circuit breaker of handle '{handle}' needs a failure threshold and timers \
+                 above zero"
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl MessageHandle {
@@ -17,7 +131,124 @@ impl MessageHandle {
         Self {
             ident: ident.into(),
             message_type: message_type.into(),
+            overflow: OverflowPolicy::default(),
+            circuit_breaker: None,
+        }
+    }
+
+    /// Sets what the generated `send_*` helper does when the channel is full
+    pub fn with_overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Guards the send helpers with a circuit breaker
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Name used for the generated send helpers, without a trailing `_handle`
+    pub(crate) fn helper_name(&self) -> &str {
+        self.ident.strip_suffix("_handle").unwrap_or(&self.ident)
+    }
+
+    /// Whether the handle sends payloads wrapped in `Correlated`, as correlated actors do
+    pub fn is_correlated(&self) -> bool {
+        self.message_type
+            .starts_with(&format!("{}<", crate::blox::actor::CORRELATED_TYPE))
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Backpressure-aware `send_*` and `try_send_*` helpers for this handle
+    fn send_helpers(&self) -> String {
+        let ident = &self.ident;
+        let name = self.helper_name();
+        let message = format!("Message<{}>", self.message_type);
+
+        let (send_doc, send_body) = match self.overflow {
+            OverflowPolicy::Block => (
+                "waiting for capacity when the channel is full",
+                format!("self.{ident}.send(message).await"),
+            ),
+            OverflowPolicy::DropNew => (
+                "dropping the message when the channel is full",
+                format!(
+                    r#"match self.{ident}.try_send(message) {{
+            Ok(()) | Err(TrySendError::Full(_)) => Ok(()),
+            Err(TrySendError::Closed(message)) => Err(SendError(message)),
+        }}"#
+                ),
+            ),
+        };
+
+        if self.circuit_breaker.is_some() {
+            return self.guarded_send_helpers(send_doc);
         }
+        format!(
+            r#"    /// Sends through `{ident}`, {send_doc}
+    pub async fn send_{name}(&self, message: {message}) -> Result<(), SendError<{message}>> {{
+        {send_body}
+    }}
+
+    /// Sends through `{ident}`, failing immediately when the channel is full
+    pub fn try_send_{name}(&self, message: {message}) -> Result<(), TrySendError<{message}>> {{
+        self.{ident}.try_send(message)
+    }}"#
+        )
+    }
+
+    #[cfg(feature = "codegen")]
+    /// Send helpers that reject messages while the handle's circuit is open and record
+    /// whether each send succeeded; a full channel counts as a failure
+    fn guarded_send_helpers(&self, send_doc: &str) -> String {
+        let ident = &self.ident;
+        let name = self.helper_name();
+        let message = format!("Message<{}>", self.message_type);
+
+        let send_body = match self.overflow {
+            OverflowPolicy::Block => format!(
+                r#"let sent = self.{ident}.send(message).await;
+        self.breakers.{name}.record(sent.is_ok());
+        sent.map_err(BreakerError::Failed)"#
+            ),
+            OverflowPolicy::DropNew => format!(
+                r#"let sent = self.{ident}.try_send(message);
+        self.breakers.{name}.record(sent.is_ok());
+        match sent {{
+            Ok(()) | Err(TrySendError::Full(_)) => Ok(()),
+            Err(TrySendError::Closed(message)) => Err(BreakerError::Failed(SendError(message))),
+        }}"#
+            ),
+        };
+
+        format!(
+            r#"    /// Sends through `{ident}` unless its circuit is open, {send_doc}
+    pub async fn send_{name}(
+        &self,
+        message: {message},
+    ) -> Result<(), BreakerError<SendError<{message}>, {message}>> {{
+        if !self.breakers.{name}.allows() {{
+            return Err(BreakerError::Open(message));
+        }}
+        {send_body}
+    }}
+
+    /// Sends through `{ident}` unless its circuit is open, failing immediately when the
+    /// channel is full
+    pub fn try_send_{name}(
+        &self,
+        message: {message},
+    ) -> Result<(), BreakerError<TrySendError<{message}>, {message}>> {{
+        if !self.breakers.{name}.allows() {{
+            return Err(BreakerError::Open(message));
+        }}
+        let sent = self.{ident}.try_send(message);
+        self.breakers.{name}.record(sent.is_ok());
+        sent.map_err(BreakerError::Failed)
+    }}"#
+        )
     }
 
     /// Create a standard system message handle
@@ -26,12 +257,13 @@ impl MessageHandle {
     }
 }
 
+#[cfg(feature = "codegen")]
 impl ToRust for MessageHandle {
-    fn to_rust(&self, _generator: &ActorGenerator) -> String {
-        format!(
+    fn to_source(&self, _ctx: &RenderCtx) -> Result<String, RenderError> {
+        Ok(format!(
             "pub {}: TokioMessageHandle<{}>",
             self.ident, self.message_type
-        )
+        ))
     }
 }
 
@@ -42,6 +274,268 @@ pub struct MessageReceiver {
     pub ident: String,
     /// Type of message this receiver accepts
     pub message_type: String,
+    /// Receivers with a higher priority are drained first by the runtime
+    #[serde(default, skip_serializing_if = "is_default_priority")]
+    pub priority: u8,
+    /// External subject or topic whose messages are forwarded into this receiver
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ExternalSource>,
+    /// Payloads arrive stamped with sequence numbers, checked for gaps and duplicates
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub sequenced: bool,
+    /// Token bucket limiting how fast the runtime dispatches the receiver's messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+}
+
+/// What the runtime does with a message arriving while its receiver is over its rate limit
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitPolicy {
+    /// Wait for a token before dispatching, holding back the other receivers too
+    #[default]
+    Delay,
+    /// Discard the message without dispatching it
+    Shed,
+}
+
+impl RateLimitPolicy {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A receiver's rate limit: a steady rate of messages per second and a burst above it
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct RateLimit {
+    pub per_second: u32,
+    /// Messages dispatched back to back before the rate applies, `per_second` if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+    #[serde(default, skip_serializing_if = "RateLimitPolicy::is_default")]
+    pub policy: RateLimitPolicy,
+}
+
+impl RateLimit {
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            per_second,
+            burst: None,
+            policy: RateLimitPolicy::default(),
+        }
+    }
+
+    /// Allows `burst` messages back to back before the rate applies
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = Some(burst);
+        self
+    }
+
+    /// Sets what happens to messages over the limit
+    pub fn with_policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Tokens the bucket holds when full
+    pub fn burst(&self) -> u32 {
+        self.burst.unwrap_or(self.per_second)
+    }
+
+    pub fn validate(&self, receiver: &str) -> Result<(), String> {
+        if self.per_second == 0 || self.burst() == 0 {
+            return Err(format!(
+                "Rate limit of receiver '{receiver}' must allow at least one message per second \
+                 and a burst of one"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Periodic sampling of the depth of every receiver's queue and of the message loop's lag
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct QueueSampling {
+    /// Time between samples
+    pub interval_ms: u64,
+    /// Code run with every sample, with `depths: [(&str, usize); _]` by channel name and
+    /// `lag: Duration`, how late the sample was taken, in scope; e.g. recording metrics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observe: Option<String>,
+    /// Depth past which the states are sent an `Overloaded` message naming the channel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overload_threshold: Option<usize>,
+}
+
+impl QueueSampling {
+    pub fn new(interval_ms: u64) -> Self {
+        Self {
+            interval_ms,
+            observe: None,
+            overload_threshold: None,
+        }
+    }
+
+    /// Runs `code` with every sample
+    pub fn with_observe<S: Into<String>>(mut self, code: S) -> Self {
+        self.observe = Some(code.into());
+        self
+    }
+
+    /// Notifies the states when a queue grows past `threshold`
+    pub fn with_overload_threshold(mut self, threshold: usize) -> Self {
+        self.overload_threshold = Some(threshold);
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_ms == 0 {
+            return Err("Queue sampling interval must be at least 1 ms".to_string());
+        }
+        if self.overload_threshold == Some(0) {
+            return Err("Queue overload threshold must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Message broker an external source subscribes through
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Broker {
+    /// A NATS subject, through `async-nats`
+    Nats,
+    /// An MQTT topic, through `rumqttc`
+    Mqtt,
+    /// A Kafka topic, through `rdkafka`
+    Kafka,
+}
+
+impl Broker {
+    /// Cargo feature of the generated crate gating the adapter
+    pub fn feature(&self) -> &'static str {
+        match self {
+            Broker::Nats => "nats",
+            Broker::Mqtt => "mqtt",
+            Broker::Kafka => "kafka",
+        }
+    }
+
+    /// Name of the broker, as errors mention it
+    pub fn name(&self) -> &'static str {
+        match self {
+            Broker::Nats => "NATS",
+            Broker::Mqtt => "MQTT",
+            Broker::Kafka => "Kafka",
+        }
+    }
+}
+
+/// A subject or topic on an external broker feeding a receiver
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct ExternalSource {
+    pub broker: Broker,
+    /// NATS subject, MQTT topic or Kafka topic to subscribe to
+    pub subject: String,
+    /// Format payloads are decoded from, instead of the generator's wire format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<WireFormat>,
+    /// Extracts OpenTelemetry trace context from message headers
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub trace_context: bool,
+}
+
+impl ExternalSource {
+    pub fn new(broker: Broker, subject: impl Into<String>) -> Self {
+        Self {
+            broker,
+            subject: subject.into(),
+            format: None,
+            trace_context: false,
+        }
+    }
+
+    /// Continues the publisher's trace inside the actor
+    pub fn with_trace_context(mut self) -> Self {
+        self.trace_context = true;
+        self
+    }
+
+    /// Decodes payloads from `format` whatever the generator's wire format
+    pub fn with_format(mut self, format: WireFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+}
+
+/// A Kafka topic payloads of a message-set variant are published to
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct Sink {
+    /// Variant whose payload is published
+    pub variant: String,
+    pub topic: String,
+    /// Format payloads are encoded in, instead of the generator's wire format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<WireFormat>,
+    /// Injects the payload's OpenTelemetry trace context into record headers
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub trace_context: bool,
+}
+
+impl Sink {
+    pub fn new(variant: impl Into<String>, topic: impl Into<String>) -> Self {
+        Self {
+            variant: variant.into(),
+            topic: topic.into(),
+            format: None,
+            trace_context: false,
+        }
+    }
+
+    /// Lets consumers continue the trace the payload was published in
+    pub fn with_trace_context(mut self) -> Self {
+        self.trace_context = true;
+        self
+    }
+
+    /// Encodes payloads in `format` whatever the generator's wire format
+    pub fn with_format(mut self, format: WireFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+}
+
+/// Sends the payload of a message-set variant on through a handle instead of dispatching it
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct Relay {
+    /// Variant whose payload is relayed
+    pub variant: String,
+    /// Handle the payload is sent through
+    pub handle: String,
+    /// Expression building the relayed payload, with a reference to the received one in
+    /// scope as `payload`; the payload is relayed as is without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<String>,
+}
+
+impl Relay {
+    pub fn new(variant: impl Into<String>, handle: impl Into<String>) -> Self {
+        Self {
+            variant: variant.into(),
+            handle: handle.into(),
+            transform: None,
+        }
+    }
+
+    /// Relays what `transform` builds from the received payload instead of the payload itself
+    pub fn with_transform(mut self, transform: impl Into<String>) -> Self {
+        self.transform = Some(transform.into());
+        self
+    }
+}
+
+fn is_default_priority(priority: &u8) -> bool {
+    *priority == 0
 }
 
 impl MessageReceiver {
@@ -50,21 +544,55 @@ impl MessageReceiver {
         Self {
             ident: ident.into(),
             message_type: message_type.into(),
+            priority: 0,
+            source: None,
+            sequenced: false,
+            rate_limit: None,
         }
     }
 
+    /// Name of the matching channel in the generated inbox, without a trailing `_rx`
+    pub fn channel_name(&self) -> &str {
+        self.ident.strip_suffix("_rx").unwrap_or(&self.ident)
+    }
+
+    /// Sets the priority the runtime drains this receiver with
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Feeds the receiver from a subject or topic on an external broker
+    pub fn with_source(mut self, source: ExternalSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Stamps the receiver's payloads with sequence numbers the runtime checks
+    pub fn with_sequencing(mut self) -> Self {
+        self.sequenced = true;
+        self
+    }
+
+    /// Limits how fast the runtime dispatches the receiver's messages
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
     /// Create a standard system message receiver
     pub fn standard(ident: impl Into<String>) -> Self {
         Self::new(ident, "StandardMessage")
     }
 }
 
+#[cfg(feature = "codegen")]
 impl ToRust for MessageReceiver {
-    fn to_rust(&self, _generator: &ActorGenerator) -> String {
-        format!(
+    fn to_source(&self, _ctx: &RenderCtx) -> Result<String, RenderError> {
+        Ok(format!(
             "pub {}: <<TokioRuntime as Runtime>::MessageHandle<{}> as MessageSender>::ReceiverType",
             self.ident, self.message_type
-        )
+        ))
     }
 }
 
@@ -91,26 +619,46 @@ impl MessageHandles {
         self.handles.push(handle);
     }
 
+    /// Whether any handle's send helpers are guarded by a circuit breaker
+    pub fn has_circuit_breakers(&self) -> bool {
+        self.handles.iter().any(|h| h.circuit_breaker.is_some())
+    }
+
     /// Get a handle by name
     pub fn get_handle(&self, name: &str) -> Option<&MessageHandle> {
         self.handles.iter().find(|h| h.ident == name)
     }
 }
 
+#[cfg(feature = "codegen")]
 impl ToRust for MessageHandles {
-    fn to_rust(&self, generator: &ActorGenerator) -> String {
-        let fields = self
+    fn to_source(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
+        let mut fields = self
             .handles
             .iter()
-            .map(|h| h.to_rust(generator))
-            .collect::<Vec<_>>()
-            .join(",\n\t");
-        format!(
+            .map(|h| h.to_source(ctx))
+            .collect::<Result<Vec<_>, _>>()?;
+        if self.has_circuit_breakers() {
+            fields.push(format!("pub breakers: {}Breakers", ctx.actor.ident));
+        }
+        let fields = fields.join(",\n\t");
+        let helpers = if self.handles.is_empty() {
+            String::new()
+        } else {
+            let helpers = self
+                .handles
+                .iter()
+                .map(MessageHandle::send_helpers)
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            format!("\n\nimpl {} {{\n{helpers}\n}}", self.ident)
+        };
+        Ok(format!(
             "pub struct {ident} {{
     {fields}
-}}",
+}}{helpers}",
             ident = self.ident
-        )
+        ))
     }
 }
 
@@ -137,25 +685,154 @@ impl MessageReceivers {
         self.receivers.push(receiver);
     }
 
+    /// Whether any receiver checks the sequence numbers of its payloads
+    pub fn has_sequenced(&self) -> bool {
+        self.receivers.iter().any(|r| r.sequenced)
+    }
+
+    /// Whether any receiver limits the rate its messages are dispatched at
+    pub fn has_rate_limits(&self) -> bool {
+        self.receivers.iter().any(|r| r.rate_limit.is_some())
+    }
+
+    /// Whether any receiver is fed from an external broker
+    pub fn has_sources(&self) -> bool {
+        self.receivers.iter().any(|r| r.source.is_some())
+    }
+
     /// Get a receiver by name
     pub fn get_receiver(&self, name: &str) -> Option<&MessageReceiver> {
         self.receivers.iter().find(|r| r.ident == name)
     }
 }
 
+/// Templates the handles and receivers derived from a message set are named with
+///
+/// `{variant}` stands for the variant's name in `snake_case`, `{type}` for the lowercase
+/// name of its payload type, the last segment of its path outside any generic arguments,
+/// and `{index}` for its position in the message set. A variant carrying several payloads is
+/// sent as a tuple of them, with their type names joined by `_` for `{type}`, and one without
+/// any as `()`, named `unit`. Names that start with a digit or are keywords get a leading
+/// `_`, and when two variants would get the same name, the later one has its index appended.
+/// Names that still aren't identifiers are reported when the actor is validated.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct HandleNaming {
+    /// Template of handle names, `{type}_handle` by default
+    pub handle: String,
+    /// Template of receiver names, `{type}_rx` by default
+    pub receiver: String,
+}
+
+impl Default for HandleNaming {
+    fn default() -> Self {
+        Self {
+            handle: "{type}_handle".to_string(),
+            receiver: "{type}_rx".to_string(),
+        }
+    }
+}
+
+impl HandleNaming {
+    pub fn new(handle: impl Into<String>, receiver: impl Into<String>) -> Self {
+        Self {
+            handle: handle.into(),
+            receiver: receiver.into(),
+        }
+    }
+
+    /// One handle and one receiver per variant of `message_set`, in the order of the variants
+    pub fn derive(
+        &self,
+        actor_ident: &str,
+        message_set: Option<&MessageSet>,
+    ) -> (MessageHandles, MessageReceivers) {
+        let mut handles = MessageHandles::new(format!("{actor_ident}Handles"));
+        let mut receivers = MessageReceivers::new(format!("{actor_ident}Receivers"));
+        let Some(message_set) = message_set else {
+            return (handles, receivers);
+        };
+
+        for (index, variant) in message_set.get().variants.iter().enumerate() {
+            let types = variant
+                .args
+                .iter()
+                .map(|arg| arg.as_ref())
+                .collect::<Vec<_>>();
+            let type_names = types.iter().map(|ty| type_name(ty)).collect::<Vec<_>>();
+            let (message_type, type_name) = match types.as_slice() {
+                [] => ("()".to_string(), "unit".to_string()),
+                [single] => (single.to_string(), type_names.join("_")),
+                many => (format!("({})", many.join(", ")), type_names.join("_")),
+            };
+            let name = |template: &str| {
+                let name = template
+                    .replace("{variant}", &snake_case(&variant.ident))
+                    .replace("{type}", &type_name)
+                    .replace("{index}", &index.to_string());
+                if !is_ident(&name) && is_ident(&format!("_{name}")) {
+                    format!("_{name}")
+                } else {
+                    name
+                }
+            };
+
+            let mut handle = name(&self.handle);
+            if handles.get_handle(&handle).is_some() {
+                handle = format!("{handle}_{index}");
+            }
+            handles.add_handle(MessageHandle::new(handle, message_type.clone()));
+
+            let mut receiver = name(&self.receiver);
+            if receivers.get_receiver(&receiver).is_some() {
+                receiver = format!("{receiver}_{index}");
+            }
+            receivers.add_receiver(MessageReceiver::new(receiver, message_type));
+        }
+        (handles, receivers)
+    }
+}
+
+/// Lowercase name of a payload type for naming its channel, like `handle` for
+/// `tokio::Handle<Runtime>`
+fn type_name(ty: &str) -> String {
+    let outer = ty.split('<').next().unwrap_or_default();
+    let last = outer.rsplit("::").next().unwrap_or_default();
+    let name = last
+        .trim_start_matches(|c: char| !is_ident_char(c))
+        .chars()
+        .take_while(|&c| is_ident_char(c))
+        .collect::<String>();
+    if name.is_empty() {
+        "payload".to_string()
+    } else {
+        name.to_lowercase()
+    }
+}
+
+#[cfg(feature = "codegen")]
 impl ToRust for MessageReceivers {
-    fn to_rust(&self, generator: &ActorGenerator) -> String {
-        let fields = self
+    fn to_source(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
+        let mut fields = self
             .receivers
             .iter()
-            .map(|mr| mr.to_rust(generator))
-            .collect::<Vec<_>>()
-            .join(",\n\t");
-        format!(
+            .map(|mr| mr.to_source(ctx))
+            .collect::<Result<Vec<_>, _>>()?;
+        // The actor's own queue carries whole messages, so it is not a spec receiver
+        if let (Some(message_set), true) =
+            (&ctx.actor.component.message_set, ctx.actor.has_self_queue())
+        {
+            fields.push(format!(
+                "pub {SELF_RECEIVER}: UnboundedReceiver<{}>",
+                message_set.get().ident
+            ));
+        }
+        let fields = fields.join(",\n\t");
+        Ok(format!(
             "pub struct {ident} {{
     {fields}
 }}",
             ident = self.ident
-        )
+        ))
     }
 }
@@ -1,12 +1,71 @@
+use alloc::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 use super::enums::EnumDef;
+use crate::alloc_prelude::*;
+use crate::link::{Link, type_names};
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
 pub struct MessageSet {
     pub def: EnumDef,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub custom_types: Vec<EnumDef>,
+    /// Traits implemented by external payload types, keyed by type name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub payload_traits: BTreeMap<String, Vec<String>>,
+    /// Other names for existing types, generated as `pub type` items
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<TypeAlias>,
+    /// Distinct types wrapping an existing one, generated as tuple structs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub newtypes: Vec<Newtype>,
+    /// Version of the message set on the wire, bumped when variants change so peers running
+    /// the previous version can be bridged with generated shims
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+}
+
+/// `pub type {ident} = {ty};`, e.g. `OrderId` for `u64`
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct TypeAlias {
+    pub ident: String,
+    pub ty: Link,
+}
+
+impl TypeAlias {
+    pub fn new(ident: impl Into<String>, ty: impl Into<Link>) -> Self {
+        Self {
+            ident: ident.into(),
+            ty: ty.into(),
+        }
+    }
+}
+
+/// `pub struct {ident}(pub {ty});`, deriving what custom types do and `derives`
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct Newtype {
+    pub ident: String,
+    pub ty: Link,
+    /// Traits derived on top of the ones every custom type derives, e.g. `Eq` and `Hash`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derives: Vec<String>,
+}
+
+impl Newtype {
+    pub fn new(ident: impl Into<String>, ty: impl Into<Link>) -> Self {
+        Self {
+            ident: ident.into(),
+            ty: ty.into(),
+            derives: Vec::new(),
+        }
+    }
+
+    /// Derives `derives` too
+    pub fn with_derives<D: Into<String>>(mut self, derives: impl IntoIterator<Item = D>) -> Self {
+        self.derives.extend(derives.into_iter().map(Into::into));
+        self
+    }
 }
 
 impl MessageSet {
@@ -14,11 +73,76 @@ impl MessageSet {
         Self {
             def,
             custom_types: Vec::new(),
+            payload_traits: BTreeMap::new(),
+            aliases: Vec::new(),
+            newtypes: Vec::new(),
+            version: None,
         }
     }
 
     pub fn with_custom_types(def: EnumDef, custom_types: Vec<EnumDef>) -> Self {
-        Self { def, custom_types }
+        Self {
+            def,
+            custom_types,
+            payload_traits: BTreeMap::new(),
+            aliases: Vec::new(),
+            newtypes: Vec::new(),
+            version: None,
+        }
+    }
+
+    pub fn with_aliases(mut self, aliases: Vec<TypeAlias>) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    pub fn with_newtypes(mut self, newtypes: Vec<Newtype>) -> Self {
+        self.newtypes = newtypes;
+        self
+    }
+
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Names of the types the messaging module defines
+    pub fn defined_types(&self) -> impl Iterator<Item = &str> {
+        core::iter::once(self.def.ident.as_str())
+            .chain(self.custom_types.iter().map(|c| c.ident.as_str()))
+            .chain(self.aliases.iter().map(|a| a.ident.as_str()))
+            .chain(self.newtypes.iter().map(|n| n.ident.as_str()))
+    }
+
+    /// Checks that every defined type has a name of its own and no alias or newtype wraps
+    /// itself
+    pub fn validate(&self) -> Result<(), String> {
+        let defined = self.defined_types().collect::<Vec<_>>();
+        for (i, ident) in defined.iter().enumerate() {
+            if defined[..i].contains(ident) {
+                return Err(format!(
+                    "Type '{ident}' is defined twice in the message set"
+                ));
+            }
+        }
+        let wrapped = self
+            .aliases
+            .iter()
+            .map(|a| (&a.ident, &a.ty))
+            .chain(self.newtypes.iter().map(|n| (&n.ident, &n.ty)));
+        for (ident, ty) in wrapped {
+            if type_names(ty.as_ref()).contains(ident) {
+                return Err(format!("Type '{ident}' is defined in terms of itself"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the spec declares that an external payload type implements a trait
+    pub fn payload_implements(&self, type_name: &str, trait_name: &str) -> bool {
+        self.payload_traits
+            .get(type_name)
+            .is_some_and(|traits| traits.iter().any(|t| t == trait_name))
     }
 
     pub fn get(&self) -> &EnumDef {
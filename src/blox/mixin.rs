@@ -0,0 +1,112 @@
+#[cfg(feature = "std")]
+use std::{error::Error, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::alloc_prelude::*;
+use crate::{Method, field::Field};
+
+/// A reusable group of fields and methods merged into an actor's extended state
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct Mixin {
+    pub ident: String,
+    #[serde(default)]
+    pub fields: Vec<Field>,
+    #[serde(default)]
+    pub methods: Vec<Method>,
+}
+
+impl Mixin {
+    pub fn new<S>(ident: S, fields: Vec<Field>, methods: Vec<Method>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            ident: ident.into(),
+            fields,
+            methods,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn from_json_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Error reading mixin {}: {e}", path.display()),
+            )
+        })?;
+        serde_json::from_str(&contents).map_err(From::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_apply_mixin_merges_fields_and_methods() {
+        let mut actor = create_test_actor();
+        let mixin = Mixin::from_json_file(Path::new("tests/mixins/has_metrics.json"))
+            .expect("Mixin fixture should load");
+
+        actor
+            .component
+            .ext_state
+            .apply_mixin(&mixin)
+            .expect("Mixin should apply");
+
+        let ext_state = &actor.component.ext_state;
+        assert!(
+            ext_state
+                .fields()
+                .iter()
+                .any(|f| f.ident() == "messages_handled")
+        );
+        assert!(
+            ext_state
+                .methods()
+                .iter()
+                .any(|m| m.ident() == "record_message")
+        );
+    }
+
+    #[test]
+    fn test_apply_mixin_detects_conflicts() {
+        let mut actor = create_test_actor();
+        let mixin = Mixin::new("clashing", vec![Field::new("field1", "u8")], vec![]);
+
+        let err = actor
+            .component
+            .ext_state
+            .apply_mixin(&mixin)
+            .expect_err("Conflicting field should be rejected");
+        assert!(err.contains("field1"));
+        assert!(err.contains("clashing"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_resolve_mixins_relative_to_spec() {
+        let mut actor = create_test_actor();
+        actor
+            .component
+            .ext_state
+            .add_mixin("mixins/has_metrics.json");
+
+        actor
+            .resolve_mixins(Path::new("tests"))
+            .expect("Mixins should resolve");
+
+        let ext_state = &actor.component.ext_state;
+        assert!(ext_state.mixins().is_empty());
+        assert!(
+            ext_state
+                .fields()
+                .iter()
+                .any(|f| f.ident() == "messages_handled")
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+/// Key of an overlay object whose items are appended to the base array
+const APPEND: &str = "$append";
+/// Key of an overlay object whose items replace base items with the same `ident`
+const MERGE: &str = "$merge";
+
+/// The overlay for `env` next to a spec: `spec.json` becomes `spec.prod.json`
+pub fn overlay_path(spec: &Path, env: &str) -> PathBuf {
+    let stem = spec.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match spec.extension() {
+        Some(ext) => format!("{stem}.{env}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{env}"),
+    };
+    spec.with_file_name(name)
+}
+
+/// Deep-merges an environment overlay into a spec
+///
+/// Objects are merged key by key and `null` removes a key. Arrays are replaced
+/// unless the overlay gives `{"$append": [...]}`, which appends its items, or
+/// `{"$merge": [...]}`, which merges each item into the base item with the same
+/// `ident` and appends the rest.
+pub fn merge(base: &mut Value, overlay: Value) -> Result<(), String> {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match value {
+                    Value::Null => {
+                        base.remove(&key);
+                    }
+                    value => match base.get_mut(&key) {
+                        Some(existing) => {
+                            merge(existing, value).map_err(|e| format!("{key}: {e}"))?
+                        }
+                        None => {
+                            base.insert(key, strip_strategies(value)?);
+                        }
+                    },
+                }
+            }
+            Ok(())
+        }
+        (Value::Array(base), Value::Object(overlay)) if is_strategy(&overlay) => {
+            let (strategy, items) = array_strategy(overlay)?;
+            match strategy {
+                APPEND => base.extend(items),
+                _ => {
+                    for item in items {
+                        match item.get("ident").and_then(|ident| find_ident(base, ident)) {
+                            Some(existing) => merge(existing, item)?,
+                            None => base.push(item),
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        (base, overlay) => {
+            *base = strip_strategies(overlay)?;
+            Ok(())
+        }
+    }
+}
+
+fn is_strategy(object: &Map<String, Value>) -> bool {
+    object.contains_key(APPEND) || object.contains_key(MERGE)
+}
+
+/// Splits `{"$append": [...]}` or `{"$merge": [...]}` into its strategy and items
+fn array_strategy(mut object: Map<String, Value>) -> Result<(&'static str, Vec<Value>), String> {
+    let strategy = if object.contains_key(APPEND) {
+        APPEND
+    } else {
+        MERGE
+    };
+    let items = object.remove(strategy);
+    if !object.is_empty() {
+        return Err(format!("`{strategy}` can't be combined with other keys"));
+    }
+    match items {
+        Some(Value::Array(items)) => Ok((strategy, items)),
+        _ => Err(format!("`{strategy}` expects an array")),
+    }
+}
+
+fn find_ident<'a>(items: &'a mut [Value], ident: &Value) -> Option<&'a mut Value> {
+    items
+        .iter_mut()
+        .find(|item| item.get("ident") == Some(ident))
+}
+
+/// Turns array strategies with nothing to apply to into plain arrays
+fn strip_strategies(value: Value) -> Result<Value, String> {
+    match value {
+        Value::Object(object) if is_strategy(&object) => {
+            array_strategy(object).map(|(_, items)| Value::Array(items))
+        }
+        Value::Object(object) => object
+            .into_iter()
+            .map(|(key, value)| Ok((key, strip_strategies(value)?)))
+            .collect::<Result<_, _>>()
+            .map(Value::Object),
+        Value::Array(items) => items
+            .into_iter()
+            .map(strip_strategies)
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        value => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::Actor;
+    use serde_json::json;
+
+    #[test]
+    fn test_overlay_path() {
+        assert_eq!(
+            overlay_path(Path::new("specs/actor.json"), "prod"),
+            PathBuf::from("specs/actor.prod.json")
+        );
+        assert_eq!(
+            overlay_path(Path::new("actor"), "dev"),
+            PathBuf::from("actor.dev")
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut base = json!({
+            "ident": "Actor",
+            "path": "src",
+            "health_check": true,
+            "fields": [{ "ident": "a", "ty": "u8" }, { "ident": "b", "ty": "u8" }],
+            "methods": [{ "ident": "m" }],
+            "tags": ["x"],
+        });
+        merge(
+            &mut base,
+            json!({
+                "path": "prod/src",
+                "health_check": null,
+                "fields": { "$merge": [{ "ident": "b", "ty": "u64" }, { "ident": "c", "ty": "u8" }] },
+                "methods": { "$append": [{ "ident": "n" }] },
+                "tags": ["y"],
+                "schedule": { "timers": { "$append": [{ "ident": "tick" }] } },
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(
+            base,
+            json!({
+                "ident": "Actor",
+                "path": "prod/src",
+                "fields": [
+                    { "ident": "a", "ty": "u8" },
+                    { "ident": "b", "ty": "u64" },
+                    { "ident": "c", "ty": "u8" },
+                ],
+                "methods": [{ "ident": "m" }, { "ident": "n" }],
+                "tags": ["y"],
+                "schedule": { "timers": [{ "ident": "tick" }] },
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_strategy() {
+        let mut base = json!({ "fields": [] });
+        assert_eq!(
+            merge(&mut base, json!({ "fields": { "$append": {} } })),
+            Err("fields: `$append` expects an array".to_string())
+        );
+        assert_eq!(
+            merge(
+                &mut base,
+                json!({ "fields": { "$merge": [], "$append": [] } })
+            ),
+            Err("fields: `$append` can't be combined with other keys".to_string())
+        );
+    }
+
+    #[test]
+    fn test_from_json_file_for_env() {
+        let spec = PathBuf::from("tests/project/actors/actor.json");
+        let base = Actor::from_json_file(&spec).unwrap();
+        let prod = Actor::from_json_file_for_env(&spec, "prod").unwrap();
+
+        let fields = |actor: &Actor| {
+            actor
+                .component
+                .ext_state
+                .fields()
+                .iter()
+                .map(|f| format!("{}: {}", f.ident(), f.ty()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(fields(&base), ["field1: String", "field2: i32"]);
+        assert_eq!(
+            fields(&prod),
+            ["field1: String", "field2: i64", "region: String"]
+        );
+        assert_eq!(prod.component.states, base.component.states);
+
+        // Environments without an overlay are most likely misspelt
+        assert_eq!(
+            Actor::from_json_file_for_env(&spec, "dev")
+                .unwrap_err()
+                .to_string(),
+            "tests/project/actors/actor.json has no overlay for 'dev': \
+             tests/project/actors/actor.dev.json doesn't exist"
+        );
+    }
+}
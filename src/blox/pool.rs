@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use crate::alloc_prelude::*;
+
+/// How a pool router picks the instance that receives a message
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Routing {
+    /// Cycle through the instances in order
+    #[default]
+    RoundRobin,
+    /// Hash the pool's key so messages with equal keys reach the same instance
+    HashByKey,
+}
+
+/// Generates a router that spreads an actor's messages across several instances
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+#[serde(rename = "pool")]
+pub struct Pool {
+    #[serde(default)]
+    pub routing: Routing,
+    /// Expression over the outgoing `message` producing a hashable key, used by `HashByKey`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl Pool {
+    pub fn round_robin() -> Self {
+        Self::default()
+    }
+
+    pub fn hash_by_key<S: Into<String>>(key: S) -> Self {
+        Self {
+            routing: Routing::HashByKey,
+            key: Some(key.into()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.routing, &self.key) {
+            (Routing::HashByKey, None) => {
+                Err("Pool routing 'hash_by_key' requires a 'key' expression".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Generates a dispatcher that owns one instance per key, spawned on first use
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(rename = "shard")]
+pub struct Shard {
+    /// Expression over the incoming `message` producing an owned key
+    pub key: String,
+    /// Type of the key, which must implement `Eq`, `Hash` and `Clone`
+    pub key_type: String,
+}
+
+impl Shard {
+    pub fn new<K, T>(key: K, key_type: T) -> Self
+    where
+        K: Into<String>,
+        T: Into<String>,
+    {
+        Self {
+            key: key.into(),
+            key_type: key_type.into(),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+
+use super::component::Component;
+use crate::alloc_prelude::*;
+
+/// Sends a message to the actor whenever a cron expression fires
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct Schedule {
+    /// Five-field cron expression (minute, hour, day of month, month, day of week) in UTC,
+    /// or one of `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly`
+    pub cron: String,
+    /// Variant the message is sent as
+    pub variant: String,
+    /// Expression building the payload sent each time
+    #[serde(default = "default_payload")]
+    pub payload: String,
+}
+
+fn default_payload() -> String {
+    "Default::default()".to_string()
+}
+
+impl Schedule {
+    pub fn new<C, V>(cron: C, variant: V) -> Self
+    where
+        C: Into<String>,
+        V: Into<String>,
+    {
+        Self {
+            cron: cron.into(),
+            variant: variant.into(),
+            payload: default_payload(),
+        }
+    }
+
+    /// Sets the expression building the payload sent each time
+    pub fn with_payload<S: Into<String>>(mut self, payload: S) -> Self {
+        self.payload = payload.into();
+        self
+    }
+
+    pub fn validate(&self, component: &Component) -> Result<(), String> {
+        Cron::parse(&self.cron)?;
+
+        let args = component
+            .message_set
+            .as_ref()
+            .and_then(|ms| ms.get().variants.iter().find(|v| v.ident == self.variant))
+            .map(|v| v.args.len());
+        match args {
+            None => Err(format!(
+                "Schedule '{}' sends unknown variant '{}'",
+                self.cron, self.variant
+            )),
+            Some(1) => match component.receiver_for_variant(&self.variant) {
+                None => Err(format!(
+                    "Variant '{}' of schedule '{}' has no receiver",
+                    self.variant, self.cron
+                )),
+                // The receiver checks one sender's stream, which the scheduler would interleave
+                Some(receiver) if receiver.sequenced => Err(format!(
+                    "Variant '{}' of schedule '{}' arrives on sequenced receiver '{}', which schedules can't send to",
+                    self.variant, self.cron, receiver.ident
+                )),
+                Some(_) => Ok(()),
+            },
+            Some(_) => Err(format!(
+                "Variant '{}' of schedule '{}' must carry exactly one payload",
+                self.variant, self.cron
+            )),
+        }
+    }
+}
+
+/// A parsed cron expression, each field a bit set of the values it fires on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cron {
+    pub minutes: u64,
+    pub hours: u32,
+    pub days_of_month: u32,
+    pub months: u16,
+    /// Sunday is bit 0
+    pub days_of_week: u8,
+    /// Both day fields are restricted, so a day matching either one fires
+    pub either_day: bool,
+}
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+const DAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expanded = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields = expanded.split_whitespace().collect::<Vec<_>>();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(format!(
+                "Cron expression '{expr}' must have 5 fields, found {}",
+                fields.len()
+            ));
+        };
+
+        let field = |name: &str, value: &str, min: u8, max: u8, names: &[&str]| {
+            parse_field(value, min, max, names)
+                .map_err(|e| format!("Invalid {name} field in cron expression '{expr}': {e}"))
+        };
+        // Day of week 7 is Sunday too
+        let days_of_week = field("day of week", day_of_week, 0, 7, DAY_NAMES)?;
+        Ok(Self {
+            minutes: field("minute", minute, 0, 59, &[])?,
+            hours: field("hour", hour, 0, 23, &[])? as u32,
+            days_of_month: field("day of month", day_of_month, 1, 31, &[])? as u32,
+            months: field("month", month, 1, 12, MONTH_NAMES)? as u16,
+            days_of_week: ((days_of_week | days_of_week >> 7) & 0x7f) as u8,
+            either_day: !day_of_month.starts_with('*') && !day_of_week.starts_with('*'),
+        })
+    }
+}
+
+/// Parses one field into a bit set, accepting `*`, values, ranges, steps and lists
+fn parse_field(field: &str, min: u8, max: u8, names: &[&str]) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u8, String> {
+        // Named values count from the field's minimum, so `jan` is 1 and `sun` is 0
+        let named = names
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(s))
+            .map(|i| i as u8 + min);
+        let value = match named {
+            Some(value) => value,
+            None => s.parse().map_err(|_| format!("'{s}' is not a value"))?,
+        };
+        if (min..=max).contains(&value) {
+            Ok(value)
+        } else {
+            Err(format!("{value} is outside {min}-{max}"))
+        }
+    };
+
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u8>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("'{step}' is not a step")),
+            },
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // A single value with a step runs to the end of the field, as in `5/15`
+            None if step > 1 => (value(range)?, max),
+            None => {
+                let value = value(range)?;
+                (value, value)
+            }
+        };
+        if start > end {
+            return Err(format!("range {start}-{end} is reversed"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cron() {
+        let cron = Cron::parse("*/15 9-17 * * mon-fri").unwrap();
+        assert_eq!(cron.minutes, 1 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(cron.hours, 0b111111111 << 9);
+        assert_eq!(cron.days_of_month, u32::MAX << 1);
+        assert_eq!(cron.months, 0b111111111111 << 1);
+        assert_eq!(cron.days_of_week, 0b0111110);
+        assert!(!cron.either_day);
+
+        let cron = Cron::parse("0,30 0 1,15 jan,JUL 7").unwrap();
+        assert_eq!(cron.minutes, 1 | 1 << 30);
+        assert_eq!(cron.days_of_month, 1 << 1 | 1 << 15);
+        assert_eq!(cron.months, 1 << 1 | 1 << 7);
+        assert_eq!(cron.days_of_week, 1);
+        assert!(cron.either_day);
+
+        assert_eq!(Cron::parse("@daily"), Cron::parse("0 0 * * *"));
+        assert_eq!(
+            Cron::parse("5/20 * * * *").unwrap().minutes,
+            1 << 5 | 1 << 25 | 1 << 45
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_cron() {
+        assert_eq!(
+            Cron::parse("* * * *").unwrap_err(),
+            "Cron expression '* * * *' must have 5 fields, found 4"
+        );
+        assert_eq!(
+            Cron::parse("60 * * * *").unwrap_err(),
+            "Invalid minute field in cron expression '60 * * * *': 60 is outside 0-59"
+        );
+        assert_eq!(
+            Cron::parse("* * 0 * *").unwrap_err(),
+            "Invalid day of month field in cron expression '* * 0 * *': 0 is outside 1-31"
+        );
+        assert!(Cron::parse("*/0 * * * *").is_err());
+        assert!(Cron::parse("* 5-2 * * *").is_err());
+        assert!(Cron::parse("* * * foo *").is_err());
+    }
+}
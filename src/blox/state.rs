@@ -1,7 +1,15 @@
+#[cfg(feature = "codegen")]
+use alloc::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 use super::enums::{EnumDef, EnumVariant};
-use crate::create::{ActorGenerator, ToRust};
+use crate::alloc_prelude::*;
+#[cfg(feature = "codegen")]
+use crate::create::{DispatchStrategy, RenderCtx, RenderError, ToRust, deprecated_attr};
+#[cfg(feature = "codegen")]
+use crate::lint::mentions;
+use crate::{Field, Method};
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
 #[serde(rename = "state_enum")]
@@ -17,6 +25,71 @@ impl StateEnum {
     }
 }
 
+/// An outgoing message sent through one of the component's handles without waiting, logged
+/// and dropped when the channel is full or closed
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(rename = "emit")]
+pub struct Emit {
+    /// Name of the handle in the component's `MessageHandles`
+    pub handle: String,
+    /// Expression building the message, which may borrow the received `message`
+    ///
+    /// Handles of correlated actors carrying `Correlated` payloads take the payload instead,
+    /// which is tagged with the received message's correlation ID before it is sent.
+    pub message: String,
+}
+
+impl Emit {
+    pub fn new<H, M>(handle: H, message: M) -> Self
+    where
+        H: Into<String>,
+        M: Into<String>,
+    {
+        Self {
+            handle: handle.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// A transition taken when a state receives a message set variant
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(rename = "transition")]
+pub struct Transition {
+    /// Message set variant that triggers the transition
+    pub on: String,
+    /// State entered, which may be any state including a parent or ancestor
+    pub target: String,
+    /// Messages sent before the transition is taken
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emit: Vec<Emit>,
+}
+
+impl Transition {
+    pub fn new<O, T>(on: O, target: T) -> Self
+    where
+        O: Into<String>,
+        T: Into<String>,
+    {
+        Self {
+            on: on.into(),
+            target: target.into(),
+            emit: Vec::new(),
+        }
+    }
+
+    /// Adds messages sent through handles when the transition is taken
+    pub fn with_emit(mut self, emit: Vec<Emit>) -> Self {
+        self.emit = emit;
+        self
+    }
+
+    #[cfg(feature = "codegen")]
+    fn has_emits(&self) -> bool {
+        !self.emit.is_empty()
+    }
+}
+
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
 #[serde(rename = "state")]
 pub struct State {
@@ -24,6 +97,20 @@ pub struct State {
     pub parent: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub variants: Option<Vec<EnumVariant>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Field>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub methods: Vec<Method>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<Transition>,
+    /// Why the state is deprecated, rendered as `#[deprecated(note = ...)]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+    /// Boolean expressions that hold while the actor is in the state, asserted with
+    /// `debug_assert!` on entry; they may read the extended state as `ext` and the state as
+    /// `self`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invariants: Vec<String>,
 }
 
 impl State {
@@ -35,7 +122,319 @@ impl State {
             ident: ident.into(),
             parent,
             variants,
+            fields: Vec::new(),
+            methods: Vec::new(),
+            transitions: Vec::new(),
+            deprecated: None,
+            invariants: Vec::new(),
+        }
+    }
+
+    /// Adds data fields, turning the state into a struct with named fields
+    pub fn with_fields(mut self, fields: Vec<Field>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Adds helper methods emitted in an inherent `impl` block for the state
+    pub fn with_methods(mut self, methods: Vec<Method>) -> Self {
+        self.methods = methods;
+        self
+    }
+
+    /// Adds the transitions rendered into the state's `handle_message`
+    pub fn with_transitions(mut self, transitions: Vec<Transition>) -> Self {
+        self.transitions = transitions;
+        self
+    }
+
+    /// Adds invariants asserted whenever the state is entered
+    pub fn with_invariants(mut self, invariants: Vec<String>) -> Self {
+        self.invariants = invariants;
+        self
+    }
+
+    /// Deprecates the generated state
+    pub fn deprecated<S: Into<String>>(mut self, note: S) -> Self {
+        self.deprecated = Some(note.into());
+        self
+    }
+
+    /// Whether the state carries data rather than being a unit struct
+    pub fn has_data(&self) -> bool {
+        !self.fields.is_empty() || self.variants.as_ref().is_some_and(|v| !v.is_empty())
+    }
+
+    /// Expression constructing the initial value of this state
+    pub fn constructor(&self) -> String {
+        if self.has_data() {
+            format!("{}::default()", self.ident)
+        } else {
+            self.ident.clone()
+        }
+    }
+
+    #[cfg(feature = "codegen")]
+    fn render_handle_message(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
+        let component_type = ctx.component_type();
+        let message_set = ctx.message_set();
+        let signature = |state_machine: &str, message: &str| {
+            format!(
+                r#"fn handle_message(
+        &self,
+        {state_machine}: &mut StateMachine<{component_type}>,
+        {message}: {message_set},
+    ) -> Option<Transition<<{component_type} as Components>::States, {message_set}>>"#
+            )
+        };
+
+        // Invariants checked after every message must also hold in the state transitioned from
+        let check_invariants = ctx.options.invariants_after_messages && !self.invariants.is_empty();
+        if self.transitions.is_empty() {
+            return Ok(if check_invariants {
+                format!(
+                    "{} {{\n        self.assert_invariants(state_machine);\n        None\n    }}",
+                    signature("state_machine", "_message")
+                )
+            } else {
+                format!(
+                    "{} {{\n        None\n    }}",
+                    signature("_state_machine", "_message")
+                )
+            });
         }
+
+        let states = &ctx.actor.component.states;
+        let handles = &ctx.actor.component.message_handles;
+        let state_enum = &states.state_enum.get().ident;
+        let message_variants = ctx
+            .actor
+            .component
+            .message_set
+            .as_ref()
+            .map(|message_set| message_set.get().variants.as_slice())
+            .unwrap_or_default();
+
+        let arms = self
+            .transitions
+            .iter()
+            .map(|transition| {
+                let variant = message_variants
+                    .iter()
+                    .find(|v| v.ident == transition.on)
+                    .ok_or_else(|| {
+                        RenderError::InvalidSpec(format!(
+                            "State '{}' transitions on unknown message '{}'",
+                            self.ident, transition.on
+                        ))
+                    })?;
+                let target = states.get_state(&transition.target).ok_or_else(|| {
+                    RenderError::InvalidSpec(format!(
+                        "State '{}' transitions to unknown state '{}'",
+                        self.ident, transition.target
+                    ))
+                })?;
+                let pattern = if variant.args.is_empty() {
+                    format!("{message_set}::{}", variant.ident)
+                } else {
+                    format!("{message_set}::{}(..)", variant.ident)
+                };
+                let transition_value = format!(
+                    "Some(Transition::To({state_enum}::{}({})))",
+                    target.ident,
+                    target.constructor()
+                );
+                if !transition.has_emits() {
+                    return Ok(format!("            {pattern} => {transition_value},"));
+                }
+
+                let sends = transition
+                    .emit
+                    .iter()
+                    .map(|emit| {
+                        let handle = handles.get_handle(&emit.handle).ok_or_else(|| {
+                            RenderError::InvalidSpec(format!(
+                                "State '{}' emits through unknown handle '{}'",
+                                self.ident, emit.handle
+                            ))
+                        })?;
+                        // Guarded handles send through the helper so the breaker sees the result
+                        let send = if handle.circuit_breaker.is_some() {
+                            format!("try_send_{}", handle.helper_name())
+                        } else {
+                            format!("{}.try_send", emit.handle)
+                        };
+                        let sent = if handle.is_correlated() {
+                            format!("Message::new(0, message.correlate({}))", emit.message)
+                        } else {
+                            emit.message.clone()
+                        };
+                        // A full or closed channel drops the message, which is worth a log
+                        Ok(format!(
+                            r#"                if state_machine.self_handles.{send}({sent}).is_err() {{
+                    eprintln!("{state} dropped a message it emits through `{handle}`");
+                }}"#,
+                            state = self.ident,
+                            handle = emit.handle,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, RenderError>>()?
+                    .join("\n");
+                Ok(format!(
+                    "            {pattern} => {{\n{sends}\n                {transition_value}\n            }}"
+                ))
+            })
+            .collect::<Result<Vec<_>, RenderError>>()?
+            .join("\n");
+
+        let fallthrough = if self.transitions.len() < message_variants.len() {
+            "\n            _ => None,"
+        } else {
+            ""
+        };
+        let state_machine =
+            if check_invariants || self.transitions.iter().any(Transition::has_emits) {
+                "state_machine"
+            } else {
+                "_state_machine"
+            };
+
+        let dispatch = format!("match &message {{\n{arms}{fallthrough}\n        }}");
+        let body = if check_invariants {
+            format!(
+                "let transition = {dispatch};\n        self.assert_invariants(state_machine);\n        transition"
+            )
+        } else {
+            dispatch
+        };
+        Ok(format!(
+            "{} {{\n        {body}\n    }}",
+            signature(state_machine, "message")
+        ))
+    }
+
+    /// `on_entry` asserting the state's invariants and the inherent method asserting them, if
+    /// it declares any
+    #[cfg(feature = "codegen")]
+    fn render_invariants(&self, ctx: &RenderCtx) -> (String, String) {
+        if self.invariants.is_empty() {
+            return (String::new(), String::new());
+        }
+        let state_name = &self.ident;
+        let component_type = ctx.component_type();
+        let reads_ext = self.invariants.iter().any(|expr| mentions(expr, "ext"));
+        let (state_machine, ext) = if reads_ext {
+            (
+                "state_machine",
+                "\n        let ext = &state_machine.extended_state;",
+            )
+        } else {
+            ("_state_machine", "")
+        };
+        let asserts = self
+            .invariants
+            .iter()
+            .map(|expr| {
+                // The message is a format string, so braces in the expression are escaped
+                let message = format!("invariant of {state_name} violated: {expr}")
+                    .replace('{', "{{")
+                    .replace('}', "}}");
+                format!("\n        debug_assert!({expr}, {message:?});")
+            })
+            .collect::<String>();
+
+        let on_entry = format!(
+            r#"
+
+    fn on_entry(&self, state_machine: &mut StateMachine<{component_type}>) {{
+        self.assert_invariants(state_machine);
+    }}"#
+        );
+        let assert_invariants = format!(
+            r#"
+
+impl {state_name} {{
+    /// Asserts the invariants of the {state_name} state in debug builds
+    fn assert_invariants(&self, {state_machine}: &StateMachine<{component_type}>) {{{ext}{asserts}
+    }}
+}}"#
+        );
+        (on_entry, assert_invariants)
+    }
+
+    #[cfg(feature = "codegen")]
+    fn render_definition(&self, derives: &str) -> String {
+        let state_name = &self.ident;
+        let variants = self.variants.as_deref().unwrap_or_default();
+        let deprecated = |note: Option<&str>, indent: &str| {
+            note.map(|note| format!("{indent}{}\n", deprecated_attr(note)))
+                .unwrap_or_default()
+        };
+
+        let definition = if !variants.is_empty() {
+            let body = variants
+                .iter()
+                .map(|variant| {
+                    let deprecated = deprecated(variant.deprecated.as_deref(), "    ");
+                    if variant.args.is_empty() {
+                        format!("{deprecated}    {},", variant.ident)
+                    } else {
+                        let args = variant
+                            .args
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        format!("{deprecated}    {}({args}),", variant.ident)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let first = &variants[0];
+            let default_value = if first.args.is_empty() {
+                format!("{state_name}::{}", first.ident)
+            } else {
+                let defaults = vec!["Default::default()"; first.args.len()].join(", ");
+                format!("{state_name}::{}({defaults})", first.ident)
+            };
+
+            format!(
+                r#"#[derive(Debug, Clone, PartialEq{derives})]
+pub enum {state_name} {{
+{body}
+}}
+
+impl Default for {state_name} {{
+    fn default() -> Self {{
+        {default_value}
+    }}
+}}"#
+            )
+        } else if !self.fields.is_empty() {
+            let fields = self
+                .fields
+                .iter()
+                .map(|f| {
+                    let deprecated = deprecated(f.deprecation(), "    ");
+                    format!("{deprecated}    pub {}: {},", f.ident(), f.ty())
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            format!(
+                r#"#[derive(Debug, Clone, PartialEq, Default{derives})]
+pub struct {state_name} {{
+{fields}
+}}"#
+            )
+        } else {
+            format!(
+                r#"#[derive(Debug, Clone, PartialEq, Eq{derives})]
+pub struct {state_name};"#
+            )
+        };
+        format!("{}{definition}", deprecated(self.deprecated.as_deref(), ""))
     }
 }
 
@@ -61,6 +460,15 @@ impl States {
     }
 
     pub fn validate(&self) -> Result<(), String> {
+        if let Some(state) = self.states.iter().find(|state| {
+            !state.fields.is_empty() && state.variants.as_ref().is_some_and(|v| !v.is_empty())
+        }) {
+            return Err(format!(
+                "State '{}' declares both fields and variants",
+                state.ident
+            ));
+        }
+
         if let Some(state) = self.states.iter().find(|state| {
             // find state with a parent not in the list of states
             state
@@ -75,6 +483,31 @@ impl States {
             ));
         }
 
+        for state in &self.states {
+            if let Some(transition) = state
+                .transitions
+                .iter()
+                .find(|t| self.get_state(&t.target).is_none())
+            {
+                return Err(format!(
+                    "State '{}' transitions to unknown state '{}'",
+                    state.ident, transition.target
+                ));
+            }
+
+            if let Some(transition) = state.transitions.iter().enumerate().find_map(|(i, t)| {
+                state.transitions[..i]
+                    .iter()
+                    .any(|other| other.on == t.on)
+                    .then_some(t)
+            }) {
+                return Err(format!(
+                    "State '{}' declares more than one transition on '{}'",
+                    state.ident, transition.on
+                ));
+            }
+        }
+
         for variant in &self.state_enum.get().variants {
             variant
                 .args
@@ -97,86 +530,224 @@ impl States {
     }
 }
 
+#[cfg(feature = "codegen")]
 impl ToRust for State {
-    fn to_rust(&self, generator: &ActorGenerator) -> String {
+    fn to_source(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
         let state_name = &self.ident;
-        let component_type = generator.component_type();
-        let message_set = generator.message_set();
+        let component_type = ctx.component_type();
+        let definition = self.render_definition(&persisted_derives(ctx));
+        let handle_message = self.render_handle_message(ctx)?;
+        let (on_entry, assert_invariants) = self.render_invariants(ctx);
+
+        let methods_impl = if self.methods.is_empty() {
+            String::new()
+        } else {
+            let methods = self
+                .methods
+                .iter()
+                .map(|m| m.to_source(ctx))
+                .collect::<Result<Vec<_>, _>>()?
+                .join("\n    ");
+            format!("\n\nimpl {state_name} {{\n    {methods}\n}}")
+        };
 
-        format!(
+        Ok(format!(
             r#"/// State implementation for {state_name} state
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct {state_name};
+{definition}
 
 impl State<{component_type}> for {state_name} {{
-    fn handle_message(
-        &self,
-        _state_machine: &mut StateMachine<{component_type}>,
-        _message: {message_set},
-    ) -> Option<Transition<<{component_type} as Components>::States, {message_set}>> {{
-        None
-    }}
-}}"#
-        )
+    {handle_message}{on_entry}
+}}{assert_invariants}{methods_impl}"#
+        ))
     }
 }
 
-impl ToRust for StateEnum {
-    fn to_rust(&self, generator: &ActorGenerator) -> String {
-        let enum_def = self.get();
-        let enum_name = &enum_def.ident;
-        let component_type = generator.component_type();
-        let message_set = generator.message_set();
-
-        // Use actual states from the generator, not the empty enum_def.variants
-        let actual_states = &generator.actor().component.states.states;
-
-        let variants = actual_states
+#[cfg(feature = "codegen")]
+impl StateEnum {
+    /// Rustdoc table describing every state's parent, handled messages and transitions
+    fn render_doc_table(states: &[State]) -> String {
+        let link = |state: &str| format!("[`{state}`](Self::{state})");
+        let rows = states
             .iter()
             .map(|state| {
+                let parent = state
+                    .parent
+                    .as_deref()
+                    .map_or_else(|| "-".to_string(), link);
+                let (handles, transitions) = if state.transitions.is_empty() {
+                    ("-".to_string(), "-".to_string())
+                } else {
+                    let handles = state
+                        .transitions
+                        .iter()
+                        .map(|t| format!("`{}`", t.on))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let transitions = state
+                        .transitions
+                        .iter()
+                        .map(|t| {
+                            let emits = t
+                                .emit
+                                .iter()
+                                .map(|emit| format!("`{}`", emit.handle))
+                                .collect::<Vec<_>>();
+                            let emits = if emits.is_empty() {
+                                String::new()
+                            } else {
+                                format!(" (emits through {})", emits.join(", "))
+                            };
+                            format!("`{}` -> {}{emits}", t.on, link(&t.target))
+                        })
+                        .collect::<Vec<_>>()
+                        .join("<br>");
+                    (handles, transitions)
+                };
                 format!(
-                    "    /// {} state\n    {}({}),",
-                    state.ident, state.ident, state.ident
+                    "/// | {} | {parent} | {handles} | {transitions} |",
+                    link(&state.ident)
                 )
             })
             .collect::<Vec<_>>()
             .join("\n");
 
-        let handle_message_arms = actual_states.iter()
-            .map(|state| format!("            {enum_name}::{state_name}(state) => state.handle_message(state_machine, message),", state_name = state.ident))
-            .collect::<Vec<_>>()
-            .join("\n");
+        format!(
+            "///\n/// | State | Parent | Handles | Transitions |\n/// |-------|--------|---------|-------------|\n{rows}\n"
+        )
+    }
+}
 
-        let on_entry_arms = actual_states.iter()
-            .map(|state| format!("            {enum_name}::{state_name}(state) => state.on_entry(state_machine),", state_name = state.ident))
-            .collect::<Vec<_>>()
-            .join("\n");
+/// States of each submodule `states/mod.rs` is split into, keyed by the submodule's name
+#[cfg(feature = "codegen")]
+pub(crate) type StateGroups<'a> = BTreeMap<String, Vec<&'a State>>;
+
+/// `State` methods the state enum dispatches, with the arguments passed on
+#[cfg(feature = "codegen")]
+const DISPATCHED_METHODS: [(&str, &str); 4] = [
+    ("handle_message", "state_machine, message"),
+    ("on_entry", "state_machine"),
+    ("on_exit", "state_machine"),
+    ("parent", ""),
+];
+
+#[cfg(feature = "codegen")]
+impl StateEnum {
+    /// Groups states by the lowercased first letter of their ident, for splitting
+    /// `states/mod.rs`
+    pub(crate) fn letter_groups(states: &[State]) -> StateGroups<'_> {
+        let mut groups = StateGroups::new();
+        for state in states {
+            let group = state
+                .ident
+                .chars()
+                .next()
+                .filter(char::is_ascii_alphabetic)
+                .map_or_else(
+                    || "other".to_string(),
+                    |c| c.to_ascii_lowercase().to_string(),
+                );
+            groups.entry(group).or_default().push(state);
+        }
+        groups
+    }
+
+    /// Renders the state enum, dispatching through the submodules of `groups` when
+    /// `states/mod.rs` is split
+    pub(crate) fn render(
+        &self,
+        ctx: &RenderCtx,
+        groups: Option<&StateGroups>,
+    ) -> Result<String, RenderError> {
+        let enum_def = self.get();
+        let enum_name = &enum_def.ident;
+        let component_type = ctx.component_type();
+        let message_set = ctx.message_set();
+
+        // Use actual states from the actor, not the empty enum_def.variants
+        let actual_states = &ctx.actor.component.states.states;
+        let doc_table = Self::render_doc_table(actual_states);
 
-        let on_exit_arms = actual_states
+        let variants = actual_states
             .iter()
             .map(|state| {
                 format!(
-                    "            {enum_name}::{state_name}(state) => state.on_exit(state_machine),",
-                    state_name = state.ident
+                    "    /// {} state\n    {}({}),",
+                    state.ident, state.ident, state.ident
                 )
             })
             .collect::<Vec<_>>()
             .join("\n");
 
-        let parent_arms = actual_states
+        // Stashed messages get another chance in every state entered
+        let unstash = if ctx.actor.stash.is_some() {
+            "\n        state_machine.extended_state.unstash_all();"
+        } else {
+            ""
+        };
+
+        // Split enums hand each group of states to the helper its submodule defines
+        let arms = |method: &str, args: &str| match groups {
+            None => Self::render_arms(enum_name, actual_states.iter(), method, args),
+            Some(groups) => groups
+                .iter()
+                .map(|(group, states)| {
+                    let patterns = states
+                        .iter()
+                        .map(|state| format!("{enum_name}::{}(_)", state.ident))
+                        .collect::<Vec<_>>()
+                        .join(" | ");
+                    format!("            {patterns} => self.{method}_{group}({args}),")
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        // The machine rests in `Uninit` until it is initialized, or in the first state of
+        // specs without one
+        let initial = actual_states
             .iter()
-            .map(|state| {
-                format!(
-                    "            {enum_name}::{state_name}(state) => state.parent(),",
-                    state_name = state.ident
+            .find(|state| state.ident == "Uninit")
+            .or(actual_states.first())
+            .ok_or_else(|| {
+                RenderError::InvalidSpec(format!("'{enum_name}' has no states to start in"))
+            })?;
+
+        let matching = |arms: &str| format!("match self {{\n{arms}\n        }}");
+        let (handle_message, on_entry, on_exit, parent, as_state) = match ctx.options.dispatch {
+            DispatchStrategy::Match => {
+                let [handle_message, on_entry, on_exit, parent] =
+                    DISPATCHED_METHODS.map(|(method, args)| matching(&arms(method, args)));
+                (handle_message, on_entry, on_exit, parent, String::new())
+            }
+            // One match picks the state; its methods are then called through a vtable
+            DispatchStrategy::TraitObject => {
+                let arms = match groups {
+                    None => Self::render_arms(enum_name, actual_states.iter(), "", ""),
+                    Some(_) => arms("as_state", ""),
+                };
+                (
+                    "self.as_state().handle_message(state_machine, message)".to_string(),
+                    "self.as_state().on_entry(state_machine);".to_string(),
+                    "self.as_state().on_exit(state_machine)".to_string(),
+                    "self.as_state().parent()".to_string(),
+                    format!(
+                        r#"
+impl {enum_name} {{
+    /// The state as a trait object, which every `State` method dispatches through
+    fn as_state(&self) -> &dyn State<{component_type}> {{
+        match self {{
+{arms}
+        }}
+    }}
+}}
+"#
+                    ),
                 )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+            }
+        };
 
-        format!(
+        Ok(format!(
             r#"/// Enumeration of all possible states for the actor's state machine
-#[derive(Clone, PartialEq, Debug)]
+{doc_table}#[derive(Clone, PartialEq, Debug{persisted_derives})]
 pub enum {enum_name} {{
 {variants}
 }}
@@ -188,33 +759,25 @@ impl State<{component_type}> for {enum_name} {{
         state_machine: &mut StateMachine<{component_type}>,
         message: {message_set},
     ) -> Option<Transition<<{component_type} as Components>::States, {message_set}>> {{
-        match self {{
-{handle_message_arms}
-        }}
+        {handle_message}
     }}
 
     /// Executes actions when entering a state
     fn on_entry(&self, state_machine: &mut StateMachine<{component_type}>) {{
-        match self {{
-{on_entry_arms}
-        }}
+        {on_entry}{unstash}
     }}
 
     /// Executes actions when exiting a state
     fn on_exit(&self, state_machine: &mut StateMachine<{component_type}>) {{
-        match self {{
-{on_exit_arms}
-        }}
+        {on_exit}
     }}
 
     /// Returns the parent state in the state machine hierarchy
     fn parent(&self) -> {enum_name} {{
-        match self {{
-{parent_arms}
-        }}
+        {parent}
     }}
 }}
-
+{as_state}
 impl StateEnum for {enum_name} {{
     fn new() -> Self {{
         Self::default()
@@ -223,24 +786,131 @@ impl StateEnum for {enum_name} {{
 
 impl Default for {enum_name} {{
     fn default() -> Self {{
-        {enum_name}::Uninit(Uninit)
+        {enum_name}::{initial_ident}({initial_constructor})
     }}
-}}"#
+}}"#,
+            persisted_derives = persisted_derives(ctx),
+            initial_ident = initial.ident,
+            initial_constructor = initial.constructor(),
+        ))
+    }
+
+    /// Renders the submodule of one group of a split `states/mod.rs`: the group's state
+    /// modules and the helpers the state enum dispatches to them through
+    pub(crate) fn render_group(&self, ctx: &RenderCtx, group: &str, states: &[&State]) -> String {
+        let enum_name = &self.get().ident;
+        let component_type = ctx.component_type();
+        let message_set = ctx.message_set();
+
+        let modules = states
+            .iter()
+            .map(|state| format!("pub mod {};", state.ident.to_lowercase()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let state_machine = format!("state_machine: &mut StateMachine<{component_type}>");
+        let helper = |method: &str, params: &str, ret: &str, arms: String| {
+            format!(
+                r#"    /// `{method}` of the states in this group
+    pub(super) fn {method}_{group}(&self{params}){ret} {{
+        match self {{
+{arms}
+            _ => unreachable!("{{self:?}} is not in group '{group}'"),
+        }}
+    }}"#
+            )
+        };
+        let helpers = match ctx.options.dispatch {
+            DispatchStrategy::Match => DISPATCHED_METHODS
+                .map(|(method, args)| {
+                    let (params, ret) = match method {
+                        "handle_message" => (
+                            format!(", {state_machine}, message: {message_set}"),
+                            format!(
+                                " -> Option<Transition<<{component_type} as Components>::States, {message_set}>>"
+                            ),
+                        ),
+                        "parent" => (String::new(), format!(" -> {enum_name}")),
+                        _ => (format!(", {state_machine}"), String::new()),
+                    };
+                    let arms = Self::render_arms(enum_name, states.iter().copied(), method, args);
+                    helper(method, &params, &ret, arms)
+                })
+                .join("\n\n"),
+            DispatchStrategy::TraitObject => helper(
+                "as_state",
+                "",
+                &format!(" -> &dyn State<{component_type}>"),
+                Self::render_arms(enum_name, states.iter().copied(), "", ""),
+            ),
+        };
+
+        format!(
+            r#"//! States of {enum_name} split out of `states/mod.rs` to keep it within its line budget
+
+use super::*;
+
+{modules}
+
+impl {enum_name} {{
+{helpers}
+}}
+"#
         )
     }
+
+    /// Renders one match arm per state calling `method` on it, or returning the state itself
+    /// when `method` is empty
+    fn render_arms<'a>(
+        enum_name: &str,
+        states: impl Iterator<Item = &'a State>,
+        method: &str,
+        args: &str,
+    ) -> String {
+        states
+            .map(|state| {
+                let call = if method.is_empty() {
+                    "state".to_string()
+                } else {
+                    format!("state.{method}({args})")
+                };
+                format!("            {enum_name}::{}(state) => {call},", state.ident)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
+#[cfg(feature = "codegen")]
+impl ToRust for StateEnum {
+    fn to_source(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
+        self.render(ctx, None)
+    }
+}
+
+#[cfg(feature = "codegen")]
+/// Serde derives, each after a comma, that let the runtime persist the state the actor is in
+fn persisted_derives(ctx: &RenderCtx) -> String {
+    if !ctx.actor.lifecycle.persist_state {
+        return String::new();
+    }
+    RenderCtx::WIRE_DERIVES
+        .iter()
+        .map(|derive| format!(", {derive}"))
+        .collect()
+}
+
+#[cfg(feature = "codegen")]
 impl ToRust for States {
-    fn to_rust(&self, generator: &ActorGenerator) -> String {
+    fn to_source(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
         let state_impls = self
             .states
             .iter()
-            .map(|state| state.to_rust(generator))
-            .collect::<Vec<_>>()
+            .map(|state| state.to_source(ctx))
+            .collect::<Result<Vec<_>, _>>()?
             .join("\n\n");
 
-        let state_enum_impl = self.state_enum.to_rust(generator);
+        let state_enum_impl = self.state_enum.to_source(ctx)?;
 
-        format!("{state_impls}\n\n{state_enum_impl}")
+        Ok(format!("{state_impls}\n\n{state_enum_impl}"))
     }
 }
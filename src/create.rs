@@ -1,44 +1,250 @@
+mod assertions_gen;
+mod bridge_gen;
+mod children_gen;
+mod circuit_breaker_gen;
+mod compat_gen;
+mod config;
+mod constants_gen;
+mod correlation_gen;
+mod demo_gen;
+mod deps;
+pub mod emit;
 mod file_gen;
+mod fuzz_gen;
+mod header_gen;
+mod health_gen;
+mod history_gen;
+mod impact;
+mod loom_gen;
+mod migration_gen;
+mod panic_gen;
+mod persistence_gen;
+mod plan;
+mod pool_gen;
+mod queue_sampling_gen;
+mod rate_limit_gen;
+mod readme_gen;
+mod relay_gen;
+mod render;
+mod report;
+mod schedule_gen;
+mod search;
+mod secrets_gen;
+mod sequence_gen;
+mod sources_gen;
 mod state_gen;
+mod supervisor_gen;
+
+pub use compat_gen::render_message_shims;
+pub use config::{CONFIG_FILE, EmitConfig, GenerateConfig, ProjectConfig};
+pub use demo_gen::EXAMPLES_DIR;
+pub use deps::{LayeringRules, ModuleDependencies, UNEXPECTED_DEPENDENCIES};
+pub use file_gen::create_module;
+pub use fuzz_gen::{CrateRoot, FUZZ_DIR};
+pub use header_gen::LicenseHeader;
+pub(crate) use history_gen::render_history_methods;
+pub use impact::{ActorImpact, Impact, ModuleImpact, SpecElement, find_actor_specs};
+pub use migration_gen::render_migration;
+pub use plan::ModulePlan;
+pub use readme_gen::DEFAULT_README_TEMPLATE;
+pub(crate) use render::deprecated_attr;
+pub use render::{
+    DispatchStrategy, Edition, GeneratorOptions, GeneratorOptionsBuilder, ImportFallback,
+    LineEndings, RenderCtx, RenderError, RustVersion, WireFormat,
+};
+pub use report::{FileReport, GenerationReport, Phase, PhaseTimings};
+pub use search::SearchHit;
+#[allow(deprecated)]
+pub use state_gen::{
+    generate_inner_states, generate_inner_states_with_graph,
+    generate_inner_states_with_structural_analysis, generate_state_enum_impl,
+    generate_state_enum_impl_with_graph, generate_state_enum_impl_with_structural_analysis,
+    render_state_enum_module, render_state_module,
+};
 
-pub use file_gen::*;
-pub use state_gen::*;
-
-use crate::blox::actor::Actor;
-use crate::blox::state::State;
+use assertions_gen::render_static_assertions;
+use bridge_gen::render_bridge_module;
+use children_gen::{
+    render_child_arms, render_child_spawns, render_children_module, render_forward,
+};
+use circuit_breaker_gen::render_circuit_breaker_module;
+use constants_gen::render_constants;
+use correlation_gen::{
+    render_correlated_dispatch, render_correlation_helpers, render_correlation_module,
+};
+use demo_gen::render_demo;
+use file_gen::{declare_module, write_atomically};
+use fuzz_gen::render_fuzz_targets;
+use health_gen::{render_health_arm, render_health_module};
+use history_gen::{render_recorded_dispatch, render_variant_names};
+use loom_gen::{render_loom_imports, render_loom_tests};
+use panic_gen::render_panic_policy;
+use persistence_gen::{render_persistence_module, render_state_save};
+use pool_gen::{render_pool_module, render_shard_module};
+use queue_sampling_gen::{render_queue_overload, render_queue_sampler, render_queue_sampling_arm};
+use rate_limit_gen::{render_rate_limit_guard, render_rate_limit_module};
+use readme_gen::render_readme;
+use relay_gen::render_relay;
+use schedule_gen::render_scheduler_module;
+use secrets_gen::{render_secret_loader, resolve_secrets};
+use sequence_gen::{render_sequence_check, render_sequence_module};
+use sources_gen::{render_sinks_module, render_sources_module};
+use state_gen::render_split_state_enum_module;
+use supervisor_gen::{render_snapshot_save, render_supervisor_module};
+
+use crate::analysis;
+use crate::blox::actor::{Actor, HEALTH_RECEIVER, SELF_RECEIVER, UNSTASHED_FIELD};
+use crate::blox::constant::ConstantModule;
+use crate::blox::message_set::Newtype;
+use crate::blox::state::{State, StateEnum, StateGroups};
+use crate::diagnostics::Diagnostic;
 use crate::graph::CodeGenGraph;
+use proc_macro2::{LexError, TokenStream};
 use std::{
+    borrow::Cow,
     error::Error,
-    fs::{self, File},
-    path::Path,
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
 };
 
+/// Spec elements rendered as Rust
 pub trait ToRust {
-    fn to_rust(&self, generator: &ActorGenerator) -> String;
+    /// Renders the Rust source, laid out as it is written to the generated files
+    fn to_source(&self, ctx: &RenderCtx) -> Result<String, RenderError>;
+
+    /// Renders the Rust tokens, for callers composing them or checking them with `syn`
+    ///
+    /// Fails when the source doesn't lex, e.g. because of an unbalanced brace in code taken
+    /// from the spec.
+    fn to_rust(&self, ctx: &RenderCtx) -> Result<TokenStream, RenderError> {
+        let source = self.to_source(ctx)?;
+        source
+            .parse()
+            .map_err(|e: LexError| RenderError::InvalidRust(e.to_string()))
+    }
 }
 
 /// Unified generator for all actor-related code generation
 pub struct ActorGenerator {
     graph: CodeGenGraph,
     actor: Actor,
+    options: GeneratorOptions,
+    timings: PhaseTimings,
 }
 
 impl ActorGenerator {
+    /// Clippy lints generated files trigger for some specs, by file name
+    const CLIPPY_ALLOWS: &[(&str, &[&str])] = &[
+        // `new` takes one parameter per field
+        ("ext_state.rs", &["clippy::too_many_arguments"]),
+        // Receiver types are spelled through the runtime's associated types
+        ("component.rs", &["clippy::type_complexity"]),
+        // Variant payloads can differ widely in size
+        ("messaging.rs", &["clippy::large_enum_variant"]),
+        // A Kafka consumer of one topic matches a single topic name
+        ("sources.rs", &["clippy::single_match"]),
+    ];
+
     /// Creates a new ActorGenerator for the given actor.
     pub fn new(actor: Actor) -> Result<Self, Box<dyn Error>> {
+        Self::with_options(actor, GeneratorOptions::default())
+    }
+
+    /// Creates a new ActorGenerator for the given actor using the given options.
+    pub fn with_options(
+        mut actor: Actor,
+        options: GeneratorOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        options.validate_target()?;
+        actor.infer_channels()?;
+
+        // Health probes ride on their own receiver, so every module has to see it
+        actor.add_health_receiver();
+        actor.add_self_handle();
+        actor.add_state_store();
+        actor.add_history();
+        actor.add_restart_snapshot();
+        actor.add_child_inboxes();
+        actor.wrap_message_payloads();
+        actor.add_overloaded_variant();
+
         let mut generator = Self {
             graph: CodeGenGraph::new(),
             actor,
+            options,
+            timings: PhaseTimings::default(),
         };
-        generator.graph.analyze_actor(&generator.actor)?;
+
+        // Same phases as `CodeGenGraph::analyze_actor`, timed separately
+        let start = Instant::now();
+        generator.graph.bootstrap_bloxide_types();
+        for (type_name, full_path) in &generator.options.framework_types {
+            generator
+                .graph
+                .register_framework_type(type_name, full_path);
+        }
+        generator.graph.discover_actor_types(&generator.actor)?;
+        if !generator.options.edition.has_future_in_prelude() {
+            // The runtime names `Future` in `Runnable::run`'s signature
+            let runtime = format!("{}::runtime", generator.actor_module());
+            generator
+                .graph
+                .add_dependency_by_path(&runtime, "std::future::Future");
+        }
+        if generator
+            .options
+            .supports(RustVersion::RETURN_POSITION_IMPL_TRAIT)
+            && !generator
+                .actor
+                .component
+                .message_receivers
+                .receivers
+                .is_empty()
+        {
+            // The `{Actor}Api` trait's send methods take messages and surface channel errors
+            let component = format!("{}::component", generator.actor_module());
+            for import in [
+                "bloxide_tokio::messaging::Message",
+                "tokio::sync::mpsc::error::SendError",
+            ] {
+                generator.graph.add_dependency_by_path(&component, import);
+            }
+            if !generator.options.edition.has_future_in_prelude() {
+                generator
+                    .graph
+                    .add_dependency_by_path(&component, "std::future::Future");
+            }
+        }
+        generator.timings.analyze = start.elapsed();
+
+        let start = Instant::now();
+        generator.graph.resolve_type_relationships()?;
+        generator.timings.resolve = start.elapsed();
+
         Ok(generator)
     }
 
+    /// Gets the render context used by `ToRust` implementors
+    pub fn ctx(&self) -> RenderCtx<'_> {
+        RenderCtx::new(&self.actor, &self.graph, &self.options)
+    }
+
     /// Gets a reference to the actor
     pub fn actor(&self) -> &Actor {
         &self.actor
     }
 
+    /// Gets a reference to the generator options
+    pub fn options(&self) -> &GeneratorOptions {
+        &self.options
+    }
+
+    /// Gets the timings of the phases run so far
+    pub fn timings(&self) -> &PhaseTimings {
+        &self.timings
+    }
+
     /// Gets a reference to the internal graph
     pub fn graph(&self) -> &CodeGenGraph {
         &self.graph
@@ -49,6 +255,22 @@ impl ActorGenerator {
         &mut self.graph
     }
 
+    /// Runs the spec analyses and returns their findings
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = analysis::analyze(&self.ctx());
+        diagnostics.extend(self.graph.unresolved_types().iter().map(|(ty, module)| {
+            Diagnostic::warning(
+                "unresolved-type",
+                format!(
+                    "Cannot resolve type '{ty}' used in module '{module}'; use a qualified path for external types"
+                ),
+            )
+        }));
+        // A spec that can't be planned fails generation with its own error
+        diagnostics.extend(self.layering_violations().unwrap_or_default());
+        diagnostics
+    }
+
     /// Gets the component type name for this actor
     pub fn component_type(&self) -> &str {
         &self.actor.component.ident
@@ -56,23 +278,17 @@ impl ActorGenerator {
 
     /// Gets the message set name for this actor
     pub fn message_set(&self) -> String {
-        self.actor
-            .component
-            .message_set
-            .as_ref()
-            .map(|ms| ms.get().ident.clone())
-            .unwrap_or_else(|| format!("<{} as Components>::MessageSet", self.component_type()))
+        self.ctx().message_set()
     }
 
-    /// Gets the actor module name (lowercase)
+    /// Gets the actor module path, including any group it is nested under
     pub fn actor_module(&self) -> String {
-        self.actor.ident.to_lowercase()
+        self.actor.module_path()
     }
 
     /// Generates the component definition
-    pub fn generate_component(&mut self) -> Result<String, Box<dyn Error>> {
-        let actor_module = self.actor.ident.to_lowercase();
-        let component_module_path = format!("{actor_module}::component");
+    pub fn generate_component(&self) -> Result<String, RenderError> {
+        let actor_module = self.actor_module();
         let mod_comment = format!(
             r"//! # {actor_module} Components
 //!
@@ -82,63 +298,62 @@ impl ActorGenerator {
 "
         );
 
-        let component_module_idx = self
-            .graph
-            .graph
-            .find_module_by_path_hierarchical(&component_module_path)
-            .expect("Component module should exist after analysis");
-        let imports = self
-            .graph
-            .get_imports_for_module(component_module_idx)
-            .collect::<Vec<_>>();
-
-        let header = if !imports.is_empty() {
-            let imports_section = format!("{}\n\n", imports.join("\n"));
-            format!("{mod_comment}{imports_section}")
-        } else {
-            mod_comment
-        };
+        let ctx = self.ctx();
+        let imports_section = ctx.imports_section(&format!("{actor_module}::component"))?;
 
         Ok(format!(
-            "{header}\n\n{}",
-            self.actor.component.to_rust(self)
+            "{mod_comment}{imports_section}\n\n{}{}",
+            render_constants(&ctx, ConstantModule::Component),
+            self.actor.component.to_source(&ctx)?
         ))
     }
 
     /// Generates the message set module
-    pub fn generate_messaging(&mut self) -> Result<Option<String>, Box<dyn Error>> {
+    pub fn generate_messaging(&self) -> Result<Option<String>, RenderError> {
         let Some(message_set) = &self.actor.component.message_set else {
             return Ok(None);
         };
 
         let enum_def = message_set.get();
-        let actor_module = self.actor.ident.to_lowercase();
-
-        let messaging_module_path = format!("{actor_module}::messaging");
-        let messaging_module_idx = self
-            .graph
-            .graph
-            .find_module_by_path_hierarchical(&messaging_module_path)
-            .expect("Messaging module should exist after analysis");
-        let imports = self
-            .graph
-            .get_imports_for_module(messaging_module_idx)
-            .collect::<Vec<_>>();
-
-        let imports_section = if imports.is_empty() {
-            String::new()
-        } else {
-            format!("{}\n\n", imports.join("\n"))
-        };
+        let actor_module = self.actor_module();
+        let imports_section = self
+            .ctx()
+            .imports_section(&format!("{actor_module}::messaging"))?;
 
         let custom_types = message_set
             .custom_types
             .iter()
             .map(|enum_def| self.generate_custom_type_definition(enum_def))
             .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .chain(message_set.aliases.iter().map(|alias| {
+                format!(
+                    "/// Alias of `{ty}`\npub type {ident} = {ty};",
+                    ident = alias.ident,
+                    ty = alias.ty
+                )
+            }))
+            .chain(
+                message_set
+                    .newtypes
+                    .iter()
+                    .map(|newtype| self.generate_newtype_definition(newtype)),
+            )
+            .collect::<Vec<_>>()
             .join("\n\n");
 
-        let enum_definition = self.generate_enum_definition(enum_def)?;
+        let enum_definition = self.generate_enum_definition(enum_def, &self.ctx())?;
+        let boxed_conversions = Self::generate_boxed_conversions(enum_def);
+        let wire_codecs = self
+            .options
+            .wire_format
+            .map(|format| Self::generate_wire_codecs(&enum_def.ident, format))
+            .unwrap_or_default();
+        let correlation_helpers = if self.actor.correlation {
+            render_correlation_helpers(&self.ctx())
+        } else {
+            String::new()
+        };
 
         let content = format!(
             r#"//! # {ident} Message Module
@@ -150,39 +365,28 @@ impl ActorGenerator {
 //! - `MessageSet` - The top-level message set enum that wraps all message types
 {imports_section}
 
-{enum_definition}
+{constants}{enum_definition}
 
 {custom_types}
 
 impl MessageSet for {ident} {{}}
-"#,
+{boxed_conversions}{wire_codecs}{correlation_helpers}{variant_names}{queue_overload}"#,
             ident = enum_def.ident,
+            constants = render_constants(&self.ctx(), ConstantModule::Messaging),
+            variant_names = render_variant_names(&self.ctx()),
+            queue_overload = render_queue_overload(&self.ctx()),
         );
 
         Ok(Some(content))
     }
 
     /// Generates the runtime module
-    pub fn generate_runtime(&self) -> Result<String, Box<dyn Error>> {
+    pub fn generate_runtime(&self) -> Result<String, RenderError> {
         let actor_name = &self.actor.ident;
-        let actor_module = self.actor.ident.to_lowercase();
-
-        let runtime_module_path = format!("{actor_module}::runtime");
-        let runtime_module_idx = self
-            .graph
-            .graph
-            .find_module_by_path_hierarchical(&runtime_module_path)
-            .expect("Runtime module should exist after analysis");
-        let imports = self
-            .graph
-            .get_imports_for_module(runtime_module_idx)
-            .collect::<Vec<_>>();
-
-        let imports_section = if imports.is_empty() {
-            String::new()
-        } else {
-            format!("{}\n\n", imports.join("\n"))
-        };
+        let actor_module = self.actor_module();
+        let imports_section = self
+            .ctx()
+            .imports_section(&format!("{actor_module}::runtime"))?;
 
         let message_set_name = self
             .actor
@@ -192,35 +396,156 @@ impl MessageSet for {ident} {{}}
             .map(|ms| ms.get().ident.clone())
             .unwrap_or_default();
 
+        let receivers = &self.actor.component.message_receivers.receivers;
         let mut select_arms = String::new();
+        if receivers.iter().any(|receiver| receiver.priority > 0) {
+            // Poll arms in order so higher priority receivers are drained first
+            select_arms.push_str("                    biased;\n");
+        }
+        if self.actor.health_check {
+            select_arms.push_str(&render_health_arm(&self.ctx()));
+        }
+        let dispatch = |message: &str| {
+            let dispatch = |message: &str| {
+                if self.actor.correlation {
+                    render_correlated_dispatch(message, self.actor.traces())
+                } else {
+                    format!(
+                        "                        self.state_machine.dispatch({message}, &current_state);"
+                    )
+                }
+            };
+            let dispatch = if self.actor.history.is_some() {
+                render_recorded_dispatch(&self.ctx(), message, &dispatch("message"))
+            } else {
+                dispatch(message)
+            };
+            let mut dispatch = render_panic_policy(&self.ctx(), &dispatch);
+            if self.actor.lifecycle.persist_state {
+                dispatch.push_str(&render_state_save());
+            }
+            if self.actor.lifecycle.persists_ext_state() {
+                dispatch.push_str(&render_snapshot_save());
+            }
+            dispatch
+        };
         if let Some(message_set) = &self.actor.component.message_set {
-            let iter = self
-                .actor
-                .component
-                .message_receivers
-                .receivers
-                .clone()
-                .into_iter()
-                .zip(message_set.get().variants.clone());
-
-            for (receiver, variant) in iter {
+            let mut arms = receivers
+                .iter()
+                .filter(|receiver| receiver.ident != HEALTH_RECEIVER)
+                .zip(&message_set.get().variants)
+                .collect::<Vec<_>>();
+            arms.sort_by_key(|(receiver, _)| std::cmp::Reverse(receiver.priority));
+
+            for (receiver, variant) in arms {
+                let sequence_check = if receiver.sequenced {
+                    render_sequence_check(&self.ctx(), receiver)
+                } else {
+                    String::new()
+                };
+                let forward = self
+                    .actor
+                    .children
+                    .iter()
+                    .find_map(|child| {
+                        child
+                            .forward_for(&variant.ident)
+                            .map(|forward| render_forward(child, forward))
+                    })
+                    .or_else(|| {
+                        self.actor
+                            .relays
+                            .iter()
+                            .find(|relay| relay.variant == variant.ident)
+                            .map(|relay| render_relay(&self.ctx(), relay))
+                    });
+                let handle = forward.unwrap_or_else(|| {
+                    format!(
+                        "                        let current_state = self.state_machine.current_state.clone();\n{}",
+                        dispatch(&if variant.boxed {
+                            format!("{message_set_name}::{}(Box::new(msg))", variant.ident)
+                        } else {
+                            format!("{message_set_name}::{}(msg)", variant.ident)
+                        })
+                    )
+                });
                 select_arms.push_str(&format!(
                     r#"                    Some(msg) = self.receivers.{ident}.recv() => {{
-                        let current_state = self.state_machine.current_state.clone();
-                        self.state_machine.dispatch({message_set_name}::{variant_name}(msg), &current_state);
+{rate_limit_guard}{sequence_check}{handle}
                     }}
 "#,
                     ident = receiver.ident,
-                    variant_name = variant.ident
+                    rate_limit_guard = render_rate_limit_guard(receiver),
                 ));
             }
         }
+        select_arms.push_str(&render_child_arms(&self.ctx(), dispatch)?);
+        select_arms.push_str(&render_queue_sampling_arm(&self.ctx(), dispatch));
+        if self.actor.has_self_queue() {
+            // Deferred messages wait behind everything that arrived from outside
+            select_arms.push_str(&format!(
+                r#"                    Some(msg) = self.receivers.{SELF_RECEIVER}.recv() => {{
+                        let current_state = self.state_machine.current_state.clone();
+{dispatch}
+                    }}
+"#,
+                dispatch = dispatch("msg")
+            ));
+        }
+
+        // Stashed messages handed back to the state just entered come before anything new
+        let replay = if self.actor.stash.is_some() && self.actor.component.message_set.is_some() {
+            let dispatch = dispatch("msg")
+                .lines()
+                .map(|line| line.strip_prefix("    ").unwrap_or(line))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                r#"                while let Some(msg) = self.state_machine.extended_state.{UNSTASHED_FIELD}.pop_front() {{
+                    let current_state = self.state_machine.current_state.clone();
+{dispatch}
+                }}
+"#
+            )
+        } else {
+            String::new()
+        };
 
         let states = &self.actor.component.states;
-        let first_state = &states.states[0];
-        let second_state = states.states.get(1).unwrap_or(&states.states[0]);
+        let first_state = states.states.first().ok_or_else(|| {
+            RenderError::InvalidSpec("the runtime requires at least one state".to_string())
+        })?;
+        let second_state = states.states.get(1).unwrap_or(first_state);
         let state_enum_name = &states.state_enum.get().ident;
 
+        let first = format!(
+            "{state_enum_name}::{}({})",
+            first_state.ident,
+            first_state.constructor()
+        );
+        let second = format!(
+            "{state_enum_name}::{}({})",
+            second_state.ident,
+            second_state.constructor()
+        );
+        // A persisted actor resumes in the state it saved last
+        let init = if self.actor.lifecycle.persist_state {
+            format!(
+                r#"        let restored = self.state_machine.extended_state.state_store.load();
+        self.state_machine.init(
+            &{first},
+            restored.as_ref().unwrap_or(&{second}),
+        );"#
+            )
+        } else {
+            format!(
+                r#"        self.state_machine.init(
+            &{first},
+            &{second},
+        );"#
+            )
+        };
+
         let content = format!(
             r#"{imports_section}use super::{{
     component::{actor_name}Components,
@@ -234,17 +559,14 @@ impl MessageSet for {ident} {{}}
 
 impl Runnable<{actor_name}Components> for Blox<{actor_name}Components> {{
     fn run(mut self: Box<Self>) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {{
-        self.state_machine.init(
-            &{state_enum_name}::{first_state}({first_state}),
-            &{state_enum_name}::{second_state}({second_state}),
-        );
+{child_spawns}{pre_start}{init}
 
         Box::pin(async move {{
-            loop {{
-                select! {{
-{select_arms}
+{sequences}{rate_limits}{queue_sampler}            loop {{
+{replay}                select! {{
+{select_arms}                    else => break,
                 }}
-            }}
+            }}{post_stop}
         }})
     }}
 }}"#,
@@ -252,205 +574,655 @@ impl Runnable<{actor_name}Components> for Blox<{actor_name}Components> {{
             first_state_lower = first_state.ident.to_lowercase(),
             second_state = second_state.ident,
             second_state_lower = second_state.ident.to_lowercase(),
+            sequences = if receivers.iter().any(|receiver| receiver.sequenced) {
+                format!("            let mut sequences = {actor_name}Sequences::default();\n")
+            } else {
+                String::new()
+            },
+            rate_limits = if self.actor.component.message_receivers.has_rate_limits() {
+                format!("            let mut rate_limits = {actor_name}RateLimits::default();\n")
+            } else {
+                String::new()
+            },
+            queue_sampler = render_queue_sampler(&self.ctx()),
+            child_spawns = render_child_spawns(&self.ctx())?,
+            pre_start = self.actor.lifecycle.render_pre_start(),
+            post_stop = self.actor.lifecycle.render_post_stop(),
         );
 
         Ok(content)
     }
 
+    /// Generates the pool router module if the spec asks for one
+    pub fn generate_pool(&self) -> Result<Option<String>, RenderError> {
+        self.actor
+            .pool
+            .as_ref()
+            .map(|pool| render_pool_module(&self.ctx(), pool))
+            .transpose()
+    }
+
+    /// Generates the key-sharded dispatcher module if the spec asks for one
+    pub fn generate_shard(&self) -> Result<Option<String>, RenderError> {
+        self.actor
+            .shard
+            .as_ref()
+            .map(|shard| render_shard_module(&self.ctx(), shard))
+            .transpose()
+    }
+
+    /// Generates the service bridge module if the spec asks for one
+    pub fn generate_bridge(&self) -> Result<Option<String>, RenderError> {
+        self.actor
+            .bridge
+            .as_ref()
+            .map(|bridge| render_bridge_module(&self.ctx(), bridge))
+            .transpose()
+    }
+
+    /// Generates the external source adapters if any receiver declares a source
+    pub fn generate_sources(&self) -> Result<Option<String>, RenderError> {
+        if !self.actor.component.message_receivers.has_sources() {
+            return Ok(None);
+        }
+        render_sources_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the Kafka publishing helpers if the spec declares sinks
+    pub fn generate_sinks(&self) -> Result<Option<String>, RenderError> {
+        if self.actor.sinks.is_empty() {
+            return Ok(None);
+        }
+        render_sinks_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the cron scheduler if the spec declares schedules
+    pub fn generate_scheduler(&self) -> Result<Option<String>, RenderError> {
+        if self.actor.schedules.is_empty() {
+            return Ok(None);
+        }
+        render_scheduler_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the sequence module if any receiver is sequenced
+    pub fn generate_sequence(&self) -> Result<Option<String>, RenderError> {
+        if !self.actor.component.message_receivers.has_sequenced() {
+            return Ok(None);
+        }
+        render_sequence_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the circuit breaker module if any handle is guarded by one
+    pub fn generate_circuit_breaker(&self) -> Result<Option<String>, RenderError> {
+        if !self.actor.component.message_handles.has_circuit_breakers() {
+            return Ok(None);
+        }
+        render_circuit_breaker_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the persistence module if the actor persists its current state
+    pub fn generate_persistence(&self) -> Result<Option<String>, RenderError> {
+        if !self.actor.lifecycle.persist_state {
+            return Ok(None);
+        }
+        render_persistence_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the rate limit module if any receiver is rate limited
+    pub fn generate_rate_limit(&self) -> Result<Option<String>, RenderError> {
+        if !self.actor.component.message_receivers.has_rate_limits() {
+            return Ok(None);
+        }
+        render_rate_limit_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the correlation module if the spec asks for correlation IDs
+    pub fn generate_correlation(&self) -> Result<Option<String>, RenderError> {
+        if !self.actor.correlation {
+            return Ok(None);
+        }
+        render_correlation_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the health probe module if the spec asks for one
+    pub fn generate_health(&self) -> Result<Option<String>, RenderError> {
+        if !self.actor.health_check {
+            return Ok(None);
+        }
+        render_health_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the supervisor module if the spec declares a restart policy
+    pub fn generate_supervisor(&self) -> Result<Option<String>, RenderError> {
+        if self.actor.lifecycle.restart.is_never() {
+            return Ok(None);
+        }
+        render_supervisor_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the children module if the spec embeds other actors
+    pub fn generate_children(&self) -> Result<Option<String>, RenderError> {
+        if self.actor.children.is_empty() {
+            return Ok(None);
+        }
+        render_children_module(&self.ctx()).map(Some)
+    }
+
+    /// Generates the module's README if the options ask for one
+    pub fn generate_readme(&self) -> Result<Option<String>, RenderError> {
+        if !self.options.readme {
+            return Ok(None);
+        }
+        Ok(Some(render_readme(&self.ctx(), &self.plan()?)))
+    }
+
     /// Generates the extended state module
-    pub fn generate_ext_state(&mut self) -> String {
+    pub fn generate_ext_state(&self) -> Result<String, RenderError> {
         let ident = &self.actor.ident;
-        format!(
+        let actor_module = self.actor_module();
+        let mut imports_section = self
+            .ctx()
+            .imports_section(&format!("{actor_module}::ext_state"))?;
+        let loom_tests = self
+            .options
+            .loom_tests
+            .then(|| render_loom_tests(&self.ctx()))
+            .flatten();
+        if loom_tests.is_some() {
+            imports_section = render_loom_imports(&imports_section);
+        }
+        Ok(format!(
             r#"//! # {ident} Extended State
 //! 
 //! Extended state for the {ident} component.
 //! This file defines the extended state data structure that persists across state transitions.
 
-/// Extended state for the {ident} component
-{ext_state}
-"#,
-            ext_state = self.actor.component.ext_state.to_rust(self),
-        )
+{imports_section}{constants}/// Extended state for the {ident} component
+{ext_state}{secret_loader}{loom_tests}"#,
+            constants = render_constants(&self.ctx(), ConstantModule::ExtState),
+            ext_state = self.actor.component.ext_state.to_source(&self.ctx())?,
+            secret_loader = render_secret_loader(&self.ctx()),
+            loom_tests = loom_tests.unwrap_or_default(),
+        ))
     }
 
     /// Generates individual state implementations using ToRust
-    pub fn generate_state_impl(&self, state: &State) -> Result<String, Box<dyn Error>> {
-        let actor_mod = self.actor_module();
-        let state_module_path = format!("{actor_mod}::states::{}", state.ident.to_lowercase());
-        let state_module_idx = self
-            .graph
-            .graph
-            .find_module_by_path_hierarchical(&state_module_path)
-            .expect("State module should exist after analysis");
-        let imports = self
-            .graph
-            .get_imports_for_module(state_module_idx)
-            .collect::<Vec<_>>();
-
-        let imports_section = if imports.is_empty() {
-            String::new()
-        } else {
-            format!("{}\n\n", imports.join("\n"))
-        };
-
-        // Use ToRust trait directly
-        let state_code = state.to_rust(self);
-
-        Ok(format!("{imports_section}{state_code}"))
+    pub fn generate_state_impl(&self, state: &State) -> Result<String, RenderError> {
+        render_state_module(&self.ctx(), state)
     }
 
     /// Generates the state enum implementation using ToRust
-    pub fn generate_state_enum(&self) -> Result<String, Box<dyn Error>> {
-        let actor_mod = self.actor_module();
-        let state_module_path = format!("{actor_mod}::states");
-        let state_module_idx = self
-            .graph
-            .graph
-            .find_module_by_path_hierarchical(&state_module_path)
-            .expect("States module should exist after analysis");
-        let imports = self
-            .graph
-            .get_imports_for_module(state_module_idx)
-            .collect::<Vec<_>>();
-
-        let imports_section = if imports.is_empty() {
-            String::new()
-        } else {
-            format!("{}\n\n", imports.join("\n"))
-        };
+    pub fn generate_state_enum(&self) -> Result<String, RenderError> {
+        render_state_enum_module(&self.ctx())
+    }
 
-        // Use ToRust trait directly
-        let state_enum_code = self.actor.component.states.state_enum.to_rust(self);
+    /// Generates all files for the actor module
+    pub fn generate_all_files(&self) -> Result<(), Box<dyn Error>> {
+        self.generate().map(|_| ())
+    }
 
-        Ok(format!("{imports_section}{state_enum_code}"))
+    /// Generates all files for the actor module and reports what was generated
+    pub fn generate(&self) -> Result<GenerationReport, Box<dyn Error>> {
+        self.generate_with_progress(|_| {})
     }
 
-    /// Generates all files for the actor module
-    pub fn generate_all_files(&mut self) -> Result<(), Box<dyn Error>> {
-        // Validate states first
-        self.actor.component.states.validate()?;
+    /// Generates all files for the actor module like `generate`, calling `on_phase` as each
+    /// phase starts
+    pub fn generate_with_progress(
+        &self,
+        mut on_phase: impl FnMut(Phase),
+    ) -> Result<GenerationReport, Box<dyn Error>> {
+        let mut timings = self.timings.clone();
+
+        // Validate the spec and the paths it writes to first
+        on_phase(Phase::Validate);
+        let start = Instant::now();
+        if self.actor.path.as_os_str().is_empty() {
+            return Err(format!(
+                "The spec has no `path` and no `{CONFIG_FILE}` sets an output directory"
+            )
+            .into());
+        }
+        self.actor.component.validate()?;
+        self.actor.validate_idents()?;
+        self.actor.validate_paths()?;
+        if let Some(pool) = &self.actor.pool {
+            pool.validate()?;
+        }
+        if let Some(bridge) = &self.actor.bridge {
+            bridge.validate(&self.actor.component)?;
+        }
+        self.actor.validate_sources()?;
+        self.actor.validate_sinks()?;
+        self.actor.validate_children()?;
+        self.actor.validate_relays()?;
+        self.actor.validate_constants()?;
+        self.actor.validate_trace_context()?;
+        self.actor
+            .lifecycle
+            .validate(&self.actor.component.states)?;
+        if (self.actor.has_self_queue() || self.actor.stash.is_some())
+            && self.actor.component.message_set.is_none()
+        {
+            return Err("Self messages and stashing require a message set to queue".into());
+        }
+        if let Some(stash) = &self.actor.stash {
+            stash.validate()?;
+        }
+        let ext_state = &self.actor.component.ext_state;
+        if let Some(config) = ext_state.config() {
+            config.validate(ext_state.init_args())?;
+        }
+        if let Some(queue_sampling) = &self.actor.queue_sampling {
+            queue_sampling.validate()?;
+        }
+        if let Some(history) = &self.actor.history {
+            history.validate()?;
+            if self.actor.component.message_set.is_none() {
+                return Err("The history records message set variants, so it needs one".into());
+            }
+        }
+        for schedule in &self.actor.schedules {
+            schedule.validate(&self.actor.component)?;
+        }
+        if let Some(error) = analysis::ext_state_send(&self.ctx())
+            .into_iter()
+            .chain(analysis::malformed_expressions(&self.ctx()))
+            .chain(analysis::free_form_code(&self.ctx()))
+            .find(Diagnostic::is_error)
+        {
+            return Err(error.message.into());
+        }
+        resolve_secrets(&self.ctx())?;
+        if self.options.fuzz_targets && self.options.wire_format.is_none() {
+            return Err("Fuzz targets decode the wire format, so they need one".into());
+        }
+        timings.validate = start.elapsed();
+
+        on_phase(Phase::Render);
+        let start = Instant::now();
+        let files = self.render_files()?;
+        timings.render = start.elapsed();
 
+        // Stage the module so a failure never leaves it half written
+        on_phase(Phase::Write);
+        let start = Instant::now();
         let mod_path = self.actor.create_mod_path();
-        self.create_module_dir(&mod_path)?;
+        write_atomically(&mod_path, &self.options, |dir| {
+            files
+                .iter()
+                .try_for_each(|(file, content)| self.write_file(dir, &mod_path, file, content))
+        })?;
+        self.declare_group_modules()?;
+        if self.options.fuzz_targets || self.options.demo_example {
+            let krate = CrateRoot::find(&self.actor.path)?;
+            if self.options.fuzz_targets {
+                self.write_fuzz_targets(&krate)?;
+            }
+            if self.options.demo_example {
+                self.write_demo(&krate)?;
+            }
+        }
+        timings.write = start.elapsed();
 
-        // Generate all module files
-        let modules = ["messaging.rs", "ext_state.rs", "component.rs", "runtime.rs"];
-        self.create_module_files(&mod_path, &modules)?;
+        Ok(self.report(&mod_path, &files, timings)?)
+    }
 
-        // Generate messaging module if message set exists
-        if let Some(messaging_content) = self.generate_messaging()? {
-            fs::write(mod_path.join("messaging.rs"), messaging_content)?;
+    /// Adds the imports of the types `content` uses that the graph resolved but its module
+    /// lacks, ahead of the module's other imports
+    ///
+    /// Code that doesn't parse is left as rendered.
+    fn add_missing_imports(&self, content: &mut String) {
+        let Ok(missing) = self.graph.missing_imports(content) else {
+            return;
+        };
+        if missing.is_empty() {
+            return;
         }
+        let mut imports = missing
+            .iter()
+            .map(|path| format!("use {path};\n"))
+            .collect::<String>();
+        // Without imports to join, they go after the module's docs and attributes
+        let mut offset = 0;
+        let mut first_use = None;
+        for line in content.split_inclusive('\n') {
+            if line.starts_with("use ") {
+                first_use = Some(offset);
+                break;
+            }
+            if !(line.starts_with("//") || line.starts_with("#!") || line.trim().is_empty()) {
+                break;
+            }
+            offset += line.len();
+        }
+        let at = first_use.unwrap_or_else(|| {
+            imports.push('\n');
+            offset
+        });
+        content.insert_str(at, &imports);
+    }
 
-        // Generate component.rs
-        let component_content = self.generate_component()?;
-        fs::write(mod_path.join("component.rs"), component_content)?;
-
-        // Generate ext_state.rs
-        let ext_state_content = self.generate_ext_state();
-        fs::write(mod_path.join("ext_state.rs"), ext_state_content)?;
+    /// Writes `file` into `dir`, with the license header of the one replaced in `target_dir`
+    fn write_file(
+        &self,
+        dir: &Path,
+        target_dir: &Path,
+        file: &Path,
+        content: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let path = dir.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = match &self.options.license_header {
+            Some(header) => {
+                let existing = fs::read_to_string(target_dir.join(file)).ok();
+                Cow::Owned(header.apply(file, content, existing.as_deref()))
+            }
+            None => Cow::Borrowed(content),
+        };
+        let content = self.options.line_endings.apply(&content);
+        fs::write(&path, content.as_bytes())
+            .map_err(|e| format!("Error writing {}: {e}", path.display()).into())
+    }
 
-        // Generate runtime.rs
-        let runtime_content = self.generate_runtime()?;
-        fs::write(mod_path.join("runtime.rs"), runtime_content)?;
+    /// Writes the fuzz targets of the wire codecs into the crate the actor is generated into
+    fn write_fuzz_targets(&self, krate: &CrateRoot) -> Result<(), Box<dyn Error>> {
+        let existing = fs::read_to_string(krate.dir.join(FUZZ_DIR).join("Cargo.toml")).ok();
+        render_fuzz_targets(&self.ctx(), krate, existing.as_deref())
+            .iter()
+            .try_for_each(|(file, content)| self.write_file(&krate.dir, &krate.dir, file, content))
+    }
 
-        // Generate states module
-        self.generate_states_module(&mod_path.join("states"))?;
+    /// Writes the demo example into the crate the actor is generated into, unless the
+    /// crate already has one the user may have changed
+    fn write_demo(&self, krate: &CrateRoot) -> Result<(), Box<dyn Error>> {
+        match render_demo(&self.ctx(), krate)? {
+            Some((file, content)) if !krate.dir.join(&file).exists() => {
+                self.write_file(&krate.dir, &krate.dir, &file, &content)
+            }
+            _ => Ok(()),
+        }
+    }
 
-        // Create root mod.rs
-        let mut all_modules = modules
+    /// Declares the actor module, and any nested groups, in the `mod.rs` of the group
+    /// containing it. Like an ungrouped actor, the outermost group is left to the user.
+    fn declare_group_modules(&self) -> Result<(), Box<dyn Error>> {
+        let actor_dir = self.actor.ident.to_lowercase();
+        let segments = self.actor.group_segments();
+        let children = segments
             .iter()
-            .map(|m| m.trim_end_matches(".rs"))
-            .collect::<Vec<_>>();
-        all_modules.push("states");
-        self.create_root_mod_rs(&mod_path, &all_modules)?;
-
+            .skip(1)
+            .copied()
+            .chain((!segments.is_empty()).then_some(actor_dir.as_str()));
+
+        let mut dir = self.actor.path.clone();
+        for (segment, child) in segments.iter().zip(children) {
+            dir.push(segment);
+            declare_module(&dir, child, self.options.line_endings)?;
+        }
         Ok(())
     }
 
-    // Helper methods for file operations
-    fn create_module_dir(&self, path: &Path) -> Result<(), String> {
-        fs::create_dir_all(path)
-            .map_err(|e| format!("Error creating directory {}: {e}", path.display()))
+    /// Renders every file of the actor module, keyed by path relative to the module directory
+    pub fn render_files(&self) -> Result<Vec<(PathBuf, String)>, RenderError> {
+        let mut files = vec![
+            (
+                PathBuf::from("messaging.rs"),
+                self.generate_messaging()?.unwrap_or_default(),
+            ),
+            (PathBuf::from("component.rs"), self.generate_component()?),
+            (PathBuf::from("ext_state.rs"), self.generate_ext_state()?),
+            (PathBuf::from("runtime.rs"), self.generate_runtime()?),
+        ];
+        if let Some(pool) = self.generate_pool()? {
+            files.push((PathBuf::from("pool.rs"), pool));
+        }
+        if let Some(shard) = self.generate_shard()? {
+            files.push((PathBuf::from("shard.rs"), shard));
+        }
+        if let Some(bridge) = self.generate_bridge()? {
+            files.push((PathBuf::from("bridge.rs"), bridge));
+        }
+        if let Some(sources) = self.generate_sources()? {
+            files.push((PathBuf::from("sources.rs"), sources));
+        }
+        if let Some(sinks) = self.generate_sinks()? {
+            files.push((PathBuf::from("sinks.rs"), sinks));
+        }
+        if let Some(scheduler) = self.generate_scheduler()? {
+            files.push((PathBuf::from("scheduler.rs"), scheduler));
+        }
+        if let Some(sequence) = self.generate_sequence()? {
+            files.push((PathBuf::from("sequence.rs"), sequence));
+        }
+        if let Some(rate_limit) = self.generate_rate_limit()? {
+            files.push((PathBuf::from("rate_limit.rs"), rate_limit));
+        }
+        if let Some(circuit_breaker) = self.generate_circuit_breaker()? {
+            files.push((PathBuf::from("circuit_breaker.rs"), circuit_breaker));
+        }
+        if let Some(persistence) = self.generate_persistence()? {
+            files.push((PathBuf::from("persistence.rs"), persistence));
+        }
+        if let Some(correlation) = self.generate_correlation()? {
+            files.push((PathBuf::from("correlation.rs"), correlation));
+        }
+        if let Some(health) = self.generate_health()? {
+            files.push((PathBuf::from("health.rs"), health));
+        }
+        if let Some(supervisor) = self.generate_supervisor()? {
+            files.push((PathBuf::from("supervisor.rs"), supervisor));
+        }
+        if let Some(children) = self.generate_children()? {
+            files.push((PathBuf::from("children.rs"), children));
+        }
+
+        let states_dir = PathBuf::from("states");
+        let groups = self.state_groups()?;
+        for state in &self.actor.component.states.states {
+            files.push((
+                self.state_file(state, groups.as_ref()),
+                self.generate_state_impl(state)?,
+            ));
+        }
+        let state_enum = &self.actor.component.states.state_enum;
+        for (group, states) in groups.iter().flatten() {
+            files.push((
+                states_dir.join(group).join("mod.rs"),
+                state_enum.render_group(&self.ctx(), group, states),
+            ));
+        }
+        files.push((
+            states_dir.join("mod.rs"),
+            self.generate_states_mod_rs(groups.as_ref())?,
+        ));
+        // Generated code keeps using deprecated items until they leave the spec, so the
+        // allow covers every module under the actor's but none of the code using it
+        let root_mod = if self.actor.has_deprecations() {
+            format!("#![allow(deprecated)]\n{}", self.generate_root_mod_rs())
+        } else {
+            self.generate_root_mod_rs()
+        };
+        files.push((PathBuf::from("mod.rs"), root_mod));
+        if let Some(readme) = self.generate_readme()? {
+            files.push((PathBuf::from("README.md"), readme));
+        }
+
+        // Discovery can miss a type a module uses, so the code itself has the last word
+        for (path, content) in &mut files {
+            if path.extension().is_some_and(|ext| ext == "rs") {
+                self.add_missing_imports(content);
+            }
+        }
+
+        if self.options.static_assertions {
+            let ctx = self.ctx();
+            for (path, content) in &mut files {
+                if let Some(assertions) = render_static_assertions(&ctx, path) {
+                    content.push_str(&assertions);
+                }
+            }
+        }
+        if self.options.clippy_allows {
+            for (path, content) in &mut files {
+                let lints = Self::CLIPPY_ALLOWS
+                    .iter()
+                    .find(|(file, _)| path.as_path() == Path::new(file))
+                    .map(|(_, lints)| lints.join(", "));
+                if let Some(lints) = lints.filter(|_| !content.is_empty()) {
+                    *content = format!("#![allow({lints})]\n{content}");
+                }
+            }
+        }
+
+        Ok(files)
     }
 
-    fn create_module_files(&self, mod_path: &Path, modules: &[&str]) -> Result<(), Box<dyn Error>> {
-        modules
-            .iter()
-            .map(|mod_file| mod_path.join(mod_file))
-            .map(File::create)
-            .try_for_each(|res| {
-                res.map(|_| ())
-                    .map_err(|e| format!("Error creating file: {e}").into())
-            })
+    /// Gets the modules declared in the actor's root mod.rs, in emission order
+    pub fn root_modules(&self) -> Vec<String> {
+        self.graph
+            .submodules_in_dependency_order(&self.actor_module())
     }
 
-    fn create_root_mod_rs(&self, mod_path: &Path, modules: &[&str]) -> Result<(), Box<dyn Error>> {
-        let mod_rs_content = modules
+    fn generate_root_mod_rs(&self) -> String {
+        self.root_modules()
             .iter()
             .map(|mod_name| format!("pub mod {mod_name};"))
             .collect::<Vec<_>>()
-            .join("\n");
-
-        fs::write(mod_path.join("mod.rs"), mod_rs_content)
-            .map_err(|e| format!("Error creating mod.rs file: {e}").into())
+            .join("\n")
     }
 
-    fn generate_states_module(&self, states_path: &Path) -> Result<(), Box<dyn Error>> {
-        self.create_module_dir(states_path)?;
+    fn generate_states_mod_rs(&self, groups: Option<&StateGroups>) -> Result<String, RenderError> {
+        // State modules followed by the state enum
+        let state_module = |state: &State| state.ident.to_lowercase();
+        let state_modules = match groups {
+            None => self
+                .actor
+                .component
+                .states
+                .states
+                .iter()
+                .map(|state| format!("pub mod {};", state_module(state)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            // Re-exports keep every state module at the path it has when not split
+            Some(groups) => {
+                let declarations = groups.keys().map(|group| format!("mod {group};"));
+                let reexports = groups
+                    .iter()
+                    .map(|(group, states)| match states.as_slice() {
+                        [state] => format!("pub use {group}::{};", state_module(state)),
+                        _ => {
+                            let modules = states.iter().map(|state| state_module(state));
+                            format!(
+                                "pub use {group}::{{{}}};",
+                                modules.collect::<Vec<_>>().join(", ")
+                            )
+                        }
+                    });
+                declarations.collect::<Vec<_>>().join("\n")
+                    + "\n\n"
+                    + &reexports.collect::<Vec<_>>().join("\n")
+            }
+        };
 
-        // Generate individual state files
-        for state in &self.actor.component.states.states {
-            let state_content = self.generate_state_impl(state)?;
-            let state_file = states_path.join(format!("{}.rs", state.ident.to_lowercase()));
-            fs::write(state_file, state_content)?;
-        }
+        let state_enum_impl = render_split_state_enum_module(&self.ctx(), groups)?;
 
-        // Generate states/mod.rs with state modules and enum
-        let state_modules = self
-            .actor
-            .component
-            .states
-            .states
-            .iter()
-            .map(|state| format!("pub mod {};", state.ident.to_lowercase()))
-            .collect::<Vec<_>>()
-            .join("\n");
+        Ok(format!("{state_modules}\n\n{state_enum_impl}"))
+    }
 
-        let state_enum_impl = self.generate_state_enum()?;
+    /// Groups of the states `states/mod.rs` is split into once it would exceed
+    /// `max_file_lines`: by first letter, with letters too large for the budget split further
+    pub(crate) fn state_groups(&self) -> Result<Option<StateGroups<'_>>, RenderError> {
+        let Some(max_lines) = self.options.max_file_lines else {
+            return Ok(None);
+        };
+        if self.generate_states_mod_rs(None)?.lines().count() <= max_lines {
+            return Ok(None);
+        }
 
-        let mod_rs_content = format!("{state_modules}\n\n{state_enum_impl}");
-        fs::write(states_path.join("mod.rs"), mod_rs_content)?;
+        let ctx = self.ctx();
+        let state_enum = &self.actor.component.states.state_enum;
+        let mut groups = StateGroups::new();
+        for (letter, states) in StateEnum::letter_groups(&self.actor.component.states.states) {
+            let lines = state_enum
+                .render_group(&ctx, &letter, &states)
+                .lines()
+                .count();
+            let parts = lines.div_ceil(max_lines.max(1));
+            if parts <= 1 {
+                groups.insert(format!("{letter}_states"), states);
+                continue;
+            }
+            for (i, part) in states.chunks(states.len().div_ceil(parts)).enumerate() {
+                groups.insert(format!("{letter}{}_states", i + 1), part.to_vec());
+            }
+        }
+        // A single group would only move the whole enum one directory down
+        Ok((groups.len() > 1).then_some(groups))
+    }
 
-        Ok(())
+    /// File a state's module is written to, relative to the actor module directory
+    pub(crate) fn state_file(&self, state: &State, groups: Option<&StateGroups>) -> PathBuf {
+        let file = format!("{}.rs", state.ident.to_lowercase());
+        let group = groups.and_then(|groups| {
+            groups
+                .iter()
+                .find(|(_, states)| states.iter().any(|s| s.ident == state.ident))
+        });
+        match group {
+            Some((group, _)) => Path::new("states").join(group).join(file),
+            None => Path::new("states").join(file),
+        }
     }
 
     // Helper methods for message generation
     fn generate_enum_definition(
         &self,
         enum_def: &crate::blox::enums::EnumDef,
-    ) -> Result<String, Box<dyn Error>> {
+        ctx: &RenderCtx,
+    ) -> Result<String, RenderError> {
         let enum_name = &enum_def.ident;
+        let derives = ctx.message_set_derives();
+        let derive_attr = if derives.is_empty() {
+            String::new()
+        } else {
+            format!("#[derive({})]\n", derives.join(", "))
+        };
 
         let variants = enum_def
             .variants
             .iter()
             .fold(String::new(), |acc, variant| {
+                let doc = variant.doc.as_deref().unwrap_or(&variant.ident);
+                let deprecated = variant
+                    .deprecated
+                    .as_deref()
+                    .map(|note| format!("    {}\n", deprecated_attr(note)))
+                    .unwrap_or_default();
                 if variant.args.is_empty() {
                     format!(
-                        "{acc}    /// {ident}\n    {ident},\n",
+                        "{acc}    /// {doc}\n{deprecated}    {ident},\n",
                         ident = variant.ident
                     )
                 } else {
                     let args = variant
                         .args
                         .iter()
-                        .map(|arg| format!("Message<{arg}>"))
+                        .map(|arg| {
+                            if variant.boxed {
+                                format!("Box<Message<{arg}>>")
+                            } else {
+                                format!("Message<{arg}>")
+                            }
+                        })
                         .collect::<Vec<String>>()
                         .join(", ");
 
                     format!(
-                        "{acc}    /// {ident}\n    {ident}({args}),\n",
+                        "{acc}    /// {doc}\n{deprecated}    {ident}({args}),\n",
                         ident = variant.ident,
                     )
                 }
@@ -461,16 +1233,98 @@ impl Runnable<{actor_name}Components> for Blox<{actor_name}Components> {{
 ///
 /// This enum contains all possible message types that can be dispatched to the
 /// actor's state machine, allowing for unified message processing logic.
-pub enum {enum_name} {{
+{derive_attr}pub enum {enum_name} {{
 {variants}}}"#
         ))
     }
 
+    /// Conversions boxing the payload of each boxed variant, so messages are built from their
+    /// payload with `into()` whether or not it is boxed. Payload types more than one variant
+    /// carries get none, since the conversion could not tell the variants apart.
+    fn generate_boxed_conversions(enum_def: &crate::blox::enums::EnumDef) -> String {
+        let enum_name = &enum_def.ident;
+        enum_def
+            .variants
+            .iter()
+            .filter(|variant| variant.boxed)
+            .filter_map(|variant| match variant.args.as_slice() {
+                [payload] => Some((variant, payload)),
+                _ => None,
+            })
+            .filter(|(_, payload)| {
+                enum_def
+                    .variants
+                    .iter()
+                    .filter(|other| other.args.first() == Some(*payload))
+                    .count()
+                    == 1
+            })
+            .map(|(variant, payload)| {
+                format!(
+                    r#"
+impl From<Message<{payload}>> for {enum_name} {{
+    fn from(msg: Message<{payload}>) -> Self {{
+        {enum_name}::{variant}(Box::new(msg))
+    }}
+}}
+"#,
+                    variant = variant.ident
+                )
+            })
+            .collect()
+    }
+
+    fn generate_wire_codecs(message_set: &str, format: WireFormat) -> String {
+        let name = format.name();
+        let (encode_error, encode) = format.encoder("self");
+        let (decode_error, decode) = format.decoder();
+        format!(
+            r#"
+impl {message_set} {{
+    /// Encodes the message as {name}, for bridging the actor over a socket or broker
+    pub fn to_wire(&self) -> Result<Vec<u8>, {encode_error}> {{
+        {encode}
+    }}
+
+    /// Decodes a message encoded by `to_wire`
+    pub fn from_wire(bytes: &[u8]) -> Result<Self, {decode_error}> {{
+        {decode}
+    }}
+}}
+"#
+        )
+    }
+
+    /// Traits every type defined next to the message set derives
+    fn custom_type_derives(&self) -> Vec<&str> {
+        let mut derives = vec!["Debug", "Clone", "PartialEq"];
+        if self.options.wire_format.is_some() {
+            derives.extend(RenderCtx::WIRE_DERIVES);
+        }
+        derives
+    }
+
+    fn generate_newtype_definition(&self, newtype: &Newtype) -> String {
+        let mut derives = self.custom_type_derives();
+        for derive in &newtype.derives {
+            if !derives.contains(&derive.as_str()) {
+                derives.push(derive);
+            }
+        }
+        format!(
+            "/// Newtype over `{ty}`\n#[derive({derives})]\npub struct {ident}(pub {ty});",
+            ident = newtype.ident,
+            ty = newtype.ty,
+            derives = derives.join(", ")
+        )
+    }
+
     fn generate_custom_type_definition(
         &self,
         enum_def: &crate::blox::enums::EnumDef,
-    ) -> Result<String, Box<dyn Error>> {
+    ) -> Result<String, RenderError> {
         let enum_name = &enum_def.ident;
+        let derives = self.custom_type_derives().join(", ");
 
         let variants = enum_def
             .variants
@@ -498,7 +1352,7 @@ pub enum {enum_name} {{
 
         Ok(format!(
             r#"/// Custom type definition
-#[derive(Debug, Clone, PartialEq)]
+#[derive({derives})]
 pub enum {enum_name} {{
 {variants}}}"#
         ))
@@ -508,12 +1362,18 @@ pub enum {enum_name} {{
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::blox::enums::EnumDef;
+    use crate::blox::ext_state::{History, Stash};
+    use crate::blox::lifecycle::Lifecycle;
+    use crate::blox::message_set::{MessageSet, TypeAlias};
+    use crate::field::{Field, LockKind};
     use crate::tests::create_test_actor;
+    use std::path::Path;
 
     #[test]
     fn test_actor_generator_basic() {
         let actor = create_test_actor();
-        let mut generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
 
         let component_result = generator.generate_component();
         assert!(component_result.is_ok());
@@ -535,10 +1395,58 @@ mod tests {
         assert!(runtime_code.contains("impl Runnable<ActorComponents>"));
 
         // Test ext_state generation
-        let ext_state_code = generator.generate_ext_state();
+        let ext_state_code = generator
+            .generate_ext_state()
+            .expect("Ext state generation should succeed");
         assert!(ext_state_code.contains("Extended state for the Actor component"));
     }
 
+    #[test]
+    fn test_state_invariants() {
+        use crate::blox::state::Transition;
+
+        let actor = || {
+            let mut actor = create_test_actor();
+            actor.component.states.states[0] = State::from("Create")
+                .with_transitions(vec![Transition::new("CustomValue1", "Update")])
+                .with_invariants(vec![
+                    "ext.field1 <= 10".to_string(),
+                    "ext.field2.is_empty() || matches!(self, Create {})".to_string(),
+                ]);
+            actor.component.states.states[1] =
+                State::from("Update").with_invariants(vec!["true".to_string()]);
+            actor
+        };
+        let generator = ActorGenerator::new(actor()).expect("Generator creation should succeed");
+
+        let states = &generator.actor().component.states.states;
+        let create = generator.generate_state_impl(&states[0]).unwrap();
+        assert!(create.contains(
+            "    fn on_entry(&self, state_machine: &mut StateMachine<ActorComponents>) {\n        self.assert_invariants(state_machine);\n    }\n}"
+        ));
+        assert!(create.contains(
+            r#"    fn assert_invariants(&self, state_machine: &StateMachine<ActorComponents>) {
+        let ext = &state_machine.extended_state;
+        debug_assert!(ext.field1 <= 10, "invariant of Create violated: ext.field1 <= 10");
+        debug_assert!(ext.field2.is_empty() || matches!(self, Create {}), "invariant of Create violated: ext.field2.is_empty() || matches!(self, Create {{}})");
+    }"#
+        ));
+        assert!(create.contains("        match &message {"));
+        let update = generator.generate_state_impl(&states[1]).unwrap();
+        assert!(update.contains("fn assert_invariants(&self, _state_machine: &StateMachine<ActorComponents>) {\n        debug_assert!(true, "));
+
+        let options = GeneratorOptions::default().with_invariants_after_messages(true);
+        let generator = ActorGenerator::with_options(actor(), options)
+            .expect("Generator creation should succeed");
+        let states = &generator.actor().component.states.states;
+        let create = generator.generate_state_impl(&states[0]).unwrap();
+        assert!(create.contains(
+            "        let transition = match &message {\n            ActorMessageSet::CustomValue1(..) => Some(Transition::To(ActorStates::Update(Update))),\n            _ => None,\n        };\n        self.assert_invariants(state_machine);\n        transition\n    }"
+        ));
+        let update = generator.generate_state_impl(&states[1]).unwrap();
+        assert!(update.contains("        self.assert_invariants(state_machine);\n        None\n"));
+    }
+
     #[test]
     fn test_actor_generator_state_generation() {
         let actor = create_test_actor();
@@ -582,13 +1490,593 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_trait_object_dispatch() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let state_enum = generator.generate_state_enum().unwrap();
+        assert!(state_enum.contains(
+            "        match self {\n            ActorStates::Create(state) => state.on_exit(state_machine),"
+        ));
+        assert!(!state_enum.contains("as_state"));
+
+        let options = GeneratorOptions::default().with_dispatch(DispatchStrategy::TraitObject);
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        let state_enum = generator.generate_state_enum().unwrap();
+        assert!(state_enum.contains(
+            "    fn as_state(&self) -> &dyn State<ActorComponents> {\n        match self {\n            ActorStates::Create(state) => state,\n            ActorStates::Update(state) => state,\n        }"
+        ));
+        assert!(
+            state_enum.contains("        self.as_state().handle_message(state_machine, message)\n")
+        );
+        assert!(state_enum.contains(
+            "    fn parent(&self) -> ActorStates {\n        self.as_state().parent()\n    }"
+        ));
+        assert!(!state_enum.contains("state.on_exit(state_machine),"));
+        assert_eq!("trait-object".parse(), Ok(DispatchStrategy::TraitObject));
+    }
+
+    #[test]
+    fn test_split_states_module() {
+        let mut actor = create_test_actor();
+        actor
+            .component
+            .states
+            .states
+            .extend(["Cancel", "Delete", "Drain"].map(State::from));
+        let options = GeneratorOptions::default().with_max_file_lines(Some(60));
+        let generator = ActorGenerator::with_options(actor, options)
+            .expect("Generator creation should succeed");
+        let files = generator.render_files().unwrap();
+        let file = |name: &str| {
+            files
+                .iter()
+                .find(|(path, _)| path == Path::new(name))
+                .map(|(_, content)| content.as_str())
+                .unwrap_or_else(|| panic!("{name} should be generated"))
+        };
+
+        let states_mod = file("states/mod.rs");
+        assert!(states_mod.starts_with(
+            "mod c_states;\nmod d_states;\nmod u_states;\n\npub use c_states::{create, cancel};\npub use d_states::{delete, drain};\npub use u_states::update;\n"
+        ));
+        assert!(states_mod.contains(
+            "            ActorStates::Create(_) | ActorStates::Cancel(_) => self.on_exit_c_states(state_machine),"
+        ));
+        let group = file("states/d_states/mod.rs");
+        assert!(group.contains("use super::*;\n\npub mod delete;\npub mod drain;\n"));
+        assert!(group.contains(
+            "    pub(super) fn parent_d_states(&self) -> ActorStates {\n        match self {\n            ActorStates::Delete(state) => state.parent(),"
+        ));
+        assert!(file("states/d_states/drain.rs").contains("pub struct Drain"));
+        assert!(
+            !files
+                .iter()
+                .any(|(path, _)| path == Path::new("states/create.rs"))
+        );
+
+        let plans = generator.plan().unwrap();
+        let update = plans
+            .iter()
+            .find(|plan| plan.module == "actor::states::update")
+            .unwrap();
+        assert!(update.file.ends_with("states/u_states/update.rs"));
+
+        // A budget the module fits in leaves it whole
+        let options = GeneratorOptions::default().with_max_file_lines(Some(10_000));
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        assert!(generator.state_groups().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_root_modules_follow_graph() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let modules = generator.root_modules();
+
+        assert_eq!(
+            modules,
+            vec!["ext_state", "messaging", "runtime", "component", "states"]
+        );
+        assert_eq!(
+            modules,
+            ActorGenerator::new(create_test_actor())
+                .unwrap()
+                .root_modules()
+        );
+    }
+
+    #[test]
+    fn test_rendered_code_gets_the_imports_it_uses() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        // Receivers name the runtime type, which discovery doesn't link to the component
+        assert!(
+            !generator
+                .generate_component()
+                .unwrap()
+                .contains("use bloxide_tokio::TokioRuntime;")
+        );
+        let files = generator.render_files().unwrap();
+        let component = &files
+            .iter()
+            .find(|(path, _)| path == Path::new("component.rs"))
+            .unwrap()
+            .1;
+        assert!(component.contains(
+            "use bloxide_tokio::TokioRuntime;\nuse bloxide_tokio::TokioMessageHandle;\n"
+        ));
+        assert_eq!(
+            component
+                .matches("use bloxide_tokio::TokioRuntime;")
+                .count(),
+            1
+        );
+    }
+
     #[test]
     fn test_actor_generator_creation() {
         let actor = create_test_actor();
 
-        let mut generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
 
         let component_result = generator.generate_component();
         assert!(component_result.is_ok());
     }
+
+    #[test]
+    fn test_grouped_actor_is_nested() {
+        let mut actor = create_test_actor().with_group("billing::refunds");
+        actor.path = PathBuf::from("tests/output/grouped");
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(generator.actor_module(), "billing::refunds::actor");
+
+        let state = &generator.actor().component.states.states[0];
+        let state_impl = generator.generate_state_impl(state).unwrap();
+        assert!(
+            state_impl.contains("use crate::billing::refunds::actor::component::ActorComponents;")
+        );
+        assert!(
+            state_impl.contains("use crate::billing::refunds::actor::messaging::ActorMessageSet;")
+        );
+
+        generator.generate().expect("Generation should succeed");
+        let root = Path::new("tests/output/grouped/billing");
+        assert!(root.join("refunds/actor/states/create.rs").exists());
+        assert_eq!(
+            fs::read_to_string(root.join("mod.rs")).unwrap(),
+            "pub mod refunds;\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("refunds/mod.rs")).unwrap(),
+            "pub mod actor;\n"
+        );
+    }
+
+    #[test]
+    fn test_license_header_is_stable() {
+        let mut actor = create_test_actor();
+        actor.path = PathBuf::from("tests/output/licensed");
+        let header = LicenseHeader::new("MIT OR Apache-2.0").with_holder(Some("Acme Corp".into()));
+        let options = GeneratorOptions::default().with_license_header(Some(header));
+        let generator = ActorGenerator::with_options(actor, options)
+            .expect("Generator creation should succeed");
+
+        generator.generate().expect("Generation should succeed");
+        let path = Path::new("tests/output/licensed/actor/states/create.rs");
+        let first = fs::read_to_string(path).unwrap();
+        assert!(first.starts_with(
+            "// SPDX-License-Identifier: MIT OR Apache-2.0\n// SPDX-FileCopyrightText: "
+        ));
+
+        generator.generate().expect("Regeneration should succeed");
+        assert_eq!(fs::read_to_string(path).unwrap(), first);
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let mut actor = create_test_actor();
+        actor.path = PathBuf::from("tests/output/crlf");
+        let header = LicenseHeader::new("MIT").with_holder(Some("Acme Corp".into()));
+        let options = GeneratorOptions::default()
+            .with_license_header(Some(header))
+            .with_line_endings(LineEndings::Crlf)
+            .with_no_overwrite(true);
+        let generator = ActorGenerator::with_options(actor, options)
+            .expect("Generator creation should succeed");
+
+        generator.generate().expect("Generation should succeed");
+        let path = Path::new("tests/output/crlf/actor/component.rs");
+        let first = fs::read_to_string(path).unwrap();
+        assert!(first.starts_with("// SPDX-License-Identifier: MIT\r\n"));
+        assert_eq!(first.matches('\n').count(), first.matches("\r\n").count());
+
+        // Unchanged files are byte for byte the same, so they are not refused
+        generator.generate().expect("Regeneration should succeed");
+        assert_eq!(fs::read_to_string(path).unwrap(), first);
+    }
+
+    #[test]
+    fn test_runtime_drains_high_priority_receivers_first() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(!runtime.contains("biased;"));
+
+        let mut actor = create_test_actor();
+        let receivers = &mut actor.component.message_receivers.receivers;
+        receivers[1] = receivers[1].clone().with_priority(1);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let runtime = generator.generate_runtime().unwrap();
+
+        let biased = runtime.find("biased;").expect("select! should be biased");
+        let high = runtime.find("self.receivers.customargs_rx.recv()").unwrap();
+        let normal = runtime.find("self.receivers.standard_rx.recv()").unwrap();
+        assert!(biased < high && high < normal);
+        assert!(runtime.contains("ActorMessageSet::CustomValue2(msg)"));
+    }
+
+    #[test]
+    fn test_runtime_lifecycle_hooks() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains("    fn run(mut self: Box<Self>) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {\n        self.state_machine.init("));
+        assert!(runtime.contains(
+            "                    else => break,\n                }\n            }\n        })"
+        ));
+
+        let lifecycle = Lifecycle::default()
+            .with_pre_start("let pool = connect();\nself.ext_state.set_pool(pool);")
+            .with_post_stop("println!(\"stopped\");");
+        let actor = create_test_actor().with_lifecycle(lifecycle);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let runtime = generator.generate_runtime().unwrap();
+
+        assert!(runtime.contains(
+            "        let pool = connect();\n        self.ext_state.set_pool(pool);\n\n        self.state_machine.init("
+        ));
+        assert!(runtime.contains(
+            "                    else => break,\n                }\n            }\n\n            println!(\"stopped\");\n        })"
+        ));
+    }
+
+    #[test]
+    fn test_wire_codecs() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(!messaging.contains("to_wire"));
+        assert!(!messaging.contains("serde::Serialize"));
+
+        let options = GeneratorOptions::default().with_wire_format(Some(WireFormat::Json));
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains(
+            "#[derive(serde::Serialize, serde::Deserialize)]\npub enum ActorMessageSet {"
+        ));
+        assert!(messaging.contains(
+            "pub fn to_wire(&self) -> Result<Vec<u8>, serde_json::Error> {\n        serde_json::to_vec(self)"
+        ));
+        assert!(messaging.contains(
+            "pub fn from_wire(bytes: &[u8]) -> Result<Self, serde_json::Error> {\n        serde_json::from_slice(bytes)"
+        ));
+
+        let options = GeneratorOptions::default().with_wire_format(Some(WireFormat::Cbor));
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains("ciborium::into_writer(self, &mut bytes)?;"));
+        assert!(messaging.contains("Result<Self, ciborium::de::Error<std::io::Error>>"));
+
+        assert_eq!("postcard".parse(), Ok(WireFormat::Postcard));
+        assert!("xml".parse::<WireFormat>().is_err());
+    }
+
+    #[test]
+    fn test_boxed_variants() {
+        let mut actor = create_test_actor();
+        let message_set = actor.component.message_set.as_mut().unwrap();
+        message_set.def.variants[1] = message_set.def.variants[1].clone().boxed();
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains(
+            "    CustomValue2(Box<Message<CustomArgs>>),
+"
+        ));
+        assert!(messaging.contains(
+            "impl From<Message<CustomArgs>> for ActorMessageSet {
+    fn from(msg: Message<CustomArgs>) -> Self {
+        ActorMessageSet::CustomValue2(Box::new(msg))
+    }
+}"
+        ));
+        assert!(!messaging.contains("From<Message<bloxide_core::messaging::StandardPayload>>"));
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains("ActorMessageSet::CustomValue2(Box::new(msg))"));
+        assert!(runtime.contains("ActorMessageSet::CustomValue1(msg)"));
+    }
+
+    #[test]
+    fn test_aliases_and_newtypes() {
+        let mut actor = create_test_actor();
+        let message_set = actor.component.message_set.take().unwrap();
+        actor.component.message_set = Some(
+            message_set
+                .with_aliases(vec![TypeAlias::new("OrderId", "u64")])
+                .with_newtypes(vec![
+                    Newtype::new("Sku", "String").with_derives(["Eq", "Hash", "Clone"]),
+                ]),
+        );
+        actor
+            .component
+            .ext_state
+            .add_field(Field::new("last_order", "Option<OrderId>"));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains("/// Alias of `u64`\npub type OrderId = u64;\n\n"));
+        assert!(messaging.contains(
+            "/// Newtype over `String`\n#[derive(Debug, Clone, PartialEq, Eq, Hash)]\npub struct Sku(pub String);"
+        ));
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(ext_state.contains("use crate::actor::messaging::OrderId;\n"));
+
+        let mut actor = create_test_actor();
+        let message_set = actor.component.message_set.take().unwrap();
+        actor.component.message_set =
+            Some(message_set.with_aliases(vec![TypeAlias::new("ActorMessageSet", "u64")]));
+        assert_eq!(
+            actor.component.validate(),
+            Err("Type 'ActorMessageSet' is defined twice in the message set".to_string())
+        );
+        let message_set = MessageSet::new(EnumDef::new("Messages", vec![]))
+            .with_newtypes(vec![Newtype::new("Tree", "Vec<Tree>")]);
+        assert_eq!(
+            message_set.validate(),
+            Err("Type 'Tree' is defined in terms of itself".to_string())
+        );
+    }
+
+    #[test]
+    fn test_self_messages() {
+        let actor = create_test_actor().with_self_messages();
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(ext_state.contains("pub self_handle: UnboundedSender<ActorMessageSet>"));
+        assert!(ext_state.contains("self_handle: args.self_handle"));
+        assert!(ext_state.contains("pub fn defer(&self, message: ActorMessageSet) {"));
+        let imports = generator.ctx().imports_for("actor::ext_state").unwrap();
+        assert!(imports.contains(&"use tokio::sync::mpsc::UnboundedSender;".to_string()));
+        assert!(ext_state.contains("\nuse crate::actor::messaging::ActorMessageSet;\n"));
+
+        let component = generator.generate_component().unwrap();
+        assert!(component.contains("pub self_rx: UnboundedReceiver<ActorMessageSet>\n}"));
+        assert!(!component.contains("pub self: "));
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "                    Some(msg) = self.receivers.self_rx.recv() => {\n                        let current_state = self.state_machine.current_state.clone();\n                        self.state_machine.dispatch(msg, &current_state);\n                    }\n                    else => break,"
+        ));
+
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(!generator.generate_ext_state().unwrap().contains("defer"));
+        assert!(!generator.generate_runtime().unwrap().contains("self_rx"));
+    }
+
+    #[test]
+    fn test_unresolved_types_are_diagnostics() {
+        let mut actor = create_test_actor()
+            .with_self_messages()
+            .with_stash(Stash::new(16))
+            .with_history(History::new(8))
+            .with_correlation()
+            .with_health_check();
+        actor
+            .component
+            .ext_state
+            .add_field(Field::new("cache", "Vec<u8>").shared(LockKind::Mutex));
+        actor
+            .component
+            .ext_state
+            .add_field(Field::new("peer", "PeerId"));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        // Only the spec's own unqualified types are reported, not the ones the generator adds
+        let unresolved = generator
+            .diagnostics()
+            .into_iter()
+            .filter(|d| d.code == "unresolved-type")
+            .map(|d| d.message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unresolved,
+            [
+                "Cannot resolve type 'PeerId' used in module 'actor::ext_state'; use a qualified path for external types",
+                "Cannot resolve type 'CustomArgs' used in module 'actor::component'; use a qualified path for external types",
+                "Cannot resolve type 'CustomArgs' used in module 'actor::messaging'; use a qualified path for external types",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stash() {
+        let actor = create_test_actor().with_stash(Stash::new(16));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(ext_state.contains("pub stashed: VecDeque<ActorMessageSet>"));
+        assert!(ext_state.contains("stashed: Default::default()"));
+        assert!(!ext_state.contains("args.stashed"));
+        assert!(ext_state.contains("if self.stashed.len() >= 16 {"));
+        assert!(ext_state.contains("pub fn unstash_all(&mut self) {"));
+        assert!(ext_state.contains("pub unstashed: VecDeque<ActorMessageSet>"));
+        // Replays skip the queues, so they don't need the actor's own
+        assert!(!ext_state.contains("pub fn defer(&self, message: ActorMessageSet) {"));
+        let imports = generator.ctx().imports_for("actor::ext_state").unwrap();
+        assert!(imports.contains(&"use std::collections::VecDeque;".to_string()));
+        assert!(ext_state.contains("\nuse crate::actor::messaging::ActorMessageSet;\n"));
+
+        let state_enum = generator.generate_state_enum().unwrap();
+        assert!(
+            state_enum
+                .contains("        }\n        state_machine.extended_state.unstash_all();\n    }")
+        );
+        // They are dispatched before the channels are read again
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(!runtime.contains("self_rx"));
+        assert!(runtime.contains(
+            "            loop {\n                while let Some(msg) = self.state_machine.extended_state.unstashed.pop_front() {\n                    let current_state = self.state_machine.current_state.clone();\n                    self.state_machine.dispatch(msg, &current_state);\n                }\n                select! {\n"
+        ));
+
+        let generator = ActorGenerator::new(create_test_actor().with_stash(Stash::new(0)))
+            .expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate().unwrap_err().to_string(),
+            "Stash capacity must be at least 1"
+        );
+    }
+
+    #[test]
+    fn test_clippy_allows() {
+        let options = GeneratorOptions::default().with_clippy_allows(true);
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        let files = generator.render_files().unwrap();
+        let file = |name: &str| {
+            files
+                .iter()
+                .find(|(path, _)| path == Path::new(name))
+                .map(|(_, content)| content.as_str())
+                .unwrap()
+        };
+        assert!(file("ext_state.rs").starts_with("#![allow(clippy::too_many_arguments)]\n"));
+        assert!(file("messaging.rs").starts_with("#![allow(clippy::large_enum_variant)]\n"));
+        assert!(!file("runtime.rs").starts_with("#![allow("));
+
+        // Unused init args are named so rustc does not warn about them
+        let ext_state = file("ext_state.rs");
+        assert!(ext_state.contains("field1: args.field1,\n\tfield2: Default::default()"));
+
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let files = generator.render_files().unwrap();
+        assert!(
+            files
+                .iter()
+                .all(|(_, content)| !content.contains("#![allow("))
+        );
+    }
+
+    #[test]
+    fn test_deprecated_items() {
+        let mut actor = create_test_actor();
+        let states = &mut actor.component.states.states;
+        states[1] = State::new("Update", Some("Create".to_string()), None)
+            .deprecated("merged into Create")
+            .with_fields(vec![Field::new("retries", "u8").deprecated("always 0")]);
+        let message_set = actor.component.message_set.as_mut().unwrap();
+        message_set.def.variants[0].deprecated = Some("send \"CustomValue2\"".to_string());
+
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains(
+            "    #[deprecated(note = \"send \\\"CustomValue2\\\"\")]\n    CustomValue1("
+        ));
+        let update = generator
+            .generate_state_impl(&generator.actor.component.states.states[1])
+            .unwrap();
+        assert!(update.contains("#[deprecated(note = \"merged into Create\")]\n#[derive("));
+        assert!(update.contains("    #[deprecated(note = \"always 0\")]\n    pub retries: u8,"));
+
+        // The actor's own modules still use them without warnings
+        let files = generator.render_files().unwrap();
+        let (_, root_mod) = files
+            .iter()
+            .find(|(path, _)| path == Path::new("mod.rs"))
+            .unwrap();
+        assert!(root_mod.starts_with("#![allow(deprecated)]\n"));
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(!generator.generate_root_mod_rs().contains("deprecated"));
+    }
+
+    #[test]
+    fn test_secret_references() {
+        let actor = || {
+            let mut actor = create_test_actor();
+            actor.path = PathBuf::from("tests/output/secrets");
+            let ext_state = &mut actor.component.ext_state;
+            ext_state.add_field(
+                Field::new("api_key", "String").with_value("${secret:BLOXML_TEST_API_KEY}"),
+            );
+            ext_state.add_field(Field::new("retries", "u32").with_value("3"));
+            actor
+        };
+
+        let generator = ActorGenerator::new(actor()).expect("Generator creation should succeed");
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(ext_state.contains("api_key: load_secret(\"BLOXML_TEST_API_KEY\")?"));
+        assert!(ext_state.contains("retries: 3"));
+        assert!(ext_state.contains(
+            "fn load_secret<T: std::str::FromStr>(name: &'static str) -> Result<T, SecretError>"
+        ));
+        // Missing secrets are errors the caller can handle, through the fallible constructor
+        assert!(
+            ext_state
+                .contains("    pub fn try_new(args: ActorInitArgs) -> Result<Self, SecretError> {")
+        );
+        assert!(
+            ext_state.contains("        Self::try_new(args).unwrap_or_else(|e| panic!(\"{e}\"))")
+        );
+
+        // Generation checks the secret is set somewhere, but never writes its value
+        assert_eq!(
+            generator.generate().unwrap_err().to_string(),
+            "Secrets not set in the environment: BLOXML_TEST_API_KEY"
+        );
+        let options =
+            GeneratorOptions::default().with_secrets_file(Some("tests/secrets.env".into()));
+        ActorGenerator::with_options(actor(), options)
+            .expect("Generator creation should succeed")
+            .generate()
+            .expect("Generation should succeed");
+
+        let ext_state = fs::read_to_string("tests/output/secrets/actor/ext_state.rs").unwrap();
+        assert!(ext_state.contains("std::env::var_os(\"BLOXML_SECRETS_FILE\")"));
+        assert!(!ext_state.contains("tests/secrets.env"));
+        assert!(!ext_state.contains("not-a-real-key"));
+    }
+
+    /// Generates the test actor into the crate at `BLOXML_CLIPPY_CRATE` and runs clippy there
+    ///
+    /// The crate must depend on the runtime the generated code targets, so this only runs in CI.
+    #[test]
+    #[ignore = "needs a crate depending on the bloxide runtime in BLOXML_CLIPPY_CRATE"]
+    fn test_generated_code_is_clippy_clean() {
+        let crate_dir = PathBuf::from(
+            std::env::var("BLOXML_CLIPPY_CRATE").expect("BLOXML_CLIPPY_CRATE should be set"),
+        );
+        let mut actor = create_test_actor();
+        actor.path = crate_dir.join("src");
+        let options = GeneratorOptions::default().with_clippy_allows(true);
+        ActorGenerator::with_options(actor, options)
+            .expect("Generator creation should succeed")
+            .generate()
+            .expect("Generation should succeed");
+
+        let status = std::process::Command::new("cargo")
+            .args(["clippy", "--all-targets", "--", "-D", "warnings"])
+            .current_dir(&crate_dir)
+            .status()
+            .expect("cargo clippy should run");
+        assert!(status.success());
+    }
 }
@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use crate::create::RenderCtx;
+
+/// Render the `static_assertions` checking the framework bounds of the types a generated
+/// file defines, so a type missing one fails to compile where it is defined rather than deep
+/// inside the runtime's generics
+pub fn render_static_assertions(ctx: &RenderCtx, file: &Path) -> Option<String> {
+    let component = &ctx.actor.component;
+    let assertions = match file.to_str()? {
+        "component.rs" => vec![(
+            component.ident.clone(),
+            "bloxide_tokio::components::Components",
+        )],
+        "messaging.rs" => vec![(
+            component.message_set.as_ref()?.get().ident.clone(),
+            "bloxide_tokio::messaging::MessageSet, Send",
+        )],
+        "ext_state.rs" => vec![(
+            component.ext_state.ident().to_string(),
+            "bloxide_tokio::state_machine::ExtendedState, Send, Sync",
+        )],
+        "states/mod.rs" => vec![(
+            component.states.state_enum.get().ident.clone(),
+            "bloxide_tokio::state_machine::StateEnum, Clone, Send",
+        )],
+        _ => return None,
+    };
+    Some(
+        assertions
+            .iter()
+            .map(|(ty, bounds)| format!("\nstatic_assertions::assert_impl_all!({ty}: {bounds});\n"))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::create::{ActorGenerator, GeneratorOptions};
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_static_assertions() {
+        let options = GeneratorOptions::default().with_static_assertions(true);
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        let files = generator.render_files().unwrap();
+        let file = |name: &str| {
+            files
+                .iter()
+                .find(|(path, _)| path == Path::new(name))
+                .map(|(_, content)| content.as_str())
+                .unwrap()
+        };
+        assert!(file("component.rs").ends_with(
+            "\nstatic_assertions::assert_impl_all!(ActorComponents: bloxide_tokio::components::Components);\n"
+        ));
+        assert!(file("messaging.rs").contains(
+            "static_assertions::assert_impl_all!(ActorMessageSet: bloxide_tokio::messaging::MessageSet, Send);"
+        ));
+        assert!(file("ext_state.rs").contains(
+            "static_assertions::assert_impl_all!(ActorExtState: bloxide_tokio::state_machine::ExtendedState, Send, Sync);"
+        ));
+        assert!(file("states/mod.rs").contains(
+            "static_assertions::assert_impl_all!(ActorStates: bloxide_tokio::state_machine::StateEnum, Clone, Send);"
+        ));
+        assert!(!file("runtime.rs").contains("static_assertions"));
+
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(
+            generator
+                .render_files()
+                .unwrap()
+                .iter()
+                .all(|(_, content)| !content.contains("static_assertions"))
+        );
+    }
+}
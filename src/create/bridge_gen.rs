@@ -0,0 +1,408 @@
+use crate::blox::bridge::{Bridge, GrpcBridge, HttpBridge};
+use crate::blox::message_handlers::MessageReceiver;
+use crate::create::{RenderCtx, RenderError};
+
+/// Render the bridge module exposing the actor through a service protocol
+pub fn render_bridge_module(ctx: &RenderCtx, bridge: &Bridge) -> Result<String, RenderError> {
+    bridge
+        .validate(&ctx.actor.component)
+        .and_then(|_| ctx.actor.validate_trace_context())
+        .map_err(RenderError::InvalidSpec)?;
+    let imports_section = ctx.imports_section(&format!("{}::bridge", ctx.actor_module()))?;
+
+    let bridge = match bridge {
+        Bridge::Grpc(grpc) => render_grpc_bridge(ctx, grpc),
+        Bridge::Http(http) => render_http_bridge(ctx, http),
+    };
+    Ok(format!("{imports_section}{bridge}"))
+}
+
+/// Name of the inbox channel a variant is received on
+///
+/// Validation guarantees the variant and its receiver exist.
+fn channel_for<'a>(ctx: &RenderCtx<'a>, variant: &str) -> &'a str {
+    ctx.actor
+        .component
+        .receiver_for_variant(variant)
+        .map(MessageReceiver::channel_name)
+        .unwrap_or_default()
+}
+
+/// Expression wrapping the forwarded `request` in the payload type the inbox expects
+///
+/// Correlated actors start a new chain per request, in the trace the caller sent if the
+/// bridge propagates trace context.
+fn forwarded_payload(ctx: &RenderCtx, traced: bool) -> &'static str {
+    match (ctx.actor.correlation, traced) {
+        (false, _) => "request",
+        (true, false) => "Correlated::new(request)",
+        (true, true) => "Correlated::new(request).with_trace_context(trace_context)",
+    }
+}
+
+/// Renders reading `trace_context` from the `http::HeaderMap` named `headers`
+fn render_trace_context(headers: &str, indent: &str) -> String {
+    format!(
+        r#"{indent}let trace_context = TraceContext::from_headers(
+{indent}    {headers}
+{indent}        .iter()
+{indent}        .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+{indent});
+"#
+    )
+}
+
+fn render_grpc_bridge(ctx: &RenderCtx, grpc: &GrpcBridge) -> String {
+    let actor_name = &ctx.actor.ident;
+    let service = &grpc.service;
+    let traced = grpc.trace_context;
+
+    let methods = grpc
+        .rpcs
+        .iter()
+        .map(|rpc| {
+            let channel = channel_for(ctx, &rpc.variant);
+            // Metadata is only readable before the request is unwrapped
+            let trace_context = if traced {
+                format!(
+                    "        let headers = request.metadata().clone().into_headers();\n{}",
+                    render_trace_context("headers", "        ")
+                )
+            } else {
+                String::new()
+            };
+            format!(
+                r#"    async fn {method}(&self, request: Request<{request}>) -> Result<Response<{response}>, Status> {{
+{trace_context}        let (reply, mut response) = channel(1);
+        let request = RpcRequest {{
+            request: request.into_inner(),
+            reply,
+        }};
+        self.inbox
+            .{channel}
+            .send(Message::new(0, {payload}))
+            .await
+            .map_err(|_| Status::unavailable("{actor_name} has stopped"))?;
+        let reply = response
+            .recv()
+            .await
+            .ok_or_else(|| Status::internal("{actor_name} dropped the request without replying"))?;
+        reply.map(Response::new)
+    }}"#,
+                method = rpc.method,
+                payload = forwarded_payload(ctx, traced),
+                request = rpc.request,
+                response = rpc.response,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    format!(
+        r#"/// An RPC forwarded to the {actor_name} actor, answered through `respond`
+pub struct RpcRequest<Req, Resp> {{
+    pub request: Req,
+    reply: Sender<Result<Resp, Status>>,
+}}
+
+impl<Req, Resp> RpcRequest<Req, Resp> {{
+    /// Replies to the waiting caller; replies to callers that gave up are dropped
+    pub fn respond(&self, result: Result<Resp, Status>) {{
+        let _ = self.reply.try_send(result);
+    }}
+}}
+
+/// Serves the {service} gRPC service by forwarding every call to one {actor_name} instance
+pub struct {actor_name}GrpcService {{
+    inbox: {actor_name}Inbox,
+}}
+
+impl {actor_name}GrpcService {{
+    pub fn new(inbox: {actor_name}Inbox) -> Self {{
+        Self {{ inbox }}
+    }}
+}}
+
+#[tonic::async_trait]
+impl {service} for {actor_name}GrpcService {{
+{methods}
+}}
+"#
+    )
+}
+
+fn render_http_bridge(ctx: &RenderCtx, http: &HttpBridge) -> String {
+    let actor_name = &ctx.actor.ident;
+    let traced = http.trace_context;
+    // The body extractor has to come last
+    let (headers, trace_context) = if traced {
+        (
+            "    headers: HeaderMap,\n",
+            render_trace_context("headers", "    "),
+        )
+    } else {
+        ("", String::new())
+    };
+
+    // axum wants every method of a path registered on a single route
+    let mut paths: Vec<(&str, Vec<String>)> = Vec::new();
+    for route in &http.routes {
+        let method = format!("{}({})", route.method.routing_fn(), route.handler_name());
+        match paths.iter_mut().find(|(path, _)| *path == route.path) {
+            Some((_, methods)) => methods.push(method),
+            None => paths.push((&route.path, vec![method])),
+        }
+    }
+    let routes = paths
+        .iter()
+        .map(|(path, methods)| format!("        .route(\"{path}\", {})", methods.join(".")))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let handlers = http
+        .routes
+        .iter()
+        .map(|route| {
+            let channel = channel_for(ctx, &route.variant);
+            let extractor = route.payload.extractor();
+            format!(
+                r#"/// Handles `{description}` by forwarding it to {actor_name} as `{variant}`
+async fn {handler}(
+    State(inbox): State<{actor_name}Inbox>,
+{headers}    {extractor}(request): {extractor}<{request}>,
+) -> Result<Json<{response}>, HttpError> {{
+{trace_context}    let (reply, mut response) = channel(1);
+    let request = HttpRequest {{ request, reply }};
+    inbox
+        .{channel}
+        .send(Message::new(0, {payload}))
+        .await
+        .map_err(|_| HttpError::new(StatusCode::SERVICE_UNAVAILABLE, "{actor_name} has stopped"))?;
+    let reply = response.recv().await.ok_or_else(|| {{
+        HttpError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "{actor_name} dropped the request without replying",
+        )
+    }})?;
+    reply.map(Json)
+}}"#,
+                description = route.describe(),
+                variant = route.variant,
+                handler = route.handler_name(),
+                payload = forwarded_payload(ctx, traced),
+                request = route.request,
+                response = route.response,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    format!(
+        r#"/// An HTTP request forwarded to the {actor_name} actor, answered through `respond`
+pub struct HttpRequest<Req, Resp> {{
+    pub request: Req,
+    reply: Sender<Result<Resp, HttpError>>,
+}}
+
+impl<Req, Resp> HttpRequest<Req, Resp> {{
+    /// Replies to the waiting caller; replies to callers that gave up are dropped
+    pub fn respond(&self, result: Result<Resp, HttpError>) {{
+        let _ = self.reply.try_send(result);
+    }}
+}}
+
+/// Error answered to an HTTP request, sent back as its status and message
+#[derive(Debug)]
+pub struct HttpError {{
+    pub status: StatusCode,
+    pub message: String,
+}}
+
+impl HttpError {{
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {{
+        Self {{
+            status,
+            message: message.into(),
+        }}
+    }}
+}}
+
+impl IntoResponse for HttpError {{
+    fn into_response(self) -> Response {{
+        (self.status, self.message).into_response()
+    }}
+}}
+
+/// Builds the router serving {actor_name}'s routes through one instance
+pub fn router(inbox: {actor_name}Inbox) -> Router {{
+    Router::new()
+{routes}
+        .with_state(inbox)
+}}
+
+{handlers}
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::bridge::{
+        Bridge, GrpcBridge, HttpBridge, HttpMethod, PayloadSource, Route, Rpc,
+    };
+    use crate::create::{ActorGenerator, RenderError};
+    use crate::tests::create_test_actor;
+
+    fn grpc_bridge(variant: &str) -> Bridge {
+        Bridge::Grpc(GrpcBridge::new(
+            "crate::proto::billing",
+            "BillingService",
+            vec![Rpc::new("charge", "ChargeRequest", "ChargeReply", variant)],
+        ))
+    }
+
+    #[test]
+    fn test_grpc_bridge() {
+        let actor = create_test_actor().with_bridge(grpc_bridge("CustomValue2"));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let bridge = generator
+            .generate_bridge()
+            .unwrap()
+            .expect("Bridge should be generated");
+
+        assert!(bridge.contains("pub struct RpcRequest<Req, Resp> {"));
+        assert!(bridge.contains("impl BillingService for ActorGrpcService {"));
+        assert!(bridge.contains(
+            "async fn charge(&self, request: Request<ChargeRequest>) -> Result<Response<ChargeReply>, Status> {"
+        ));
+        assert!(bridge.contains("self.inbox\n            .customargs\n            .send("));
+        assert!(
+            bridge.contains("use crate::proto::billing::billing_service_server::BillingService;")
+        );
+        assert!(bridge.contains("use crate::proto::billing::ChargeRequest;"));
+        assert!(bridge.contains("use tonic::Status;"));
+        assert!(generator.root_modules().contains(&"bridge".to_string()));
+    }
+
+    #[test]
+    fn test_grpc_bridge_requires_known_variant() {
+        let actor = create_test_actor().with_bridge(grpc_bridge("Missing"));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate_bridge(),
+            Err(RenderError::InvalidSpec(
+                "RPC 'charge' is forwarded as unknown variant 'Missing'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_http_bridge() {
+        let bridge = Bridge::Http(HttpBridge::new(vec![
+            Route::new(
+                HttpMethod::Post,
+                "/values",
+                "CustomArgs",
+                "CustomArgs",
+                "CustomValue2",
+            ),
+            Route::new(
+                HttpMethod::Get,
+                "/values",
+                "StandardPayload",
+                "CustomArgs",
+                "CustomValue1",
+            )
+            .with_payload(PayloadSource::Query),
+        ]));
+        let actor = create_test_actor().with_bridge(bridge);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let bridge = generator.generate_bridge().unwrap().unwrap();
+
+        assert!(bridge.contains(
+            "    Router::new()\n        .route(\"/values\", post(customvalue2).get(customvalue1))\n        .with_state(inbox)"
+        ));
+        assert!(bridge.contains(
+            "async fn customvalue2(\n    State(inbox): State<ActorInbox>,\n    Json(request): Json<CustomArgs>,\n) -> Result<Json<CustomArgs>, HttpError> {"
+        ));
+        assert!(bridge.contains("    Query(request): Query<StandardPayload>,"));
+        assert!(bridge.contains("    inbox\n        .standard\n        .send("));
+        assert!(bridge.contains("use axum::extract::Query;"));
+        assert!(bridge.contains("use axum::routing::get;"));
+        assert!(!bridge.contains("use axum::extract::Path;"));
+
+        let duplicate = Bridge::Http(HttpBridge::new(vec![
+            Route::new(HttpMethod::Post, "/values", "A", "B", "CustomValue1"),
+            Route::new(HttpMethod::Post, "/values", "A", "B", "CustomValue2"),
+        ]));
+        let generator = ActorGenerator::new(create_test_actor().with_bridge(duplicate))
+            .expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate_bridge(),
+            Err(RenderError::InvalidSpec(
+                "Route 'POST /values' is declared more than once".to_string()
+            ))
+        );
+
+        let shared = Bridge::Http(HttpBridge::new(vec![
+            Route::new(HttpMethod::Post, "/values", "A", "B", "CustomValue1"),
+            Route::new(HttpMethod::Put, "/Values", "A", "B", "CustomValue1"),
+        ]));
+        let generator = ActorGenerator::new(create_test_actor().with_bridge(shared))
+            .expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate_bridge(),
+            Err(RenderError::InvalidSpec(
+                "Route 'PUT /Values' and route 'POST /values' would share the handler 'customvalue1'; forward them as different variants".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_traced_bridges() {
+        let grpc = match grpc_bridge("CustomValue2") {
+            Bridge::Grpc(grpc) => Bridge::Grpc(grpc.with_trace_context()),
+            bridge => bridge,
+        };
+        let actor = create_test_actor().with_correlation().with_bridge(grpc);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let bridge = generator.generate_bridge().unwrap().unwrap();
+        assert!(bridge.contains(
+            "ChargeReply>, Status> {\n        let headers = request.metadata().clone().into_headers();\n        let trace_context = TraceContext::from_headers(\n            headers\n"
+        ));
+        assert!(bridge.contains(
+            ".send(Message::new(0, Correlated::new(request).with_trace_context(trace_context)))"
+        ));
+        assert!(bridge.contains("use crate::actor::correlation::TraceContext;"));
+
+        let bridge = Bridge::Http(
+            HttpBridge::new(vec![Route::new(
+                HttpMethod::Post,
+                "/values",
+                "CustomArgs",
+                "CustomArgs",
+                "CustomValue2",
+            )])
+            .with_trace_context(),
+        );
+        let actor = create_test_actor().with_correlation().with_bridge(bridge);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let bridge = generator.generate_bridge().unwrap().unwrap();
+        assert!(bridge.contains(
+            "    State(inbox): State<ActorInbox>,\n    headers: HeaderMap,\n    Json(request): Json<CustomArgs>,\n"
+        ));
+        assert!(bridge.contains(
+            "    let trace_context = TraceContext::from_headers(\n        headers\n            .iter()\n"
+        ));
+        assert!(bridge.contains("use axum::http::HeaderMap;"));
+
+        // Correlated actors wrap what they forward even without tracing
+        let actor = create_test_actor()
+            .with_correlation()
+            .with_bridge(grpc_bridge("CustomValue2"));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let bridge = generator.generate_bridge().unwrap().unwrap();
+        assert!(bridge.contains(".send(Message::new(0, Correlated::new(request)))"));
+        assert!(!bridge.contains("TraceContext"));
+    }
+}
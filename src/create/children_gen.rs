@@ -0,0 +1,341 @@
+use crate::blox::actor::{Actor, HEALTH_RECEIVER};
+use crate::blox::child::{Child, Forward};
+use crate::create::{RenderCtx, RenderError};
+
+/// Capacity of the channels created for an actor spawned by generated code
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Code creating an actor's channels and the `Blox` reading them, indented for a function
+/// body, with the inbox sending to it
+pub(crate) struct Wiring {
+    /// `let` statements creating a channel per receiver and handle
+    pub channels: String,
+    /// Expression building the inbox from the sending side of the receivers' channels
+    pub inbox: String,
+    /// Expression building the `Blox` with `ext_state` as its extended state
+    pub blox: String,
+}
+
+/// Render the wiring of `actor`, whose extended state is built by the `ext_state` expression
+///
+/// The receiving side of a handle's channel is bound to `_{handle}_rx`, which the caller
+/// either reads or keeps alive for as long as the actor sends through it.
+pub(crate) fn render_wiring(actor: &Actor, ext_state: &str) -> Wiring {
+    let actor_name = &actor.ident;
+    let receivers = &actor.component.message_receivers;
+    let handles = &actor.component.message_handles;
+
+    let channels = receivers
+        .receivers
+        .iter()
+        .map(|receiver| {
+            format!(
+                "    let ({}, {}) = TokioMessageHandle::new(0, {CHANNEL_CAPACITY});",
+                receiver.channel_name(),
+                receiver.ident
+            )
+        })
+        .chain(handles.handles.iter().map(|handle| {
+            format!(
+                "    let ({ident}, _{ident}_rx) = TokioMessageHandle::new(0, {CHANNEL_CAPACITY});",
+                ident = handle.ident
+            )
+        }))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let fields = |idents: Vec<&str>, indent: &str| {
+        idents
+            .iter()
+            .map(|ident| format!("{indent}{ident},\n"))
+            .collect::<String>()
+    };
+    let inbox = format!(
+        "{actor_name}Inbox {{\n        id: {actor_name}Id(Default::default()),\n{}    }}",
+        fields(
+            receivers
+                .receivers
+                .iter()
+                .map(|r| r.channel_name())
+                .collect(),
+            "        ",
+        )
+    );
+    let blox = format!(
+        r#"Blox::<{component}>::new(
+        {receivers_ident} {{
+{receiver_fields}        }},
+        {ext_state},
+        {handles_ident} {{
+{handle_fields}        }},
+    )"#,
+        component = actor.component.ident,
+        receivers_ident = receivers.ident,
+        receiver_fields = fields(
+            receivers
+                .receivers
+                .iter()
+                .map(|r| r.ident.as_str())
+                .collect(),
+            "            ",
+        ),
+        handles_ident = handles.ident,
+        handle_fields = fields(
+            handles.handles.iter().map(|h| h.ident.as_str()).collect(),
+            "            ",
+        ),
+    );
+    Wiring {
+        channels,
+        inbox,
+        blox,
+    }
+}
+
+/// Render the children module: an outbox per child actor, and a function per child spawning
+/// it and returning its inbox and outbox
+pub fn render_children_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    let imports_section = ctx.imports_section(&format!("{}::children", ctx.actor_module()))?;
+    let mut outboxes = Vec::new();
+    for child in &ctx.actor.children {
+        let outbox = render_outbox(child.actor().map_err(RenderError::InvalidSpec)?);
+        if !outboxes.contains(&outbox) {
+            outboxes.push(outbox);
+        }
+    }
+    let spawns = ctx
+        .actor
+        .children
+        .iter()
+        .map(render_spawn)
+        .collect::<Result<Vec<_>, _>>()?;
+    let items = outboxes.into_iter().chain(spawns).collect::<Vec<_>>();
+    Ok(format!("{imports_section}{}\n", items.join("\n\n")))
+}
+
+/// Render the struct holding the receiving side of the channels an actor spawned as a child
+/// sends through, one field per handle
+fn render_outbox(actor: &Actor) -> String {
+    let fields = actor
+        .component
+        .message_handles
+        .handles
+        .iter()
+        .map(|handle| {
+            format!(
+                "    pub {}: <<TokioRuntime as Runtime>::MessageHandle<{}> as MessageSender>::ReceiverType,\n",
+                handle.ident, handle.message_type
+            )
+        })
+        .collect::<String>();
+    format!(
+        "/// What a {actor_name} spawned as a child sends through its handles, read by its parent\npub struct {actor_name}Outbox {{\n{fields}}}",
+        actor_name = actor.ident
+    )
+}
+
+fn render_spawn(child: &Child) -> Result<String, RenderError> {
+    let actor = child.actor().map_err(RenderError::InvalidSpec)?;
+    let ext_state = format!(
+        "<{} as ExtendedState>::new(Default::default())",
+        actor.component.ext_state.ident()
+    );
+    let Wiring {
+        channels,
+        inbox,
+        blox,
+    } = render_wiring(actor, &ext_state);
+    let outbox = actor
+        .component
+        .message_handles
+        .handles
+        .iter()
+        .map(|handle| format!("        {ident}: _{ident}_rx,\n", ident = handle.ident))
+        .collect::<String>();
+    Ok(format!(
+        r#"/// Spawns the `{ident}` child, a {actor_name} whose init args are at their defaults, and
+/// returns its inbox and outbox
+pub fn spawn_{ident}() -> ({actor_name}Inbox, {actor_name}Outbox) {{
+{channels}
+    let blox = {blox};
+    spawn(Box::new(blox).run());
+    let inbox = {inbox};
+    let outbox = {actor_name}Outbox {{
+{outbox}    }};
+    (inbox, outbox)
+}}"#,
+        ident = child.ident,
+        actor_name = actor.ident,
+    ))
+}
+
+/// Render the statements of `run` spawning the children, before the `pre_start` block
+///
+/// The inbox goes into the extended state, and the outbox into a `{child}_outbox` local the
+/// runtime's arms read.
+pub fn render_child_spawns(ctx: &RenderCtx) -> Result<String, RenderError> {
+    let mut spawns = String::new();
+    for child in &ctx.actor.children {
+        let has_handles = !child
+            .actor()
+            .map_err(RenderError::InvalidSpec)?
+            .component
+            .message_handles
+            .handles
+            .is_empty();
+        spawns.push_str(&format!(
+            "        let ({ident}, {outbox}) = spawn_{ident}();\n        self.state_machine.extended_state.{ident} = Some({ident});\n",
+            ident = child.ident,
+            outbox = if has_handles {
+                format!("mut {}_outbox", child.ident)
+            } else {
+                "_".to_string()
+            },
+        ));
+    }
+    if !spawns.is_empty() {
+        spawns.push('\n');
+    }
+    Ok(spawns)
+}
+
+/// Render the runtime arms reading what the children send through their handles
+///
+/// A message is dispatched as the variant whose receiver takes its type, unless that receiver
+/// checks sequence numbers, which only hold within one stream. Other messages are read and
+/// dropped, so the child never waits on a full channel.
+pub fn render_child_arms(
+    ctx: &RenderCtx,
+    dispatch: impl Fn(&str) -> String,
+) -> Result<String, RenderError> {
+    let Some(message_set) = &ctx.actor.component.message_set else {
+        return Ok(String::new());
+    };
+    let message_set = message_set.get();
+    let variants = ctx
+        .actor
+        .component
+        .message_receivers
+        .receivers
+        .iter()
+        .filter(|receiver| receiver.ident != HEALTH_RECEIVER)
+        .zip(&message_set.variants)
+        .collect::<Vec<_>>();
+
+    let mut arms = String::new();
+    for child in &ctx.actor.children {
+        let handles = &child
+            .actor()
+            .map_err(RenderError::InvalidSpec)?
+            .component
+            .message_handles
+            .handles;
+        for handle in handles {
+            let variant = variants.iter().find(|(receiver, _)| {
+                receiver.message_type == handle.message_type && !receiver.sequenced
+            });
+            let (message, body) = match variant {
+                Some((_, variant)) => (
+                    "msg",
+                    format!(
+                        "                        let current_state = self.state_machine.current_state.clone();\n{}",
+                        dispatch(&if variant.boxed {
+                            format!("{}::{}(Box::new(msg))", message_set.ident, variant.ident)
+                        } else {
+                            format!("{}::{}(msg)", message_set.ident, variant.ident)
+                        })
+                    ),
+                ),
+                None => (
+                    "_",
+                    format!(
+                        "                        // No receiver of {} takes a `{}`",
+                        ctx.actor.ident, handle.message_type
+                    ),
+                ),
+            };
+            arms.push_str(&format!(
+                r#"                    Some({message}) = {child}_outbox.{handle}.recv() => {{
+{body}
+                    }}
+"#,
+                child = child.ident,
+                handle = handle.ident,
+            ));
+        }
+    }
+    Ok(arms)
+}
+
+/// Render the body of a runtime arm handing a message on to the child taking it over,
+/// instead of dispatching it
+pub fn render_forward(child: &Child, forward: &Forward) -> String {
+    format!(
+        r#"                        // Handed on to the `{ident}` child, which drops it once stopped
+                        if let Some(child) = &self.state_machine.extended_state.{ident} {{
+                            let _ = child.{channel}.send(msg).await;
+                        }}"#,
+        ident = child.ident,
+        channel = forward.channel
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::blox::actor::Actor;
+    use crate::create::ActorGenerator;
+
+    fn gateway() -> ActorGenerator {
+        let actor = Actor::from_json_file(&PathBuf::from("tests/children/gateway.json")).unwrap();
+        ActorGenerator::new(actor).expect("Generator creation should succeed")
+    }
+
+    #[test]
+    fn test_children_module() {
+        let children = gateway().generate_children().unwrap().unwrap();
+        assert!(children.contains("use crate::session::component::SessionInbox;\n"));
+        assert!(children.contains("pub fn spawn_session() -> (SessionInbox, SessionOutbox) {\n"));
+        assert!(children.contains(
+            "    let (customargs_handle, _customargs_handle_rx) = TokioMessageHandle::new(0, 16);"
+        ));
+        assert!(children.contains("pub struct SessionOutbox {\n    pub standard_handle: <<TokioRuntime as Runtime>::MessageHandle<StandardPayload> as MessageSender>::ReceiverType,\n"));
+        assert!(children.contains("use bloxide_tokio::TokioRuntime;\n"));
+        assert!(
+            children
+                .contains("        <SessionExtState as ExtendedState>::new(Default::default()),")
+        );
+        assert!(children.contains(
+            "    spawn(Box::new(blox).run());\n    let inbox = SessionInbox {\n        id: SessionId(Default::default()),\n        standard,\n        customargs,\n    };\n    let outbox = SessionOutbox {\n        standard_handle: _standard_handle_rx,\n        customargs_handle: _customargs_handle_rx,\n    };\n    (inbox, outbox)\n}"
+        ));
+
+        let generator = ActorGenerator::new(crate::tests::create_test_actor())
+            .expect("Generator creation should succeed");
+        assert_eq!(generator.generate_children().unwrap(), None);
+    }
+
+    #[test]
+    fn test_runtime_spawns_and_forwards() {
+        let generator = gateway();
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains("use crate::gateway::children::spawn_session;\n"));
+        assert!(runtime.contains(
+            "        let (session, mut session_outbox) = spawn_session();\n        self.state_machine.extended_state.session = Some(session);\n\n        self.state_machine.init("
+        ));
+        // What the child sends comes back as the variant taking its type, or is dropped
+        assert!(runtime.contains(
+            "                    Some(msg) = session_outbox.standard_handle.recv() => {\n                        let current_state = self.state_machine.current_state.clone();\n                        self.state_machine.dispatch(GatewayMessageSet::Standard(msg), &current_state);\n"
+        ));
+        assert!(runtime.contains(
+            "                    Some(_) = session_outbox.customargs_handle.recv() => {\n                        // No receiver of Gateway takes a `CustomArgs`\n"
+        ));
+        assert!(runtime.contains(
+            "                        if let Some(child) = &self.state_machine.extended_state.session {\n                            let _ = child.standard.send(msg).await;\n                        }"
+        ));
+        assert_eq!(runtime.matches("dispatch(").count(), 1);
+
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(ext_state.contains("    pub session: Option<SessionInbox>"));
+        assert!(ext_state.contains("use crate::session::component::SessionInbox;\n"));
+    }
+}
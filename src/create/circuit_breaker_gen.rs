@@ -0,0 +1,246 @@
+use crate::create::{RenderCtx, RenderError};
+
+/// Render the circuit breaker module: the breaker, its error and one breaker per guarded handle
+pub fn render_circuit_breaker_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    let actor_name = &ctx.actor.ident;
+    let imports_section =
+        ctx.imports_section(&format!("{}::circuit_breaker", ctx.actor_module()))?;
+
+    let guarded = ctx
+        .actor
+        .component
+        .message_handles
+        .handles
+        .iter()
+        .filter_map(|handle| Some((handle.helper_name(), handle.circuit_breaker.as_ref()?)))
+        .collect::<Vec<_>>();
+    let fields = guarded
+        .iter()
+        .map(|(name, _)| format!("    pub {name}: CircuitBreaker,"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let breakers = guarded
+        .iter()
+        .map(|(name, breaker)| {
+            format!(
+                "            {name}: CircuitBreaker::new(\n                {},\n                Duration::from_millis({}),\n                Duration::from_millis({}),\n            ),",
+                breaker.failure_threshold,
+                breaker.open_ms,
+                breaker.half_open_ms()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(format!(
+        r#"{imports_section}/// Position of a circuit breaker, as reported to health probes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {{
+    /// Sends go through; consecutive failures are counted
+    Closed,
+    /// Sends are rejected until the downstream had time to recover
+    Open,
+    /// Sends go through again; the first failure opens the circuit
+    HalfOpen,
+}}
+
+/// Why a send through a handle guarded by a circuit breaker failed
+#[derive(Debug)]
+pub enum BreakerError<E, T> {{
+    /// The circuit is open, so the message was not sent
+    Open(T),
+    /// The send itself failed
+    Failed(E),
+}}
+
+#[derive(Debug)]
+enum Phase {{
+    Closed {{ failures: u32 }},
+    Open {{ until: Instant }},
+    HalfOpen {{ until: Instant }},
+}}
+
+/// Opens after consecutive failed sends, then lets sends through again once the open timer
+/// runs out, closing if none fails before the half-open timer does
+#[derive(Debug)]
+pub struct CircuitBreaker {{
+    failure_threshold: u32,
+    open_for: Duration,
+    half_open_for: Duration,
+    phase: Mutex<Phase>,
+}}
+
+impl CircuitBreaker {{
+    pub fn new(failure_threshold: u32, open_for: Duration, half_open_for: Duration) -> Self {{
+        Self {{
+            failure_threshold,
+            open_for,
+            half_open_for,
+            phase: Mutex::new(Phase::Closed {{ failures: 0 }}),
+        }}
+    }}
+
+    /// Moves past timers that ran out, returning the current phase
+    fn phase(&self) -> MutexGuard<'_, Phase> {{
+        let mut phase = self.phase.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        match *phase {{
+            Phase::Open {{ until }} if now >= until => {{
+                *phase = Phase::HalfOpen {{
+                    until: now + self.half_open_for,
+                }};
+            }}
+            Phase::HalfOpen {{ until }} if now >= until => {{
+                *phase = Phase::Closed {{ failures: 0 }};
+            }}
+            _ => {{}}
+        }}
+        phase
+    }}
+
+    pub fn state(&self) -> CircuitState {{
+        match *self.phase() {{
+            Phase::Closed {{ .. }} => CircuitState::Closed,
+            Phase::Open {{ .. }} => CircuitState::Open,
+            Phase::HalfOpen {{ .. }} => CircuitState::HalfOpen,
+        }}
+    }}
+
+    /// Whether a send may go through, which it may unless the circuit is open
+    pub fn allows(&self) -> bool {{
+        self.state() != CircuitState::Open
+    }}
+
+    /// Records whether a send, or a call the handler made downstream, succeeded
+    pub fn record(&self, success: bool) {{
+        let mut phase = self.phase();
+        let open = Phase::Open {{
+            until: Instant::now() + self.open_for,
+        }};
+        match &mut *phase {{
+            Phase::Closed {{ failures }} if success => *failures = 0,
+            Phase::Closed {{ failures }} => {{
+                *failures += 1;
+                if *failures >= self.failure_threshold {{
+                    *phase = open;
+                }}
+            }}
+            Phase::HalfOpen {{ .. }} if !success => *phase = open,
+            Phase::HalfOpen {{ .. }} | Phase::Open {{ .. }} => {{}}
+        }}
+    }}
+}}
+
+/// Circuit breakers of {actor_name}'s guarded handles, by handle
+#[derive(Debug)]
+pub struct {actor_name}Breakers {{
+{fields}
+}}
+
+impl {actor_name}Breakers {{
+    /// Every breaker with its state, for health reports
+    pub fn states(&self) -> Vec<(&'static str, CircuitState)> {{
+        vec![
+{states}
+        ]
+    }}
+}}
+
+impl Default for {actor_name}Breakers {{
+    fn default() -> Self {{
+        Self {{
+{breakers}
+        }}
+    }}
+}}
+"#,
+        states = guarded
+            .iter()
+            .map(|(name, _)| format!("            (\"{name}\", self.{name}.state()),"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::message_handlers::{CircuitBreaker, OverflowPolicy};
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_circuit_breakers() {
+        let mut actor = create_test_actor().with_health_check();
+        let handles = &mut actor.component.message_handles.handles;
+        handles[0] = handles[0]
+            .clone()
+            .with_overflow(OverflowPolicy::DropNew)
+            .with_circuit_breaker(CircuitBreaker::new(5, 30_000).with_half_open_ms(10_000));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let breaker = generator
+            .generate_circuit_breaker()
+            .unwrap()
+            .expect("Circuit breaker module should be generated");
+        assert!(breaker.contains("pub enum CircuitState {"));
+        assert!(
+            breaker.contains("pub struct ActorBreakers {\n    pub standard: CircuitBreaker,\n}")
+        );
+        assert!(breaker.contains(
+            "            standard: CircuitBreaker::new(\n                5,\n                Duration::from_millis(30000),\n                Duration::from_millis(10000),\n            ),"
+        ));
+        assert!(breaker.contains("use std::sync::Mutex;"));
+
+        let component = generator.generate_component().unwrap();
+        assert!(component.contains("pub breakers: ActorBreakers"));
+        assert!(component.contains(
+            "        if !self.breakers.standard.allows() {\n            return Err(BreakerError::Open(message));\n        }"
+        ));
+        assert!(component.contains(
+            "        self.breakers.standard.record(sent.is_ok());\n        match sent {"
+        ));
+        assert!(component.contains("use crate::actor::circuit_breaker::BreakerError;"));
+
+        let health = generator.generate_health().unwrap().unwrap();
+        assert!(health.contains("    pub circuits: Vec<(&'static str, CircuitState)>,"));
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(
+            runtime.contains(
+                "                            circuits: self.state_machine.self_handles.breakers.states(),"
+            )
+        );
+        assert!(
+            generator
+                .root_modules()
+                .contains(&"circuit_breaker".to_string())
+        );
+    }
+
+    #[test]
+    fn test_no_circuit_breakers_by_default() {
+        let generator = ActorGenerator::new(create_test_actor().with_health_check())
+            .expect("Generator creation should succeed");
+        assert!(generator.generate_circuit_breaker().unwrap().is_none());
+        assert!(!generator.generate_component().unwrap().contains("breakers"));
+        assert!(
+            !generator
+                .generate_health()
+                .unwrap()
+                .unwrap()
+                .contains("circuits")
+        );
+
+        let mut actor = create_test_actor();
+        let handles = &mut actor.component.message_handles.handles;
+        handles[0] = handles[0]
+            .clone()
+            .with_circuit_breaker(CircuitBreaker::new(0, 1_000));
+        assert_eq!(
+            actor.component.validate(),
+            Err(format!(
+                "Circuit breaker of handle '{}' needs a failure threshold and timers above zero",
+                actor.component.message_handles.handles[0].ident
+            ))
+        );
+    }
+}
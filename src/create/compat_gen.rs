@@ -0,0 +1,280 @@
+use crate::blox::actor::Actor;
+use crate::blox::enums::{EnumDef, EnumVariant};
+use crate::create::RenderError;
+
+/// Render the shims converting messages between the message sets of `previous` and `current`,
+/// named after their message set versions, with the file name they go in
+///
+/// Variants both versions carry unchanged convert directly; the rest surface as variants of
+/// an error enum holding the message, so a node in a rolling deployment can reject or
+/// translate what its peer does not understand. Returns `None` when both versions declare
+/// the same variants.
+pub fn render_message_shims(
+    previous: &Actor,
+    current: &Actor,
+) -> Result<Option<(String, String)>, RenderError> {
+    let (old_def, from) = versioned_message_set(previous, "previous")?;
+    let (new_def, to) = versioned_message_set(current, "current")?;
+    if from.checked_add(1) != Some(to) {
+        return Err(RenderError::InvalidSpec(format!(
+            "Message shims go from one message set version to the next, not from {from} to {to}"
+        )));
+    }
+    if old_def.variants.len() == new_def.variants.len()
+        && new_def.variants.iter().all(|new| carried(new, old_def))
+    {
+        return Ok(None);
+    }
+
+    let old_enum = format!("{}V{from}", new_def.ident);
+    let new_enum = format!("{}V{to}", new_def.ident);
+    let content = format!(
+        r#"//! Conversions between version {from} and {to} of {actor_name}'s message set
+//!
+//! Variants carried unchanged by both versions convert directly; every other variant is
+//! handed back in the conversion's error.
+
+use bloxide_tokio::messaging::Message;
+
+{old_definition}
+
+{new_definition}
+
+{upgrade}
+
+{downgrade}
+"#,
+        actor_name = current.ident,
+        old_definition = render_snapshot(&old_enum, from, old_def),
+        new_definition = render_snapshot(&new_enum, to, new_def),
+        upgrade = render_conversion((&old_enum, old_def), (&new_enum, new_def)),
+        downgrade = render_conversion((&new_enum, new_def), (&old_enum, old_def)),
+    );
+    Ok(Some((format!("messages_v{from}_v{to}.rs"), content)))
+}
+
+fn versioned_message_set<'a>(
+    actor: &'a Actor,
+    which: &str,
+) -> Result<(&'a EnumDef, u32), RenderError> {
+    let message_set = actor.component.message_set.as_ref().ok_or_else(|| {
+        RenderError::InvalidSpec(format!(
+            "The {which} spec of {} has no message set to convert",
+            actor.ident
+        ))
+    })?;
+    let version = message_set.version.ok_or_else(|| {
+        RenderError::InvalidSpec(format!(
+            "Message shims are named after message set versions, but the {which} spec of {} has none",
+            actor.ident
+        ))
+    })?;
+    Ok((message_set.get(), version))
+}
+
+/// Whether `variant` is in `def` with the same payload
+fn carried(variant: &EnumVariant, def: &EnumDef) -> bool {
+    def.variants.iter().any(|other| {
+        other.ident == variant.ident && other.args == variant.args && other.boxed == variant.boxed
+    })
+}
+
+/// Render the message set as of a version, in the form it is sent in
+fn render_snapshot(ident: &str, version: u32, def: &EnumDef) -> String {
+    let variants = def
+        .variants
+        .iter()
+        .map(|variant| format!("    {}{},", variant.ident, payload(variant)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        r#"/// Message set as of version {version}
+pub enum {ident} {{
+{variants}
+}}"#
+    )
+}
+
+fn payload(variant: &EnumVariant) -> String {
+    if variant.args.is_empty() {
+        return String::new();
+    }
+    let args = variant
+        .args
+        .iter()
+        .map(|arg| {
+            if variant.boxed {
+                format!("Box<Message<{arg}>>")
+            } else {
+                format!("Message<{arg}>")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("({args})")
+}
+
+/// Binds each of the variant's payloads in a pattern, or nothing for a unit variant
+fn bindings(variant: &EnumVariant) -> String {
+    if variant.args.is_empty() {
+        return String::new();
+    }
+    let bindings = (0..variant.args.len())
+        .map(|i| format!("arg{i}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("({bindings})")
+}
+
+/// Render `From` from one version to another when every variant carries over, or an error
+/// enum of the variants that don't and `TryFrom` otherwise
+fn render_conversion((src, src_def): (&str, &EnumDef), (dst, dst_def): (&str, &EnumDef)) -> String {
+    let unsupported = src_def
+        .variants
+        .iter()
+        .filter(|variant| !carried(variant, dst_def))
+        .collect::<Vec<_>>();
+    let arms = |wrap: &dyn Fn(&EnumVariant, String) -> String| {
+        src_def
+            .variants
+            .iter()
+            .map(|variant| {
+                let pattern = format!("{src}::{}{}", variant.ident, bindings(variant));
+                format!(
+                    "            {pattern} => {},",
+                    wrap(variant, bindings(variant))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    if unsupported.is_empty() {
+        let arms = arms(&|variant, bindings| format!("{dst}::{}{bindings}", variant.ident));
+        return format!(
+            r#"impl From<{src}> for {dst} {{
+    fn from(message: {src}) -> Self {{
+        match message {{
+{arms}
+        }}
+    }}
+}}"#
+        );
+    }
+
+    let variants = unsupported
+        .iter()
+        .map(|variant| format!("    {}{},", variant.ident, payload(variant)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let arms = arms(&|variant, bindings| {
+        if carried(variant, dst_def) {
+            format!("Ok({dst}::{}{bindings})", variant.ident)
+        } else {
+            format!("Err({src}Unsupported::{}{bindings})", variant.ident)
+        }
+    });
+    format!(
+        r#"/// Messages of {src} that {dst} has no variant for
+pub enum {src}Unsupported {{
+{variants}
+}}
+
+impl TryFrom<{src}> for {dst} {{
+    type Error = {src}Unsupported;
+
+    fn try_from(message: {src}) -> Result<Self, Self::Error> {{
+        match message {{
+{arms}
+        }}
+    }}
+}}"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Link;
+    use crate::tests::create_test_actor;
+
+    fn versioned(version: u32, variants: Vec<EnumVariant>) -> Actor {
+        let mut actor = create_test_actor();
+        let message_set = actor.component.message_set.as_mut().unwrap();
+        message_set.def.variants = variants;
+        message_set.version = Some(version);
+        actor
+    }
+
+    #[test]
+    fn test_message_shims() {
+        let ping = EnumVariant::new("Ping", vec![Link::new("u32")]);
+        let previous = versioned(
+            1,
+            vec![ping.clone(), EnumVariant::new("Legacy", Vec::new())],
+        );
+        let current = versioned(
+            2,
+            vec![
+                ping,
+                EnumVariant::new("Resize", vec![Link::new("u16"), Link::new("u16")]).boxed(),
+            ],
+        );
+
+        let (file, shims) = render_message_shims(&previous, &current)
+            .unwrap()
+            .expect("Changed variants should need shims");
+        assert_eq!(file, "messages_v1_v2.rs");
+        assert!(
+            shims.contains("pub enum ActorMessageSetV1 {\n    Ping(Message<u32>),\n    Legacy,\n}")
+        );
+        assert!(shims.contains(
+            "pub enum ActorMessageSetV2 {\n    Ping(Message<u32>),\n    Resize(Box<Message<u16>>, Box<Message<u16>>),\n}"
+        ));
+        assert!(shims.contains(
+            "pub enum ActorMessageSetV1Unsupported {\n    Legacy,\n}\n\nimpl TryFrom<ActorMessageSetV1> for ActorMessageSetV2 {"
+        ));
+        assert!(shims.contains(
+            "            ActorMessageSetV1::Ping(arg0) => Ok(ActorMessageSetV2::Ping(arg0)),\n            ActorMessageSetV1::Legacy => Err(ActorMessageSetV1Unsupported::Legacy),"
+        ));
+        assert!(shims.contains(
+            "ActorMessageSetV2::Resize(arg0, arg1) => Err(ActorMessageSetV2Unsupported::Resize(arg0, arg1)),"
+        ));
+    }
+
+    #[test]
+    fn test_message_shims_from() {
+        let ping = EnumVariant::new("Ping", vec![Link::new("u32")]);
+        let previous = versioned(3, vec![ping.clone()]);
+        let current = versioned(4, vec![ping.clone(), EnumVariant::new("Stop", Vec::new())]);
+
+        let (_, shims) = render_message_shims(&previous, &current).unwrap().unwrap();
+        assert!(shims.contains(
+            "impl From<ActorMessageSetV3> for ActorMessageSetV4 {\n    fn from(message: ActorMessageSetV3) -> Self {\n        match message {\n            ActorMessageSetV3::Ping(arg0) => ActorMessageSetV4::Ping(arg0),\n        }"
+        ));
+        assert!(shims.contains("impl TryFrom<ActorMessageSetV4> for ActorMessageSetV3 {"));
+        assert!(!shims.contains("ActorMessageSetV3Unsupported"));
+    }
+
+    #[test]
+    fn test_message_shim_versions() {
+        let variants = vec![EnumVariant::new("Ping", Vec::new())];
+        assert_eq!(
+            render_message_shims(
+                &versioned(1, variants.clone()),
+                &versioned(2, variants.clone())
+            ),
+            Ok(None)
+        );
+        assert_eq!(
+            render_message_shims(&versioned(1, variants.clone()), &versioned(3, Vec::new())),
+            Err(RenderError::InvalidSpec(
+                "Message shims go from one message set version to the next, not from 1 to 3"
+                    .to_string()
+            ))
+        );
+        let mut unversioned = versioned(1, variants);
+        unversioned.component.message_set.as_mut().unwrap().version = None;
+        assert!(render_message_shims(&unversioned, &versioned(2, Vec::new())).is_err());
+    }
+}
@@ -0,0 +1,410 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::blox::actor::Actor;
+use crate::create::emit::FieldNumbering;
+use crate::create::{
+    DispatchStrategy, Edition, GeneratorOptionsBuilder, LayeringRules, LicenseHeader, LineEndings,
+    RustVersion, WireFormat,
+};
+use crate::lint::LintLevel;
+
+/// Name of the project config file
+pub const CONFIG_FILE: &str = "bloxml.toml";
+
+/// Project settings read from `bloxml.toml`, usually kept at the workspace root
+///
+/// Paths in the file are relative to the directory containing it.
+#[derive(Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Defaults for `bloxml generate`
+    #[serde(default)]
+    pub generate: GenerateConfig,
+    /// Lint levels for lints a spec doesn't configure itself
+    #[serde(default)]
+    pub lints: BTreeMap<String, LintLevel>,
+    /// Defaults for `bloxml emit`
+    #[serde(default)]
+    pub emit: EmitConfig,
+    /// Dependencies between generated modules `check` rejects
+    #[serde(default)]
+    pub layering: LayeringRules,
+}
+
+impl ProjectConfig {
+    /// Finds the `bloxml.toml` closest to `spec`, in its directory or the nearest parent
+    pub fn find(spec: &Path) -> Option<PathBuf> {
+        match spec.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => Self::find_from(dir),
+            _ => Self::find_from(Path::new(".")),
+        }
+    }
+
+    /// Finds the `bloxml.toml` in `dir` or the nearest parent
+    pub fn find_from(dir: &Path) -> Option<PathBuf> {
+        fs::canonicalize(dir)
+            .ok()?
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Loads the `bloxml.toml` that applies to `spec`, if there is one
+    pub fn discover(spec: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        Self::find(spec)
+            .map(|path| Self::from_file(&path))
+            .transpose()
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let read = |path: &Path| {
+            fs::read_to_string(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("Error reading {}: {e}", path.display()))
+            })
+        };
+        let mut config: Self = toml::from_str(&read(path)?)
+            .map_err(|e| format!("Error in {}: {e}", path.display()))?;
+
+        let root = path.parent().unwrap_or(Path::new(""));
+        let generate = &mut config.generate;
+        generate.output_dir = generate.output_dir.as_ref().map(|dir| root.join(dir));
+        generate.secrets_file = generate.secrets_file.as_ref().map(|file| root.join(file));
+        generate.readme_template = generate
+            .readme_template
+            .as_ref()
+            .map(|file| root.join(file));
+        if let Some(registry) = &mut generate.framework_registry {
+            *registry = root.join(&*registry);
+            generate.framework_types = toml::from_str(&read(registry)?)
+                .map_err(|e| format!("Error in {}: {e}", registry.display()))?;
+        }
+        Ok(config)
+    }
+
+    /// Fills in what the spec leaves to the project: its output directory and lint levels
+    pub fn apply_to_actor(&self, actor: &mut Actor) {
+        if actor.path.as_os_str().is_empty()
+            && let Some(dir) = &self.generate.output_dir
+        {
+            actor.path = dir.clone();
+        }
+        for (name, level) in &self.lints {
+            actor.lints.entry(name.clone()).or_insert(*level);
+        }
+    }
+}
+
+/// Generator options that are set, leaving the rest to the layer below
+#[derive(Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GenerateConfig {
+    pub backup: Option<bool>,
+    pub no_overwrite: Option<bool>,
+    pub wire: Option<WireFormat>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub edition: Option<Edition>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub msrv: Option<RustVersion>,
+    pub clippy_allows: Option<bool>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub dispatch: Option<DispatchStrategy>,
+    /// Assert the framework bounds of generated types; the crate needs `static_assertions`
+    pub static_assertions: Option<bool>,
+    /// Fail generation when an extended state field's type is not `Send` or `Sync`
+    pub deny_non_send: Option<bool>,
+    /// Lines `states/mod.rs` may reach before it is split into submodules
+    pub max_file_lines: Option<usize>,
+    /// SPDX header written at the top of every generated file
+    pub license_header: Option<LicenseHeader>,
+    /// Generate `cargo-fuzz` targets for the wire codecs in the crate's `fuzz/` directory
+    pub fuzz_targets: Option<bool>,
+    /// Generate loom tests for shared extended state fields; the crate needs `loom` under
+    /// `cfg(loom)`
+    pub loom_tests: Option<bool>,
+    /// Also assert state invariants after every message, not only on entry
+    pub invariants_after_messages: Option<bool>,
+    /// Reject specs with free-form Rust code, as a policy gate for audited projects
+    pub restricted: Option<bool>,
+    /// Line endings of the generated files: lf, crlf or native
+    #[serde(default, deserialize_with = "parsed")]
+    pub line_endings: Option<LineEndings>,
+    /// Write a runnable `examples/<actor>_demo.rs` into the crate, unless it already has one
+    pub demo_example: Option<bool>,
+    pub derive_message_traits: Option<bool>,
+    /// Directory actors are generated under when their spec has no `path`
+    pub output_dir: Option<PathBuf>,
+    /// TOML file mapping type names to the paths they are imported from, like framework types
+    pub framework_registry: Option<PathBuf>,
+    /// File of `NAME=value` lines generation looks for `${secret:NAME}` references missing
+    /// from the environment in; keep it out of version control
+    pub secrets_file: Option<PathBuf>,
+    /// Write a `README.md` describing the module next to the generated code
+    pub readme: Option<bool>,
+    /// File the README is rendered from instead of the default template
+    pub readme_template: Option<PathBuf>,
+    /// Types read from the framework registry
+    #[serde(skip)]
+    pub framework_types: BTreeMap<String, String>,
+}
+
+impl GenerateConfig {
+    /// Sets the options this layer sets on top of `builder`
+    pub fn apply(&self, mut builder: GeneratorOptionsBuilder) -> GeneratorOptionsBuilder {
+        if let Some(backup) = self.backup {
+            builder = builder.backup(backup);
+        }
+        if let Some(no_overwrite) = self.no_overwrite {
+            builder = builder.no_overwrite(no_overwrite);
+        }
+        if let Some(wire) = self.wire {
+            builder = builder.wire_format(wire);
+        }
+        if let Some(edition) = self.edition {
+            builder = builder.edition(edition);
+        }
+        if let Some(msrv) = self.msrv {
+            builder = builder.msrv(msrv);
+        }
+        if let Some(clippy_allows) = self.clippy_allows {
+            builder = builder.clippy_allows(clippy_allows);
+        }
+        if let Some(dispatch) = self.dispatch {
+            builder = builder.dispatch(dispatch);
+        }
+        if let Some(static_assertions) = self.static_assertions {
+            builder = builder.static_assertions(static_assertions);
+        }
+        if let Some(deny_non_send) = self.deny_non_send {
+            builder = builder.deny_non_send(deny_non_send);
+        }
+        if let Some(max_file_lines) = self.max_file_lines {
+            builder = builder.max_file_lines(max_file_lines);
+        }
+        if let Some(demo_example) = self.demo_example {
+            builder = builder.demo_example(demo_example);
+        }
+        if let Some(loom_tests) = self.loom_tests {
+            builder = builder.loom_tests(loom_tests);
+        }
+        if let Some(invariants_after_messages) = self.invariants_after_messages {
+            builder = builder.invariants_after_messages(invariants_after_messages);
+        }
+        if let Some(restricted) = self.restricted {
+            builder = builder.restricted(restricted);
+        }
+        if let Some(fuzz_targets) = self.fuzz_targets {
+            builder = builder.fuzz_targets(fuzz_targets);
+        }
+        if let Some(line_endings) = self.line_endings {
+            builder = builder.line_endings(line_endings);
+        }
+        if let Some(license_header) = &self.license_header {
+            builder = builder.license_header(license_header.clone());
+        }
+        if let Some(derive_message_traits) = self.derive_message_traits {
+            builder = builder.derive_message_traits(derive_message_traits);
+        }
+        if let Some(secrets_file) = &self.secrets_file {
+            builder = builder.secrets_file(secrets_file);
+        }
+        if let Some(readme) = self.readme {
+            builder = builder.readme(readme);
+        }
+        if let Some(readme_template) = &self.readme_template {
+            builder = builder.readme_template(readme_template);
+        }
+        for (type_name, full_path) in &self.framework_types {
+            builder = builder.framework_type(type_name, full_path);
+        }
+        builder
+    }
+}
+
+/// Settings of the emitters rendering the spec in other languages and schemas
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct EmitConfig {
+    /// Package of emitted protobuf schemas, the actor's module path if unset
+    pub package: Option<String>,
+    /// How protobuf fields and enum values are numbered
+    pub numbering: Option<FieldNumbering>,
+    /// Version of the actor's API in emitted AsyncAPI documents
+    pub version: Option<String>,
+}
+
+impl EmitConfig {
+    /// Overrides the settings `other` sets
+    pub fn merge(self, other: EmitConfig) -> EmitConfig {
+        EmitConfig {
+            package: other.package.or(self.package),
+            numbering: other.numbering.or(self.numbering),
+            version: other.version.or(self.version),
+        }
+    }
+}
+
+/// Deserializes a string through the type's `FromStr`, like `edition = "2021"`
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| s.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create::{ActorGenerator, GeneratorOptions};
+
+    #[test]
+    fn test_layers() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [generate]
+            backup = true
+            wire = "cbor"
+            edition = "2021"
+            msrv = "1.70"
+            "#,
+        )
+        .unwrap();
+        // Flags only override what they set
+        let flags = GenerateConfig {
+            edition: Some(Edition::E2018),
+            clippy_allows: Some(true),
+            ..Default::default()
+        };
+
+        let options = flags
+            .apply(config.generate.apply(GeneratorOptions::builder()))
+            .build()
+            .unwrap();
+        assert_eq!(
+            options,
+            GeneratorOptions::default()
+                .with_backup(true)
+                .with_wire_format(Some(WireFormat::Cbor))
+                .with_edition(Edition::E2018)
+                .with_msrv(Some(RustVersion::new(1, 70)))
+                .with_clippy_allows(true)
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let error = toml::from_str::<ProjectConfig>("[generate]\nedition = \"2015\"")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown edition '2015', expected 2018, 2021 or 2024"));
+        assert!(toml::from_str::<ProjectConfig>("[generate]\nbackups = true").is_err());
+
+        let config: ProjectConfig =
+            toml::from_str("[generate]\nedition = \"2024\"\nmsrv = \"1.70\"").unwrap();
+        assert_eq!(
+            config.generate.apply(GeneratorOptions::builder()).build(),
+            Err("Rust 1.70 predates the 2024 edition, which needs Rust 1.85".to_string())
+        );
+    }
+
+    #[test]
+    fn test_emit_config() {
+        let config: ProjectConfig =
+            toml::from_str("[emit]\npackage = \"acme.billing\"\nnumbering = \"hashed\"").unwrap();
+        // Flags only override what they set
+        let emit = config.emit.merge(EmitConfig {
+            package: Some("acme.refunds".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(emit.package.as_deref(), Some("acme.refunds"));
+        assert_eq!(emit.numbering, Some(FieldNumbering::Hashed));
+        assert!(toml::from_str::<ProjectConfig>("[emit]\nnumbering = \"random\"").is_err());
+    }
+
+    #[test]
+    fn test_layering_config() {
+        let config: ProjectConfig = toml::from_str(
+            "[layering]\nstates = [\"runtime\"]\nmessaging = [\"component\", \"states\"]",
+        )
+        .unwrap();
+        assert_eq!(
+            config.layering,
+            LayeringRules::default()
+                .forbid("messaging", "component")
+                .forbid("messaging", "states")
+                .forbid("states", "runtime")
+        );
+        assert!(toml::from_str::<ProjectConfig>("[layering]\nstates = \"runtime\"").is_err());
+    }
+
+    #[test]
+    fn test_discover() {
+        // Specs in subdirectories share the project's config
+        let spec = Path::new("tests/project/actors/actor.json");
+        let config = ProjectConfig::discover(spec)
+            .unwrap()
+            .expect("Config should be found above the spec");
+        assert_eq!(config.generate.wire, Some(WireFormat::Json));
+        assert_eq!(config.generate.edition, Some(Edition::E2021));
+        assert!(
+            config
+                .generate
+                .output_dir
+                .unwrap()
+                .ends_with("tests/project/generated")
+        );
+        assert_eq!(
+            config.generate.framework_types["CustomArgs"],
+            "shared::payloads::CustomArgs"
+        );
+
+        assert!(
+            ProjectConfig::discover(Path::new("tests/test_file.json"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_project_defaults() {
+        let spec = PathBuf::from("tests/project/actors/actor.json");
+        let config = ProjectConfig::discover(&spec).unwrap().unwrap();
+        let mut actor = Actor::from_json_file(&spec).unwrap();
+        assert!(actor.path.as_os_str().is_empty());
+        actor
+            .lints
+            .insert("unused-handle".to_string(), LintLevel::Warn);
+        config.apply_to_actor(&mut actor);
+
+        // The spec's own settings win over the project's
+        assert!(actor.path.ends_with("tests/project/generated"));
+        assert_eq!(actor.lints["unused-handle"], LintLevel::Warn);
+
+        let pathless = Actor::from_json_file(&spec).unwrap();
+        assert_eq!(
+            ActorGenerator::new(pathless)
+                .unwrap()
+                .generate()
+                .unwrap_err()
+                .to_string(),
+            "The spec has no `path` and no `bloxml.toml` sets an output directory"
+        );
+
+        // Registry types are imported like framework types
+        let options = config.generate.apply(GeneratorOptions::builder());
+        let generator = ActorGenerator::with_options(actor, options.build().unwrap()).unwrap();
+        let imports = generator.ctx().imports_for("actor::messaging").unwrap();
+        assert!(imports.contains(&"use shared::payloads::CustomArgs;".to_string()));
+    }
+}
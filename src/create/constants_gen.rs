@@ -0,0 +1,91 @@
+use crate::blox::constant::ConstantModule;
+use crate::create::RenderCtx;
+
+/// Render the constants the spec declares in `module`, each followed by a blank line
+pub fn render_constants(ctx: &RenderCtx, module: ConstantModule) -> String {
+    ctx.actor
+        .constants
+        .iter()
+        .filter(|constant| constant.module == module)
+        .map(|constant| format!("{}\n\n", constant.render()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::constant::{Constant, ConstantModule};
+    use crate::blox::message_set::TypeAlias;
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_constants() {
+        let mut actor = create_test_actor();
+        let message_set = actor.component.message_set.take().unwrap();
+        actor.component.message_set =
+            Some(message_set.with_aliases(vec![TypeAlias::new("Millis", "u64")]));
+        let actor = actor
+            .with_constant(Constant::new("MAX_RETRIES", "u32", "3"))
+            .with_constant(Constant::new("RETRY_DELAY", "Millis", "250"))
+            .with_constant(
+                Constant::new(
+                    "TIMEOUT",
+                    "std::time::Duration",
+                    "std::time::Duration::from_secs(5)",
+                )
+                .in_module(ConstantModule::States),
+            )
+            .with_constant(
+                Constant::new("GREETING", "&str", "\"hello\"")
+                    .in_module(ConstantModule::Messaging)
+                    .as_static(),
+            );
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(ext_state.contains("use crate::actor::messaging::Millis;\n"));
+        assert!(ext_state.contains(
+            "pub const MAX_RETRIES: u32 = 3;\n\npub const RETRY_DELAY: Millis = 250;\n\n/// Extended state for the Actor component"
+        ));
+        let states = generator.generate_state_enum().unwrap();
+        assert!(states.contains(
+            "pub const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);\n\n"
+        ));
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains("pub static GREETING: &str = \"hello\";\n\n"));
+        assert!(
+            !generator
+                .generate_component()
+                .unwrap()
+                .contains("pub const")
+        );
+    }
+
+    #[test]
+    fn test_validate_constants() {
+        let validate = |constant: Constant| {
+            create_test_actor()
+                .with_constant(constant)
+                .validate_constants()
+        };
+        assert!(validate(Constant::new("LIMIT_2", "u8", "2")).is_ok());
+        assert_eq!(
+            validate(Constant::new("maxRetries", "u32", "3")),
+            Err("Constant 'maxRetries' should be named in SCREAMING_SNAKE_CASE".to_string())
+        );
+
+        let mut actor = create_test_actor()
+            .with_constant(Constant::new("LIMIT", "u8", "2").in_module(ConstantModule::Messaging));
+        assert!(actor.validate_constants().is_ok());
+        actor.component.message_set = None;
+        assert!(actor.validate_constants().is_err());
+
+        let actor = create_test_actor()
+            .with_constant(Constant::new("LIMIT", "u8", "2"))
+            .with_constant(Constant::new("LIMIT", "u8", "3").in_module(ConstantModule::States));
+        assert_eq!(
+            actor.validate_constants(),
+            Err("Constant 'LIMIT' is declared twice".to_string())
+        );
+    }
+}
@@ -0,0 +1,491 @@
+use crate::blox::actor::{CORRELATED_TYPE, OVERLOADED_VARIANT};
+use crate::blox::message_handlers::MessageReceiver;
+use crate::create::{RenderCtx, RenderError};
+
+/// Render the correlation module: the ID and the payload wrapper carrying it
+pub fn render_correlation_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    ctx.actor
+        .validate_trace_context()
+        .map_err(RenderError::InvalidSpec)?;
+    let imports_section = ctx.imports_section(&format!("{}::correlation", ctx.actor_module()))?;
+
+    let mut derives = vec![
+        "Debug".to_string(),
+        "Clone".to_string(),
+        "PartialEq".to_string(),
+    ];
+    if ctx.options.wire_format.is_some() {
+        derives.extend(RenderCtx::WIRE_DERIVES.iter().map(ToString::to_string));
+    }
+    let id_derives = derives
+        .iter()
+        .map(String::as_str)
+        .chain(["Copy", "Eq", "Hash"])
+        .collect::<Vec<_>>()
+        .join(", ");
+    let correlated = if ctx.actor.traces() {
+        render_traced_payload(ctx, &derives)
+    } else {
+        render_payload(&derives.join(", "))
+    };
+
+    Ok(format!(
+        r#"{imports_section}static NEXT_CORRELATION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Identifies every message caused by one request, however many actors it crosses
+#[derive({id_derives})]
+pub struct CorrelationId(u64);
+
+impl CorrelationId {{
+    /// Starts a new chain with an ID unique within the process
+    pub fn new() -> Self {{
+        Self(NEXT_CORRELATION_ID.fetch_add(1, Ordering::Relaxed))
+    }}
+
+    /// Continues a chain whose ID arrived from outside the process
+    pub fn from_raw(id: u64) -> Self {{
+        Self(id)
+    }}
+
+    pub fn as_u64(&self) -> u64 {{
+        self.0
+    }}
+}}
+
+impl Default for CorrelationId {{
+    fn default() -> Self {{
+        Self::new()
+    }}
+}}
+
+impl std::fmt::Display for CorrelationId {{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{
+        write!(f, "{{:016x}}", self.0)
+    }}
+}}
+
+{correlated}"#
+    ))
+}
+
+/// Render the payload wrapper carrying only the correlation ID
+fn render_payload(derives: &str) -> String {
+    format!(
+        r#"/// A payload tagged with the correlation ID of the request that caused it
+#[derive({derives})]
+pub struct {CORRELATED_TYPE}<T> {{
+    pub correlation_id: CorrelationId,
+    pub payload: T,
+}}
+
+impl<T> {CORRELATED_TYPE}<T> {{
+    /// Tags a payload that starts a new chain
+    pub fn new(payload: T) -> Self {{
+        Self {{
+            correlation_id: CorrelationId::new(),
+            payload,
+        }}
+    }}
+
+    /// Tags a payload caused by this one, so it carries the same ID
+    pub fn follow<U>(&self, payload: U) -> {CORRELATED_TYPE}<U> {{
+        {CORRELATED_TYPE} {{
+            correlation_id: self.correlation_id,
+            payload,
+        }}
+    }}
+}}
+"#
+    )
+}
+
+/// Render the payload wrapper carrying the correlation ID and the OpenTelemetry trace context
+/// the request arrived with, and the carrier the context is propagated in
+fn render_traced_payload(ctx: &RenderCtx, derives: &[String]) -> String {
+    let context_derives = derives
+        .iter()
+        .map(String::as_str)
+        .chain(["Default"])
+        .collect::<Vec<_>>()
+        .join(", ");
+    // Payloads encoded before tracing was turned on still decode
+    let field_attr = if ctx.options.wire_format.is_some() {
+        "    #[serde(default, skip_serializing_if = \"TraceContext::is_empty\")]\n"
+    } else {
+        ""
+    };
+    let derives = derives.join(", ");
+
+    format!(
+        r#"/// OpenTelemetry context propagated across the actor boundary, as the headers encoding it
+#[derive({context_derives})]
+pub struct TraceContext(pub HashMap<String, String>);
+
+impl TraceContext {{
+    /// Captures the context of the current span through the global propagator
+    pub fn current() -> Self {{
+        let mut context = Self::default();
+        let otel_context = tracing::Span::current().context();
+        global::get_text_map_propagator(|propagator| {{
+            propagator.inject_context(&otel_context, &mut context)
+        }});
+        context
+    }}
+
+    /// Reads the context from request or message headers
+    pub fn from_headers<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {{
+        Self(
+            headers
+                .into_iter()
+                .map(|(name, value)| (name.to_lowercase(), value.to_string()))
+                .collect(),
+        )
+    }}
+
+    /// Headers encoding the context, to send along with the payload
+    pub fn headers(&self) -> impl Iterator<Item = (&str, &str)> {{
+        self.0.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }}
+
+    pub fn is_empty(&self) -> bool {{
+        self.0.is_empty()
+    }}
+
+    /// Makes `span` a child of the span the context was captured in
+    pub fn attach_to(&self, span: &tracing::Span) {{
+        let parent = global::get_text_map_propagator(|propagator| propagator.extract(self));
+        let _ = span.set_parent(parent);
+    }}
+}}
+
+impl Extractor for TraceContext {{
+    fn get(&self, key: &str) -> Option<&str> {{
+        self.0.get(&key.to_lowercase()).map(String::as_str)
+    }}
+
+    fn keys(&self) -> Vec<&str> {{
+        self.0.keys().map(String::as_str).collect()
+    }}
+}}
+
+impl Injector for TraceContext {{
+    fn set(&mut self, key: &str, value: String) {{
+        self.0.insert(key.to_lowercase(), value);
+    }}
+}}
+
+/// A payload tagged with the correlation ID and trace context of the request that caused it
+#[derive({derives})]
+pub struct {CORRELATED_TYPE}<T> {{
+    pub correlation_id: CorrelationId,
+{field_attr}    pub trace_context: TraceContext,
+    pub payload: T,
+}}
+
+impl<T> {CORRELATED_TYPE}<T> {{
+    /// Tags a payload that starts a new chain in the current trace
+    pub fn new(payload: T) -> Self {{
+        Self {{
+            correlation_id: CorrelationId::new(),
+            trace_context: TraceContext::current(),
+            payload,
+        }}
+    }}
+
+    /// Tags a payload caused by this one, so it carries the same ID and trace
+    pub fn follow<U>(&self, payload: U) -> {CORRELATED_TYPE}<U> {{
+        {CORRELATED_TYPE} {{
+            correlation_id: self.correlation_id,
+            trace_context: self.trace_context.clone(),
+            payload,
+        }}
+    }}
+
+    /// Continues the trace `trace_context` was captured in instead
+    pub fn with_trace_context(mut self, trace_context: TraceContext) -> Self {{
+        self.trace_context = trace_context;
+        self
+    }}
+}}
+"#
+    )
+}
+
+/// Render the message set helpers reading and propagating the correlation ID
+pub fn render_correlation_helpers(ctx: &RenderCtx) -> String {
+    let Some(message_set) = &ctx.actor.component.message_set else {
+        return String::new();
+    };
+    let message_set = &message_set.get();
+    let receivers = &ctx.actor.component.message_receivers.receivers;
+
+    // One arm per variant with a payload, reading `field` of its correlated payload. The
+    // runtime's own `Overloaded` notice is not correlated and falls through to `None`.
+    let match_arms = |field: &str, wrap: &dyn Fn(String) -> String| {
+        let arms = message_set
+            .variants
+            .iter()
+            .zip(receivers)
+            .filter(|(variant, _)| !variant.args.is_empty() && variant.ident != OVERLOADED_VARIANT)
+            .map(|(variant, receiver)| {
+                let args = if variant.args.len() > 1 {
+                    "msg, .."
+                } else {
+                    "msg"
+                };
+                format!(
+                    "            {}::{}({args}) => Some({}),",
+                    message_set.ident,
+                    variant.ident,
+                    wrap(correlated_field(receiver, "msg", field))
+                )
+            })
+            .collect::<Vec<_>>();
+        let fallthrough = if arms.len() < message_set.variants.len() {
+            "\n            _ => None,"
+        } else {
+            ""
+        };
+        format!("{}{fallthrough}", arms.join("\n"))
+    };
+    let arms = match_arms("correlation_id", &|field| field);
+
+    let (trace_context_fn, trace_context_field) = if ctx.actor.traces() {
+        let arms = match_arms("trace_context", &|field| format!("&{field}"));
+        (
+            format!(
+                r#"
+
+    /// Gets the trace context the message carries
+    pub fn trace_context(&self) -> Option<&TraceContext> {{
+        match self {{
+{arms}
+        }}
+    }}"#
+            ),
+            "\n            trace_context: self.trace_context().cloned().unwrap_or_default(),",
+        )
+    } else {
+        (String::new(), "")
+    };
+
+    format!(
+        r#"
+impl {message_set} {{
+    /// Gets the correlation ID the message carries
+    pub fn correlation_id(&self) -> Option<CorrelationId> {{
+        match self {{
+{arms}
+        }}
+    }}{trace_context_fn}
+
+    /// Tags a payload caused by this message with its correlation ID, or a new one
+    pub fn correlate<T>(&self, payload: T) -> {CORRELATED_TYPE}<T> {{
+        {CORRELATED_TYPE} {{
+            correlation_id: self.correlation_id().unwrap_or_default(),{trace_context_field}
+            payload,
+        }}
+    }}
+}}
+"#,
+        message_set = message_set.ident
+    )
+}
+
+/// Expression reading `field` of the correlated payload of a message received on `receiver`
+fn correlated_field(receiver: &MessageReceiver, message: &str, field: &str) -> String {
+    // Sequence numbers wrap the correlated payload
+    if receiver.sequenced {
+        format!("{message}.payload().payload.{field}")
+    } else {
+        format!("{message}.payload().{field}")
+    }
+}
+
+/// Render a dispatch of `message` inside a tracing span carrying its correlation ID
+///
+/// When the actor `traces`, the span continues the trace the message was sent in.
+pub fn render_correlated_dispatch(message: &str, traces: bool) -> String {
+    let span = r#"tracing::info_span!("dispatch", correlation_id = ?message.correlation_id())"#;
+    let span = if traces {
+        format!(
+            r#"let span = {span};
+                        if let Some(trace_context) = message.trace_context() {{
+                            trace_context.attach_to(&span);
+                        }}
+                        let _span = span.entered();"#
+        )
+    } else {
+        format!("let _span = {span}.entered();")
+    };
+    format!(
+        r#"                        let message = {message};
+                        {span}
+                        self.state_machine.dispatch(message, &current_state);"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::bridge::{Bridge, HttpBridge};
+    use crate::blox::message_handlers::QueueSampling;
+    use crate::blox::schedule::Schedule;
+    use crate::blox::state::{Emit, State, Transition};
+    use crate::create::{ActorGenerator, GeneratorOptions, RenderError, WireFormat};
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_correlation() {
+        let mut actor = create_test_actor().with_correlation().with_self_messages();
+        let receivers = &mut actor.component.message_receivers.receivers;
+        let receiver = receivers.remove(1);
+        receivers.insert(1, receiver.with_sequencing());
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let correlation = generator
+            .generate_correlation()
+            .unwrap()
+            .expect("Correlation module should be generated");
+        assert!(correlation.contains("pub struct CorrelationId(u64);"));
+        assert!(correlation.contains("pub fn follow<U>(&self, payload: U) -> Correlated<U> {"));
+        assert!(correlation.contains("use std::sync::atomic::AtomicU64;"));
+
+        // Sequence numbers wrap the correlated payload
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains(
+            "CustomValue1(Message<Correlated<bloxide_core::messaging::StandardPayload>>)"
+        ));
+        assert!(messaging.contains("CustomValue2(Message<Sequenced<Correlated<CustomArgs>>>)"));
+        assert!(messaging.contains(
+            "            ActorMessageSet::CustomValue1(msg) => Some(msg.payload().correlation_id),\n            ActorMessageSet::CustomValue2(msg) => Some(msg.payload().payload.correlation_id),\n        }"
+        ));
+        assert!(messaging.contains("use crate::actor::correlation::CorrelationId;"));
+        assert!(messaging.contains("use crate::actor::correlation::Correlated;"));
+
+        let component = generator.generate_component().unwrap();
+        assert!(component.contains(
+            "pub customargs_handle: TokioMessageHandle<Sequenced<Correlated<CustomArgs>>>"
+        ));
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "                        let message = ActorMessageSet::CustomValue2(msg);\n                        let _span = tracing::info_span!(\"dispatch\", correlation_id = ?message.correlation_id()).entered();"
+        ));
+        assert!(runtime.contains("                        let message = msg;\n"));
+        assert!(
+            generator
+                .root_modules()
+                .contains(&"correlation".to_string())
+        );
+    }
+
+    #[test]
+    fn test_correlation_with_overload_notice() {
+        let actor = create_test_actor()
+            .with_correlation()
+            .with_health_check()
+            .with_queue_sampling(QueueSampling::new(500).with_overload_threshold(64));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        // The runtime raises the notice itself, so it carries no correlation ID
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains("    Overloaded(Message<QueueOverload>),\n"));
+        assert!(messaging.contains(
+            "            ActorMessageSet::CustomValue2(msg) => Some(msg.payload().correlation_id),\n            _ => None,\n        }"
+        ));
+        assert!(!messaging.contains("ActorMessageSet::Overloaded(msg) =>"));
+    }
+
+    #[test]
+    fn test_correlated_schedules_and_emits() {
+        let mut actor = create_test_actor()
+            .with_correlation()
+            .with_schedules(vec![Schedule::new("0 9 * * *", "CustomValue1")]);
+        actor.component.states.states[0] = State::from("Create").with_transitions(vec![
+            Transition::new("CustomValue2", "Update").with_emit(vec![Emit::new(
+                "customargs_handle",
+                "CustomArgs::default()",
+            )]),
+        ]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        // A schedule starts a chain, while an emit continues the received message's
+        let scheduler = generator.generate_scheduler().unwrap().unwrap();
+        assert!(scheduler.contains(
+            "inbox.standard.send(Message::new(0, Correlated::new(Default::default()))).await"
+        ));
+        assert!(scheduler.contains("use crate::actor::correlation::Correlated;"));
+
+        let state = &generator.actor().component.states.states[0];
+        let state_impl = generator.generate_state_impl(state).unwrap();
+        assert!(state_impl.contains(
+            "if state_machine.self_handles.customargs_handle.try_send(Message::new(0, message.correlate(CustomArgs::default()))).is_err() {"
+        ));
+        assert!(
+            state_impl.contains("use bloxide_tokio::messaging::Message;"),
+            "{state_impl}"
+        );
+    }
+
+    #[test]
+    fn test_trace_context() {
+        let bridge = Bridge::Http(HttpBridge::new(vec![]).with_trace_context());
+        let actor = create_test_actor()
+            .with_correlation()
+            .with_bridge(bridge.clone());
+        let options = GeneratorOptions::default().with_wire_format(Some(WireFormat::Json));
+        let generator = ActorGenerator::with_options(actor, options)
+            .expect("Generator creation should succeed");
+
+        let correlation = generator.generate_correlation().unwrap().unwrap();
+        assert!(correlation.contains("pub struct TraceContext(pub HashMap<String, String>);"));
+        assert!(correlation.contains("impl Extractor for TraceContext {"));
+        assert!(correlation.contains(
+            "    #[serde(default, skip_serializing_if = \"TraceContext::is_empty\")]\n    pub trace_context: TraceContext,"
+        ));
+        assert!(correlation.contains("            trace_context: TraceContext::current(),"));
+        assert!(correlation.contains("use tracing_opentelemetry::OpenTelemetrySpanExt;"));
+
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains(
+            "            ActorMessageSet::CustomValue1(msg) => Some(&msg.payload().trace_context),"
+        ));
+        assert!(messaging.contains(
+            "            trace_context: self.trace_context().cloned().unwrap_or_default(),"
+        ));
+        assert!(messaging.contains("use crate::actor::correlation::TraceContext;"));
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "                        if let Some(trace_context) = message.trace_context() {\n                            trace_context.attach_to(&span);\n                        }\n                        let _span = span.entered();"
+        ));
+
+        // Trace context travels in correlated payloads
+        let generator = ActorGenerator::new(create_test_actor().with_bridge(bridge))
+            .expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate_bridge(),
+            Err(RenderError::InvalidSpec(
+                "Propagating trace context requires `correlation`, whose payloads carry it"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_correlation_wire_derives() {
+        let options = GeneratorOptions::default().with_wire_format(Some(WireFormat::Json));
+        let generator =
+            ActorGenerator::with_options(create_test_actor().with_correlation(), options)
+                .expect("Generator creation should succeed");
+        let correlation = generator.generate_correlation().unwrap().unwrap();
+        assert!(correlation.contains(
+            "#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Copy, Eq, Hash)]\npub struct CorrelationId(u64);"
+        ));
+
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(generator.generate_correlation().unwrap().is_none());
+        assert!(!generator.generate_runtime().unwrap().contains("info_span"));
+    }
+}
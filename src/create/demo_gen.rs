@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+
+use crate::blox::actor::HEALTH_RECEIVER;
+use crate::create::children_gen::{Wiring, render_wiring};
+use crate::create::{CrateRoot, RenderCtx, RenderError};
+
+/// Directory of the demo examples, next to the `Cargo.toml` of the crate they run
+pub const EXAMPLES_DIR: &str = "examples";
+
+/// Render `examples/<actor>_demo.rs`, which spawns the actor, sends it a default payload on
+/// every variant of its message set and prints the transitions it makes, with its path
+/// relative to the crate root
+///
+/// Returns `None` for an actor without a message set, which nothing can be sent to.
+pub fn render_demo(
+    ctx: &RenderCtx,
+    krate: &CrateRoot,
+) -> Result<Option<(PathBuf, String)>, RenderError> {
+    let actor = ctx.actor;
+    let component = &actor.component;
+    let Some(message_set) = &component.message_set else {
+        return Ok(None);
+    };
+    if actor.has_self_queue() || component.message_handles.has_circuit_breakers() {
+        return Err(RenderError::InvalidSpec(format!(
+            "The demo of {} can't wire self messages or circuit breakers",
+            actor.ident
+        )));
+    }
+    let states = &component.states.states;
+    let (Some(first), Some(second)) = (states.first(), states.get(1).or(states.first())) else {
+        return Err(RenderError::InvalidSpec(
+            "the demo requires at least one state".to_string(),
+        ));
+    };
+
+    let actor_name = &actor.ident;
+    let module = format!("{}::{}", krate.lib_name(), ctx.actor_module());
+    let message_enum = &message_set.get().ident;
+    let state_enum = &component.states.state_enum.get().ident;
+    let ext_state = component.ext_state.ident();
+    let receivers = &component.message_receivers;
+    let handles = &component.message_handles;
+
+    let defaults = vec!["Default::default()"; component.ext_state.fields().len()].join(", ");
+    let Wiring {
+        channels,
+        inbox,
+        blox,
+    } = render_wiring(actor, &format!("{ext_state}::new({defaults})"));
+    let mut state_imports = [first, second]
+        .iter()
+        .map(|state| format!("{}::{}", state.ident.to_lowercase(), state.ident))
+        .collect::<Vec<_>>();
+    state_imports.dedup();
+    state_imports.push(state_enum.clone());
+    let state_imports = state_imports.join(", ");
+
+    let spec_receivers = receivers
+        .receivers
+        .iter()
+        .filter(|receiver| receiver.ident != HEALTH_RECEIVER);
+    let variants = message_set.get().variants.iter().zip(spec_receivers);
+    let select_arms = variants
+        .clone()
+        .map(|(variant, receiver)| {
+            let message = if variant.boxed {
+                "Box::new(msg)"
+            } else {
+                "msg"
+            };
+            format!(
+                "                Some(msg) = blox.receivers.{}.recv() => {message_enum}::{}({message}),",
+                receiver.ident, variant.ident
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let sends = variants
+        .map(|(variant, receiver)| {
+            format!(
+                r#"    println!("sending {message_enum}::{variant}");
+    inbox
+        .{channel}
+        .send(Message::new(0, Default::default()))
+        .await
+        .expect("the actor should be running");"#,
+                variant = variant.ident,
+                channel = receiver.channel_name()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let content = format!(
+        r#"//! Demo of the {actor_name} actor: spawns it, sends a sample of every message it handles
+//! and prints the transitions it makes
+//!
+//! Run it with `cargo run --example {actor_dir}_demo`. Payloads are built with `Default`, and so
+//! are the fields of `{ext_state}`. bloxml writes this file once, so it can be edited freely.
+
+use bloxide_tokio::TokioMessageHandle;
+use bloxide_tokio::components::Blox;
+use bloxide_tokio::messaging::Message;
+use tokio::select;
+
+use {module}::component::{{
+    {actor_name}Components, {actor_name}Id, {actor_name}Inbox, {handles_ident}, {receivers_ident},
+}};
+use {module}::ext_state::{ext_state};
+use {module}::messaging::{message_enum};
+use {module}::states::{{{state_imports}}};
+
+#[tokio::main]
+async fn main() {{
+{channels}
+    let inbox = {inbox};
+    let mut blox = {blox};
+    blox.state_machine.init(
+        &{state_enum}::{first_ident}({first_constructor}),
+        &{state_enum}::{second_ident}({second_constructor}),
+    );
+
+    // Dispatches like the generated runtime, printing every change of state
+    let actor = tokio::spawn(async move {{
+        loop {{
+            let message = select! {{
+{select_arms}
+                else => break,
+            }};
+            let current_state = blox.state_machine.current_state.clone();
+            blox.state_machine.dispatch(message, &current_state);
+            if blox.state_machine.current_state != current_state {{
+                println!("{{current_state:?}} -> {{:?}}", blox.state_machine.current_state);
+            }}
+        }}
+    }});
+
+{sends}
+
+    // The actor stops once every channel to it is closed
+    drop(inbox);
+    actor.await.expect("the actor should not panic");
+}}
+"#,
+        actor_dir = actor_name.to_lowercase(),
+        handles_ident = handles.ident,
+        receivers_ident = receivers.ident,
+        first_ident = first.ident,
+        first_constructor = first.constructor(),
+        second_ident = second.ident,
+        second_constructor = second.constructor(),
+    );
+    let file = PathBuf::from(EXAMPLES_DIR).join(format!("{}_demo.rs", actor_name.to_lowercase()));
+    Ok(Some((file, content)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::blox::message_handlers::{CircuitBreaker, MessageHandle};
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    fn krate() -> CrateRoot {
+        CrateRoot {
+            dir: PathBuf::from("."),
+            package: "my-app".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_demo() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let (file, demo) = render_demo(&generator.ctx(), &krate()).unwrap().unwrap();
+
+        assert_eq!(file, Path::new("examples/actor_demo.rs"));
+        assert!(demo.contains("`cargo run --example actor_demo`"));
+        assert!(demo.contains("use my_app::actor::ext_state::ActorExtState;"));
+        assert!(
+            demo.contains(
+                "use my_app::actor::states::{create::Create, update::Update, ActorStates};"
+            )
+        );
+        assert!(demo.contains(
+            "    let (standard, standard_rx) = TokioMessageHandle::new(0, 16);\n    let (customargs, customargs_rx) = TokioMessageHandle::new(0, 16);"
+        ));
+        assert!(demo.contains(
+            "    let (standard_handle, _standard_handle_rx) = TokioMessageHandle::new(0, 16);"
+        ));
+        assert!(
+            demo.contains("        ActorExtState::new(Default::default(), Default::default()),")
+        );
+        assert!(demo.contains(
+            "        &ActorStates::Create(Create),\n        &ActorStates::Update(Update),"
+        ));
+        assert!(demo.contains(
+            "                Some(msg) = blox.receivers.customargs_rx.recv() => ActorMessageSet::CustomValue2(msg),"
+        ));
+        assert!(demo.contains(
+            "    println!(\"sending ActorMessageSet::CustomValue1\");\n    inbox\n        .standard\n        .send(Message::new(0, Default::default()))"
+        ));
+    }
+
+    #[test]
+    fn test_demo_rejects_unwired_features() {
+        let mut actor = create_test_actor();
+        actor.self_messages = true;
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert!(render_demo(&generator.ctx(), &krate()).is_err());
+
+        let mut actor = create_test_actor();
+        actor.component.message_handles.handles = vec![
+            MessageHandle::new("standard_handle", "StandardPayload")
+                .with_circuit_breaker(CircuitBreaker::new(3, 1_000)),
+        ];
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert!(render_demo(&generator.ctx(), &krate()).is_err());
+
+        let mut actor = create_test_actor();
+        actor.component.message_set = None;
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(render_demo(&generator.ctx(), &krate()), Ok(None));
+    }
+}
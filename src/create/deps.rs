@@ -0,0 +1,287 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::create::{ActorGenerator, RenderError};
+use crate::diagnostics::Diagnostic;
+use crate::graph::{Node, Relation};
+
+/// Dependencies between generated modules that go against the direction the generated code
+/// is layered in, by the part of the actor each module belongs to
+pub const UNEXPECTED_DEPENDENCIES: &[(&str, &str)] = &[
+    ("messaging", "component"),
+    ("messaging", "ext_state"),
+    ("messaging", "runtime"),
+    ("messaging", "states"),
+    ("ext_state", "runtime"),
+    ("ext_state", "states"),
+    ("component", "runtime"),
+    ("states", "runtime"),
+];
+
+/// Dependencies between parts of the generated code a project forbids, read from the
+/// `[layering]` table of `bloxml.toml`
+///
+/// Parts are named like the modules of an actor, such as `messaging`, `states` or
+/// `runtime`, and map to the parts they must not depend on:
+///
+/// ```toml
+/// [layering]
+/// messaging = ["component", "runtime"]
+/// states = ["runtime"]
+/// ```
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct LayeringRules(BTreeMap<String, Vec<String>>);
+
+impl LayeringRules {
+    /// Forbids `part` to depend on `dependency`
+    pub fn forbid<P: Into<String>, D: Into<String>>(mut self, part: P, dependency: D) -> Self {
+        self.0
+            .entry(part.into())
+            .or_default()
+            .push(dependency.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.values().all(Vec::is_empty)
+    }
+
+    /// Whether `part` must not depend on `dependency`
+    pub fn forbids(&self, part: &str, dependency: &str) -> bool {
+        self.0
+            .get(part)
+            .is_some_and(|forbidden| forbidden.iter().any(|d| d == dependency))
+    }
+}
+
+/// Dependencies between generated modules, counted in the items one module uses from another
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleDependencies {
+    /// Generated modules by path, with the part of the actor they belong to, like `states`
+    modules: BTreeMap<String, String>,
+    uses: BTreeMap<(String, String), BTreeSet<String>>,
+}
+
+impl ModuleDependencies {
+    /// Adds the modules and dependencies of another actor, for a project-wide report
+    pub fn merge(&mut self, other: ModuleDependencies) {
+        self.modules.extend(other.modules);
+        for (edge, items) in other.uses {
+            self.uses.entry(edge).or_default().extend(items);
+        }
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = &str> {
+        self.modules.keys().map(String::as_str)
+    }
+
+    /// Number of items `from` uses from `to`
+    pub fn count(&self, from: &str, to: &str) -> usize {
+        self.uses
+            .get(&(from.to_string(), to.to_string()))
+            .map_or(0, BTreeSet::len)
+    }
+
+    /// Dependencies with the number of items used, ordered by module
+    pub fn dependencies(&self) -> impl Iterator<Item = (&str, &str, usize)> {
+        self.uses
+            .iter()
+            .map(|((from, to), items)| (from.as_str(), to.as_str(), items.len()))
+    }
+
+    /// Whether `from` depending on `to` is one of the [`UNEXPECTED_DEPENDENCIES`]
+    pub fn is_unexpected(&self, from: &str, to: &str) -> bool {
+        match (self.modules.get(from), self.modules.get(to)) {
+            (Some(from), Some(to)) => UNEXPECTED_DEPENDENCIES
+                .iter()
+                .any(|&(layer, dependency)| layer == from && dependency == to),
+            _ => false,
+        }
+    }
+
+    /// Dependencies that go against the layering of the generated code
+    pub fn unexpected(&self) -> impl Iterator<Item = (&str, &str, usize)> {
+        self.dependencies()
+            .filter(|(from, to, _)| self.is_unexpected(from, to))
+    }
+
+    /// Errors for the dependencies the project's layering rules forbid, naming what is used
+    pub fn violations(&self, rules: &LayeringRules) -> Vec<Diagnostic> {
+        self.uses
+            .iter()
+            .filter_map(|((from, to), items)| {
+                let (part, dependency) = (self.modules.get(from)?, self.modules.get(to)?);
+                rules.forbids(part, dependency).then(|| {
+                    let items = items.iter().map(String::as_str).collect::<Vec<_>>();
+                    Diagnostic::error(
+                        "layering",
+                        format!(
+                            "{part} must not depend on {dependency}, but {from} uses {}",
+                            items.join(", ")
+                        ),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Renders the N×N matrix of item counts, rows depending on columns
+    ///
+    /// Columns are numbered after the rows to keep the table narrow, and unexpected
+    /// dependencies are marked with `!`.
+    pub fn matrix(&self) -> String {
+        let modules = self.modules.keys().collect::<Vec<_>>();
+        let width = modules.iter().map(|module| module.len()).max().unwrap_or(0);
+        let number = modules.len().to_string().len();
+        let cell = number.max(3) + 1;
+
+        let mut matrix = format!("{:>number$} {:width$}", "", "");
+        for column in 1..=modules.len() {
+            matrix.push_str(&format!("{column:>cell$}"));
+        }
+        matrix.push('\n');
+        for (row, from) in modules.iter().enumerate() {
+            matrix.push_str(&format!("{:>number$} {from:width$}", row + 1));
+            for to in &modules {
+                let value = match self.count(from, to) {
+                    0 => ".".to_string(),
+                    count if self.is_unexpected(from, to) => format!("{count}!"),
+                    count => count.to_string(),
+                };
+                matrix.push_str(&format!("{value:>cell$}"));
+            }
+            matrix.push('\n');
+        }
+        matrix
+    }
+}
+
+/// The dependencies as an adjacency list, with the unexpected ones marked
+impl fmt::Display for ModuleDependencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (from, to, count) in self.dependencies() {
+            let unexpected = if self.is_unexpected(from, to) {
+                " (unexpected)"
+            } else {
+                ""
+            };
+            writeln!(f, "{from} -> {to}: {count}{unexpected}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ActorGenerator {
+    /// Dependencies between the modules generation writes, from the `Uses` edges of the graph
+    pub fn module_dependencies(&self) -> Result<ModuleDependencies, RenderError> {
+        let actor_mod = self.actor_module();
+        let graph = self.graph().rust_graph();
+        let modules = self
+            .plan()?
+            .into_iter()
+            .filter_map(|plan| {
+                let part = plan
+                    .module
+                    .strip_prefix(&format!("{actor_mod}::"))?
+                    .split("::")
+                    .next()?
+                    .to_string();
+                Some((plan.module, part))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut uses = BTreeMap::<_, BTreeSet<_>>::new();
+        for from in modules.keys() {
+            let Some(index) = graph.find_module_by_path_hierarchical(from) else {
+                continue;
+            };
+            for entry in graph.find_connected_nodes(index) {
+                if entry.relation != Relation::Uses || matches!(entry.node, Node::Module(_)) {
+                    continue;
+                }
+                let item = self.node_path(entry.index);
+                let Some((to, _)) = item.rsplit_once("::") else {
+                    continue;
+                };
+                if to != from && modules.contains_key(to) {
+                    uses.entry((from.clone(), to.to_string()))
+                        .or_default()
+                        .insert(item);
+                }
+            }
+        }
+        Ok(ModuleDependencies { modules, uses })
+    }
+
+    /// Errors for the dependencies between generated modules the options' layering rules
+    /// forbid
+    pub fn layering_violations(&self) -> Result<Vec<Diagnostic>, RenderError> {
+        let rules = &self.options().layering;
+        if rules.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self.module_dependencies()?.violations(rules))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LayeringRules;
+    use crate::create::{ActorGenerator, GeneratorOptions};
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_module_dependencies() {
+        let mut generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let deps = generator.module_dependencies().unwrap();
+        assert!(deps.count("actor::states", "actor::messaging") > 0);
+        assert!(deps.count("actor::component", "actor::ext_state") > 0);
+        assert_eq!(deps.unexpected().count(), 0);
+
+        generator
+            .graph_mut()
+            .add_dependency_by_path("actor::states", "crate::actor::runtime::ActorRuntime");
+        let deps = generator.module_dependencies().unwrap();
+        assert_eq!(
+            deps.unexpected().collect::<Vec<_>>(),
+            vec![("actor::states", "actor::runtime", 1)]
+        );
+        assert!(
+            deps.to_string()
+                .contains("actor::states -> actor::runtime: 1 (unexpected)\n")
+        );
+        let matrix = deps.matrix();
+        let row = matrix
+            .lines()
+            .find(|line| line.contains("actor::states "))
+            .unwrap();
+        assert!(row.contains("1!"), "{matrix}");
+    }
+
+    #[test]
+    fn test_layering_violations() {
+        let rules = LayeringRules::default()
+            .forbid("states", "runtime")
+            .forbid("messaging", "component");
+        let options = GeneratorOptions::builder().layering(rules).build().unwrap();
+        let mut generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        assert!(generator.layering_violations().unwrap().is_empty());
+
+        generator
+            .graph_mut()
+            .add_dependency_by_path("actor::states", "crate::actor::runtime::ActorRuntime");
+        let violations = generator.layering_violations().unwrap();
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].is_error());
+        assert_eq!(
+            violations[0].to_string(),
+            "error[layering]: states must not depend on runtime, but actor::states uses actor::runtime::ActorRuntime"
+        );
+        assert!(generator.diagnostics().contains(&violations[0]));
+    }
+}
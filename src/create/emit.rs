@@ -0,0 +1,267 @@
+//! Emitters rendering the spec into languages and schemas other than Rust, used by
+//! `bloxml emit`
+
+mod asyncapi;
+mod protobuf;
+mod typescript;
+
+use std::collections::BTreeSet;
+
+use crate::blox::enums::EnumDef;
+use crate::create::{ActorGenerator, EmitConfig, RenderCtx, RenderError};
+pub(crate) use crate::ident::snake_case;
+
+pub use asyncapi::AsyncApiEmitter;
+pub use protobuf::{FieldNumbering, ProtobufEmitter};
+pub use typescript::TypeScriptEmitter;
+
+/// Renders an actor's spec, and the graph built from it, into another language or schema
+pub trait Emitter {
+    /// Name the emitter is selected by, like `typescript`
+    fn name(&self) -> &'static str;
+
+    /// Name of the file the output is written to
+    fn file_name(&self, ctx: &RenderCtx) -> String;
+
+    /// Renders the output for the actor
+    fn emit(&self, ctx: &RenderCtx) -> Result<String, RenderError>;
+}
+
+/// Every emitter that ships with bloxml, configured by `config`
+pub fn emitters(config: &EmitConfig) -> Vec<Box<dyn Emitter>> {
+    vec![
+        Box::new(TypeScriptEmitter),
+        Box::new(ProtobufEmitter::new(
+            config.package.clone(),
+            config.numbering.unwrap_or_default(),
+        )),
+        Box::new(match &config.version {
+            Some(version) => AsyncApiEmitter::new(version),
+            None => AsyncApiEmitter::default(),
+        }),
+    ]
+}
+
+/// Finds the built-in emitter called `name`
+pub fn emitter(name: &str, config: &EmitConfig) -> Result<Box<dyn Emitter>, String> {
+    let emitters = emitters(config);
+    let names = emitters
+        .iter()
+        .map(|e| e.name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    emitters
+        .into_iter()
+        .find(|e| e.name() == name)
+        .ok_or_else(|| format!("Unknown emitter '{name}', expected one of: {names}"))
+}
+
+impl ActorGenerator {
+    /// Renders the actor with `emitter`, returning the file name and its contents
+    pub fn emit(&self, emitter: &dyn Emitter) -> Result<(String, String), RenderError> {
+        let ctx = self.ctx();
+        Ok((emitter.file_name(&ctx), emitter.emit(&ctx)?))
+    }
+}
+
+/// The message set and its custom types, which emitters describe as the actor's payloads
+pub(crate) fn payload_enums<'a>(ctx: &RenderCtx<'a>) -> Vec<&'a EnumDef> {
+    ctx.actor
+        .component
+        .message_set
+        .iter()
+        .flat_map(|ms| ms.custom_types.iter().chain([&ms.def]))
+        .collect()
+}
+
+/// Types the payloads mention that the spec doesn't define, so other languages only know
+/// them by name
+pub(crate) fn external_types(ctx: &RenderCtx) -> BTreeSet<String> {
+    let enums = payload_enums(ctx);
+    let defined = enums
+        .iter()
+        .map(|e| e.ident.as_str())
+        .collect::<BTreeSet<_>>();
+    let mut external = BTreeSet::new();
+    for arg in enums.iter().flat_map(|e| &e.variants).flat_map(|v| &v.args) {
+        RustType::parse(arg.as_ref()).visit_named(&mut |name| {
+            if !defined.contains(name) && !is_builtin(name) {
+                external.insert(name.to_string());
+            }
+        });
+    }
+    external
+}
+
+/// Names of the Rust types emitters map to a type of their own
+fn is_builtin(name: &str) -> bool {
+    matches!(
+        name,
+        "bool"
+            | "char"
+            | "str"
+            | "String"
+            | "Vec"
+            | "VecDeque"
+            | "Option"
+            | "Box"
+            | "Arc"
+            | "Rc"
+            | "HashMap"
+            | "BTreeMap"
+            | "HashSet"
+            | "BTreeSet"
+    ) || RustType::is_number(name)
+}
+
+/// A type from the spec, parsed far enough for other languages to describe it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RustType {
+    /// `()`
+    Unit,
+    /// A path with its generic arguments, named by its last segment
+    Named(String, Vec<RustType>),
+    /// `(A, B)`
+    Tuple(Vec<RustType>),
+    /// `[T]` or `[T; N]`
+    Slice(Box<RustType>),
+}
+
+impl RustType {
+    pub(crate) fn parse(ty: &str) -> Self {
+        let ty = ty.trim().trim_start_matches('&').trim_start();
+        let ty = match ty.strip_prefix('\'') {
+            Some(lifetime) => lifetime.split_once(' ').map_or(lifetime, |(_, ty)| ty),
+            None => ty,
+        };
+        let ty = ty.strip_prefix("mut ").unwrap_or(ty).trim();
+        if let Some(inner) = ty.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+            let items = split_top_level(inner);
+            return if items.is_empty() {
+                RustType::Unit
+            } else {
+                RustType::Tuple(items.into_iter().map(RustType::parse).collect())
+            };
+        }
+        if let Some(inner) = ty.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            let element = split_top_level_on(inner, ';')
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            return RustType::Slice(Box::new(RustType::parse(element)));
+        }
+        let (path, args) = match ty.split_once('<') {
+            Some((path, rest)) => (
+                path,
+                split_top_level(rest.strip_suffix('>').unwrap_or(rest))
+                    .into_iter()
+                    .filter(|arg| !arg.starts_with('\''))
+                    .map(RustType::parse)
+                    .collect(),
+            ),
+            None => (ty, Vec::new()),
+        };
+        let name = path.rsplit("::").next().unwrap_or(path).trim();
+        RustType::Named(name.to_string(), args)
+    }
+
+    pub(crate) fn is_number(name: &str) -> bool {
+        matches!(
+            name,
+            "u8" | "u16"
+                | "u32"
+                | "u64"
+                | "u128"
+                | "usize"
+                | "i8"
+                | "i16"
+                | "i32"
+                | "i64"
+                | "i128"
+                | "isize"
+                | "f32"
+                | "f64"
+        )
+    }
+
+    /// Calls `f` with the name of every named type, including generic arguments
+    pub(crate) fn visit_named(&self, f: &mut impl FnMut(&str)) {
+        match self {
+            RustType::Unit => {}
+            RustType::Named(name, args) => {
+                f(name);
+                args.iter().for_each(|arg| arg.visit_named(f));
+            }
+            RustType::Tuple(items) => items.iter().for_each(|item| item.visit_named(f)),
+            RustType::Slice(element) => element.visit_named(f),
+        }
+    }
+}
+
+fn split_top_level(s: &str) -> Vec<&str> {
+    split_top_level_on(s, ',')
+}
+
+/// Splits on `separator` outside of `<>`, `()` and `[]`, dropping empty items
+fn split_top_level_on(s: &str, separator: char) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                items.push(s[start..i].trim());
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    items.push(s[start..].trim());
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rust_type() {
+        assert_eq!(
+            RustType::parse("std::collections::HashMap<String, Vec<(u8, a::Foo)>>"),
+            RustType::Named(
+                "HashMap".to_string(),
+                vec![
+                    RustType::Named("String".to_string(), vec![]),
+                    RustType::Named(
+                        "Vec".to_string(),
+                        vec![RustType::Tuple(vec![
+                            RustType::Named("u8".to_string(), vec![]),
+                            RustType::Named("Foo".to_string(), vec![]),
+                        ])]
+                    ),
+                ]
+            )
+        );
+        assert_eq!(
+            RustType::parse("&'a [u8; 4]"),
+            RustType::Slice(Box::new(RustType::Named("u8".to_string(), vec![])))
+        );
+        assert_eq!(RustType::parse("()"), RustType::Unit);
+    }
+
+    #[test]
+    fn test_emitter_lookup() {
+        let config = EmitConfig::default();
+        assert_eq!(emitter("typescript", &config).unwrap().name(), "typescript");
+        assert_eq!(
+            emitter("cobol", &config).err(),
+            Some(
+                "Unknown emitter 'cobol', expected one of: typescript, protobuf, asyncapi"
+                    .to_string()
+            )
+        );
+    }
+}
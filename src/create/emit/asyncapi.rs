@@ -0,0 +1,392 @@
+use serde_json::{Map, Value, json};
+
+use crate::blox::actor::{HEALTH_RECEIVER, SELF_RECEIVER};
+use crate::blox::enums::{EnumDef, EnumVariant};
+use crate::blox::message_handlers::WireFormat;
+use crate::create::emit::{Emitter, RustType, external_types, payload_enums};
+use crate::create::{RenderCtx, RenderError};
+
+/// Version of the AsyncAPI specification documents are written against
+const ASYNCAPI_VERSION: &str = "3.0.0";
+
+/// Emits an AsyncAPI document (YAML) of the actor's channels, the messages on them and the
+/// operations the actor performs, for registering it in an API catalog
+///
+/// Receivers become channels the actor receives on and handles channels it sends on. Sinks
+/// become channels addressed by their topic. In-process channels have no address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsyncApiEmitter {
+    /// Version of the actor's API in the document's `info`
+    pub version: String,
+}
+
+impl AsyncApiEmitter {
+    pub fn new(version: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+        }
+    }
+}
+
+impl Default for AsyncApiEmitter {
+    fn default() -> Self {
+        Self::new("1.0.0")
+    }
+}
+
+impl Emitter for AsyncApiEmitter {
+    fn name(&self) -> &'static str {
+        "asyncapi"
+    }
+
+    fn file_name(&self, ctx: &RenderCtx) -> String {
+        format!("{}.asyncapi.yaml", ctx.actor.ident.to_lowercase())
+    }
+
+    fn emit(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
+        let actor = ctx.actor;
+        let component = &actor.component;
+        let content_type = content_type(ctx.options.wire_format);
+
+        let mut channels = Map::new();
+        let mut operations = Map::new();
+        let mut messages = Map::new();
+        let mut add_channel = |id: &str,
+                               address: Option<&str>,
+                               action: &str,
+                               message: &str,
+                               payload: Value,
+                               description: String| {
+            messages.insert(
+                message.to_string(),
+                json!({
+                    "name": message,
+                    "contentType": content_type,
+                    "payload": payload,
+                }),
+            );
+            channels.insert(
+                id.to_string(),
+                json!({
+                    "address": address,
+                    "description": description,
+                    "messages": {
+                        message: { "$ref": format!("#/components/messages/{message}") }
+                    },
+                }),
+            );
+            operations.insert(
+                format!("{action}_{id}"),
+                json!({
+                    "action": action,
+                    "channel": { "$ref": format!("#/channels/{id}") },
+                    "messages": [{ "$ref": format!("#/channels/{id}/messages/{message}") }],
+                }),
+            );
+        };
+
+        // The runtime dispatches each receiver as the message set variant in the same position
+        let receivers = component
+            .message_receivers
+            .receivers
+            .iter()
+            .filter(|r| r.ident != HEALTH_RECEIVER && r.ident != SELF_RECEIVER);
+        let variants = component
+            .message_set
+            .iter()
+            .flat_map(|ms| &ms.def.variants)
+            .map(Some)
+            .chain(std::iter::repeat(None));
+        for (receiver, variant) in receivers.zip(variants) {
+            let (message, payload) = match variant {
+                Some(variant) => (variant.ident.clone(), variant_payload(variant)),
+                None => (
+                    receiver.message_type.clone(),
+                    schema(&RustType::parse(&receiver.message_type)),
+                ),
+            };
+            let source = receiver.source.as_ref();
+            add_channel(
+                &receiver.ident,
+                source.map(|s| s.subject.as_str()),
+                "receive",
+                &message,
+                payload,
+                match source {
+                    Some(source) => format!(
+                        "Forwarded from {} into the actor's `{}` receiver",
+                        source.broker.feature(),
+                        receiver.ident
+                    ),
+                    None => format!("The actor's `{}` receiver", receiver.ident),
+                },
+            );
+        }
+        for handle in &component.message_handles.handles {
+            add_channel(
+                &handle.ident,
+                None,
+                "send",
+                &handle.message_type,
+                schema(&RustType::parse(&handle.message_type)),
+                format!("Messages the actor sends through `{}`", handle.ident),
+            );
+        }
+        for sink in &actor.sinks {
+            let variant = component
+                .message_set
+                .iter()
+                .flat_map(|ms| &ms.def.variants)
+                .find(|v| v.ident == sink.variant);
+            add_channel(
+                &format!("{}_sink", sink.variant.to_lowercase()),
+                Some(&sink.topic),
+                "send",
+                &sink.variant,
+                variant.map_or(json!({}), variant_payload),
+                format!("Kafka topic `{}` payloads are published to", sink.variant),
+            );
+        }
+
+        let mut schemas = Map::new();
+        for name in external_types(ctx) {
+            schemas.insert(name, json!({ "description": "Defined outside the spec" }));
+        }
+        for def in payload_enums(ctx) {
+            schemas.insert(def.ident.clone(), enum_schema(def));
+        }
+
+        let description = format!(
+            "Channels of the {} actor, generated by bloxml from its spec",
+            actor.ident
+        );
+        // Lead with the version and info, like hand-written documents do
+        let sections = [
+            ("asyncapi", json!(ASYNCAPI_VERSION)),
+            (
+                "info",
+                json!({
+                    "title": actor.ident,
+                    "version": self.version,
+                    "description": description,
+                }),
+            ),
+            ("channels", Value::Object(channels)),
+            ("operations", Value::Object(operations)),
+            (
+                "components",
+                json!({ "messages": messages, "schemas": schemas }),
+            ),
+        ];
+        let mut yaml = String::new();
+        for (key, value) in sections {
+            yaml.push_str(&to_yaml(&json!({ key: value }), 0));
+        }
+        Ok(yaml)
+    }
+}
+
+/// MIME type of payloads encoded in the generator's wire format
+fn content_type(format: Option<WireFormat>) -> &'static str {
+    match format {
+        Some(WireFormat::Json) | None => "application/json",
+        Some(WireFormat::Cbor) => "application/cbor",
+        Some(WireFormat::Postcard) => "application/octet-stream",
+    }
+}
+
+/// Schema of a variant's arguments: the argument itself, or a tuple of them
+fn variant_payload(variant: &EnumVariant) -> Value {
+    let args = variant
+        .args
+        .iter()
+        .map(|arg| schema(&RustType::parse(arg.as_ref())))
+        .collect::<Vec<_>>();
+    match <[Value; 1]>::try_from(args) {
+        Ok([arg]) => arg,
+        Err(args) if args.is_empty() => json!({ "type": "null" }),
+        Err(args) => tuple_schema(args),
+    }
+}
+
+/// Schema of an enum the way serde tags it by default
+fn enum_schema(def: &EnumDef) -> Value {
+    let variants = def
+        .variants
+        .iter()
+        .map(|variant| {
+            let mut schema = if variant.args.is_empty() {
+                json!({ "const": variant.ident })
+            } else {
+                json!({
+                    "type": "object",
+                    "properties": { &variant.ident: variant_payload(variant) },
+                    "required": [variant.ident],
+                    "additionalProperties": false,
+                })
+            };
+            if let Some(doc) = &variant.doc {
+                schema["description"] = json!(doc);
+            }
+            if variant.deprecated.is_some() {
+                schema["deprecated"] = json!(true);
+            }
+            schema
+        })
+        .collect::<Vec<_>>();
+    json!({ "oneOf": variants })
+}
+
+fn tuple_schema(items: Vec<Value>) -> Value {
+    json!({
+        "type": "array",
+        "minItems": items.len(),
+        "maxItems": items.len(),
+        "items": items,
+    })
+}
+
+/// JSON Schema of the value serde_json encodes a Rust type as
+fn schema(ty: &RustType) -> Value {
+    match ty {
+        RustType::Unit => json!({ "type": "null" }),
+        RustType::Tuple(items) => tuple_schema(items.iter().map(schema).collect()),
+        RustType::Slice(element) => json!({ "type": "array", "items": schema(element) }),
+        RustType::Named(name, args) => match (name.as_str(), args.as_slice()) {
+            (name, _) if name.starts_with('f') && RustType::is_number(name) => {
+                json!({ "type": "number" })
+            }
+            (name, _) if RustType::is_number(name) => json!({ "type": "integer" }),
+            ("bool", _) => json!({ "type": "boolean" }),
+            ("String" | "str" | "char", _) => json!({ "type": "string" }),
+            ("Vec" | "VecDeque", [element]) => json!({ "type": "array", "items": schema(element) }),
+            ("HashSet" | "BTreeSet", [element]) => {
+                json!({ "type": "array", "items": schema(element), "uniqueItems": true })
+            }
+            ("Option", [inner]) => json!({ "oneOf": [schema(inner), { "type": "null" }] }),
+            ("Box" | "Arc" | "Rc", [inner]) => schema(inner),
+            ("HashMap" | "BTreeMap", [_, value]) => {
+                json!({ "type": "object", "additionalProperties": schema(value) })
+            }
+            (name, _) => json!({ "$ref": format!("#/components/schemas/{name}") }),
+        },
+    }
+}
+
+/// Writes a JSON value as block-style YAML
+fn to_yaml(value: &Value, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| match value {
+                Value::Object(inner) if !inner.is_empty() => {
+                    format!("{pad}{}:\n{}", scalar(key), to_yaml(value, indent + 2))
+                }
+                Value::Array(items) if !items.is_empty() => {
+                    format!("{pad}{}:\n{}", scalar(key), to_yaml(value, indent + 2))
+                }
+                value => format!("{pad}{}: {}\n", scalar(key), inline(value)),
+            })
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Object(inner) if !inner.is_empty() => {
+                    // The first key shares the line with the dash
+                    let nested = to_yaml(item, indent + 2);
+                    format!("{pad}- {}", &nested[indent + 2..])
+                }
+                Value::Array(inner) if !inner.is_empty() => {
+                    format!("{pad}-\n{}", to_yaml(item, indent + 2))
+                }
+                item => format!("{pad}- {}\n", inline(item)),
+            })
+            .collect(),
+        value => format!("{pad}{}\n", inline(value)),
+    }
+}
+
+/// A scalar, or an empty collection, written on the line of its key
+fn inline(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => scalar(s),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+    }
+}
+
+/// A string, quoted unless YAML would read it back as the same plain string
+fn scalar(s: &str) -> String {
+    let plain = s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '/' | '-' | ' '))
+        && !s.ends_with(' ')
+        && !matches!(
+            s.to_ascii_lowercase().as_str(),
+            "true" | "false" | "null" | "yes" | "no" | "on" | "off" | "y" | "n"
+        );
+    if plain {
+        s.to_string()
+    } else {
+        Value::String(s.to_string()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blox::message_handlers::{Broker, ExternalSource};
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_asyncapi_emitter() {
+        let mut actor = create_test_actor();
+        actor.component.message_receivers.receivers[0].source =
+            Some(ExternalSource::new(Broker::Nats, "orders.created"));
+        let message_set = actor.component.message_set.as_mut().unwrap();
+        message_set.custom_types.push(EnumDef::new(
+            "CustomArgs",
+            vec![
+                EnumVariant::new("Stop", vec![]).with_doc("Stops the actor"),
+                EnumVariant::new("Resize", vec![crate::Link::new("Vec<u32>")]),
+            ],
+        ));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let (file, yaml) = generator.emit(&AsyncApiEmitter::new("2.1.0")).unwrap();
+
+        assert_eq!(file, "actor.asyncapi.yaml");
+        assert!(yaml.starts_with("asyncapi: \"3.0.0\"\ninfo:\n"));
+        assert!(yaml.contains("  version: \"2.1.0\"\n"));
+        assert!(yaml.contains(
+            "  standard_rx:\n    address: orders.created\n    description: \"Forwarded from nats into the actor's `standard_rx` receiver\"\n    messages:\n      CustomValue1:\n        \"$ref\": \"#/components/messages/CustomValue1\"\n"
+        ));
+        assert!(yaml.contains("  customargs_handle:\n    address: null\n"));
+        assert!(yaml.contains(
+            "  send_customargs_handle:\n    action: send\n    channel:\n      \"$ref\": \"#/channels/customargs_handle\"\n    messages:\n      - \"$ref\": \"#/channels/customargs_handle/messages/CustomArgs\"\n"
+        ));
+        assert!(yaml.contains("  receive_standard_rx:\n    action: receive\n"));
+        assert!(yaml.contains(
+            "    CustomArgs:\n      oneOf:\n        - const: Stop\n          description: Stops the actor\n        - additionalProperties: false\n          properties:\n            Resize:\n              items:\n                type: integer\n              type: array\n          required:\n            - Resize\n          type: object\n"
+        ));
+        assert!(
+            yaml.contains("    StandardPayload:\n      description: Defined outside the spec\n")
+        );
+    }
+
+    #[test]
+    fn test_yaml_scalars() {
+        assert_eq!(scalar("orders.created"), "orders.created");
+        assert_eq!(scalar("yes"), "\"yes\"");
+        assert_eq!(scalar("1.0.0"), "\"1.0.0\"");
+        assert_eq!(scalar("a: b"), "\"a: b\"");
+        assert_eq!(
+            to_yaml(&json!({ "list": [1, { "a": [], "b": {} }] }), 0),
+            "list:\n  - 1\n  - a: []\n    b: {}\n"
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::blox::enums::{EnumDef, EnumVariant};
+use crate::create::emit::{Emitter, RustType, external_types, payload_enums, snake_case};
+use crate::create::{RenderCtx, RenderError};
+
+/// How the fields and enum values of the emitted schema are numbered
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldNumbering {
+    /// In declaration order, so inserting a variant renumbers the ones after it
+    #[default]
+    Sequential,
+    /// From a hash of the name, so numbers survive reordering and inserting variants
+    Hashed,
+}
+
+impl FieldNumbering {
+    /// Numbers `names`, failing if two hashed names collide
+    fn assign<'a>(
+        self,
+        scope: &str,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Vec<u32>, RenderError> {
+        let names = names.into_iter().collect::<Vec<_>>();
+        match self {
+            FieldNumbering::Sequential => Ok((1..).take(names.len()).collect()),
+            FieldNumbering::Hashed => {
+                let mut taken = BTreeMap::new();
+                names
+                    .iter()
+                    .map(|name| {
+                        let number = hashed_number(name);
+                        match taken.insert(number, *name) {
+                            Some(other) => Err(RenderError::InvalidSpec(format!(
+                                "`{other}` and `{name}` in {scope} hash to the same field number \
+                                 {number}; rename one or use sequential numbering"
+                            ))),
+                            None => Ok(number),
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Field number for `name` in the valid range, skipping the numbers protobuf reserves
+fn hashed_number(name: &str) -> u32 {
+    const MAX_FIELD: u32 = (1 << 29) - 1;
+    // FNV-1a, which stays the same across Rust releases unlike `DefaultHasher`
+    let hash = name.bytes().fold(0x811c_9dc5u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    let number = hash % MAX_FIELD + 1;
+    if (19_000..20_000).contains(&number) {
+        number + 1_000
+    } else {
+        number
+    }
+}
+
+impl FromStr for FieldNumbering {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sequential" => Ok(FieldNumbering::Sequential),
+            "hashed" => Ok(FieldNumbering::Hashed),
+            other => Err(format!(
+                "unknown field numbering '{other}', expected sequential or hashed"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FieldNumbering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FieldNumbering::Sequential => "sequential",
+            FieldNumbering::Hashed => "hashed",
+        })
+    }
+}
+
+/// Emits a proto3 schema of the message set and its custom types, for contracts between
+/// services
+///
+/// Enums with only unit variants become proto enums; the others become messages with a
+/// `oneof` over their variants. The `Message` envelope is left to the transport.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtobufEmitter {
+    /// Package of the schema, the actor's module name if unset
+    pub package: Option<String>,
+    pub numbering: FieldNumbering,
+}
+
+impl ProtobufEmitter {
+    pub fn new(package: Option<String>, numbering: FieldNumbering) -> Self {
+        Self { package, numbering }
+    }
+}
+
+impl Emitter for ProtobufEmitter {
+    fn name(&self) -> &'static str {
+        "protobuf"
+    }
+
+    fn file_name(&self, ctx: &RenderCtx) -> String {
+        format!("{}.proto", ctx.actor.ident.to_lowercase())
+    }
+
+    fn emit(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
+        let package = self
+            .package
+            .clone()
+            .unwrap_or_else(|| ctx.actor_module().replace("::", "."));
+        let mut definitions = String::new();
+        for name in external_types(ctx) {
+            definitions.push_str(&format!(
+                "\n// Defined outside the spec; fill in its fields\nmessage {name} {{}}\n"
+            ));
+        }
+        for def in payload_enums(ctx) {
+            definitions.push_str(&self.render_enum(def)?);
+        }
+
+        let imports = if definitions.contains("google.protobuf.Empty") {
+            "\nimport \"google/protobuf/empty.proto\";\n"
+        } else {
+            ""
+        };
+        Ok(format!(
+            "// Message payloads of the {} actor, generated by bloxml from its spec.\n\
+             syntax = \"proto3\";\n\npackage {package};\n{imports}{definitions}",
+            ctx.actor.ident
+        ))
+    }
+}
+
+impl ProtobufEmitter {
+    fn render_enum(&self, def: &EnumDef) -> Result<String, RenderError> {
+        if def.variants.iter().all(|variant| variant.args.is_empty()) {
+            return self.render_proto_enum(def);
+        }
+
+        let fields = def
+            .variants
+            .iter()
+            .map(|variant| snake_case(&variant.ident))
+            .collect::<Vec<_>>();
+        let numbers = self
+            .numbering
+            .assign(&def.ident, fields.iter().map(String::as_str))?;
+        let mut nested = String::new();
+        let mut oneof = String::new();
+        for ((variant, field), number) in def.variants.iter().zip(&fields).zip(numbers) {
+            let ty = match variant.args.as_slice() {
+                [] => "google.protobuf.Empty".to_string(),
+                [arg] => match proto_field(arg.as_ref())? {
+                    // `oneof` fields can't be repeated, maps or optional
+                    ProtoField::Single(ty) => ty,
+                    field => {
+                        nested.push_str(&self.render_variant_message(variant, &[field])?);
+                        variant.ident.clone()
+                    }
+                },
+                args => {
+                    let fields = args
+                        .iter()
+                        .map(|arg| proto_field(arg.as_ref()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    nested.push_str(&self.render_variant_message(variant, &fields)?);
+                    variant.ident.clone()
+                }
+            };
+            oneof.push_str(&format!(
+                "{}    {ty} {field} = {number}{};\n",
+                render_comment(variant, "    "),
+                deprecated_option(variant)
+            ));
+        }
+        Ok(format!(
+            "\nmessage {} {{\n{nested}  oneof value {{\n{oneof}  }}\n}}\n",
+            def.ident
+        ))
+    }
+
+    /// Nested message carrying the arguments of a variant as `field_0`, `field_1`, ...
+    fn render_variant_message(
+        &self,
+        variant: &EnumVariant,
+        fields: &[ProtoField],
+    ) -> Result<String, RenderError> {
+        let names = (0..fields.len())
+            .map(|i| format!("field_{i}"))
+            .collect::<Vec<_>>();
+        let numbers = self
+            .numbering
+            .assign(&variant.ident, names.iter().map(String::as_str))?;
+        let body = fields
+            .iter()
+            .zip(&names)
+            .zip(numbers)
+            .map(|((field, name), number)| format!("    {field} {name} = {number};\n"))
+            .collect::<String>();
+        Ok(format!("  message {} {{\n{body}  }}\n", variant.ident))
+    }
+
+    fn render_proto_enum(&self, def: &EnumDef) -> Result<String, RenderError> {
+        let prefix = snake_case(&def.ident).to_uppercase();
+        let values = def
+            .variants
+            .iter()
+            .map(|variant| format!("{prefix}_{}", snake_case(&variant.ident).to_uppercase()))
+            .collect::<Vec<_>>();
+        // proto3 enums default to their zero value, which no variant takes
+        let numbers = self
+            .numbering
+            .assign(&def.ident, values.iter().map(String::as_str))?;
+        let body = def
+            .variants
+            .iter()
+            .zip(&values)
+            .zip(numbers)
+            .map(|((variant, value), number)| {
+                format!(
+                    "{}  {value} = {number}{};\n",
+                    render_comment(variant, "  "),
+                    deprecated_option(variant)
+                )
+            })
+            .collect::<String>();
+        Ok(format!(
+            "\nenum {} {{\n  {prefix}_UNSPECIFIED = 0;\n{body}}}\n",
+            def.ident
+        ))
+    }
+}
+
+fn render_comment(variant: &EnumVariant, indent: &str) -> String {
+    variant
+        .doc
+        .as_deref()
+        .map(|doc| format!("{indent}// {doc}\n"))
+        .unwrap_or_default()
+}
+
+fn deprecated_option(variant: &EnumVariant) -> &'static str {
+    if variant.deprecated.is_some() {
+        " [deprecated = true]"
+    } else {
+        ""
+    }
+}
+
+/// A field's type along with its label
+enum ProtoField {
+    Single(String),
+    Optional(String),
+    Repeated(String),
+    Map(String, String),
+}
+
+impl fmt::Display for ProtoField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtoField::Single(ty) => write!(f, "{ty}"),
+            ProtoField::Optional(ty) => write!(f, "optional {ty}"),
+            ProtoField::Repeated(ty) => write!(f, "repeated {ty}"),
+            ProtoField::Map(key, value) => write!(f, "map<{key}, {value}>"),
+        }
+    }
+}
+
+/// Types protobuf allows as map keys
+const MAP_KEYS: &[&str] = &["int32", "int64", "uint32", "uint64", "bool", "string"];
+
+fn proto_field(arg: &str) -> Result<ProtoField, RenderError> {
+    let unsupported = || {
+        RenderError::InvalidSpec(format!(
+            "`{arg}` has no protobuf equivalent; wrap it in a custom type"
+        ))
+    };
+    let ty = RustType::parse(arg);
+    match &ty {
+        RustType::Slice(element) => match element.as_ref() {
+            RustType::Named(name, _) if name == "u8" => Ok(ProtoField::Single("bytes".to_string())),
+            element => Ok(ProtoField::Repeated(
+                proto_scalar(element).ok_or_else(unsupported)?,
+            )),
+        },
+        RustType::Named(name, args) => match (name.as_str(), args.as_slice()) {
+            ("Vec" | "VecDeque", [RustType::Named(element, _)]) if element == "u8" => {
+                Ok(ProtoField::Single("bytes".to_string()))
+            }
+            ("Vec" | "VecDeque" | "HashSet" | "BTreeSet", [element]) => Ok(ProtoField::Repeated(
+                proto_scalar(element).ok_or_else(unsupported)?,
+            )),
+            ("Option", [inner]) => Ok(ProtoField::Optional(
+                proto_scalar(inner).ok_or_else(unsupported)?,
+            )),
+            ("HashMap" | "BTreeMap", [key, value]) => Ok(ProtoField::Map(
+                proto_scalar(key)
+                    .filter(|key| MAP_KEYS.contains(&key.as_str()))
+                    .ok_or_else(unsupported)?,
+                proto_scalar(value).ok_or_else(unsupported)?,
+            )),
+            _ => proto_scalar(&ty)
+                .map(ProtoField::Single)
+                .ok_or_else(unsupported),
+        },
+        RustType::Unit | RustType::Tuple(_) => Err(unsupported()),
+    }
+}
+
+/// The proto type of a field holding one `ty`, or `None` if it needs a label
+fn proto_scalar(ty: &RustType) -> Option<String> {
+    let RustType::Named(name, args) = ty else {
+        return None;
+    };
+    let scalar = match (name.as_str(), args.as_slice()) {
+        ("u8" | "u16" | "u32", _) => "uint32",
+        ("u64" | "usize", _) => "uint64",
+        ("i8" | "i16" | "i32", _) => "int32",
+        ("i64" | "isize", _) => "int64",
+        ("f32", _) => "float",
+        ("f64", _) => "double",
+        ("bool", _) => "bool",
+        ("String" | "str" | "char", _) => "string",
+        ("Box" | "Arc" | "Rc", [inner]) => return proto_scalar(inner),
+        ("u128" | "i128" | "Vec" | "VecDeque" | "HashSet" | "BTreeSet" | "Option", _)
+        | ("HashMap" | "BTreeMap", _) => return None,
+        (name, []) => name,
+        _ => return None,
+    };
+    Some(scalar.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Link;
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_protobuf_emitter() {
+        let mut actor = create_test_actor();
+        let message_set = actor.component.message_set.as_mut().unwrap();
+        message_set.custom_types.extend([
+            EnumDef::new(
+                "Level",
+                vec![
+                    EnumVariant::new("Low", vec![]),
+                    EnumVariant::new("High", vec![]).deprecated("use Low"),
+                ],
+            ),
+            EnumDef::new(
+                "Command",
+                vec![
+                    EnumVariant::new("Stop", vec![]).with_doc("Stops the actor"),
+                    EnumVariant::new("Move", vec![Link::new("i32"), Link::new("Option<i64>")]),
+                    EnumVariant::new("Tags", vec![Link::new("Vec<String>")]),
+                    EnumVariant::new("Raw", vec![Link::new("Vec<u8>")]),
+                ],
+            ),
+        ]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let (file, proto) = generator
+            .emit(&ProtobufEmitter::new(
+                Some("acme.actor".to_string()),
+                FieldNumbering::Sequential,
+            ))
+            .unwrap();
+
+        assert_eq!(file, "actor.proto");
+        assert!(proto.contains("syntax = \"proto3\";\n\npackage acme.actor;\n\nimport \"google/protobuf/empty.proto\";\n"));
+        assert!(proto.contains("message CustomArgs {}"));
+        assert!(proto.contains(
+            "enum Level {\n  LEVEL_UNSPECIFIED = 0;\n  LEVEL_LOW = 1;\n  LEVEL_HIGH = 2 [deprecated = true];\n}"
+        ));
+        assert!(proto.contains(
+            "message Command {\n  message Move {\n    int32 field_0 = 1;\n    optional int64 field_1 = 2;\n  }\n  message Tags {\n    repeated string field_0 = 1;\n  }\n  oneof value {\n    // Stops the actor\n    google.protobuf.Empty stop = 1;\n    Move move = 2;\n    Tags tags = 3;\n    bytes raw = 4;\n  }\n}"
+        ));
+        assert!(proto.contains(
+            "message ActorMessageSet {\n  oneof value {\n    StandardPayload custom_value1 = 1;\n    CustomArgs custom_value2 = 2;\n  }\n}"
+        ));
+
+        // Hashed numbers don't depend on where a variant is declared
+        let hashed = ProtobufEmitter::new(None, FieldNumbering::Hashed);
+        let (_, proto) = generator.emit(&hashed).unwrap();
+        assert!(proto.contains("package actor;"));
+        assert!(proto.contains(&format!("Tags tags = {};", hashed_number("tags"))));
+    }
+
+    #[test]
+    fn test_unsupported_payload() {
+        let mut actor = create_test_actor();
+        let message_set = actor.component.message_set.as_mut().unwrap();
+        message_set.custom_types.push(EnumDef::new(
+            "Pair",
+            vec![EnumVariant::new("Both", vec![Link::new("(u8, u8)")])],
+        ));
+        let generator = ActorGenerator::new(actor).unwrap();
+        assert_eq!(
+            generator.emit(&ProtobufEmitter::default()),
+            Err(RenderError::InvalidSpec(
+                "`(u8, u8)` has no protobuf equivalent; wrap it in a custom type".to_string()
+            ))
+        );
+    }
+}
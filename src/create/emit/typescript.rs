@@ -0,0 +1,178 @@
+use crate::blox::enums::{EnumDef, EnumVariant};
+use crate::create::emit::{Emitter, RustType, external_types, payload_enums};
+use crate::create::{RenderCtx, RenderError};
+
+/// Emits TypeScript definitions (`.d.ts`) of the message set and its custom types, in the
+/// shape serde gives them on the wire
+pub struct TypeScriptEmitter;
+
+impl Emitter for TypeScriptEmitter {
+    fn name(&self) -> &'static str {
+        "typescript"
+    }
+
+    fn file_name(&self, ctx: &RenderCtx) -> String {
+        format!("{}.d.ts", ctx.actor.ident.to_lowercase())
+    }
+
+    fn emit(&self, ctx: &RenderCtx) -> Result<String, RenderError> {
+        let mut out = format!(
+            "// Message payloads of the {} actor, generated by bloxml from its spec.\n",
+            ctx.actor.ident
+        );
+
+        for name in external_types(ctx) {
+            out.push_str(&format!(
+                "\n/** Defined outside the spec */\nexport type {name} = unknown;\n"
+            ));
+        }
+        let Some(message_set) = &ctx.actor.component.message_set else {
+            return Ok(out);
+        };
+        out.push_str(
+            r#"
+/** Envelope every message travels in, mirroring bloxide's `Message` */
+export interface Message<T> {
+  id: number;
+  payload: T;
+}
+"#,
+        );
+        for def in payload_enums(ctx) {
+            let envelope = std::ptr::eq(def, &message_set.def);
+            out.push_str(&render_enum(def, envelope));
+        }
+        Ok(out)
+    }
+}
+
+/// Renders an enum the way serde tags it by default: unit variants as their name, the
+/// others as an object keyed by the variant's name
+fn render_enum(def: &EnumDef, envelope: bool) -> String {
+    let variants = def
+        .variants
+        .iter()
+        .map(|variant| {
+            format!(
+                "{}  | {}",
+                render_doc(variant),
+                render_variant(variant, envelope)
+            )
+        })
+        .collect::<Vec<_>>();
+    let body = if variants.is_empty() {
+        " never".to_string()
+    } else {
+        format!("\n{}", variants.join("\n"))
+    };
+    format!("\nexport type {} ={body};\n", def.ident)
+}
+
+fn render_doc(variant: &EnumVariant) -> String {
+    let mut tags = variant.doc.iter().cloned().collect::<Vec<_>>();
+    if let Some(note) = &variant.deprecated {
+        tags.push(format!("@deprecated {note}"));
+    }
+    if tags.is_empty() {
+        String::new()
+    } else {
+        format!("  /** {} */\n", tags.join(" "))
+    }
+}
+
+fn render_variant(variant: &EnumVariant, envelope: bool) -> String {
+    let args = variant
+        .args
+        .iter()
+        .map(|arg| {
+            let ty = ts_type(&RustType::parse(arg.as_ref()));
+            if envelope {
+                format!("Message<{ty}>")
+            } else {
+                ty
+            }
+        })
+        .collect::<Vec<_>>();
+    match args.as_slice() {
+        [] => format!("{:?}", variant.ident),
+        [arg] => format!("{{ {}: {arg} }}", variant.ident),
+        args => format!("{{ {}: [{}] }}", variant.ident, args.join(", ")),
+    }
+}
+
+/// The TypeScript type serde_json encodes a Rust type as
+fn ts_type(ty: &RustType) -> String {
+    match ty {
+        RustType::Unit => "null".to_string(),
+        RustType::Tuple(items) => format!(
+            "[{}]",
+            items.iter().map(ts_type).collect::<Vec<_>>().join(", ")
+        ),
+        RustType::Slice(element) => array_of(element),
+        RustType::Named(name, args) => match (name.as_str(), args.as_slice()) {
+            (name, _) if RustType::is_number(name) => "number".to_string(),
+            ("bool", _) => "boolean".to_string(),
+            ("String" | "str" | "char", _) => "string".to_string(),
+            ("Vec" | "VecDeque" | "HashSet" | "BTreeSet", [element]) => array_of(element),
+            ("Option", [inner]) => format!("{} | null", ts_type(inner)),
+            ("Box" | "Arc" | "Rc", [inner]) => ts_type(inner),
+            ("HashMap" | "BTreeMap", [_, value]) => format!("Record<string, {}>", ts_type(value)),
+            (name, []) => name.to_string(),
+            (name, args) => format!(
+                "{name}<{}>",
+                args.iter().map(ts_type).collect::<Vec<_>>().join(", ")
+            ),
+        },
+    }
+}
+
+fn array_of(element: &RustType) -> String {
+    let element = ts_type(element);
+    if element.contains(' ') {
+        format!("({element})[]")
+    } else {
+        format!("{element}[]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Link;
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_typescript_emitter() {
+        let mut actor = create_test_actor();
+        let message_set = actor.component.message_set.as_mut().unwrap();
+        message_set.custom_types.push(EnumDef::new(
+            "Command",
+            vec![
+                EnumVariant::new("Stop", vec![]),
+                EnumVariant::new("Move", vec![Link::new("(i32, i32)")]),
+                EnumVariant::new(
+                    "Tag",
+                    vec![
+                        Link::new("Vec<Option<String>>"),
+                        Link::new("std::collections::HashMap<String, u64>"),
+                    ],
+                )
+                .with_doc("Labels the actor")
+                .deprecated("use Move"),
+            ],
+        ));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let (file, ts) = generator.emit(&TypeScriptEmitter).unwrap();
+
+        assert_eq!(file, "actor.d.ts");
+        assert!(ts.contains("export type CustomArgs = unknown;"));
+        assert!(ts.contains("export type StandardPayload = unknown;"));
+        assert!(ts.contains(
+            "export type Command =\n  | \"Stop\"\n  | { Move: [number, number] }\n  /** Labels the actor @deprecated use Move */\n  | { Tag: [(string | null)[], Record<string, number>] };\n"
+        ));
+        assert!(ts.contains(
+            "export type ActorMessageSet =\n  | { CustomValue1: Message<StandardPayload> }\n  | { CustomValue2: Message<CustomArgs> };\n"
+        ));
+    }
+}
@@ -1,19 +1,198 @@
 use crate::blox::actor::Actor;
-use crate::create::ActorGenerator;
+use crate::create::{ActorGenerator, GeneratorOptions, LineEndings};
 use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 
 /// Creates the actor module
 pub fn create_module(actor: Actor) -> Result<(), Box<dyn Error>> {
-    let mut generator = ActorGenerator::new(actor)?;
+    let generator = ActorGenerator::new(actor)?;
     generator.generate_all_files()
 }
 
+/// Adds `pub mod {module};` to the `mod.rs` in `dir` unless it is already declared
+pub(crate) fn declare_module(
+    dir: &Path,
+    module: &str,
+    line_endings: LineEndings,
+) -> Result<(), Box<dyn Error>> {
+    let mod_rs = dir.join("mod.rs");
+    let declaration = format!("pub mod {module};");
+    let existing = if mod_rs.exists() {
+        fs::read_to_string(&mod_rs)?
+    } else {
+        String::new()
+    };
+    if existing.lines().any(|line| line.trim() == declaration) {
+        return Ok(());
+    }
+
+    let separator = if existing.is_empty() || existing.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    let content = format!("{existing}{separator}{declaration}\n");
+    fs::write(&mod_rs, line_endings.apply(&content).as_bytes())?;
+    Ok(())
+}
+
+/// Sibling of `target` used while writing, so the final rename stays on one filesystem
+fn sibling_path(target: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(target.file_name().unwrap_or_default());
+    name.push(suffix);
+    target.with_file_name(name)
+}
+
+fn remove_dir_if_exists(path: &Path) -> std::io::Result<()> {
+    if path.exists() {
+        fs::remove_dir_all(path)?;
+    }
+    Ok(())
+}
+
+/// Paths of all files below `dir`, relative to it
+fn files_below(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Applies the overwrite options to the staged files that would replace ones in `target`
+///
+/// Files in `target` the run doesn't write are left alone, so neither option applies to them.
+fn protect_existing(
+    staging: &Path,
+    target: &Path,
+    options: &GeneratorOptions,
+) -> Result<(), Box<dyn Error>> {
+    if !options.backup && !options.no_overwrite {
+        return Ok(());
+    }
+
+    let mut changed = Vec::new();
+    for relative in files_below(staging)? {
+        let existing = target.join(&relative);
+        if existing.is_file() && fs::read(&existing)? != fs::read(staging.join(&relative))? {
+            changed.push(relative);
+        }
+    }
+
+    if options.no_overwrite && !changed.is_empty() {
+        let files = changed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(format!(
+            "Refusing to overwrite modified files in {}: {files}",
+            target.display()
+        )
+        .into());
+    }
+
+    for relative in changed {
+        let mut backup_name = relative.clone().into_os_string();
+        backup_name.push(".bak");
+        fs::copy(target.join(&relative), staging.join(backup_name))?;
+    }
+    Ok(())
+}
+
+/// Copies the files in `target` the run didn't write into `staging`, so swapping it in
+/// keeps hand-written modules and earlier backups
+fn keep_unmanaged(staging: &Path, target: &Path) -> Result<(), Box<dyn Error>> {
+    if !target.is_dir() {
+        return Ok(());
+    }
+    for relative in files_below(target)? {
+        let staged = staging.join(&relative);
+        if staged.exists() {
+            continue;
+        }
+        if let Some(parent) = staged.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(target.join(&relative), staged)?;
+    }
+    Ok(())
+}
+
+/// Runs `write` against a staging directory and moves it over `target` only on success.
+///
+/// Files in `target` the run doesn't write are carried over, so only generated files are
+/// replaced. An error or panic while writing removes the staging directory and leaves any
+/// previous `target` untouched. A previous `target` is restored if the final swap fails.
+pub(crate) fn write_atomically<F>(
+    target: &Path,
+    options: &GeneratorOptions,
+    write: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&Path) -> Result<(), Box<dyn Error>>,
+{
+    let staging = sibling_path(target, ".staging");
+    let backup = sibling_path(target, ".backup");
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    remove_dir_if_exists(&staging)?;
+    fs::create_dir_all(&staging)?;
+
+    let written = panic::catch_unwind(AssertUnwindSafe(|| write(&staging)))
+        .unwrap_or_else(|_| Err("Generation panicked while writing the module".into()))
+        .and_then(|()| protect_existing(&staging, target, options))
+        .and_then(|()| keep_unmanaged(&staging, target));
+    if let Err(e) = written {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    remove_dir_if_exists(&backup)?;
+    let had_previous = target.exists();
+    if had_previous {
+        fs::rename(target, &backup)?;
+    }
+
+    if let Err(e) = fs::rename(&staging, target) {
+        if had_previous {
+            let _ = fs::rename(&backup, target);
+        }
+        let _ = fs::remove_dir_all(&staging);
+        return Err(format!(
+            "Error moving generated module into {}: {e}",
+            target.display()
+        )
+        .into());
+    }
+
+    if had_previous {
+        fs::remove_dir_all(&backup)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
-    use super::create_module;
+    use super::*;
     use crate::tests::create_test_actor;
-    use std::path::Path;
 
     const TEST_PATH: &str = "tests/output";
 
@@ -25,4 +204,125 @@ mod tests {
         create_module(test_actor).expect("Failed to create module");
         assert!(path.join(ident).exists());
     }
+
+    #[test]
+    fn test_declare_module() {
+        let dir = Path::new(TEST_PATH).join("declare_module");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("mod.rs"), "pub mod handwritten;").unwrap();
+
+        declare_module(&dir, "actor", LineEndings::Crlf).unwrap();
+        declare_module(&dir, "actor", LineEndings::Crlf).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("mod.rs")).unwrap(),
+            "pub mod handwritten;\r\npub mod actor;\r\n"
+        );
+    }
+
+    #[test]
+    fn test_failed_write_keeps_previous_module() {
+        let target = Path::new(TEST_PATH).join("atomic_failure");
+        write_atomically(&target, &GeneratorOptions::default(), |dir| {
+            Ok(fs::write(dir.join("mod.rs"), "old")?)
+        })
+        .expect("Initial write should succeed");
+
+        let result = write_atomically(&target, &GeneratorOptions::default(), |dir| {
+            fs::write(dir.join("mod.rs"), "new")?;
+            Err("template failed".into())
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(target.join("mod.rs")).unwrap(), "old");
+        assert!(!sibling_path(&target, ".staging").exists());
+
+        let result = write_atomically(&target, &GeneratorOptions::default(), |dir| {
+            fs::write(dir.join("partial.rs"), "")?;
+            panic!("template panicked")
+        });
+        assert!(result.is_err());
+        assert!(!target.join("partial.rs").exists());
+    }
+
+    #[test]
+    fn test_successful_write_replaces_generated_files() {
+        let target = Path::new(TEST_PATH).join("atomic_success");
+        write_atomically(&target, &GeneratorOptions::default(), |dir| {
+            Ok(fs::write(dir.join("stale.rs"), "")?)
+        })
+        .expect("Initial write should succeed");
+        write_atomically(&target, &GeneratorOptions::default(), |dir| {
+            Ok(fs::write(dir.join("mod.rs"), "new")?)
+        })
+        .expect("Replacement should succeed");
+
+        assert_eq!(fs::read_to_string(target.join("mod.rs")).unwrap(), "new");
+        // Files the run didn't write aren't generation's to delete
+        assert!(target.join("stale.rs").exists());
+        assert!(!sibling_path(&target, ".backup").exists());
+    }
+
+    #[test]
+    fn test_no_overwrite_and_backup() {
+        let target = Path::new(TEST_PATH).join("atomic_protect");
+        let write_mod = |contents: &'static str| {
+            move |dir: &Path| -> Result<(), Box<dyn Error>> {
+                Ok(fs::write(dir.join("mod.rs"), contents)?)
+            }
+        };
+        write_atomically(&target, &GeneratorOptions::default(), write_mod("old"))
+            .expect("Initial write should succeed");
+
+        let no_overwrite = GeneratorOptions::default().with_no_overwrite(true);
+        write_atomically(&target, &no_overwrite, write_mod("old"))
+            .expect("Identical files are not overwrites");
+        let err = write_atomically(&target, &no_overwrite, write_mod("new")).unwrap_err();
+        assert!(err.to_string().contains("mod.rs"));
+        assert_eq!(fs::read_to_string(target.join("mod.rs")).unwrap(), "old");
+
+        let backup = GeneratorOptions::default().with_backup(true);
+        write_atomically(&target, &backup, write_mod("new")).expect("Backup write should succeed");
+        assert_eq!(fs::read_to_string(target.join("mod.rs")).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(target.join("mod.rs.bak")).unwrap(),
+            "old"
+        );
+    }
+
+    #[test]
+    fn test_protection_keeps_unmanaged_files() {
+        let target = Path::new(TEST_PATH).join("atomic_unmanaged");
+        let _ = fs::remove_dir_all(&target);
+        write_atomically(&target, &GeneratorOptions::default(), |dir| {
+            Ok(fs::write(dir.join("mod.rs"), "old")?)
+        })
+        .expect("Initial write should succeed");
+        fs::write(target.join("handwritten.rs"), "fn helper() {}").unwrap();
+        fs::write(target.join("states.rs.bak"), "older").unwrap();
+
+        let backup = GeneratorOptions::default().with_backup(true);
+        write_atomically(&target, &backup, |dir| {
+            Ok(fs::write(dir.join("mod.rs"), "new")?)
+        })
+        .expect("Backup write should succeed");
+        let no_overwrite = GeneratorOptions::default().with_no_overwrite(true);
+        write_atomically(&target, &no_overwrite, |dir| {
+            Ok(fs::write(dir.join("mod.rs"), "new")?)
+        })
+        .expect("Unmanaged files are not overwrites");
+
+        assert_eq!(fs::read_to_string(target.join("mod.rs")).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(target.join("mod.rs.bak")).unwrap(),
+            "old"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("handwritten.rs")).unwrap(),
+            "fn helper() {}"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("states.rs.bak")).unwrap(),
+            "older"
+        );
+    }
 }
@@ -0,0 +1,265 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::blox::message_handlers::WireFormat;
+use crate::create::RenderCtx;
+use crate::create::emit::snake_case;
+
+/// Directory of the `cargo-fuzz` crate, next to the `Cargo.toml` of the fuzzed crate
+pub const FUZZ_DIR: &str = "fuzz";
+
+/// The crate an actor module is generated into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateRoot {
+    /// Directory containing the crate's `Cargo.toml`
+    pub dir: PathBuf,
+    /// Package name, as the fuzz crate depends on it
+    pub package: String,
+}
+
+impl CrateRoot {
+    /// Finds the crate whose `Cargo.toml` is in `dir` or the nearest parent that has a package
+    pub fn find(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let start = std::path::absolute(dir)?;
+        for dir in start.ancestors() {
+            let manifest = dir.join("Cargo.toml");
+            let Ok(content) = fs::read_to_string(&manifest) else {
+                continue;
+            };
+            let manifest: toml::Table = toml::from_str(&content)
+                .map_err(|e| format!("Error in {}: {e}", manifest.display()))?;
+            let package = manifest
+                .get("package")
+                .and_then(|package| package.get("name"))
+                .and_then(toml::Value::as_str);
+            if let Some(package) = package {
+                return Ok(Self {
+                    dir: dir.to_path_buf(),
+                    package: package.to_string(),
+                });
+            }
+        }
+        Err(format!(
+            "Fuzz targets go next to the fuzzed crate, but no package contains {}",
+            start.display()
+        )
+        .into())
+    }
+
+    /// Name the crate's library is imported by
+    pub(crate) fn lib_name(&self) -> String {
+        self.package.replace('-', "_")
+    }
+}
+
+/// Render a `cargo-fuzz` target decoding untrusted bytes into each payload type the actor
+/// defines and into its whole message set through `from_wire`, keyed by path relative to the
+/// crate root
+///
+/// The fuzz `Cargo.toml` is rendered from `existing`, so targets of other actors sharing the
+/// fuzz crate are kept. Returns nothing without a wire format or a message set.
+pub fn render_fuzz_targets(
+    ctx: &RenderCtx,
+    krate: &CrateRoot,
+    existing: Option<&str>,
+) -> Vec<(PathBuf, String)> {
+    let (Some(format), Some(message_set)) =
+        (ctx.options.wire_format, &ctx.actor.component.message_set)
+    else {
+        return Vec::new();
+    };
+    let message_enum = &message_set.get().ident;
+    let messaging = format!("{}::{}::messaging", krate.lib_name(), ctx.actor_module());
+    let (_, decode) = format.decoder();
+    let actor = ctx.actor.ident.to_lowercase();
+
+    let payloads = message_set
+        .custom_types
+        .iter()
+        .filter(|custom| {
+            message_set.get().variants.iter().any(|variant| {
+                variant
+                    .args
+                    .iter()
+                    .any(|arg| arg.to_string() == custom.ident)
+            })
+        })
+        .map(|custom| {
+            (
+                format!("{actor}_{}", snake_case(&custom.ident)),
+                custom.ident.clone(),
+                format!("let _: Result<{}, _> = {decode};", custom.ident),
+            )
+        });
+    let targets = std::iter::once((
+        format!("{actor}_message_set"),
+        message_enum.clone(),
+        format!("let _ = {message_enum}::from_wire(bytes);"),
+    ))
+    .chain(payloads)
+    .map(|(name, ty, decode)| {
+        let content = format!(
+            r#"//! Fuzzes decoding {ty} from {format_name}
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use {messaging}::{ty};
+
+fuzz_target!(|bytes: &[u8]| {{
+    {decode}
+}});
+"#,
+            format_name = format.name(),
+        );
+        (name, content)
+    })
+    .collect::<Vec<_>>();
+
+    let mut manifest = existing
+        .map(str::to_string)
+        .unwrap_or_else(|| render_fuzz_manifest(ctx, krate, format));
+    for (name, _) in &targets {
+        if !manifest.contains(&format!("name = \"{name}\"")) {
+            manifest.push_str(&format!(
+                "\n[[bin]]\nname = \"{name}\"\npath = \"fuzz_targets/{name}.rs\"\ntest = false\ndoc = false\nbench = false\n"
+            ));
+        }
+    }
+
+    let dir = Path::new(FUZZ_DIR);
+    std::iter::once((dir.join("Cargo.toml"), manifest))
+        .chain(
+            targets.into_iter().map(|(name, content)| {
+                (dir.join("fuzz_targets").join(format!("{name}.rs")), content)
+            }),
+        )
+        .collect()
+}
+
+fn render_fuzz_manifest(ctx: &RenderCtx, krate: &CrateRoot, format: WireFormat) -> String {
+    format!(
+        r#"[package]
+name = "{package}-fuzz"
+version = "0.0.0"
+publish = false
+edition = "{edition}"
+
+[package.metadata]
+cargo-fuzz = true
+
+[dependencies]
+libfuzzer-sys = "0.4"
+{format_dependency}
+
+[dependencies.{package}]
+path = ".."
+"#,
+        package = krate.package,
+        edition = ctx.options.edition.year(),
+        format_dependency = format.dependency(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blox::enums::{EnumDef, EnumVariant};
+    use crate::blox::message_set::MessageSet;
+    use crate::create::{ActorGenerator, GeneratorOptions};
+    use crate::link::Link;
+    use crate::tests::create_test_actor;
+
+    fn krate() -> CrateRoot {
+        CrateRoot {
+            dir: PathBuf::from("."),
+            package: "my-app".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_fuzz_targets() {
+        let mut actor = create_test_actor();
+        let message_set = actor.component.message_set.as_ref().unwrap().get().clone();
+        actor.component.message_set = Some(MessageSet::with_custom_types(
+            message_set,
+            vec![
+                EnumDef::new(
+                    "CustomArgs",
+                    vec![EnumVariant::new("Value", vec![Link::new("u32")])],
+                ),
+                EnumDef::new("Unused", vec![EnumVariant::new("Value", vec![])]),
+            ],
+        ));
+        let options = GeneratorOptions::default().with_wire_format(Some(WireFormat::Json));
+        let generator = ActorGenerator::with_options(actor, options)
+            .expect("Generator creation should succeed");
+
+        let files = render_fuzz_targets(&generator.ctx(), &krate(), None);
+        let paths = files.iter().map(|(path, _)| path).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                Path::new("fuzz/Cargo.toml"),
+                Path::new("fuzz/fuzz_targets/actor_message_set.rs"),
+                Path::new("fuzz/fuzz_targets/actor_custom_args.rs"),
+            ]
+        );
+        let manifest = &files[0].1;
+        assert!(manifest.starts_with("[package]\nname = \"my-app-fuzz\""));
+        assert!(manifest.contains("serde_json = \"1\"\n\n[dependencies.my-app]\npath = \"..\""));
+        assert!(manifest.contains(
+            "[[bin]]\nname = \"actor_custom_args\"\npath = \"fuzz_targets/actor_custom_args.rs\""
+        ));
+        assert!(
+            files[1]
+                .1
+                .contains("use my_app::actor::messaging::ActorMessageSet;")
+        );
+        assert!(
+            files[1]
+                .1
+                .contains("    let _ = ActorMessageSet::from_wire(bytes);\n")
+        );
+        assert!(
+            files[2]
+                .1
+                .contains("    let _: Result<CustomArgs, _> = serde_json::from_slice(bytes);\n")
+        );
+
+        // Targets already in the fuzz crate are listed once
+        let regenerated = render_fuzz_targets(&generator.ctx(), &krate(), Some(manifest));
+        assert_eq!(&regenerated[0].1, manifest);
+        let other = "[package]\nname = \"my-app-fuzz\"\n\n[[bin]]\nname = \"other_message_set\"\n";
+        let shared = render_fuzz_targets(&generator.ctx(), &krate(), Some(other));
+        assert!(shared[0].1.starts_with(other));
+        assert!(shared[0].1.contains("name = \"actor_custom_args\""));
+    }
+
+    #[test]
+    fn test_no_fuzz_targets_without_wire_format() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(render_fuzz_targets(&generator.ctx(), &krate(), None).is_empty());
+
+        let mut actor = create_test_actor();
+        actor.path = PathBuf::from("tests/output/fuzz");
+        let options = GeneratorOptions::default().with_fuzz_targets(true);
+        let generator = ActorGenerator::with_options(actor, options)
+            .expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate().unwrap_err().to_string(),
+            "Fuzz targets decode the wire format, so they need one"
+        );
+    }
+
+    #[test]
+    fn test_crate_root() {
+        let krate = CrateRoot::find(Path::new("tests/output")).unwrap();
+        assert_eq!(krate.package, "bloxml");
+        assert_eq!(krate.lib_name(), "bloxml");
+        assert!(krate.dir.join("Cargo.toml").is_file());
+    }
+}
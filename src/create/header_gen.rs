@@ -0,0 +1,143 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+const LICENSE_TAG: &str = "SPDX-License-Identifier:";
+const COPYRIGHT_TAG: &str = "SPDX-FileCopyrightText:";
+
+/// SPDX header the writer puts at the top of every generated file
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LicenseHeader {
+    /// SPDX license expression, e.g. `MIT OR Apache-2.0`
+    pub spdx: String,
+    /// Copyright holder; the copyright line is left out without one
+    #[serde(default)]
+    pub holder: Option<String>,
+    /// Copyright year, by default the year the file was first generated
+    #[serde(default)]
+    pub year: Option<u32>,
+}
+
+impl LicenseHeader {
+    pub fn new<S: Into<String>>(spdx: S) -> Self {
+        Self {
+            spdx: spdx.into(),
+            holder: None,
+            year: None,
+        }
+    }
+
+    /// Sets the copyright holder
+    pub fn with_holder(mut self, holder: Option<String>) -> Self {
+        self.holder = holder;
+        self
+    }
+
+    /// Sets the copyright year instead of filling it in
+    pub fn with_year(mut self, year: Option<u32>) -> Self {
+        self.year = year;
+        self
+    }
+
+    /// Puts the header in front of a generated file, in the comment syntax of its extension
+    ///
+    /// Without a set year, the one in the header of the `existing` file is kept, so
+    /// regenerating an unchanged module leaves it byte for byte the same.
+    pub fn apply(&self, file: &Path, content: &str, existing: Option<&str>) -> String {
+        let (open, close) = match file.extension().and_then(|e| e.to_str()) {
+            Some("rs") => ("// ", ""),
+            Some("md") => ("<!-- ", " -->"),
+            _ => return content.to_string(),
+        };
+
+        let mut header = format!("{open}{LICENSE_TAG} {}{close}\n", self.spdx);
+        if let Some(holder) = &self.holder {
+            let year = self
+                .year
+                .or_else(|| existing.and_then(header_year))
+                .unwrap_or_else(current_year);
+            header.push_str(&format!("{open}{COPYRIGHT_TAG} {year} {holder}{close}\n"));
+        }
+        format!("{header}\n{content}")
+    }
+}
+
+/// Year of the copyright line in the header a file starts with, if it has one
+fn header_year(existing: &str) -> Option<u32> {
+    existing
+        .lines()
+        .take_while(|line| line.starts_with("//") || line.starts_with("<!--"))
+        .find_map(|line| line.split_once(COPYRIGHT_TAG))
+        .and_then(|(_, rest)| rest.split_whitespace().next()?.parse().ok())
+}
+
+fn current_year() -> u32 {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400);
+    year_of_day(days as i64)
+}
+
+/// Gregorian year of a day counted from 1970-01-01
+fn year_of_day(days: i64) -> u32 {
+    // Count from 0000-03-01, so leap days end each 400-year era and each year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // January and February close the March-based year
+    let january_or_later = (5 * day_of_year + 2) / 153 >= 10;
+    (era * 400 + year_of_era + i64::from(january_or_later)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_license_header() {
+        let header = LicenseHeader::new("MIT OR Apache-2.0")
+            .with_holder(Some("Acme Corp".to_string()))
+            .with_year(Some(2024));
+        assert_eq!(
+            header.apply(Path::new("states/mod.rs"), "pub mod create;\n", None),
+            "// SPDX-License-Identifier: MIT OR Apache-2.0\n// SPDX-FileCopyrightText: 2024 Acme Corp\n\npub mod create;\n"
+        );
+        assert!(
+            header
+                .apply(Path::new("README.md"), "# Actor\n", None)
+                .starts_with("<!-- SPDX-License-Identifier: MIT OR Apache-2.0 -->\n")
+        );
+        assert_eq!(header.apply(Path::new("schema.json"), "{}", None), "{}");
+        assert_eq!(
+            LicenseHeader::new("MIT").apply(Path::new("mod.rs"), "", None),
+            "// SPDX-License-Identifier: MIT\n\n"
+        );
+    }
+
+    #[test]
+    fn test_license_header_keeps_existing_year() {
+        let header = LicenseHeader::new("Apache-2.0").with_holder(Some("Acme Corp".to_string()));
+        let existing = "// SPDX-License-Identifier: Apache-2.0\n// SPDX-FileCopyrightText: 2021 Acme Corp\n\npub mod create;\n";
+        assert_eq!(
+            header.apply(Path::new("mod.rs"), "pub mod create;\n", Some(existing)),
+            existing
+        );
+        let fresh = header.apply(Path::new("mod.rs"), "", Some("pub mod create;\n"));
+        assert!(fresh.contains(&format!("{COPYRIGHT_TAG} {} Acme Corp", current_year())));
+    }
+
+    #[test]
+    fn test_year_of_day() {
+        assert_eq!(year_of_day(0), 1970);
+        assert_eq!(year_of_day(19_722), 2023);
+        assert_eq!(year_of_day(19_723), 2024);
+        assert_eq!(year_of_day(20_088), 2024);
+        assert_eq!(year_of_day(20_089), 2025);
+        assert_eq!(year_of_day(-1), 1969);
+    }
+}
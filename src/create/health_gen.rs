@@ -0,0 +1,123 @@
+use crate::blox::actor::HEALTH_RECEIVER;
+use crate::create::{RenderCtx, RenderError};
+
+/// Render the health module: the probe, the report it answers with and the inbox helper
+pub fn render_health_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    let actor_name = &ctx.actor.ident;
+    let imports_section = ctx.imports_section(&format!("{}::health", ctx.actor_module()))?;
+    let circuits = if ctx.actor.component.message_handles.has_circuit_breakers() {
+        "\n    /// State of each circuit breaker, by handle\n    pub circuits: Vec<(&'static str, CircuitState)>,"
+    } else {
+        ""
+    };
+
+    Ok(format!(
+        r#"{imports_section}/// Health of one {actor_name} instance, as reported to a health probe
+#[derive(Debug, Clone)]
+pub struct {actor_name}Health {{
+    /// The state the instance is in
+    pub state: String,
+    /// Messages waiting on each channel, by channel name
+    pub queue_depths: Vec<(&'static str, usize)>,{circuits}
+}}
+
+/// Asks an {actor_name} instance for its health
+///
+/// The runtime answers on `reply` itself; probes are never dispatched to the states.
+pub struct HealthCheck {{
+    pub reply: Sender<{actor_name}Health>,
+}}
+
+impl {actor_name}Inbox {{
+    /// Probes the instance and waits for its health, or `None` if it has stopped
+    pub async fn health_check(&self) -> Option<{actor_name}Health> {{
+        let (reply, mut response) = channel(1);
+        self.health.send(Message::new(0, HealthCheck {{ reply }})).await.ok()?;
+        response.recv().await
+    }}
+}}
+"#
+    ))
+}
+
+/// Render the runtime's `select!` arm answering health probes
+pub fn render_health_arm(ctx: &RenderCtx) -> String {
+    let actor_name = &ctx.actor.ident;
+    let queue_depths = ctx
+        .actor
+        .component
+        .message_receivers
+        .receivers
+        .iter()
+        .map(|receiver| {
+            format!(
+                "                            (\"{}\", self.receivers.{}.len()),",
+                receiver.channel_name(),
+                receiver.ident
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let circuits = if ctx.actor.component.message_handles.has_circuit_breakers() {
+        "\n                            circuits: self.state_machine.self_handles.breakers.states(),"
+    } else {
+        ""
+    };
+
+    format!(
+        r#"                    Some(probe) = self.receivers.{HEALTH_RECEIVER}.recv() => {{
+                        let health = {actor_name}Health {{
+                            state: format!("{{:?}}", self.state_machine.current_state),
+                            queue_depths: vec![
+{queue_depths}
+                            ],{circuits}
+                        }};
+                        let _ = probe.payload().reply.try_send(health);
+                    }}
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_no_health_probe_by_default() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(generator.generate_health().unwrap().is_none());
+        assert!(!generator.root_modules().contains(&"health".to_string()));
+        assert!(!generator.generate_runtime().unwrap().contains("health_rx"));
+    }
+
+    #[test]
+    fn test_health_probe() {
+        let actor = create_test_actor().with_health_check();
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let health = generator
+            .generate_health()
+            .unwrap()
+            .expect("Health module should be generated");
+
+        assert!(health.contains("pub struct ActorHealth {"));
+        assert!(health.contains("pub reply: Sender<ActorHealth>,"));
+        assert!(health.contains("pub async fn health_check(&self) -> Option<ActorHealth> {"));
+        assert!(health.contains("use crate::actor::component::ActorInbox;"));
+        assert!(health.contains("use tokio::sync::mpsc::channel;"));
+        assert!(generator.root_modules().contains(&"health".to_string()));
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains("Some(probe) = self.receivers.health_rx.recv() => {"));
+        assert!(runtime.contains("(\"customargs\", self.receivers.customargs_rx.len()),"));
+        assert!(runtime.contains("(\"health\", self.receivers.health_rx.len()),"));
+        assert!(runtime.contains("use crate::actor::health::ActorHealth;"));
+        // The probe is answered by the runtime, not dispatched to the states
+        assert!(!runtime.contains("::HealthCheck(msg)"));
+
+        let component = generator.generate_component().unwrap();
+        assert!(component.contains("pub health: TokioMessageHandle<HealthCheck>,"));
+        assert!(component.contains("use crate::actor::health::HealthCheck;"));
+    }
+}
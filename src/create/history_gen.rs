@@ -0,0 +1,153 @@
+use crate::blox::actor::HISTORY_FIELD;
+use crate::create::RenderCtx;
+
+/// Render the extended state methods recording and printing the actor's history, if it keeps
+/// one
+pub fn render_history_methods(ctx: &RenderCtx) -> String {
+    let Some(history) = &ctx.actor.history else {
+        return String::new();
+    };
+    format!(
+        r#"
+	/// Records a message about to be handled in `state`, forgetting the oldest record once
+	/// {capacity} are kept
+	pub fn record_history(&mut self, state: &impl core::fmt::Debug, variant: &str) {{
+	    if self.{HISTORY_FIELD}.len() >= {capacity} {{
+	        self.{HISTORY_FIELD}.pop_front();
+	    }}
+	    self.{HISTORY_FIELD}.push_back((SystemTime::now(), format!("{{state:?}}"), variant.to_string()));
+	}}
+
+	/// The recorded messages, oldest first, one per line with the seconds since the Unix epoch
+	/// they arrived at
+	pub fn dump_history(&self) -> String {{
+	    self.{HISTORY_FIELD}
+	        .iter()
+	        .map(|(at, state, variant)| {{
+	            let at = at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+	            format!("{{}}.{{:03}} {{state}} <- {{variant}}\n", at.as_secs(), at.subsec_millis())
+	        }})
+	        .collect()
+	}}
+"#,
+        capacity = history.capacity
+    )
+}
+
+/// Render `variant_name` on the message set, naming what the history records
+pub fn render_variant_names(ctx: &RenderCtx) -> String {
+    let Some(message_set) = ctx
+        .actor
+        .component
+        .message_set
+        .as_ref()
+        .filter(|_| ctx.actor.history.is_some())
+    else {
+        return String::new();
+    };
+    let arms = message_set
+        .get()
+        .variants
+        .iter()
+        .map(|variant| {
+            let pattern = if variant.args.is_empty() { "" } else { "(..)" };
+            format!(
+                "            Self::{ident}{pattern} => \"{ident}\",",
+                ident = variant.ident
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        r#"
+impl {ident} {{
+    /// Name of the variant, as the actor's history records it
+    pub fn variant_name(&self) -> &'static str {{
+        match self {{
+{arms}
+        }}
+    }}
+}}
+"#,
+        ident = message_set.get().ident
+    )
+}
+
+/// Render the runtime recording `message` in the history before `dispatch` handles it as
+/// `message`, and panicking with the history if handling it does
+pub fn render_recorded_dispatch(ctx: &RenderCtx, message: &str, dispatch: &str) -> String {
+    let dispatch = dispatch
+        .lines()
+        .map(|line| format!("    {line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        r#"                        let message = {message};
+                        self.state_machine
+                            .extended_state
+                            .record_history(&current_state, message.variant_name());
+                        let dispatched = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {{
+{dispatch}
+                        }}));
+                        if let Err(panic) = dispatched {{
+                            let reason = panic
+                                .downcast_ref::<&str>()
+                                .map(|reason| reason.to_string())
+                                .or_else(|| panic.downcast_ref::<String>().cloned())
+                                .unwrap_or_default();
+                            panic!(
+                                "{{reason}}\n{actor} history, oldest first:\n{{}}",
+                                self.state_machine.extended_state.dump_history()
+                            );
+                        }}"#,
+        actor = ctx.actor.ident
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::ext_state::History;
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_history() {
+        let actor = create_test_actor()
+            .with_self_messages()
+            .with_history(History::new(32));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(
+            ext_state.contains("use std::collections::VecDeque;\nuse std::time::SystemTime;\n")
+        );
+        assert!(ext_state.contains("pub history: VecDeque<(SystemTime, String, String)>"));
+        assert!(ext_state.contains("history: Default::default()"));
+        assert!(ext_state.contains("\tpub fn record_history(&mut self, state: &impl core::fmt::Debug, variant: &str) {\n\t    if self.history.len() >= 32 {"));
+        assert!(ext_state.contains("\tpub fn dump_history(&self) -> String {"));
+
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains(
+            "            Self::CustomValue1(..) => \"CustomValue1\",\n            Self::CustomValue2(..) => \"CustomValue2\",\n"
+        ));
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "                        let message = ActorMessageSet::CustomValue1(msg);\n                        self.state_machine\n                            .extended_state\n                            .record_history(&current_state, message.variant_name());\n                        let dispatched = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {\n                            self.state_machine.dispatch(message, &current_state);\n                        }));"
+        ));
+        // Deferred messages are recorded too
+        assert_eq!(runtime.matches(".record_history(").count(), 3);
+        assert!(runtime.contains("\"{reason}\\nActor history, oldest first:\\n{}\","));
+    }
+
+    #[test]
+    fn test_history_validation() {
+        let mut actor = create_test_actor().with_history(History::new(0));
+        actor.path = "tests/output/history".into();
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate().unwrap_err().to_string(),
+            "History capacity must be at least 1"
+        );
+    }
+}
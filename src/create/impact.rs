@@ -0,0 +1,418 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use petgraph::graph::NodeIndex;
+
+use crate::blox::actor::Actor;
+use crate::create::ActorGenerator;
+use crate::graph::{Node, Relation};
+use crate::link;
+
+/// An element of a spec, written like `states.Create` or `variants.Ping`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpecElement {
+    State(String),
+    Variant(String),
+    Field(String),
+    Handle(String),
+    Receiver(String),
+    /// A custom type of the message set
+    Type(String),
+}
+
+impl SpecElement {
+    fn parts(&self) -> (&'static str, &str) {
+        match self {
+            SpecElement::State(name) => ("states", name),
+            SpecElement::Variant(name) => ("variants", name),
+            SpecElement::Field(name) => ("fields", name),
+            SpecElement::Handle(name) => ("handles", name),
+            SpecElement::Receiver(name) => ("receivers", name),
+            SpecElement::Type(name) => ("types", name),
+        }
+    }
+}
+
+impl fmt::Display for SpecElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, name) = self.parts();
+        write!(f, "{kind}.{name}")
+    }
+}
+
+impl FromStr for SpecElement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, name) = s
+            .split_once('.')
+            .filter(|(_, name)| !name.is_empty())
+            .ok_or_else(|| format!("expected an element like 'states.Create', got '{s}'"))?;
+        let name = name.to_string();
+        match kind {
+            "states" => Ok(SpecElement::State(name)),
+            "variants" => Ok(SpecElement::Variant(name)),
+            "fields" => Ok(SpecElement::Field(name)),
+            "handles" => Ok(SpecElement::Handle(name)),
+            "receivers" => Ok(SpecElement::Receiver(name)),
+            "types" => Ok(SpecElement::Type(name)),
+            _ => Err(format!(
+                "unknown element kind '{kind}', expected states, variants, fields, handles, \
+                 receivers or types"
+            )),
+        }
+    }
+}
+
+/// How a generated module is affected by a change
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ModuleImpact {
+    /// The module defines a changed type
+    Defines(String),
+    /// The module imports a changed type
+    Uses(String),
+}
+
+/// Another actor of the project whose spec names types the change affects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActorImpact {
+    pub spec: PathBuf,
+    pub actor: String,
+    pub types: Vec<String>,
+}
+
+/// What changing one element of a spec affects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Impact {
+    pub element: SpecElement,
+    /// Generated types whose definition changes with the element
+    pub types: Vec<String>,
+    /// Generated modules affected, by module path
+    pub modules: Vec<(String, ModuleImpact)>,
+    /// Other actors of the project affected
+    pub actors: Vec<ActorImpact>,
+}
+
+impl Impact {
+    /// Finds the other actors among `specs` whose specs name a changed type
+    pub fn with_actors(mut self, specs: &[(PathBuf, Actor)]) -> Self {
+        self.actors = specs
+            .iter()
+            .filter_map(|(spec, actor)| {
+                let mentioned = mentioned_types(actor);
+                let types = self
+                    .types
+                    .iter()
+                    .filter(|ty| mentioned.contains(*ty))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                (!types.is_empty()).then(|| ActorImpact {
+                    spec: spec.clone(),
+                    actor: actor.ident.clone(),
+                    types,
+                })
+            })
+            .collect();
+        self
+    }
+}
+
+impl fmt::Display for Impact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} changes {}", self.element, self.types.join(", "))?;
+        for (module, impact) in &self.modules {
+            match impact {
+                ModuleImpact::Defines(ty) => writeln!(f, "  {module} defines {ty}")?,
+                ModuleImpact::Uses(ty) => writeln!(f, "  {module} uses {ty}")?,
+            }
+        }
+        if self.actors.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, "other actors:")?;
+        self.actors.iter().try_for_each(|actor| {
+            writeln!(
+                f,
+                "  {} ({}) uses {}",
+                actor.actor,
+                actor.spec.display(),
+                actor.types.join(", ")
+            )
+        })
+    }
+}
+
+impl ActorGenerator {
+    /// Reports the generated modules affected by changing `element`, from the type graph
+    pub fn impact(&self, element: &SpecElement) -> Result<Impact, String> {
+        let types = self.changed_types(element)?;
+        let actor_mod = self.actor_module();
+        let graph = self.graph.rust_graph();
+        let path = |index| self.node_path(index);
+
+        let mut modules = BTreeSet::new();
+        for ty in &types {
+            let generated = graph
+                .find_by_name(ty)
+                .into_iter()
+                .filter(|entry| matches!(entry.node, Node::Type(_)))
+                .filter(|entry| path(entry.index).starts_with(&format!("{actor_mod}::")));
+            for entry in generated {
+                for dependent in graph.find_dependents(entry.index) {
+                    if !matches!(dependent.node, Node::Module(_)) {
+                        continue;
+                    }
+                    let module = path(dependent.index);
+                    let impact = match dependent.relation {
+                        Relation::Contains => ModuleImpact::Defines(ty.clone()),
+                        _ => ModuleImpact::Uses(ty.clone()),
+                    };
+                    modules.insert((module, impact));
+                }
+            }
+        }
+
+        Ok(Impact {
+            element: element.clone(),
+            types,
+            modules: modules.into_iter().collect(),
+            actors: Vec::new(),
+        })
+    }
+
+    /// Path of a graph node relative to the crate root
+    pub(super) fn node_path(&self, index: NodeIndex) -> String {
+        // Generated items are registered both with and without the `crate::` prefix
+        let path = self.graph.rust_graph().get_node_path(index);
+        match path.strip_prefix("crate::") {
+            Some(path) => path.to_string(),
+            None => path,
+        }
+    }
+
+    /// Generated types whose definition changes with `element`
+    fn changed_types(&self, element: &SpecElement) -> Result<Vec<String>, String> {
+        let component = &self.actor().component;
+        let missing = || format!("{} is not in the spec", element);
+        let message_set = component.message_set.as_ref().map(|ms| ms.get());
+
+        let types = match element {
+            SpecElement::State(name) => {
+                component
+                    .states
+                    .states
+                    .iter()
+                    .find(|s| &s.ident == name)
+                    .ok_or_else(missing)?;
+                vec![
+                    name.clone(),
+                    component.states.state_enum.get().ident.clone(),
+                ]
+            }
+            SpecElement::Variant(name) => {
+                let message_set = message_set.ok_or_else(missing)?;
+                message_set
+                    .variants
+                    .iter()
+                    .find(|v| &v.ident == name)
+                    .ok_or_else(missing)?;
+                vec![message_set.ident.clone()]
+            }
+            SpecElement::Field(name) => {
+                let ext_state = &component.ext_state;
+                ext_state
+                    .fields()
+                    .iter()
+                    .find(|f| f.ident() == name)
+                    .ok_or_else(missing)?;
+                vec![ext_state.ident().to_string()]
+            }
+            SpecElement::Handle(name) => {
+                let handles = &component.message_handles;
+                handles
+                    .handles
+                    .iter()
+                    .find(|h| &h.ident == name)
+                    .ok_or_else(missing)?;
+                vec![handles.ident.clone()]
+            }
+            SpecElement::Receiver(name) => {
+                let receivers = &component.message_receivers;
+                receivers
+                    .receivers
+                    .iter()
+                    .find(|r| &r.ident == name)
+                    .ok_or_else(missing)?;
+                vec![receivers.ident.clone()]
+            }
+            SpecElement::Type(name) => {
+                component
+                    .message_set
+                    .iter()
+                    .flat_map(|ms| &ms.custom_types)
+                    .find(|c| &c.ident == name)
+                    .ok_or_else(missing)?;
+                vec![name.clone()]
+            }
+        };
+        Ok(types)
+    }
+}
+
+/// Type names an actor's spec uses in its messages and extended state
+fn mentioned_types(actor: &Actor) -> BTreeSet<String> {
+    let component = &actor.component;
+    let variant_args = component
+        .message_set
+        .iter()
+        .flat_map(|ms| std::iter::once(&ms.def).chain(&ms.custom_types))
+        .flat_map(|def| &def.variants)
+        .flat_map(|variant| &variant.args)
+        .map(|arg| arg.to_string());
+    let channels = component
+        .message_handles
+        .handles
+        .iter()
+        .map(|h| h.message_type.clone())
+        .chain(
+            component
+                .message_receivers
+                .receivers
+                .iter()
+                .map(|r| r.message_type.clone()),
+        );
+    let fields = component
+        .ext_state
+        .fields()
+        .iter()
+        .map(|f| f.ty().to_string());
+
+    variant_args
+        .chain(channels)
+        .chain(fields)
+        .flat_map(|ty| link::type_names(&ty))
+        .collect()
+}
+
+/// Loads every actor spec under `dir` other than `exclude`, skipping JSON files that aren't
+/// actor specs, like mixins and overlays
+pub fn find_actor_specs(dir: &Path, exclude: Option<&Path>) -> Vec<(PathBuf, Actor)> {
+    let exclude = exclude.and_then(|path| fs::canonicalize(path).ok());
+    let mut specs = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect::<Vec<_>>();
+        paths.sort();
+        for path in paths {
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if hidden || path.ends_with("target") {
+                continue;
+            }
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "json")
+                && (exclude.is_none() || fs::canonicalize(&path).ok() != exclude)
+                && let Ok(actor) = Actor::from_json_file(&path)
+            {
+                specs.push((path, actor));
+            }
+        }
+    }
+    specs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    specs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_parse_element() {
+        assert_eq!(
+            "states.Create".parse(),
+            Ok(SpecElement::State("Create".to_string()))
+        );
+        assert_eq!(
+            SpecElement::Variant("Ping".to_string()).to_string(),
+            "variants.Ping"
+        );
+        assert!("states.".parse::<SpecElement>().is_err());
+        assert!("rooms.Create".parse::<SpecElement>().is_err());
+    }
+
+    #[test]
+    fn test_impact() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+
+        let impact = generator
+            .impact(&SpecElement::State("Update".to_string()))
+            .unwrap();
+        assert_eq!(impact.types, ["Update", "ActorStates"]);
+
+        assert!(impact.modules.contains(&(
+            "actor::states::update".to_string(),
+            ModuleImpact::Defines("Update".to_string())
+        )));
+        assert!(impact.modules.contains(&(
+            "actor::states".to_string(),
+            ModuleImpact::Defines("ActorStates".to_string())
+        )));
+        assert!(
+            impact
+                .modules
+                .iter()
+                .any(|(_, impact)| *impact == ModuleImpact::Uses("ActorStates".to_string()))
+        );
+
+        let impact = generator
+            .impact(&SpecElement::Variant("CustomValue2".to_string()))
+            .unwrap();
+        assert!(impact.modules.contains(&(
+            "actor::messaging".to_string(),
+            ModuleImpact::Defines("ActorMessageSet".to_string())
+        )));
+
+        assert_eq!(
+            generator.impact(&SpecElement::Field("nope".to_string())),
+            Err("fields.nope is not in the spec".to_string())
+        );
+    }
+
+    #[test]
+    fn test_impact_on_other_actors() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let mut other = create_test_actor();
+        other.ident = "Client".to_string();
+        other.component.message_handles.handles[0].message_type = "ActorMessageSet".to_string();
+        let specs = vec![
+            (PathBuf::from("client.json"), other),
+            (PathBuf::from("unrelated.json"), create_test_actor()),
+        ];
+
+        let impact = generator
+            .impact(&SpecElement::Variant("CustomValue1".to_string()))
+            .unwrap()
+            .with_actors(&specs);
+        assert_eq!(
+            impact.actors,
+            [ActorImpact {
+                spec: PathBuf::from("client.json"),
+                actor: "Client".to_string(),
+                types: vec!["ActorMessageSet".to_string()],
+            }]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::create::RenderCtx;
+use crate::field::{Field, LockKind};
+
+/// Render the `std::sync` imports of the extended state so they come from `loom` under
+/// `cfg(loom)`, where the loom tests run
+pub fn render_loom_imports(imports_section: &str) -> String {
+    imports_section
+        .lines()
+        .map(|line| match line.strip_prefix("use std::sync::") {
+            Some(item) => {
+                format!("#[cfg(not(loom))]\n{line}\n#[cfg(loom)]\nuse loom::sync::{item}\n")
+            }
+            None => format!("{line}\n"),
+        })
+        .collect()
+}
+
+/// Render the loom tests contending for each shared field of the extended state, between
+/// its generated accessors and a thread holding the handle they share, so a locking pattern
+/// that can deadlock fails under `--cfg loom`
+///
+/// The state under test has every field at its default. Returns `None` without shared fields.
+pub fn render_loom_tests(ctx: &RenderCtx) -> Option<String> {
+    let ext_state = &ctx.actor.component.ext_state;
+    let shared = ext_state
+        .fields()
+        .iter()
+        .filter(|f| f.is_shared())
+        .collect::<Vec<_>>();
+    if shared.is_empty() {
+        return None;
+    }
+
+    let defaults = vec!["Default::default()"; ext_state.fields().len()].join(", ");
+    let tests = shared
+        .iter()
+        .map(|field| render_loom_test(field))
+        .collect::<String>();
+    Some(format!(
+        r#"
+#[cfg(all(test, loom))]
+mod loom_tests {{
+    use super::*;
+
+    /// Extended state with every field at its default
+    fn ext_state() -> {ident} {{
+        {ident}::new({defaults})
+    }}
+{tests}}}
+"#,
+        ident = ext_state.ident(),
+    ))
+}
+
+/// Render the test taking the field's locks through its accessors on one thread and through
+/// its handle on another; a `RwLock` is read then written on one and the other way round on
+/// the other
+fn render_loom_test(field: &Field) -> String {
+    let ident = field.ident();
+    let (name, accessors, handle) = match field.lock() {
+        LockKind::Mutex => (
+            "lock_is_exclusive",
+            format!("drop(state.lock_{ident}());"),
+            "drop(handle.lock().unwrap_or_else(|e| e.into_inner()));",
+        ),
+        LockKind::RwLock => (
+            "reads_and_writes_interleave",
+            format!("drop(state.read_{ident}());\n            drop(state.write_{ident}());"),
+            "drop(handle.write().unwrap_or_else(|e| e.into_inner()));\n                drop(handle.read().unwrap_or_else(|e| e.into_inner()));",
+        ),
+    };
+    format!(
+        r#"
+    #[test]
+    fn {ident}_{name}() {{
+        loom::model(|| {{
+            let state = ext_state();
+            let handle = state.{ident}_handle();
+            let thread = loom::thread::spawn(move || {{
+                {handle}
+            }});
+            {accessors}
+            thread.join().unwrap();
+        }});
+    }}
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::actor::Actor;
+    use crate::blox::ext_state::{ExtState, InitArgs};
+    use crate::create::{ActorGenerator, GeneratorOptions};
+    use crate::field::{Field, LockKind};
+    use crate::tests::create_test_actor;
+
+    fn shared_actor() -> Actor {
+        let mut actor = create_test_actor();
+        actor.component.ext_state = ExtState::new(
+            "ActorExtState",
+            vec![
+                Field::new("count", "u32"),
+                Field::new("peers", "Vec<String>").shared(LockKind::RwLock),
+                Field::new("stats", "u64").shared(LockKind::Mutex),
+            ],
+            vec![],
+            InitArgs::default(),
+        );
+        actor
+    }
+
+    #[test]
+    fn test_loom_tests() {
+        let options = GeneratorOptions::default().with_loom_tests(true);
+        let generator = ActorGenerator::with_options(shared_actor(), options)
+            .expect("Generator creation should succeed");
+        let ext_state = generator.generate_ext_state().unwrap();
+
+        assert!(ext_state.contains(
+            "#[cfg(not(loom))]\nuse std::sync::Arc;\n#[cfg(loom)]\nuse loom::sync::Arc;\n"
+        ));
+        assert!(ext_state.contains("#[cfg(loom)]\nuse loom::sync::MutexGuard;\n"));
+        assert!(ext_state.contains("#[cfg(all(test, loom))]\nmod loom_tests {"));
+        assert!(ext_state.contains(
+            "        ActorExtState::new(Default::default(), Default::default(), Default::default())"
+        ));
+        assert!(ext_state.contains(
+            "    fn stats_lock_is_exclusive() {\n        loom::model(|| {\n            let state = ext_state();\n            let handle = state.stats_handle();"
+        ));
+        assert!(ext_state.contains(
+            "                drop(handle.lock().unwrap_or_else(|e| e.into_inner()));\n            });\n            drop(state.lock_stats());\n            thread.join().unwrap();"
+        ));
+        assert!(ext_state.contains("    fn peers_reads_and_writes_interleave() {"));
+        assert!(ext_state.contains(
+            "            drop(state.read_peers());\n            drop(state.write_peers());"
+        ));
+        assert!(!ext_state.contains("fn count_"));
+
+        let generator =
+            ActorGenerator::new(shared_actor()).expect("Generator creation should succeed");
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(!ext_state.contains("loom"));
+    }
+
+    #[test]
+    fn test_no_loom_tests_without_shared_fields() {
+        let options = GeneratorOptions::default().with_loom_tests(true);
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        assert!(!generator.generate_ext_state().unwrap().contains("loom"));
+    }
+}
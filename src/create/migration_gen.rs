@@ -0,0 +1,181 @@
+use crate::blox::actor::Actor;
+use crate::create::RenderError;
+use crate::field::Field;
+
+/// Render the skeleton migrating extended state persisted by `previous` to `current`, named
+/// after their spec versions, with the file name it goes in
+///
+/// Returns `None` when both specs declare the same extended state fields.
+pub fn render_migration(
+    previous: &Actor,
+    current: &Actor,
+) -> Result<Option<(String, String)>, RenderError> {
+    let version = |actor: &Actor, which| {
+        actor.version.ok_or_else(|| {
+            RenderError::InvalidSpec(format!(
+                "Migrations are named after spec versions, but the {which} spec of {} has none",
+                actor.ident
+            ))
+        })
+    };
+    let from = version(previous, "previous")?;
+    let to = version(current, "current")?;
+    if from.checked_add(1) != Some(to) {
+        return Err(RenderError::InvalidSpec(format!(
+            "Migrations go from one spec version to the next, not from {from} to {to}"
+        )));
+    }
+
+    let old_fields = previous.component.ext_state.fields();
+    let new_fields = current.component.ext_state.fields();
+    let same = |a: &Field, b: &Field| a.ident() == b.ident() && a.ty() == b.ty();
+    if old_fields.len() == new_fields.len()
+        && new_fields
+            .iter()
+            .all(|new| old_fields.iter().any(|old| same(old, new)))
+    {
+        return Ok(None);
+    }
+
+    let old_struct = format!("{}V{from}", previous.component.ext_state.ident());
+    let new_struct = format!("{}V{to}", current.component.ext_state.ident());
+    let function = format!("migrate_v{from}_to_v{to}");
+
+    let mut moved = false;
+    let mapping = new_fields
+        .iter()
+        .map(|new| {
+            let ident = new.ident();
+            let value = match old_fields.iter().find(|old| old.ident() == ident) {
+                Some(old) if same(old, new) => {
+                    moved = true;
+                    format!("old.{ident}")
+                }
+                Some(old) => format!(
+                    "todo!(\"`{ident}` changed from `{}` to `{}`\")",
+                    old.ty(),
+                    new.ty()
+                ),
+                None => format!("todo!(\"`{ident}: {}` was added\")", new.ty()),
+            };
+            format!("        {ident}: {value},")
+        })
+        .chain(
+            old_fields
+                .iter()
+                .filter(|old| new_fields.iter().all(|new| new.ident() != old.ident()))
+                .map(|old| format!("        // `{}: {}` was removed", old.ident(), old.ty())),
+        )
+        .collect::<Vec<_>>()
+        .join("\n");
+    // Nothing is read from the old state when no field survived unchanged
+    let param = if moved { "old" } else { "_old" };
+
+    let content = format!(
+        r#"//! Migration of {actor_name}'s persisted extended state from spec version {from} to {to}
+//!
+//! Fields kept with the same type are moved over; every `todo!` needs a value before the
+//! migration can run.
+
+{old_definition}
+
+{new_definition}
+
+/// Migrates extended state persisted by version {from} of the spec to version {to}
+pub fn {function}({param}: {old_struct}) -> {new_struct} {{
+    {new_struct} {{
+{mapping}
+    }}
+}}
+"#,
+        actor_name = current.ident,
+        old_definition = render_snapshot(&old_struct, from, old_fields),
+        new_definition = render_snapshot(&new_struct, to, new_fields),
+    );
+    Ok(Some((format!("{function}.rs"), content)))
+}
+
+/// Render the extended state fields as of a spec version, in the form they are persisted in
+fn render_snapshot(ident: &str, version: u32, fields: &[Field]) -> String {
+    let fields = fields
+        .iter()
+        .map(|f| format!("    pub {}: {},", f.ident(), f.ty()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        r#"/// Extended state as of spec version {version}
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct {ident} {{
+{fields}
+}}"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blox::ext_state::{ExtState, InitArgs};
+    use crate::tests::create_test_actor;
+
+    fn versioned(version: u32, fields: Vec<Field>) -> Actor {
+        let mut actor = create_test_actor().with_version(version);
+        actor.component.ext_state =
+            ExtState::new("ActorExtState", fields, Vec::new(), InitArgs::default());
+        actor
+    }
+
+    #[test]
+    fn test_migration_skeleton() {
+        let previous = versioned(
+            1,
+            vec![
+                Field::new("count", "u32"),
+                Field::new("total", "u32"),
+                Field::new("legacy", "bool"),
+            ],
+        );
+        let current = versioned(
+            2,
+            vec![
+                Field::new("count", "u32"),
+                Field::new("total", "u64"),
+                Field::new("name", "String"),
+            ],
+        );
+
+        let (file, migration) = render_migration(&previous, &current)
+            .unwrap()
+            .expect("Changed fields should need a migration");
+        assert_eq!(file, "migrate_v1_to_v2.rs");
+        assert!(migration.contains(
+            "pub struct ActorExtStateV1 {\n    pub count: u32,\n    pub total: u32,\n    pub legacy: bool,\n}"
+        ));
+        assert!(migration.contains("pub struct ActorExtStateV2 {\n    pub count: u32,"));
+        assert!(migration.contains(
+            "pub fn migrate_v1_to_v2(old: ActorExtStateV1) -> ActorExtStateV2 {\n    ActorExtStateV2 {\n        count: old.count,\n        total: todo!(\"`total` changed from `u32` to `u64`\"),\n        name: todo!(\"`name: String` was added\"),\n        // `legacy: bool` was removed\n    }\n}"
+        ));
+    }
+
+    #[test]
+    fn test_migration_versions() {
+        let fields = vec![Field::new("count", "u32")];
+        assert_eq!(
+            render_migration(&versioned(1, fields.clone()), &versioned(2, fields.clone())),
+            Ok(None)
+        );
+
+        let changed = vec![Field::new("count", "u64")];
+        assert_eq!(
+            render_migration(
+                &versioned(1, fields.clone()),
+                &versioned(3, changed.clone())
+            ),
+            Err(RenderError::InvalidSpec(
+                "Migrations go from one spec version to the next, not from 1 to 3".to_string()
+            ))
+        );
+        let mut unversioned = versioned(1, fields);
+        unversioned.version = None;
+        assert!(render_migration(&unversioned, &versioned(2, changed)).is_err());
+    }
+}
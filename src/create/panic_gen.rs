@@ -0,0 +1,115 @@
+use crate::blox::lifecycle::PanicPolicy;
+use crate::blox::state::State;
+use crate::create::RenderCtx;
+
+/// Render the runtime handling a message with `dispatch` under the actor's panic policy
+///
+/// `dispatch` is returned as is when panics propagate. Otherwise it runs in `catch_unwind`
+/// and a panic is reported on stderr with the state it happened in, then the message is
+/// dropped or the state machine re-entered in the recovery state.
+pub fn render_panic_policy(ctx: &RenderCtx, dispatch: &str) -> String {
+    let policy = &ctx.actor.lifecycle.on_panic;
+    if !policy.catches() {
+        return dispatch.to_string();
+    }
+    let dispatch = dispatch
+        .lines()
+        .map(|line| format!("    {line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let states = &ctx.actor.component.states;
+    let path = |state: &State| {
+        format!(
+            "{}::{}(super::states::{}::{})",
+            states.state_enum.get().ident,
+            state.ident,
+            state.ident.to_lowercase(),
+            state.constructor()
+        )
+    };
+    let (outcome, recovery) = match policy {
+        PanicPolicy::Recover { state } => (
+            format!("recovering in {state}"),
+            match (states.states.first(), states.get_state(state)) {
+                (Some(initial), Some(state)) => format!(
+                    "\n                            self.state_machine.init(&{}, &{});",
+                    path(initial),
+                    path(state)
+                ),
+                // Rejected when the spec is validated
+                _ => String::new(),
+            },
+        ),
+        _ => ("dropping the message".to_string(), String::new()),
+    };
+    format!(
+        r#"                        let handled = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {{
+{dispatch}
+                        }}));
+                        if let Err(panic) = handled {{
+                            let reason = panic
+                                .downcast_ref::<&str>()
+                                .map(|reason| reason.to_string())
+                                .or_else(|| panic.downcast_ref::<String>().cloned())
+                                .unwrap_or_default();
+                            eprintln!("{actor} panicked in {{current_state:?}}, {outcome}: {{reason}}");{recovery}
+                        }}"#,
+        actor = ctx.actor.ident
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::blox::lifecycle::{Lifecycle, PanicPolicy};
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_panic_policy() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(
+            !generator
+                .generate_runtime()
+                .unwrap()
+                .contains("catch_unwind")
+        );
+
+        let mut actor = create_test_actor();
+        actor.lifecycle = Lifecycle::default().with_panic_policy(PanicPolicy::Discard);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "                        let handled = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {\n                            self.state_machine.dispatch(ActorMessageSet::CustomValue1(msg), &current_state);\n                        }));"
+        ));
+        assert!(runtime.contains(
+            "eprintln!(\"Actor panicked in {current_state:?}, dropping the message: {reason}\");\n                        }"
+        ));
+
+        let mut actor = create_test_actor();
+        actor.lifecycle = Lifecycle::default().with_panic_policy(PanicPolicy::Recover {
+            state: "Create".to_string(),
+        });
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "eprintln!(\"Actor panicked in {current_state:?}, recovering in Create: {reason}\");\n                            self.state_machine.init(&ActorStates::Create(super::states::create::Create), &ActorStates::Create(super::states::create::Create));"
+        ));
+    }
+
+    #[test]
+    fn test_panic_policy_validation() {
+        let mut actor = create_test_actor();
+        actor.path = PathBuf::from("tests/output/panic_policy");
+        actor.lifecycle = Lifecycle::default().with_panic_policy(PanicPolicy::Recover {
+            state: "Failed".to_string(),
+        });
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate().unwrap_err().to_string(),
+            "Panics recover to unknown state 'Failed'"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use crate::create::{RenderCtx, RenderError};
+
+/// Render the persistence module: the store the current state is saved to and an in-memory one
+pub fn render_persistence_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    let actor_name = &ctx.actor.ident;
+    let state_enum = &ctx.actor.component.states.state_enum.get().ident;
+    let imports_section = ctx.imports_section(&format!("{}::persistence", ctx.actor_module()))?;
+
+    Ok(format!(
+        r#"{imports_section}/// Where {actor_name} saves the state it is in, so it resumes there after a restart
+pub trait {actor_name}StateStore: Send + Sync {{
+    /// The state saved last, if any
+    fn load(&self) -> Option<{state_enum}>;
+
+    /// Saves the state the actor just transitioned to
+    fn save(&self, state: &{state_enum});
+}}
+
+/// Shared store, as the extended state holds it
+pub type {actor_name}StateStoreRef = Arc<dyn {actor_name}StateStore>;
+
+/// Keeps the state in memory, so it survives restarts by a supervisor but not the process
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryStateStore {{
+    state: Arc<Mutex<Option<{state_enum}>>>,
+}}
+
+impl {actor_name}StateStore for InMemoryStateStore {{
+    fn load(&self) -> Option<{state_enum}> {{
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }}
+
+    fn save(&self, state: &{state_enum}) {{
+        *self.state.lock().unwrap_or_else(|e| e.into_inner()) = Some(state.clone());
+    }}
+}}
+"#
+    ))
+}
+
+/// Render the save that follows a dispatch, skipped when the state did not change
+pub fn render_state_save() -> String {
+    r#"
+                        if self.state_machine.current_state != current_state {
+                            let state = &self.state_machine.current_state;
+                            self.state_machine.extended_state.state_store.save(state);
+                        }"#
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::lifecycle::Lifecycle;
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_persisted_state() {
+        let mut actor = create_test_actor();
+        actor.lifecycle = Lifecycle::default().with_persisted_state();
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let persistence = generator
+            .generate_persistence()
+            .unwrap()
+            .expect("Persistence module should be generated");
+        assert!(persistence.contains("pub trait ActorStateStore: Send + Sync {"));
+        assert!(persistence.contains("    fn load(&self) -> Option<ActorStates>;"));
+        assert!(persistence.contains("pub type ActorStateStoreRef = Arc<dyn ActorStateStore>;"));
+        assert!(persistence.contains("use std::sync::Mutex;"));
+
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(ext_state.contains("state_store: ActorStateStoreRef"));
+        let imports = generator.ctx().imports_for("actor::ext_state").unwrap();
+        assert!(
+            imports.contains(&"use crate::actor::persistence::ActorStateStoreRef;".to_string())
+        );
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "        let restored = self.state_machine.extended_state.state_store.load();"
+        ));
+        assert!(
+            runtime
+                .contains("            restored.as_ref().unwrap_or(&ActorStates::Update(Update)),")
+        );
+        assert!(runtime.contains(
+            "                            self.state_machine.extended_state.state_store.save(state);"
+        ));
+
+        let states = generator.generate_state_enum().unwrap();
+        assert!(
+            states.contains(
+                "#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]"
+            )
+        );
+        assert!(
+            generator
+                .root_modules()
+                .contains(&"persistence".to_string())
+        );
+    }
+
+    #[test]
+    fn test_no_persistence_by_default() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(generator.generate_persistence().unwrap().is_none());
+        assert!(
+            !generator
+                .generate_runtime()
+                .unwrap()
+                .contains("state_store")
+        );
+        assert!(
+            !generator
+                .generate_state_enum()
+                .unwrap()
+                .contains("serde::Serialize")
+        );
+    }
+}
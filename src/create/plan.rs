@@ -0,0 +1,249 @@
+use std::{fmt, path::PathBuf};
+
+use crate::blox::bridge::Bridge;
+use crate::create::{ActorGenerator, RenderError};
+
+/// A module that generation will write, with its resolved imports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModulePlan {
+    /// Module path relative to the crate root, e.g. `actor::component`
+    pub module: String,
+    /// File the module will be written to
+    pub file: PathBuf,
+    /// Resolved `use` statements for the module
+    pub imports: Vec<String>,
+    /// Types the module defines
+    pub defines: Vec<String>,
+}
+
+impl fmt::Display for ModulePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} ({})", self.module, self.file.display())?;
+        if self.defines.is_empty() {
+            writeln!(f, "  defines: -")?;
+        } else {
+            writeln!(f, "  defines: {}", self.defines.join(", "))?;
+        }
+        if self.imports.is_empty() {
+            writeln!(f, "  imports: -")
+        } else {
+            writeln!(f, "  imports:")?;
+            self.imports
+                .iter()
+                .try_for_each(|import| writeln!(f, "    {import}"))
+        }
+    }
+}
+
+impl ActorGenerator {
+    /// Plans every module generation would write, without touching the filesystem
+    pub fn plan(&self) -> Result<Vec<ModulePlan>, RenderError> {
+        let ctx = self.ctx();
+        let actor_mod = self.actor_module();
+        let mod_path = self.actor().create_mod_path();
+        let component = &self.actor().component;
+
+        let mut plans = vec![ModulePlan {
+            module: actor_mod.clone(),
+            file: mod_path.join("mod.rs"),
+            imports: Vec::new(),
+            defines: Vec::new(),
+        }];
+
+        for module in self.root_modules() {
+            let module_path = format!("{actor_mod}::{module}");
+            let (file, defines) = match module.as_str() {
+                "component" => (
+                    mod_path.join("component.rs"),
+                    vec![
+                        component.ident.clone(),
+                        component.message_receivers.ident.clone(),
+                        component.message_handles.ident.clone(),
+                        self.actor().id_ident(),
+                        format!("{}Inbox", self.actor().ident),
+                    ],
+                ),
+                "messaging" => (
+                    mod_path.join("messaging.rs"),
+                    component
+                        .message_set
+                        .iter()
+                        .flat_map(|ms| ms.defined_types().map(str::to_string))
+                        .collect(),
+                ),
+                "ext_state" => (
+                    mod_path.join("ext_state.rs"),
+                    vec![component.ext_state.ident().to_string()],
+                ),
+                "states" => (
+                    mod_path.join("states").join("mod.rs"),
+                    vec![component.states.state_enum.get().ident.clone()],
+                ),
+                "pool" => (
+                    mod_path.join("pool.rs"),
+                    vec![format!("{}Router", self.actor().ident)],
+                ),
+                "shard" => (
+                    mod_path.join("shard.rs"),
+                    vec![format!("{}Shards", self.actor().ident)],
+                ),
+                "bridge" => (
+                    mod_path.join("bridge.rs"),
+                    match &self.actor().bridge {
+                        Some(Bridge::Grpc(_)) => vec![
+                            "RpcRequest".to_string(),
+                            format!("{}GrpcService", self.actor().ident),
+                        ],
+                        Some(Bridge::Http(_)) => {
+                            vec!["HttpRequest".to_string(), "HttpError".to_string()]
+                        }
+                        None => Vec::new(),
+                    },
+                ),
+                "sequence" => (
+                    mod_path.join("sequence.rs"),
+                    vec![
+                        "Sequenced".to_string(),
+                        "SequenceCounter".to_string(),
+                        "SequenceCheck".to_string(),
+                        "SequenceTracker".to_string(),
+                        format!("{}Sequences", self.actor().ident),
+                    ],
+                ),
+                "rate_limit" => (
+                    mod_path.join("rate_limit.rs"),
+                    vec![
+                        "TokenBucket".to_string(),
+                        format!("{}RateLimits", self.actor().ident),
+                    ],
+                ),
+                "circuit_breaker" => (
+                    mod_path.join("circuit_breaker.rs"),
+                    vec![
+                        "CircuitState".to_string(),
+                        "BreakerError".to_string(),
+                        "CircuitBreaker".to_string(),
+                        format!("{}Breakers", self.actor().ident),
+                    ],
+                ),
+                "persistence" => (
+                    mod_path.join("persistence.rs"),
+                    vec![
+                        format!("{}StateStore", self.actor().ident),
+                        format!("{}StateStoreRef", self.actor().ident),
+                        "InMemoryStateStore".to_string(),
+                    ],
+                ),
+                "correlation" => {
+                    let mut defines = vec!["CorrelationId".to_string()];
+                    if self.actor().traces() {
+                        defines.push("TraceContext".to_string());
+                    }
+                    defines.push("Correlated".to_string());
+                    (mod_path.join("correlation.rs"), defines)
+                }
+                "health" => (
+                    mod_path.join("health.rs"),
+                    vec![
+                        "HealthCheck".to_string(),
+                        format!("{}Health", self.actor().ident),
+                    ],
+                ),
+                _ => (mod_path.join(format!("{module}.rs")), Vec::new()),
+            };
+            // Generated functions are registered in the graph along with what they use
+            let defines = defines
+                .into_iter()
+                .chain(self.graph().generated_functions(&module_path))
+                .collect();
+
+            plans.push(ModulePlan {
+                imports: ctx.imports_for(&module_path)?,
+                module: module_path,
+                file,
+                defines,
+            });
+
+            if module == "states" {
+                let groups = self.state_groups()?;
+                for state in &component.states.states {
+                    let state_mod = state.ident.to_lowercase();
+                    let state_path = format!("{actor_mod}::states::{state_mod}");
+                    plans.push(ModulePlan {
+                        imports: ctx.imports_for(&state_path)?,
+                        module: state_path,
+                        file: mod_path.join(self.state_file(state, groups.as_ref())),
+                        defines: vec![state.ident.clone()],
+                    });
+                }
+            }
+        }
+
+        Ok(plans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::lifecycle::{Lifecycle, RestartPolicy, RestartState};
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_plan_lists_every_module() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let plans = generator.plan().expect("Planning should succeed");
+
+        let modules = plans.iter().map(|p| p.module.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            modules,
+            vec![
+                "actor",
+                "actor::ext_state",
+                "actor::messaging",
+                "actor::runtime",
+                "actor::component",
+                "actor::states",
+                "actor::states::create",
+                "actor::states::update",
+            ]
+        );
+
+        let component = plans
+            .iter()
+            .find(|p| p.module == "actor::component")
+            .unwrap();
+        assert!(component.file.ends_with("actor/component.rs"));
+        assert!(component.defines.contains(&"ActorComponents".to_string()));
+        assert!(
+            component
+                .imports
+                .contains(&"use bloxide_tokio::components::Components;".to_string())
+        );
+
+        let rendered = component.to_string();
+        assert!(rendered.starts_with("actor::component ("));
+        assert!(rendered.contains("    use bloxide_tokio::components::Components;"));
+    }
+
+    #[test]
+    fn test_plan_lists_generated_functions() {
+        let mut actor = create_test_actor();
+        actor.lifecycle =
+            Lifecycle::default().with_restart(RestartPolicy::OnPanic, RestartState::Fresh);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let plans = generator.plan().expect("Planning should succeed");
+
+        let supervisor = plans
+            .iter()
+            .find(|p| p.module == "actor::supervisor")
+            .unwrap();
+        assert_eq!(supervisor.defines, vec!["supervise"]);
+        assert!(
+            supervisor
+                .imports
+                .contains(&"use crate::actor::component::ActorComponents;".to_string())
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::blox::pool::{Pool, Routing, Shard};
+use crate::create::{RenderCtx, RenderError};
+
+/// Render the pool module: the router and `spawn_pool`
+pub fn render_pool_module(ctx: &RenderCtx, pool: &Pool) -> Result<String, RenderError> {
+    pool.validate().map_err(RenderError::InvalidSpec)?;
+
+    let actor = ctx.actor;
+    let actor_name = &actor.ident;
+    let receivers = &actor.component.message_receivers.receivers;
+    let imports_section = ctx.imports_section(&format!("{}::pool", ctx.actor_module()))?;
+
+    let pick_member = match (pool.routing, &pool.key) {
+        (Routing::HashByKey, Some(key)) => format!("self.member_for(&{key})"),
+        _ => "self.next_member()".to_string(),
+    };
+    let send_methods = receivers
+        .iter()
+        .map(|receiver| {
+            let channel = receiver.channel_name();
+            let message = format!("Message<{}>", receiver.message_type);
+            format!(
+                r#"    /// Sends to the `{channel}` channel of the instance picked by the router
+    pub async fn send_{channel}(&self, message: {message}) -> Result<(), SendError<{message}>> {{
+        let member = {pick_member};
+        member.{channel}.send(message).await
+    }}"#
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let (routing_doc, routing_field, routing_init, routing_fn) = match pool.routing {
+        Routing::RoundRobin => (
+            "in turn",
+            "\n    next: AtomicUsize,",
+            "\n            next: AtomicUsize::new(0),",
+            r#"    fn next_member(&self) -> &{inbox} {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.members.len();
+        &self.members[index]
+    }"#,
+        ),
+        Routing::HashByKey => (
+            "by key",
+            "",
+            "",
+            r#"    fn member_for<K: Hash + ?Sized>(&self, key: &K) -> &{inbox} {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.members[hasher.finish() as usize % self.members.len()]
+    }"#,
+        ),
+    };
+    let routing_fn = routing_fn.replace("{inbox}", &format!("{actor_name}Inbox"));
+
+    Ok(format!(
+        r#"{imports_section}/// Routes messages across a pool of {actor_name} instances, {routing_doc}
+pub struct {actor_name}Router {{
+    members: Vec<{actor_name}Inbox>,{routing_field}
+}}
+
+impl {actor_name}Router {{
+    /// Creates a router over already spawned instances
+    pub fn new(members: Vec<{actor_name}Inbox>) -> Self {{
+        assert!(!members.is_empty(), "a pool needs at least one instance");
+        Self {{
+            members,{routing_init}
+        }}
+    }}
+
+    /// Gets the instances the router distributes across
+    pub fn members(&self) -> &[{actor_name}Inbox] {{
+        &self.members
+    }}
+
+{routing_fn}
+
+{send_methods}
+}}
+
+/// Spawns `n` {actor_name} instances and returns a router distributing messages across them
+///
+/// `spawn_instance` is called with the ID of each instance, numbered from 0, spawns it and
+/// returns its inbox. Panics if `n` is more instances than `{id_ident}` can number.
+pub fn spawn_pool<F>(n: usize, mut spawn_instance: F) -> {actor_name}Router
+where
+    F: FnMut({id_ident}) -> {actor_name}Inbox,
+{{
+    let members = (0..n)
+        .map(|i| {{
+            let id = <{id_type} as std::convert::TryFrom<usize>>::try_from(i)
+                .unwrap_or_else(|_| panic!("instance {{i}} has no {id_ident}"));
+            spawn_instance({id_ident}::from(id))
+        }})
+        .collect();
+    {actor_name}Router::new(members)
+}}
+"#,
+        id_ident = actor.id_ident(),
+        id_type = actor.id_type(),
+    ))
+}
+
+/// Render the shard module: a dispatcher owning one lazily spawned instance per key
+pub fn render_shard_module(ctx: &RenderCtx, shard: &Shard) -> Result<String, RenderError> {
+    let actor = ctx.actor;
+    let actor_name = &actor.ident;
+    let key_type = &shard.key_type;
+    let key = &shard.key;
+    let imports_section = ctx.imports_section(&format!("{}::shard", ctx.actor_module()))?;
+
+    let send_methods = actor
+        .component
+        .message_receivers
+        .receivers
+        .iter()
+        .map(|receiver| {
+            let channel = receiver.channel_name();
+            let message = format!("Message<{}>", receiver.message_type);
+            format!(
+                r#"    /// Sends to the `{channel}` channel of the instance owning the message's key
+    pub async fn send_{channel}(&self, message: {message}) -> Result<(), SendError<{message}>> {{
+        let key: {key_type} = {key};
+        self.shard_for(&key).{channel}.send(message).await
+    }}"#
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    Ok(format!(
+        r#"{imports_section}/// Dispatches messages to one {actor_name} instance per key, spawning instances on first use
+pub struct {actor_name}Shards<F> {{
+    shards: Mutex<HashMap<{key_type}, {actor_name}Inbox>>,
+    spawn_shard: Mutex<F>,
+}}
+
+impl<F> {actor_name}Shards<F>
+where
+    F: FnMut(&{key_type}) -> {actor_name}Inbox,
+{{
+    /// Creates a dispatcher that calls `spawn_shard` for every key it sees for the first time
+    pub fn new(spawn_shard: F) -> Self {{
+        Self {{
+            shards: Mutex::new(HashMap::new()),
+            spawn_shard: Mutex::new(spawn_shard),
+        }}
+    }}
+
+    /// Gets the inbox of the instance owning `key`, spawning the instance if needed
+    pub fn shard_for(&self, key: &{key_type}) -> {actor_name}Inbox {{
+        let mut shards = self.shards.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(inbox) = shards.get(key) {{
+            return inbox.clone();
+        }}
+
+        let mut spawn_shard = self.spawn_shard.lock().unwrap_or_else(|e| e.into_inner());
+        let inbox = (*spawn_shard)(key);
+        shards.insert(key.clone(), inbox.clone());
+        inbox
+    }}
+
+    /// Gets the number of instances spawned so far
+    pub fn len(&self) -> usize {{
+        self.shards.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }}
+
+    /// Whether no instance has been spawned yet
+    pub fn is_empty(&self) -> bool {{
+        self.len() == 0
+    }}
+
+{send_methods}
+}}
+"#
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::pool::{Pool, Shard};
+    use crate::create::{ActorGenerator, RenderError};
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_round_robin_pool() {
+        let actor = create_test_actor().with_pool(Pool::round_robin());
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let pool = generator
+            .generate_pool()
+            .unwrap()
+            .expect("Pool should be generated");
+
+        assert!(pool.contains("pub struct ActorRouter {\n    members: Vec<ActorInbox>,"));
+        assert!(pool.contains("let member = self.next_member();"));
+        assert!(
+            pool.contains("pub async fn send_standard(&self, message: Message<StandardPayload>)")
+        );
+        assert!(
+            pool.contains("pub fn spawn_pool<F>(n: usize, mut spawn_instance: F) -> ActorRouter")
+        );
+        assert!(pool.contains("    F: FnMut(ActorId) -> ActorInbox,"));
+        assert!(
+            pool.contains(
+                "            let id = <u16 as std::convert::TryFrom<usize>>::try_from(i)\n"
+            )
+        );
+        assert!(pool.contains("            spawn_instance(ActorId::from(id))\n"));
+        assert!(pool.contains("use crate::actor::component::ActorId;"));
+        assert!(pool.contains("use std::sync::atomic::AtomicUsize;"));
+        assert!(pool.contains("use crate::actor::component::ActorInbox;"));
+        assert!(!pool.contains("DefaultHasher"));
+        assert!(generator.root_modules().contains(&"pool".to_string()));
+    }
+
+    #[test]
+    fn test_hash_by_key_pool() {
+        let actor = create_test_actor().with_pool(Pool::hash_by_key("message.id()"));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let pool = generator.generate_pool().unwrap().unwrap();
+
+        assert!(pool.contains("let member = self.member_for(&message.id());"));
+        assert!(pool.contains("use std::collections::hash_map::DefaultHasher;"));
+        assert!(!pool.contains("AtomicUsize"));
+
+        let mut invalid = Pool::hash_by_key("message.id()");
+        invalid.key = None;
+        let generator = ActorGenerator::new(create_test_actor().with_pool(invalid))
+            .expect("Generator creation should succeed");
+        assert!(matches!(
+            generator.generate_pool(),
+            Err(RenderError::InvalidSpec(_))
+        ));
+    }
+
+    #[test]
+    fn test_no_pool_by_default() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(generator.generate_pool().unwrap().is_none());
+        assert!(!generator.root_modules().contains(&"pool".to_string()));
+    }
+
+    #[test]
+    fn test_shard_dispatcher() {
+        let actor = create_test_actor()
+            .with_shard(Shard::new("message.payload().tenant.clone()", "TenantId"));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let shard = generator
+            .generate_shard()
+            .unwrap()
+            .expect("Shard should be generated");
+
+        assert!(shard.contains("pub struct ActorShards<F> {"));
+        assert!(shard.contains("shards: Mutex<HashMap<TenantId, ActorInbox>>,"));
+        assert!(shard.contains("    F: FnMut(&TenantId) -> ActorInbox,"));
+        assert!(shard.contains(
+            "        let key: TenantId = message.payload().tenant.clone();\n        self.shard_for(&key).customargs.send(message).await"
+        ));
+        assert!(shard.contains("use std::collections::HashMap;"));
+        assert!(shard.contains("use crate::actor::component::ActorInbox;"));
+        assert!(generator.root_modules().contains(&"shard".to_string()));
+    }
+}
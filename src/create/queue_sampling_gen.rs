@@ -0,0 +1,172 @@
+use crate::blox::actor::{OVERLOADED_VARIANT, QUEUE_OVERLOAD_TYPE};
+use crate::create::RenderCtx;
+
+/// Render the payload of the `Overloaded` notification, defined next to the message set
+pub fn render_queue_overload(ctx: &RenderCtx) -> String {
+    if !ctx.actor.notifies_overload() {
+        return String::new();
+    }
+    format!(
+        r#"
+/// A receiver's queue held more messages than the overload threshold when it was sampled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct {QUEUE_OVERLOAD_TYPE} {{
+    /// Name of the overloaded channel
+    pub channel: &'static str,
+    /// Messages waiting on it
+    pub depth: usize,
+    /// Depth the channel was allowed before it counted as overloaded
+    pub threshold: usize,
+}}
+"#
+    )
+}
+
+/// Render the interval the runtime samples its queues on, created before the message loop
+pub fn render_queue_sampler(ctx: &RenderCtx) -> String {
+    ctx.actor
+        .queue_sampling
+        .as_ref()
+        .map(|sampling| {
+            format!(
+                "            let mut queue_sampler = tokio::time::interval(std::time::Duration::from_millis({}));\n",
+                sampling.interval_ms
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Render the runtime's `select!` arm sampling queue depths and lag, handing them to the
+/// `observe` hook and dispatching `Overloaded` with `dispatch` for every queue past the
+/// threshold
+pub fn render_queue_sampling_arm(ctx: &RenderCtx, dispatch: impl Fn(&str) -> String) -> String {
+    let Some(sampling) = &ctx.actor.queue_sampling else {
+        return String::new();
+    };
+    let depths = ctx
+        .actor
+        .component
+        .message_receivers
+        .receivers
+        .iter()
+        .map(|receiver| {
+            format!(
+                "                            (\"{}\", self.receivers.{}.len()),",
+                receiver.channel_name(),
+                receiver.ident
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let observe = sampling
+        .observe
+        .as_deref()
+        .map(|code| {
+            let code = code
+                .trim()
+                .lines()
+                .map(|line| format!("                        {line}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("\n{code}")
+        })
+        .unwrap_or_default();
+    let overload = match (
+        sampling.overload_threshold,
+        &ctx.actor.component.message_set,
+    ) {
+        (Some(threshold), Some(message_set)) => {
+            let message = format!(
+                "{}::{OVERLOADED_VARIANT}(Message::new(0, {QUEUE_OVERLOAD_TYPE} {{ channel, depth, threshold: {threshold} }}))",
+                message_set.get().ident
+            );
+            let dispatch = dispatch(&message)
+                .lines()
+                .map(|line| format!("    {line}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                r#"
+                        for (channel, depth) in depths.into_iter().filter(|(_, depth)| *depth > {threshold}) {{
+                            let current_state = self.state_machine.current_state.clone();
+{dispatch}
+                        }}"#
+            )
+        }
+        _ => String::new(),
+    };
+    let lag = if sampling.observe.is_some() {
+        "lag"
+    } else {
+        "_lag"
+    };
+
+    format!(
+        r#"                    scheduled = queue_sampler.tick() => {{
+                        let {lag} = scheduled.elapsed();
+                        let depths = [
+{depths}
+                        ];{observe}{overload}
+                    }}
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::blox::message_handlers::QueueSampling;
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_queue_sampling() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(
+            !generator
+                .generate_runtime()
+                .unwrap()
+                .contains("queue_sampler")
+        );
+
+        let actor = create_test_actor().with_queue_sampling(
+            QueueSampling::new(500)
+                .with_observe("metrics::record(&depths, lag);")
+                .with_overload_threshold(64),
+        );
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "            let mut queue_sampler = tokio::time::interval(std::time::Duration::from_millis(500));\n"
+        ));
+        assert!(runtime.contains(
+            "                    scheduled = queue_sampler.tick() => {\n                        let lag = scheduled.elapsed();\n                        let depths = [\n                            (\"standard\", self.receivers.standard_rx.len()),\n                            (\"customargs\", self.receivers.customargs_rx.len()),\n                        ];\n                        metrics::record(&depths, lag);\n"
+        ));
+        assert!(runtime.contains(
+            "                        for (channel, depth) in depths.into_iter().filter(|(_, depth)| *depth > 64) {\n                            let current_state = self.state_machine.current_state.clone();\n                            self.state_machine.dispatch(ActorMessageSet::Overloaded(Message::new(0, QueueOverload { channel, depth, threshold: 64 })), &current_state);\n                        }"
+        ));
+        assert!(runtime.contains("use crate::actor::messaging::QueueOverload;"));
+        assert!(runtime.contains("use bloxide_tokio::messaging::Message;"));
+
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains("    Overloaded(Message<QueueOverload>),\n"));
+        assert!(messaging.contains("pub struct QueueOverload {"));
+        // The notification has no channel of its own
+        let component = generator.generate_component().unwrap();
+        assert!(!component.contains("overloaded"));
+    }
+
+    #[test]
+    fn test_queue_sampling_validation() {
+        let mut actor = create_test_actor().with_queue_sampling(QueueSampling::new(0));
+        actor.path = PathBuf::from("tests/output/queue_sampling");
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate().unwrap_err().to_string(),
+            "Queue sampling interval must be at least 1 ms"
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use crate::blox::message_handlers::{MessageReceiver, RateLimitPolicy};
+use crate::create::{RenderCtx, RenderError};
+
+/// Render the rate limit module: the token bucket and one bucket per rate-limited receiver
+pub fn render_rate_limit_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    let actor_name = &ctx.actor.ident;
+    let imports_section = ctx.imports_section(&format!("{}::rate_limit", ctx.actor_module()))?;
+
+    let limited = ctx
+        .actor
+        .component
+        .message_receivers
+        .receivers
+        .iter()
+        .filter_map(|receiver| Some((&receiver.ident, receiver.rate_limit.as_ref()?)))
+        .collect::<Vec<_>>();
+    let fields = limited
+        .iter()
+        .map(|(ident, _)| format!("    pub {ident}: TokenBucket,"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let buckets = limited
+        .iter()
+        .map(|(ident, limit)| {
+            format!(
+                "            {ident}: TokenBucket::new({}, {}),",
+                limit.per_second,
+                limit.burst()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(format!(
+        r#"{imports_section}/// A token bucket refilled at a steady rate, holding at most a burst of tokens
+#[derive(Debug)]
+pub struct TokenBucket {{
+    per_second: f64,
+    burst: f64,
+    tokens: f64,
+    refilled: Instant,
+}}
+
+impl TokenBucket {{
+    /// A full bucket refilled with `per_second` tokens a second, up to `burst`
+    pub fn new(per_second: u32, burst: u32) -> Self {{
+        Self {{
+            per_second: f64::from(per_second),
+            burst: f64::from(burst),
+            tokens: f64::from(burst),
+            refilled: Instant::now(),
+        }}
+    }}
+
+    fn refill(&mut self) {{
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.burst);
+        self.refilled = now;
+    }}
+
+    /// Takes a token if one is available
+    pub fn try_acquire(&mut self) -> bool {{
+        self.refill();
+        if self.tokens < 1.0 {{
+            return false;
+        }}
+        self.tokens -= 1.0;
+        true
+    }}
+
+    /// Takes a token, first waiting for one to be refilled if the bucket is empty
+    pub async fn acquire(&mut self) {{
+        self.refill();
+        if self.tokens < 1.0 {{
+            let missing = 1.0 - self.tokens;
+            sleep(Duration::from_secs_f64(missing / self.per_second)).await;
+            self.refill();
+        }}
+        self.tokens = (self.tokens - 1.0).max(0.0);
+    }}
+}}
+
+/// Token buckets of {actor_name}'s rate-limited receivers, owned by the runtime
+#[derive(Debug)]
+pub struct {actor_name}RateLimits {{
+{fields}
+}}
+
+impl Default for {actor_name}RateLimits {{
+    fn default() -> Self {{
+        Self {{
+{buckets}
+        }}
+    }}
+}}
+"#
+    ))
+}
+
+/// Render the guard a rate-limited receiver's `select!` arm runs before dispatching
+///
+/// Delayed messages wait for a token; shed messages are dropped when none is left.
+pub fn render_rate_limit_guard(receiver: &MessageReceiver) -> String {
+    let ident = &receiver.ident;
+    match receiver.rate_limit.as_ref().map(|limit| limit.policy) {
+        None => String::new(),
+        Some(RateLimitPolicy::Delay) => {
+            format!("                        rate_limits.{ident}.acquire().await;\n")
+        }
+        Some(RateLimitPolicy::Shed) => format!(
+            r#"                        if !rate_limits.{ident}.try_acquire() {{
+                            continue;
+                        }}
+"#
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::message_handlers::{RateLimit, RateLimitPolicy};
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_rate_limited_receivers() {
+        let mut actor = create_test_actor();
+        let receivers = &mut actor.component.message_receivers.receivers;
+        receivers[0] = receivers[0]
+            .clone()
+            .with_rate_limit(RateLimit::new(10).with_burst(20));
+        receivers[1] = receivers[1]
+            .clone()
+            .with_rate_limit(RateLimit::new(5).with_policy(RateLimitPolicy::Shed));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let rate_limit = generator
+            .generate_rate_limit()
+            .unwrap()
+            .expect("Rate limit module should be generated");
+        assert!(rate_limit.contains("pub struct TokenBucket {"));
+        assert!(rate_limit.contains(
+            "pub struct ActorRateLimits {\n    pub standard_rx: TokenBucket,\n    pub customargs_rx: TokenBucket,\n}"
+        ));
+        assert!(rate_limit.contains(
+            "            standard_rx: TokenBucket::new(10, 20),\n            customargs_rx: TokenBucket::new(5, 5),"
+        ));
+        assert!(rate_limit.contains("use tokio::time::Instant;"));
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "            let mut rate_limits = ActorRateLimits::default();\n            loop {"
+        ));
+        assert!(runtime.contains(
+            "                    Some(msg) = self.receivers.standard_rx.recv() => {\n                        rate_limits.standard_rx.acquire().await;\n"
+        ));
+        assert!(runtime.contains(
+            "                        if !rate_limits.customargs_rx.try_acquire() {\n                            continue;\n                        }\n"
+        ));
+        assert!(runtime.contains("use crate::actor::rate_limit::ActorRateLimits;"));
+        assert!(generator.root_modules().contains(&"rate_limit".to_string()));
+    }
+
+    #[test]
+    fn test_rate_limit_validation() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(generator.generate_rate_limit().unwrap().is_none());
+        assert!(
+            !generator
+                .generate_runtime()
+                .unwrap()
+                .contains("rate_limits")
+        );
+
+        let mut actor = create_test_actor();
+        let receivers = &mut actor.component.message_receivers.receivers;
+        receivers[0] = receivers[0]
+            .clone()
+            .with_rate_limit(RateLimit::new(10).with_burst(0));
+        assert_eq!(
+            actor.component.validate(),
+            Err(
+                "Rate limit of receiver 'standard_rx' must allow at least one message per second \
+                 and a burst of one"
+                    .to_string()
+            )
+        );
+    }
+}
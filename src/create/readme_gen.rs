@@ -0,0 +1,283 @@
+use crate::blox::actor::{HEALTH_RECEIVER, SELF_RECEIVER};
+use crate::create::{ModulePlan, RenderCtx};
+
+/// Template the README is rendered from unless the options give another
+///
+/// `{{name}}` placeholders are replaced with spec-derived content: `actor`, `module`, `spec`,
+/// `modules`, `states`, `messages` and `wiring`. Unknown placeholders are left as written.
+pub const DEFAULT_README_TEMPLATE: &str = r#"# {{actor}}
+
+Generated by bloxml from {{spec}}.
+Edit the spec and regenerate rather than editing the files in this directory.
+
+## Modules
+
+| Module | File | Defines |
+| --- | --- | --- |
+{{modules}}
+
+## States
+
+{{states}}
+
+## Messages
+
+{{messages}}
+
+## Wiring
+
+{{wiring}}
+"#;
+
+/// Render the README describing the generated module, its layout and how to wire the actor
+pub fn render_readme(ctx: &RenderCtx, plans: &[ModulePlan]) -> String {
+    let template = ctx
+        .options
+        .readme_template
+        .as_deref()
+        .unwrap_or(DEFAULT_README_TEMPLATE);
+    let spec = match &ctx.options.spec_file {
+        Some(spec) => format!("`{}`", spec.display()),
+        None => "its spec".to_string(),
+    };
+    [
+        ("actor", ctx.actor.ident.clone()),
+        ("module", format!("`{}`", ctx.actor_module())),
+        ("spec", spec),
+        ("modules", render_modules(ctx, plans)),
+        ("states", render_states(ctx)),
+        ("messages", render_messages(ctx)),
+        ("wiring", render_wiring(ctx)),
+    ]
+    .into_iter()
+    .fold(template.to_string(), |readme, (name, value)| {
+        readme.replace(&format!("{{{{{name}}}}}"), &value)
+    })
+}
+
+/// One table row per planned module, with its file relative to the actor's directory
+fn render_modules(ctx: &RenderCtx, plans: &[ModulePlan]) -> String {
+    let mod_path = ctx.actor.create_mod_path();
+    plans
+        .iter()
+        .map(|plan| {
+            let file = plan.file.strip_prefix(&mod_path).unwrap_or(&plan.file);
+            let defines = if plan.defines.is_empty() {
+                "-".to_string()
+            } else {
+                plan.defines
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            format!("| `{}` | `{}` | {defines} |", plan.module, file.display())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_states(ctx: &RenderCtx) -> String {
+    let states = &ctx.actor.component.states.states;
+    states
+        .iter()
+        .enumerate()
+        .map(|(i, state)| {
+            let mut line = format!("- `{}`", state.ident);
+            if let Some(parent) = &state.parent {
+                line.push_str(&format!(", inside `{parent}`"));
+            }
+            if i == 0 {
+                line.push_str(" (initial)");
+            }
+            for transition in &state.transitions {
+                line.push_str(&format!(
+                    "\n  - on `{}` goes to `{}`",
+                    transition.on, transition.target
+                ));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_messages(ctx: &RenderCtx) -> String {
+    let component = &ctx.actor.component;
+    let Some(message_set) = &component.message_set else {
+        return "The actor has no message set.".to_string();
+    };
+    let receivers = component
+        .message_receivers
+        .receivers
+        .iter()
+        .filter(|receiver| receiver.ident != HEALTH_RECEIVER && receiver.ident != SELF_RECEIVER);
+    message_set
+        .get()
+        .variants
+        .iter()
+        .zip(receivers)
+        .map(|(variant, receiver)| {
+            format!(
+                "- `{}::{}` arrives on `{}` carrying `{}`",
+                message_set.get().ident,
+                variant.ident,
+                receiver.ident,
+                receiver.message_type
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Steps for plugging the generated module into the crate and starting the actor
+fn render_wiring(ctx: &RenderCtx) -> String {
+    let actor = ctx.actor;
+    let actor_name = &actor.ident;
+    let component = &actor.component;
+    let actor_dir = actor.ident.to_lowercase();
+
+    let mut steps = Vec::new();
+    match actor.group_segments().first() {
+        Some(group) => steps.push(format!(
+            "Declare `pub mod {group};` next to `{}`; bloxml declares the modules nested in it.",
+            actor.path.display()
+        )),
+        None => steps.push(format!(
+            "Declare `pub mod {actor_dir};` in the module of `{}`.",
+            actor.path.display()
+        )),
+    }
+
+    let init_args = component.ext_state.init_args();
+    let args = init_args
+        .fields
+        .iter()
+        .map(|field| format!("`{}: {}`", field.ident(), field.ty()))
+        .collect::<Vec<_>>();
+    steps.push(if args.is_empty() {
+        format!(
+            "Build `{}` from `{}`, which takes no arguments.",
+            component.ext_state.ident(),
+            init_args.ident
+        )
+    } else {
+        format!(
+            "Build `{}` from `{}`: {}.",
+            component.ext_state.ident(),
+            init_args.ident,
+            args.join(", ")
+        )
+    });
+
+    let channels = |idents: Vec<&String>| {
+        idents
+            .into_iter()
+            .map(|ident| format!("`{ident}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    steps.push(format!(
+        "Create the channels of `{}` ({}) and `{}` ({}), keeping the sending side of each \
+         receiver in an `{actor_name}Inbox` for whoever sends to the actor.",
+        component.message_receivers.ident,
+        channels(
+            component
+                .message_receivers
+                .receivers
+                .iter()
+                .map(|r| &r.ident)
+                .collect()
+        ),
+        component.message_handles.ident,
+        channels(
+            component
+                .message_handles
+                .handles
+                .iter()
+                .map(|h| &h.ident)
+                .collect()
+        ),
+    ));
+    let initial = component
+        .states
+        .states
+        .first()
+        .map(|state| format!(", which starts in `{}`", state.ident))
+        .unwrap_or_default();
+    steps.push(format!(
+        "Spawn `Blox<{}>` with them through `Runnable::run`{initial}.",
+        component.ident
+    ));
+    if actor.pool.is_some() {
+        steps.push(format!(
+            "To run several instances, spawn them with `pool::spawn_pool` and send through the \
+             returned `{actor_name}Router`."
+        ));
+    }
+    if actor.shard.is_some() {
+        steps.push(format!(
+            "To run one instance per key, send through `shard::{actor_name}Shards`."
+        ));
+    }
+    if actor.bridge.is_some() {
+        steps.push(
+            "Serve the `bridge` module to accept requests from outside the process.".to_string(),
+        );
+    }
+
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| format!("{}. {step}", i + 1))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create::{ActorGenerator, GeneratorOptions};
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_readme() {
+        let options = GeneratorOptions::builder()
+            .readme(true)
+            .spec_file("specs/actor.json")
+            .build()
+            .unwrap();
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        let readme = generator.generate_readme().unwrap().unwrap();
+
+        assert!(readme.starts_with("# Actor\n\nGenerated by bloxml from `specs/actor.json`.\n"));
+        assert!(readme.contains("| `actor::component` | `component.rs` | `ActorComponents`,"));
+        assert!(readme.contains("| `actor::states::create` | `states/create.rs` | `Create` |"));
+        assert!(readme.contains("- `Create` (initial)"));
+        assert!(readme.contains("Build `ActorExtState` from `ActorInitArgs`"));
+        assert!(readme.contains("Spawn `Blox<ActorComponents>`"));
+        assert!(!readme.contains("{{"));
+
+        let files = generator.render_files().unwrap();
+        assert!(
+            files
+                .iter()
+                .any(|(file, _)| file.as_os_str() == "README.md")
+        );
+
+        // Templates only get the sections they ask for
+        let options = GeneratorOptions::default()
+            .with_readme(true)
+            .with_readme_template(Some(
+                "{{actor}} lives in {{module}}; {{unknown}}".to_string(),
+            ));
+        let generator = ActorGenerator::with_options(create_test_actor(), options).unwrap();
+        assert_eq!(
+            generator.generate_readme().unwrap().as_deref(),
+            Some("Actor lives in `actor`; {{unknown}}")
+        );
+
+        let generator = ActorGenerator::new(create_test_actor()).unwrap();
+        assert_eq!(generator.generate_readme().unwrap(), None);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::blox::message_handlers::Relay;
+use crate::create::RenderCtx;
+
+/// Render the body of a runtime arm sending a message on through the relay's handle instead
+/// of dispatching it
+///
+/// The send goes through the handle's `send_*` helper, so it waits or drops by the handle's
+/// overflow policy and is seen by its circuit breaker. A failed send drops the message, as an
+/// emit does.
+pub fn render_relay(ctx: &RenderCtx, relay: &Relay) -> String {
+    let helper = ctx
+        .actor
+        .component
+        .message_handles
+        .get_handle(&relay.handle)
+        .map(|handle| handle.helper_name())
+        .unwrap_or(&relay.handle);
+    let comment = format!(
+        "                        // Relayed through `{}` instead of being dispatched\n",
+        relay.handle
+    );
+    match &relay.transform {
+        None => format!(
+            "{comment}                        let _ = self.state_machine.self_handles.send_{helper}(msg).await;"
+        ),
+        Some(transform) => format!(
+            r#"{comment}                        let payload = msg.payload();
+                        let relayed = Message::new(0, {transform});
+                        let _ = self.state_machine.self_handles.send_{helper}(relayed).await;"#
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::message_handlers::Relay;
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_relays() {
+        let actor = create_test_actor().with_relays(vec![
+            Relay::new("CustomValue1", "standard_handle"),
+            Relay::new("CustomValue2", "standard_handle")
+                .with_transform("StandardPayload::from(payload.clone())"),
+        ]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let runtime = generator.generate_runtime().unwrap();
+
+        assert!(runtime.contains(
+            "                    Some(msg) = self.receivers.standard_rx.recv() => {\n                        // Relayed through `standard_handle` instead of being dispatched\n                        let _ = self.state_machine.self_handles.send_standard(msg).await;\n                    }"
+        ));
+        assert!(runtime.contains(
+            "                        let payload = msg.payload();\n                        let relayed = Message::new(0, StandardPayload::from(payload.clone()));\n                        let _ = self.state_machine.self_handles.send_standard(relayed).await;"
+        ));
+        assert!(runtime.contains("use bloxide_tokio::messaging::Message;\n"));
+        assert!(!runtime.contains("dispatch("));
+    }
+
+    #[test]
+    fn test_validate_relays() {
+        let validate = |relay: Relay| {
+            create_test_actor()
+                .with_relays(vec![relay])
+                .validate_relays()
+        };
+
+        assert!(validate(Relay::new("CustomValue2", "customargs_handle")).is_ok());
+        assert_eq!(
+            validate(Relay::new("Missing", "customargs_handle")),
+            Err("Relay of variant 'Missing' has no receiver to relay from".to_string())
+        );
+        assert_eq!(
+            validate(Relay::new("CustomValue2", "missing_handle")),
+            Err(
+                "Variant 'CustomValue2' is relayed through unknown handle 'missing_handle'"
+                    .to_string()
+            )
+        );
+        assert!(
+            validate(Relay::new("CustomValue2", "standard_handle"))
+                .unwrap_err()
+                .ends_with("relay it with a `transform`")
+        );
+        assert!(
+            validate(
+                Relay::new("CustomValue2", "standard_handle").with_transform("payload.into()")
+            )
+            .is_ok()
+        );
+
+        let actor = create_test_actor().with_relays(vec![
+            Relay::new("CustomValue1", "standard_handle"),
+            Relay::new("CustomValue1", "standard_handle"),
+        ]);
+        assert_eq!(
+            actor.validate_relays(),
+            Err("Variant 'CustomValue1' is handed on more than once".to_string())
+        );
+    }
+}
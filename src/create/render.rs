@@ -0,0 +1,873 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::blox::actor::Actor;
+pub use crate::blox::message_handlers::WireFormat;
+use crate::create::{LayeringRules, LicenseHeader};
+use crate::graph::CodeGenGraph;
+
+/// How imports are produced when a module is missing from the graph
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportFallback {
+    /// Fail with `RenderError::ModuleNotFound`
+    #[default]
+    Error,
+    /// Use the framework imports the module kind always needs
+    Defaults,
+    /// Emit no imports
+    Empty,
+}
+
+impl WireFormat {
+    /// Error type and body of a generated function encoding `value` into bytes
+    pub(crate) fn encoder(&self, value: &str) -> (&'static str, String) {
+        match self {
+            WireFormat::Json => ("serde_json::Error", format!("serde_json::to_vec({value})")),
+            WireFormat::Cbor => (
+                "ciborium::ser::Error<std::io::Error>",
+                format!(
+                    "let mut bytes = Vec::new();\n        ciborium::into_writer({value}, &mut bytes)?;\n        Ok(bytes)"
+                ),
+            ),
+            WireFormat::Postcard => ("postcard::Error", format!("postcard::to_allocvec({value})")),
+        }
+    }
+
+    /// Error type and body of a generated function decoding `bytes`
+    pub(crate) fn decoder(&self) -> (&'static str, &'static str) {
+        match self {
+            WireFormat::Json => ("serde_json::Error", "serde_json::from_slice(bytes)"),
+            WireFormat::Cbor => (
+                "ciborium::de::Error<std::io::Error>",
+                "ciborium::from_reader(bytes)",
+            ),
+            WireFormat::Postcard => ("postcard::Error", "postcard::from_bytes(bytes)"),
+        }
+    }
+
+    /// `Cargo.toml` dependency line of the crate implementing the format
+    pub(crate) fn dependency(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "serde_json = \"1\"",
+            WireFormat::Cbor => "ciborium = \"0.2\"",
+            WireFormat::Postcard => "postcard = { version = \"1\", features = [\"alloc\"] }",
+        }
+    }
+}
+
+/// Rust edition the generated code is written for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Edition {
+    E2018,
+    E2021,
+    #[default]
+    E2024,
+}
+
+impl Edition {
+    pub fn year(&self) -> &'static str {
+        match self {
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+
+    /// First Rust release that supports the edition
+    pub fn min_rust_version(&self) -> RustVersion {
+        match self {
+            Edition::E2018 => RustVersion::new(1, 31),
+            Edition::E2021 => RustVersion::new(1, 56),
+            Edition::E2024 => RustVersion::new(1, 85),
+        }
+    }
+
+    /// Whether `Future` is in the prelude, as it is from the 2024 edition on
+    pub fn has_future_in_prelude(&self) -> bool {
+        *self >= Edition::E2024
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.year())
+    }
+}
+
+impl FromStr for Edition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2018" => Ok(Edition::E2018),
+            "2021" => Ok(Edition::E2021),
+            "2024" => Ok(Edition::E2024),
+            _ => Err(format!(
+                "unknown edition '{s}', expected 2018, 2021 or 2024"
+            )),
+        }
+    }
+}
+
+/// How the state enum dispatches its `State` methods to the state it holds
+///
+/// This only picks the state. Each state's `handle_message` still matches on the variants of
+/// the message set either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DispatchStrategy {
+    /// A `match` over every state in each method
+    #[default]
+    Match,
+    /// A single `match` turning the state into a `&dyn State` the methods are called on,
+    /// generating less code for actors with many states at the cost of dynamic calls; it
+    /// does nothing for actors with few states and many messages
+    TraitObject,
+}
+
+impl fmt::Display for DispatchStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DispatchStrategy::Match => write!(f, "match"),
+            DispatchStrategy::TraitObject => write!(f, "trait-object"),
+        }
+    }
+}
+
+impl FromStr for DispatchStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "match" => Ok(DispatchStrategy::Match),
+            "trait-object" => Ok(DispatchStrategy::TraitObject),
+            _ => Err(format!(
+                "unknown dispatch strategy '{s}', expected match or trait-object"
+            )),
+        }
+    }
+}
+
+/// Line endings of the generated files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEndings {
+    /// `\n`, whatever the platform
+    #[default]
+    Lf,
+    /// `\r\n`, whatever the platform
+    Crlf,
+    /// `\r\n` when bloxml runs on Windows, `\n` elsewhere
+    Native,
+}
+
+impl LineEndings {
+    /// Converts the `\n` line endings code is rendered with
+    pub fn apply<'a>(&self, content: &'a str) -> Cow<'a, str> {
+        let crlf = match self {
+            LineEndings::Lf => false,
+            LineEndings::Crlf => true,
+            LineEndings::Native => cfg!(windows),
+        };
+        if !crlf || !content.contains('\n') {
+            return Cow::Borrowed(content);
+        }
+        // Lines already ending in `\r\n`, like those of a template, are kept as they are
+        Cow::Owned(content.replace("\r\n", "\n").replace('\n', "\r\n"))
+    }
+}
+
+impl fmt::Display for LineEndings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineEndings::Lf => write!(f, "lf"),
+            LineEndings::Crlf => write!(f, "crlf"),
+            LineEndings::Native => write!(f, "native"),
+        }
+    }
+}
+
+impl FromStr for LineEndings {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lf" => Ok(LineEndings::Lf),
+            "crlf" => Ok(LineEndings::Crlf),
+            "native" => Ok(LineEndings::Native),
+            _ => Err(format!(
+                "unknown line endings '{s}', expected lf, crlf or native"
+            )),
+        }
+    }
+}
+
+/// A Rust release, compared by major and minor version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RustVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl RustVersion {
+    /// `let ... else` statements
+    pub const LET_ELSE: RustVersion = RustVersion::new(1, 65);
+    /// `impl Trait` in the return type of trait methods
+    pub const RETURN_POSITION_IMPL_TRAIT: RustVersion = RustVersion::new(1, 75);
+
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+}
+
+impl fmt::Display for RustVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for RustVersion {
+    type Err = String;
+
+    /// Parses `1.70` or `1.70.0`; the patch version is ignored
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid Rust version '{s}', expected MAJOR.MINOR");
+        let mut parts = s.split('.');
+        let mut next = || parts.next().and_then(|part| part.parse().ok());
+        let (Some(major), Some(minor)) = (next(), next()) else {
+            return Err(invalid());
+        };
+        if parts
+            .next()
+            .is_some_and(|patch| patch.parse::<u32>().is_err())
+            || parts.next().is_some()
+        {
+            return Err(invalid());
+        }
+        Ok(Self::new(major, minor))
+    }
+}
+
+/// Options that influence how code is rendered and written
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeneratorOptions {
+    pub import_fallback: ImportFallback,
+    /// Derive `Debug` and `Clone` on the message set even if the spec doesn't ask for it
+    pub derive_message_traits: bool,
+    /// Keep a `.bak` copy of every existing file whose contents change
+    pub backup: bool,
+    /// Fail instead of replacing existing files whose contents differ
+    pub no_overwrite: bool,
+    /// Derive serde on the message set and generate `to_wire`/`from_wire` in this format
+    pub wire_format: Option<WireFormat>,
+    /// Edition the generated code is written for
+    pub edition: Edition,
+    /// Oldest Rust release the generated code must compile with, if older than the latest
+    pub msrv: Option<RustVersion>,
+    /// Allow the clippy lints each generated file is known to trigger for some specs
+    pub clippy_allows: bool,
+    /// How the state enum dispatches to its states, leaving message matching as it is
+    pub dispatch: DispatchStrategy,
+    /// Assert the framework bounds of the generated types with `static_assertions`
+    pub static_assertions: bool,
+    /// Fail generation when an extended state field's type is known not to be `Send` or `Sync`
+    pub deny_non_send: bool,
+    /// Lines `states/mod.rs` may reach before it is split into submodules grouping the states
+    /// by first letter
+    pub max_file_lines: Option<usize>,
+    /// SPDX header written at the top of every generated file
+    pub license_header: Option<LicenseHeader>,
+    /// Generate `cargo-fuzz` targets for the wire codecs in the crate's `fuzz/` directory
+    pub fuzz_targets: bool,
+    /// Generate loom tests for the locks of shared extended state fields
+    pub loom_tests: bool,
+    /// Also assert a state's invariants after it handles a message, not only on entry
+    pub invariants_after_messages: bool,
+    /// Reject specs with free-form Rust code, allowing only declarative constructs and
+    /// literal values
+    pub restricted: bool,
+    /// Write a runnable `examples/<actor>_demo.rs` into the crate, unless it already has one
+    pub demo_example: bool,
+    /// Types imported like framework types, by name, e.g. from a crate shared between actors
+    pub framework_types: BTreeMap<String, String>,
+    /// File of `NAME=value` lines generation looks for secrets missing from the environment
+    /// in; the generated code reads the file named by `BLOXML_SECRETS_FILE` instead
+    pub secrets_file: Option<PathBuf>,
+    /// Write a `README.md` describing the module next to the generated code
+    pub readme: bool,
+    /// Template the README is rendered from instead of the default one
+    pub readme_template: Option<String>,
+    /// Spec the actor is generated from, as the README refers to it
+    pub spec_file: Option<PathBuf>,
+    /// Dependencies between generated modules the project forbids
+    pub layering: LayeringRules,
+    /// Line endings of the written files
+    pub line_endings: LineEndings,
+}
+
+impl GeneratorOptions {
+    /// Starts building options from the defaults
+    pub fn builder() -> GeneratorOptionsBuilder {
+        GeneratorOptionsBuilder::default()
+    }
+
+    /// Sets how imports are produced for modules missing from the graph
+    pub fn with_import_fallback(mut self, import_fallback: ImportFallback) -> Self {
+        self.import_fallback = import_fallback;
+        self
+    }
+
+    /// Sets whether `Debug` and `Clone` are always derived on the message set
+    pub fn with_derive_message_traits(mut self, derive_message_traits: bool) -> Self {
+        self.derive_message_traits = derive_message_traits;
+        self
+    }
+
+    /// Sets whether `.bak` copies are kept of files being replaced
+    pub fn with_backup(mut self, backup: bool) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Sets whether generation fails rather than replacing files that differ
+    pub fn with_no_overwrite(mut self, no_overwrite: bool) -> Self {
+        self.no_overwrite = no_overwrite;
+        self
+    }
+
+    /// Sets the format of the generated wire codecs, if any
+    pub fn with_wire_format(mut self, wire_format: Option<WireFormat>) -> Self {
+        self.wire_format = wire_format;
+        self
+    }
+
+    /// Sets the edition the generated code is written for
+    pub fn with_edition(mut self, edition: Edition) -> Self {
+        self.edition = edition;
+        self
+    }
+
+    /// Sets the oldest Rust release the generated code must compile with
+    pub fn with_msrv(mut self, msrv: Option<RustVersion>) -> Self {
+        self.msrv = msrv;
+        self
+    }
+
+    /// Sets whether generated files allow the clippy lints they are known to trigger
+    pub fn with_clippy_allows(mut self, clippy_allows: bool) -> Self {
+        self.clippy_allows = clippy_allows;
+        self
+    }
+
+    /// Sets how the state enum dispatches to its states
+    pub fn with_dispatch(mut self, dispatch: DispatchStrategy) -> Self {
+        self.dispatch = dispatch;
+        self
+    }
+
+    /// Sets whether generated files assert the framework bounds of their types
+    pub fn with_static_assertions(mut self, static_assertions: bool) -> Self {
+        self.static_assertions = static_assertions;
+        self
+    }
+
+    /// Sets whether extended state fields that are not `Send` or `Sync` fail generation
+    pub fn with_deny_non_send(mut self, deny_non_send: bool) -> Self {
+        self.deny_non_send = deny_non_send;
+        self
+    }
+
+    /// Sets whether a demo example is written into the crate
+    pub fn with_demo_example(mut self, demo_example: bool) -> Self {
+        self.demo_example = demo_example;
+        self
+    }
+
+    /// Sets whether loom tests are generated for shared extended state fields
+    pub fn with_loom_tests(mut self, loom_tests: bool) -> Self {
+        self.loom_tests = loom_tests;
+        self
+    }
+
+    /// Sets whether state invariants are also asserted after every message
+    pub fn with_invariants_after_messages(mut self, invariants_after_messages: bool) -> Self {
+        self.invariants_after_messages = invariants_after_messages;
+        self
+    }
+
+    /// Sets whether specs with free-form Rust code are rejected
+    pub fn with_restricted(mut self, restricted: bool) -> Self {
+        self.restricted = restricted;
+        self
+    }
+
+    /// Sets whether `cargo-fuzz` targets are generated for the wire codecs
+    pub fn with_fuzz_targets(mut self, fuzz_targets: bool) -> Self {
+        self.fuzz_targets = fuzz_targets;
+        self
+    }
+
+    /// Sets the SPDX header written at the top of every generated file, or none
+    pub fn with_license_header(mut self, license_header: Option<LicenseHeader>) -> Self {
+        self.license_header = license_header;
+        self
+    }
+
+    /// Sets the line endings of the written files
+    pub fn with_line_endings(mut self, line_endings: LineEndings) -> Self {
+        self.line_endings = line_endings;
+        self
+    }
+
+    /// Sets the dependencies between generated modules the project forbids
+    pub fn with_layering(mut self, layering: LayeringRules) -> Self {
+        self.layering = layering;
+        self
+    }
+
+    /// Sets the lines `states/mod.rs` may reach before it is split, or no limit
+    pub fn with_max_file_lines(mut self, max_file_lines: Option<usize>) -> Self {
+        self.max_file_lines = max_file_lines;
+        self
+    }
+
+    /// Sets the file generation looks for secrets missing from the environment in
+    pub fn with_secrets_file(mut self, secrets_file: Option<PathBuf>) -> Self {
+        self.secrets_file = secrets_file;
+        self
+    }
+
+    /// Sets whether a `README.md` is written next to the generated code
+    pub fn with_readme(mut self, readme: bool) -> Self {
+        self.readme = readme;
+        self
+    }
+
+    /// Sets the template the README is rendered from, or the default one
+    pub fn with_readme_template(mut self, readme_template: Option<String>) -> Self {
+        self.readme_template = readme_template;
+        self
+    }
+
+    /// Sets the spec the actor is generated from
+    pub fn with_spec_file(mut self, spec_file: Option<PathBuf>) -> Self {
+        self.spec_file = spec_file;
+        self
+    }
+
+    /// Whether the generated code may use what `version` introduced
+    pub fn supports(&self, version: RustVersion) -> bool {
+        self.msrv.is_none_or(|msrv| msrv >= version)
+    }
+
+    /// Checks that the MSRV is recent enough for the edition
+    pub fn validate_target(&self) -> Result<(), String> {
+        match self.msrv {
+            Some(msrv) if msrv < self.edition.min_rust_version() => Err(format!(
+                "Rust {msrv} predates the {} edition, which needs Rust {}",
+                self.edition,
+                self.edition.min_rust_version()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Builds [`GeneratorOptions`] in layers, such as the defaults, then `bloxml.toml`, then
+/// command line flags, checking the combination once every layer is applied
+#[derive(Debug, Clone, Default)]
+pub struct GeneratorOptionsBuilder {
+    options: GeneratorOptions,
+    /// File the README template is read from when the options are built
+    readme_template_file: Option<PathBuf>,
+}
+
+impl GeneratorOptionsBuilder {
+    /// Sets how imports are produced for modules missing from the graph
+    pub fn import_fallback(mut self, import_fallback: ImportFallback) -> Self {
+        self.options.import_fallback = import_fallback;
+        self
+    }
+
+    /// Sets whether `Debug` and `Clone` are always derived on the message set
+    pub fn derive_message_traits(mut self, derive_message_traits: bool) -> Self {
+        self.options.derive_message_traits = derive_message_traits;
+        self
+    }
+
+    /// Sets whether `.bak` copies are kept of files being replaced
+    pub fn backup(mut self, backup: bool) -> Self {
+        self.options.backup = backup;
+        self
+    }
+
+    /// Sets whether generation fails rather than replacing files that differ
+    pub fn no_overwrite(mut self, no_overwrite: bool) -> Self {
+        self.options.no_overwrite = no_overwrite;
+        self
+    }
+
+    /// Sets the format of the generated wire codecs
+    pub fn wire_format(mut self, wire_format: WireFormat) -> Self {
+        self.options.wire_format = Some(wire_format);
+        self
+    }
+
+    /// Sets the edition the generated code is written for
+    pub fn edition(mut self, edition: Edition) -> Self {
+        self.options.edition = edition;
+        self
+    }
+
+    /// Sets the oldest Rust release the generated code must compile with
+    pub fn msrv(mut self, msrv: RustVersion) -> Self {
+        self.options.msrv = Some(msrv);
+        self
+    }
+
+    /// Sets whether generated files allow the clippy lints they are known to trigger
+    pub fn clippy_allows(mut self, clippy_allows: bool) -> Self {
+        self.options.clippy_allows = clippy_allows;
+        self
+    }
+
+    /// Sets how the state enum dispatches to its states
+    pub fn dispatch(mut self, dispatch: DispatchStrategy) -> Self {
+        self.options.dispatch = dispatch;
+        self
+    }
+
+    /// Sets whether generated files assert the framework bounds of their types
+    pub fn static_assertions(mut self, static_assertions: bool) -> Self {
+        self.options.static_assertions = static_assertions;
+        self
+    }
+
+    /// Sets whether extended state fields that are not `Send` or `Sync` fail generation
+    pub fn deny_non_send(mut self, deny_non_send: bool) -> Self {
+        self.options.deny_non_send = deny_non_send;
+        self
+    }
+
+    /// Sets whether a demo example is written into the crate
+    pub fn demo_example(mut self, demo_example: bool) -> Self {
+        self.options.demo_example = demo_example;
+        self
+    }
+
+    /// Sets whether loom tests are generated for shared extended state fields
+    pub fn loom_tests(mut self, loom_tests: bool) -> Self {
+        self.options.loom_tests = loom_tests;
+        self
+    }
+
+    /// Sets whether state invariants are also asserted after every message
+    pub fn invariants_after_messages(mut self, invariants_after_messages: bool) -> Self {
+        self.options.invariants_after_messages = invariants_after_messages;
+        self
+    }
+
+    /// Sets whether specs with free-form Rust code are rejected
+    pub fn restricted(mut self, restricted: bool) -> Self {
+        self.options.restricted = restricted;
+        self
+    }
+
+    /// Sets whether `cargo-fuzz` targets are generated for the wire codecs
+    pub fn fuzz_targets(mut self, fuzz_targets: bool) -> Self {
+        self.options.fuzz_targets = fuzz_targets;
+        self
+    }
+
+    /// Sets the SPDX header written at the top of every generated file
+    pub fn license_header(mut self, license_header: LicenseHeader) -> Self {
+        self.options.license_header = Some(license_header);
+        self
+    }
+
+    /// Sets the line endings of the written files
+    pub fn line_endings(mut self, line_endings: LineEndings) -> Self {
+        self.options.line_endings = line_endings;
+        self
+    }
+
+    /// Sets the dependencies between generated modules the project forbids
+    pub fn layering(mut self, layering: LayeringRules) -> Self {
+        self.options.layering = layering;
+        self
+    }
+
+    /// Sets the lines `states/mod.rs` may reach before it is split into submodules
+    pub fn max_file_lines(mut self, max_file_lines: usize) -> Self {
+        self.options.max_file_lines = Some(max_file_lines);
+        self
+    }
+
+    /// Adds a type imported from `full_path` wherever it is used, like a framework type
+    pub fn framework_type(
+        mut self,
+        type_name: impl Into<String>,
+        full_path: impl Into<String>,
+    ) -> Self {
+        self.options
+            .framework_types
+            .insert(type_name.into(), full_path.into());
+        self
+    }
+
+    /// Sets the file generation looks for secrets missing from the environment in
+    pub fn secrets_file(mut self, secrets_file: impl Into<PathBuf>) -> Self {
+        self.options.secrets_file = Some(secrets_file.into());
+        self
+    }
+
+    /// Sets whether a `README.md` is written next to the generated code
+    pub fn readme(mut self, readme: bool) -> Self {
+        self.options.readme = readme;
+        self
+    }
+
+    /// Sets the file the README template is read from, which also turns the README on
+    pub fn readme_template(mut self, readme_template: impl Into<PathBuf>) -> Self {
+        self.options.readme = true;
+        self.readme_template_file = Some(readme_template.into());
+        self
+    }
+
+    /// Sets the spec the actor is generated from
+    pub fn spec_file(mut self, spec_file: impl Into<PathBuf>) -> Self {
+        self.options.spec_file = Some(spec_file.into());
+        self
+    }
+
+    /// Checks the options fit together and returns them
+    pub fn build(mut self) -> Result<GeneratorOptions, String> {
+        self.options.validate_target()?;
+        if let Some(path) = &self.readme_template_file {
+            let template = fs::read_to_string(path)
+                .map_err(|e| format!("Error reading README template {}: {e}", path.display()))?;
+            self.options.readme_template = Some(template);
+        }
+        Ok(self.options)
+    }
+}
+
+/// `#[deprecated]` attribute for a spec item deprecated with `note`
+pub(crate) fn deprecated_attr(note: &str) -> String {
+    format!("#[deprecated(note = {note:?})]")
+}
+
+/// Errors that can occur while rendering Rust code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderError {
+    /// A module expected to exist after analysis was not found in the graph
+    ModuleNotFound(String),
+    /// The spec is missing something required for rendering
+    InvalidSpec(String),
+    /// The rendered source is not valid Rust
+    InvalidRust(String),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::ModuleNotFound(path) => {
+                write!(f, "Module '{path}' not found in the code generation graph")
+            }
+            RenderError::InvalidSpec(msg) => write!(f, "Invalid spec: {msg}"),
+            RenderError::InvalidRust(msg) => write!(f, "Rendered invalid Rust: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// Everything a `ToRust` implementor needs to render itself
+#[derive(Clone, Copy)]
+pub struct RenderCtx<'a> {
+    pub actor: &'a Actor,
+    pub graph: &'a CodeGenGraph,
+    pub options: &'a GeneratorOptions,
+}
+
+impl<'a> RenderCtx<'a> {
+    /// Derives the wire codecs need on the message set and its custom types
+    pub(crate) const WIRE_DERIVES: &'static [&'static str] =
+        &["serde::Serialize", "serde::Deserialize"];
+
+    pub fn new(actor: &'a Actor, graph: &'a CodeGenGraph, options: &'a GeneratorOptions) -> Self {
+        Self {
+            actor,
+            graph,
+            options,
+        }
+    }
+
+    /// Gets the component type name for this actor
+    pub fn component_type(&self) -> &'a str {
+        &self.actor.component.ident
+    }
+
+    /// Gets the message set name for this actor
+    pub fn message_set(&self) -> String {
+        self.actor
+            .component
+            .message_set
+            .as_ref()
+            .map(|ms| ms.get().ident.clone())
+            .unwrap_or_else(|| format!("<{} as Components>::MessageSet", self.component_type()))
+    }
+
+    /// Gets the traits derived on the generated message set
+    pub fn message_set_derives(&self) -> Vec<String> {
+        let mut derives = self
+            .actor
+            .component
+            .message_set
+            .as_ref()
+            .map(|ms| ms.get().derives.clone())
+            .unwrap_or_default();
+        if self.options.derive_message_traits {
+            for derive in ["Debug", "Clone"] {
+                if !derives.iter().any(|d| d == derive) {
+                    derives.push(derive.to_string());
+                }
+            }
+        }
+        if self.options.wire_format.is_some() {
+            derives.extend(Self::WIRE_DERIVES.iter().map(ToString::to_string));
+        }
+        derives
+    }
+
+    /// Gets the actor module path, including any group it is nested under
+    pub fn actor_module(&self) -> String {
+        self.actor.module_path()
+    }
+
+    /// Gets the resolved `use` statements for a generated module
+    pub fn imports_for(&self, module_path: &str) -> Result<Vec<String>, RenderError> {
+        match self
+            .graph
+            .graph
+            .find_module_by_path_hierarchical(module_path)
+        {
+            Some(module_idx) => Ok(self.graph.get_imports_for_module(module_idx).collect()),
+            None => match self.options.import_fallback {
+                ImportFallback::Error => Err(RenderError::ModuleNotFound(module_path.to_string())),
+                ImportFallback::Defaults => {
+                    Ok(CodeGenGraph::default_imports_for_module(module_path))
+                }
+                ImportFallback::Empty => Ok(Vec::new()),
+            },
+        }
+    }
+
+    /// Renders the imports of a generated module followed by a blank line
+    pub fn imports_section(&self, module_path: &str) -> Result<String, RenderError> {
+        let imports = self.imports_for(module_path)?;
+        if imports.is_empty() {
+            Ok(String::new())
+        } else {
+            Ok(format!("{}\n\n", imports.join("\n")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create::{
+        ActorGenerator, Edition, GeneratorOptions, ImportFallback, LineEndings, RenderError,
+        RustVersion,
+    };
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_imports_for_unknown_module() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let ctx = generator.ctx();
+
+        assert!(ctx.imports_for("actor::component").is_ok());
+        assert_eq!(
+            ctx.imports_for("actor::missing"),
+            Err(RenderError::ModuleNotFound("actor::missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_imports_fallback_strategies() {
+        let options = GeneratorOptions::default().with_import_fallback(ImportFallback::Defaults);
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        let imports = generator
+            .ctx()
+            .imports_for("other::states::idle")
+            .expect("Defaults fallback should not fail");
+        assert!(imports.contains(&"use bloxide_tokio::state_machine::Transition;".to_string()));
+
+        let options = GeneratorOptions::default().with_import_fallback(ImportFallback::Empty);
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        assert_eq!(generator.ctx().imports_for("other::states"), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_runtime_requires_a_state() {
+        let mut actor = create_test_actor();
+        actor.component.states.states.clear();
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        assert!(matches!(
+            generator.generate_runtime(),
+            Err(RenderError::InvalidSpec(_))
+        ));
+    }
+
+    #[test]
+    fn test_edition_and_msrv() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(!runtime.contains("use std::future::Future;"));
+
+        let options = GeneratorOptions::default()
+            .with_edition(Edition::E2021)
+            .with_msrv(Some(RustVersion::new(1, 60)));
+        let generator = ActorGenerator::with_options(create_test_actor(), options)
+            .expect("Generator creation should succeed");
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains("use std::future::Future;"));
+        assert!(!generator.options().supports(RustVersion::LET_ELSE));
+
+        let options = GeneratorOptions::default().with_msrv(Some(RustVersion::new(1, 70)));
+        assert_eq!(
+            ActorGenerator::with_options(create_test_actor(), options)
+                .err()
+                .map(|e| e.to_string()),
+            Some("Rust 1.70 predates the 2024 edition, which needs Rust 1.85".to_string())
+        );
+
+        assert_eq!("1.70.1".parse(), Ok(RustVersion::new(1, 70)));
+        assert!("1".parse::<RustVersion>().is_err());
+        assert!("1.70.x".parse::<RustVersion>().is_err());
+        assert_eq!("2018".parse(), Ok(Edition::E2018));
+        assert!("2015".parse::<Edition>().is_err());
+    }
+
+    #[test]
+    fn test_line_endings() {
+        assert_eq!(LineEndings::Lf.apply("a\nb\n"), "a\nb\n");
+        assert_eq!(LineEndings::Crlf.apply("a\nb\r\n"), "a\r\nb\r\n");
+        let native = if cfg!(windows) { "a\r\n" } else { "a\n" };
+        assert_eq!(LineEndings::Native.apply("a\n"), native);
+
+        assert_eq!("crlf".parse(), Ok(LineEndings::Crlf));
+        assert_eq!(
+            "cr".parse::<LineEndings>(),
+            Err("unknown line endings 'cr', expected lf, crlf or native".to_string())
+        );
+    }
+}
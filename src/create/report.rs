@@ -0,0 +1,198 @@
+use std::{fmt, path::Path, path::PathBuf, time::Duration};
+
+use crate::create::{ActorGenerator, RenderError};
+
+/// A file written by generation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub lines: usize,
+}
+
+/// Phase of generation an actor module is in, reported as each one starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Validate,
+    Render,
+    Write,
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Validate => "validate",
+            Phase::Render => "render",
+            Phase::Write => "write",
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Time spent in each phase of generation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// Reading the spec; only set by callers that parse it themselves
+    pub parse: Duration,
+    pub validate: Duration,
+    /// Bootstrapping framework types and discovering the actor's types
+    pub analyze: Duration,
+    pub resolve: Duration,
+    pub render: Duration,
+    pub write: Duration,
+}
+
+impl PhaseTimings {
+    fn phases(&self) -> [(&'static str, Duration); 6] {
+        [
+            ("parse", self.parse),
+            ("validate", self.validate),
+            ("analyze", self.analyze),
+            ("resolve", self.resolve),
+            ("render", self.render),
+            ("write", self.write),
+        ]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.phases().iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+impl fmt::Display for PhaseTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "timings:")?;
+        for (phase, duration) in self.phases() {
+            writeln!(f, "  {phase:<9}{:>10.3?}", duration)?;
+        }
+        write!(f, "  {:<9}{:>10.3?}", "total", self.total())
+    }
+}
+
+/// Size and complexity of a generated actor module
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GenerationReport {
+    /// Files in the generated module, in the order they were rendered
+    pub files: Vec<FileReport>,
+    pub states: usize,
+    /// Variants of the message set
+    pub message_variants: usize,
+    /// `use` statements across all generated modules
+    pub imports: usize,
+    pub graph_nodes: usize,
+    pub graph_edges: usize,
+    pub timings: PhaseTimings,
+}
+
+impl GenerationReport {
+    /// Lines across all generated files
+    pub fn total_lines(&self) -> usize {
+        self.files.iter().map(|file| file.lines).sum()
+    }
+}
+
+impl fmt::Display for GenerationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "files written: {}", self.files.len())?;
+        for file in &self.files {
+            writeln!(f, "  {:>6}  {}", file.lines, file.path.display())?;
+        }
+        writeln!(f, "  {:>6}  total", self.total_lines())?;
+        writeln!(f, "states: {}", self.states)?;
+        writeln!(f, "message variants: {}", self.message_variants)?;
+        writeln!(f, "imports: {}", self.imports)?;
+        write!(
+            f,
+            "graph: {} nodes, {} edges",
+            self.graph_nodes, self.graph_edges
+        )
+    }
+}
+
+impl ActorGenerator {
+    pub(crate) fn report(
+        &self,
+        mod_path: &Path,
+        files: &[(PathBuf, String)],
+        timings: PhaseTimings,
+    ) -> Result<GenerationReport, RenderError> {
+        let component = &self.actor().component;
+        Ok(GenerationReport {
+            files: files
+                .iter()
+                .map(|(file, content)| FileReport {
+                    path: mod_path.join(file),
+                    lines: content.lines().count(),
+                })
+                .collect(),
+            states: component.states.states.len(),
+            message_variants: component
+                .message_set
+                .as_ref()
+                .map_or(0, |message_set| message_set.get().variants.len()),
+            imports: self.plan()?.iter().map(|plan| plan.imports.len()).sum(),
+            graph_nodes: self.graph().graph.graph.node_count(),
+            graph_edges: self.graph().graph.graph.edge_count(),
+            timings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_generation_report() {
+        let mut actor = create_test_actor();
+        actor.path = PathBuf::from("tests/output/report");
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let report = generator.generate().expect("Generation should succeed");
+
+        assert_eq!(report.states, 2);
+        assert_eq!(report.message_variants, 2);
+        assert!(report.imports > 0);
+        assert!(report.graph_nodes > 0 && report.graph_edges > 0);
+        assert!(
+            report
+                .files
+                .iter()
+                .any(|file| file.path.ends_with("states/create.rs") && file.lines > 0)
+        );
+        assert!(
+            report
+                .to_string()
+                .starts_with(&format!("files written: {}", report.files.len()))
+        );
+    }
+
+    #[test]
+    fn test_phase_timings() {
+        let mut actor = create_test_actor();
+        actor.path = PathBuf::from("tests/output/timings");
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert!(generator.timings().analyze > Duration::ZERO);
+
+        let mut phases = Vec::new();
+        let timings = generator
+            .generate_with_progress(|phase| phases.push(phase))
+            .expect("Generation should succeed")
+            .timings;
+        assert_eq!(phases, [Phase::Validate, Phase::Render, Phase::Write]);
+        assert_eq!(timings.analyze, generator.timings().analyze);
+        assert!(timings.render > Duration::ZERO && timings.write > Duration::ZERO);
+        assert_eq!(timings.parse, Duration::ZERO);
+
+        let rendered = timings.to_string();
+        for phase in [
+            "parse", "validate", "analyze", "resolve", "render", "write", "total",
+        ] {
+            assert!(rendered.contains(phase));
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::blox::actor::CORRELATED_TYPE;
+use crate::blox::message_handlers::MessageReceiver;
+use crate::blox::schedule::Cron;
+use crate::create::{RenderCtx, RenderError};
+
+/// Render the scheduler module: one task per schedule, sending its message whenever it fires
+pub fn render_scheduler_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    let actor = ctx.actor;
+    let actor_name = &actor.ident;
+    let imports_section = ctx.imports_section(&format!("{}::scheduler", ctx.actor_module()))?;
+
+    let mut tasks = Vec::new();
+    for (i, schedule) in actor.schedules.iter().enumerate() {
+        schedule
+            .validate(&actor.component)
+            .map_err(RenderError::InvalidSpec)?;
+        let cron = Cron::parse(&schedule.cron).map_err(RenderError::InvalidSpec)?;
+        // A schedule firing starts a chain of its own
+        let payload = if actor.correlation {
+            format!("{CORRELATED_TYPE}::new({})", schedule.payload)
+        } else {
+            schedule.payload.clone()
+        };
+        let channel = actor
+            .component
+            .receiver_for_variant(&schedule.variant)
+            .map(MessageReceiver::channel_name)
+            .unwrap_or_default();
+
+        tasks.push(format!(
+            r#"/// Sends `{variant}` on the `{channel}` channel at `{expr}`
+async fn schedule_{i}(inbox: {actor_name}Inbox) {{
+    let cron = Cron {{
+        minutes: {minutes:#x},
+        hours: {hours:#x},
+        days_of_month: {days_of_month:#x},
+        months: {months:#x},
+        days_of_week: {days_of_week:#x},
+        either_day: {either_day},
+    }};
+    while let Some(next) = cron.next_after(Utc::now().naive_utc()) {{
+        let delay = (next - Utc::now().naive_utc()).to_std().unwrap_or_default();
+        sleep(delay).await;
+        if inbox.{channel}.send(Message::new(0, {payload})).await.is_err() {{
+            return;
+        }}
+    }}
+}}"#,
+            variant = schedule.variant,
+            expr = schedule.cron,
+            minutes = cron.minutes,
+            hours = cron.hours,
+            days_of_month = cron.days_of_month,
+            months = cron.months,
+            days_of_week = cron.days_of_week,
+            either_day = cron.either_day,
+        ));
+    }
+    let runs = (0..tasks.len())
+        .map(|i| format!("        schedule_{i}(inbox.clone()),"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let tasks = tasks.join("\n\n");
+
+    Ok(format!(
+        r#"{imports_section}/// A cron expression, each field a bit set of the values it fires on
+struct Cron {{
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    /// Sunday is bit 0
+    days_of_week: u8,
+    /// Both day fields are restricted, so a day matching either one fires
+    either_day: bool,
+}}
+
+impl Cron {{
+    fn matches_day(&self, t: NaiveDateTime) -> bool {{
+        let day_of_month = self.days_of_month & 1 << t.day() != 0;
+        let day_of_week = self.days_of_week & 1 << t.weekday().num_days_from_sunday() != 0;
+        let day = if self.either_day {{
+            day_of_month || day_of_week
+        }} else {{
+            day_of_month && day_of_week
+        }};
+        day && self.months & 1 << t.month() != 0
+    }}
+
+    /// Gets the first minute after `after` the expression fires at, or `None` if it never does
+    fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {{
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + TimeDelta::minutes(1);
+        // Five years cover every combination of leap year and weekday
+        let limit = t + TimeDelta::days(5 * 366);
+        while t < limit {{
+            if !self.matches_day(t) {{
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            }} else if self.hours & 1 << t.hour() == 0 {{
+                t = t.with_minute(0)? + TimeDelta::hours(1);
+            }} else if self.minutes & 1 << t.minute() == 0 {{
+                t += TimeDelta::minutes(1);
+            }} else {{
+                return Some(t);
+            }}
+        }}
+        None
+    }}
+}}
+
+{tasks}
+
+/// Runs every schedule of {actor_name}, in UTC, until the actor stops
+pub async fn run_schedules(inbox: {actor_name}Inbox) {{
+    join!(
+{runs}
+    );
+}}
+"#
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::schedule::Schedule;
+    use crate::create::{ActorGenerator, RenderError};
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_scheduler() {
+        let actor = create_test_actor().with_schedules(vec![
+            Schedule::new("*/15 9-17 * * mon-fri", "CustomValue1"),
+            Schedule::new("@daily", "CustomValue2").with_payload("CustomArgs::nightly()"),
+        ]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let scheduler = generator
+            .generate_scheduler()
+            .unwrap()
+            .expect("Scheduler should be generated");
+
+        assert!(scheduler.contains(
+            "/// Sends `CustomValue1` on the `standard` channel at `*/15 9-17 * * mon-fri`\nasync fn schedule_0(inbox: ActorInbox) {"
+        ));
+        assert!(scheduler.contains("        minutes: 0x200040008001,\n        hours: 0x3fe00,"));
+        assert!(scheduler.contains("        days_of_week: 0x3e,\n        either_day: false,"));
+        assert!(scheduler.contains(
+            "if inbox.customargs.send(Message::new(0, CustomArgs::nightly())).await.is_err() {"
+        ));
+        assert!(scheduler.contains(
+            "    join!(\n        schedule_0(inbox.clone()),\n        schedule_1(inbox.clone()),\n    );"
+        ));
+        assert!(scheduler.contains("use chrono::NaiveDateTime;"));
+        assert!(scheduler.contains("use tokio::join;"));
+        assert!(generator.root_modules().contains(&"scheduler".to_string()));
+    }
+
+    #[test]
+    fn test_scheduler_rejects_invalid_cron() {
+        let actor =
+            create_test_actor().with_schedules(vec![Schedule::new("0 25 * * *", "CustomValue1")]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate_scheduler(),
+            Err(RenderError::InvalidSpec(
+                "Invalid hour field in cron expression '0 25 * * *': 25 is outside 0-23"
+                    .to_string()
+            ))
+        );
+
+        let mut actor =
+            create_test_actor().with_schedules(vec![Schedule::new("0 9 * * *", "CustomValue2")]);
+        let receivers = &mut actor.component.message_receivers.receivers;
+        let receiver = receivers.remove(1);
+        receivers.insert(1, receiver.with_sequencing());
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate_scheduler(),
+            Err(RenderError::InvalidSpec(
+                "Variant 'CustomValue2' of schedule '0 9 * * *' arrives on sequenced receiver 'customargs_rx', which schedules can't send to"
+                    .to_string()
+            ))
+        );
+
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(generator.generate_scheduler().unwrap().is_none());
+    }
+}
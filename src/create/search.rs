@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::create::{ActorGenerator, RenderError};
+use crate::graph::Entry;
+
+/// Something in an actor's spec or type graph matching a search
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SearchHit {
+    /// Actor whose spec the hit comes from
+    pub actor: String,
+    /// `Type`, `Trait`, `Module` or `Function` for graph nodes, `Receiver` or `Handle` for
+    /// channels of the spec
+    pub kind: String,
+    /// Path relative to the crate root, or `Actor.receivers.name` for channels
+    pub path: String,
+    /// Generated modules importing the item
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub used_by: Vec<String>,
+}
+
+impl fmt::Display for SearchHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} {}", self.actor, self.kind, self.path)?;
+        if !self.used_by.is_empty() {
+            write!(f, " (used by {})", self.used_by.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl ActorGenerator {
+    /// Finds the graph nodes whose name contains `pattern`, ignoring case, or equals it when
+    /// `exact`, along with the receivers and handles carrying a matching message type
+    pub fn search(&self, pattern: &str, exact: bool) -> Result<Vec<SearchHit>, RenderError> {
+        let graph = self.graph.rust_graph();
+        let plans = self.plan()?;
+        let entries: Vec<Entry> = if exact {
+            graph.find_by_name(pattern)
+        } else {
+            graph.find_by_pattern(pattern).collect()
+        };
+        let matches = |name: &str| {
+            if exact {
+                name == pattern
+            } else {
+                name.to_lowercase().contains(&pattern.to_lowercase())
+            }
+        };
+
+        let actor = &self.actor().ident;
+        let mut nodes = BTreeMap::new();
+        for entry in entries {
+            // A path used before it is declared gets a second node, registered as a type
+            let kind = entry.node.node_str();
+            let path = self.node_path(entry.index);
+            match nodes.get(&path) {
+                Some(&known) if known != "Type" || kind == "Type" => {}
+                _ => {
+                    nodes.insert(path, kind);
+                }
+            }
+        }
+
+        let mut hits = BTreeSet::new();
+        for (path, kind) in nodes {
+            let imports = [format!("use {path};"), format!("use crate::{path};")];
+            let used_by = plans
+                .iter()
+                .filter(|plan| plan.imports.iter().any(|import| imports.contains(import)))
+                .map(|plan| plan.module.clone())
+                .collect();
+            hits.insert(SearchHit {
+                actor: actor.clone(),
+                kind: kind.to_string(),
+                path,
+                used_by,
+            });
+        }
+
+        let component = &self.actor().component;
+        let receivers = component
+            .message_receivers
+            .receivers
+            .iter()
+            .map(|r| ("Receiver", "receivers", &r.ident, &r.message_type));
+        let handles = component
+            .message_handles
+            .handles
+            .iter()
+            .map(|h| ("Handle", "handles", &h.ident, &h.message_type));
+        for (kind, section, ident, message_type) in receivers.chain(handles) {
+            if matches(message_type) {
+                hits.insert(SearchHit {
+                    actor: actor.clone(),
+                    kind: kind.to_string(),
+                    path: format!("{actor}.{section}.{ident}"),
+                    used_by: Vec::new(),
+                });
+            }
+        }
+        Ok(hits.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_search() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+
+        let hits = generator.search("Runnable", true).unwrap();
+        assert_eq!(
+            hits,
+            [SearchHit {
+                actor: "Actor".to_string(),
+                kind: "Trait".to_string(),
+                path: "bloxide_tokio::components::Runnable".to_string(),
+                used_by: vec!["actor::runtime".to_string()],
+            }]
+        );
+
+        // Channels carrying a payload are found by its type
+        let hits = generator.search("customargs", false).unwrap();
+        assert!(
+            hits.iter()
+                .any(|hit| hit.kind == "Receiver" && hit.path == "Actor.receivers.customargs_rx")
+        );
+        assert!(
+            hits.iter()
+                .any(|hit| hit.kind == "Handle" && hit.path == "Actor.handles.customargs_handle")
+        );
+
+        assert!(generator.search("NoSuchThing", false).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::create::RenderCtx;
+
+/// Names of the secrets the extended state's fields are loaded from
+pub fn secret_names<'a>(ctx: &RenderCtx<'a>) -> BTreeSet<&'a str> {
+    ctx.actor
+        .component
+        .ext_state
+        .fields()
+        .iter()
+        .filter_map(|field| field.secret())
+        .collect()
+}
+
+/// Reads the names set in a secrets file of `NAME=value` lines, skipping `#` comments
+fn read_secrets_file(path: &Path) -> Result<BTreeSet<String>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Error reading secrets file {}: {e}", path.display()))?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, _)| name.trim().to_string())
+        .collect())
+}
+
+/// Checks every secret is set in the environment or the secrets file, without reading the
+/// values into the generated code
+pub fn resolve_secrets(ctx: &RenderCtx) -> Result<(), String> {
+    let names = secret_names(ctx);
+    if names.is_empty() {
+        return Ok(());
+    }
+    let from_file = match &ctx.options.secrets_file {
+        Some(path) => read_secrets_file(path)?,
+        None => BTreeSet::new(),
+    };
+    let missing = names
+        .into_iter()
+        .filter(|name| env::var_os(name).is_none() && !from_file.contains(*name))
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let source = match &ctx.options.secrets_file {
+        Some(path) => format!("the environment or {}", path.display()),
+        None => "the environment".to_string(),
+    };
+    Err(format!(
+        "Secrets not set in {source}: {}",
+        missing.join(", ")
+    ))
+}
+
+/// Environment variable naming the file of `NAME=value` lines the generated code reads the
+/// secrets missing from the environment from
+pub const SECRETS_FILE_VAR: &str = "BLOXML_SECRETS_FILE";
+
+/// Render the function the extended state loads its secrets with when the actor starts, and
+/// the error it reports a missing or invalid secret with
+///
+/// The secrets file is named at run time by [`SECRETS_FILE_VAR`], so no path of the machine
+/// generating the code ends up in it.
+pub fn render_secret_loader(ctx: &RenderCtx) -> String {
+    if secret_names(ctx).is_empty() {
+        return String::new();
+    }
+    format!(
+        r#"
+/// A secret the extended state is loaded from that is not set, or can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretError {{
+    /// Set neither in the environment nor in the file named by `{SECRETS_FILE_VAR}`
+    Missing(&'static str),
+    /// Set to a value that does not parse as the field's type
+    Invalid(&'static str),
+}}
+
+impl std::fmt::Display for SecretError {{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{
+        match self {{
+            Self::Missing(name) => write!(f, "secret `{{name}}` is not set"),
+            Self::Invalid(name) => write!(f, "secret `{{name}}` has an invalid value"),
+        }}
+    }}
+}}
+
+impl std::error::Error for SecretError {{}}
+
+/// Loads a secret by name, so its value never appears in the generated code
+///
+/// The environment is read first, then the file of `NAME=value` lines named by
+/// `{SECRETS_FILE_VAR}`, if set.
+fn load_secret<T: std::str::FromStr>(name: &'static str) -> Result<T, SecretError> {{
+    std::env::var(name)
+        .ok()
+        .or_else(|| {{
+            std::fs::read_to_string(std::env::var_os("{SECRETS_FILE_VAR}")?)
+                .ok()?
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .find(|(key, _)| key.trim() == name)
+                .map(|(_, value)| value.trim().to_string())
+        }})
+        .ok_or(SecretError::Missing(name))?
+        .parse()
+        .map_err(|_| SecretError::Invalid(name))
+}}
+"#
+    )
+}
@@ -0,0 +1,204 @@
+use crate::blox::actor::SEQUENCED_TYPE;
+use crate::blox::message_handlers::MessageReceiver;
+use crate::create::{RenderCtx, RenderError};
+
+/// Render the sequence module: the payload wrapper, the sender counter and the receiver trackers
+pub fn render_sequence_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    let actor_name = &ctx.actor.ident;
+    let imports_section = ctx.imports_section(&format!("{}::sequence", ctx.actor_module()))?;
+
+    let mut derives = vec![
+        "Debug".to_string(),
+        "Clone".to_string(),
+        "PartialEq".to_string(),
+    ];
+    if ctx.options.wire_format.is_some() {
+        derives.extend(RenderCtx::WIRE_DERIVES.iter().map(ToString::to_string));
+    }
+    let derives = derives.join(", ");
+
+    let trackers = ctx
+        .actor
+        .component
+        .message_receivers
+        .receivers
+        .iter()
+        .filter(|receiver| receiver.sequenced)
+        .map(|receiver| format!("    pub {}: SequenceTracker,", receiver.ident))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(format!(
+        r#"{imports_section}/// A payload stamped with its position in the sender's stream
+#[derive({derives})]
+pub struct {SEQUENCED_TYPE}<T> {{
+    pub seq: u64,
+    pub payload: T,
+}}
+
+/// Stamps outgoing payloads with consecutive sequence numbers, starting at 0
+#[derive(Debug, Default)]
+pub struct SequenceCounter {{
+    next: AtomicU64,
+}}
+
+impl SequenceCounter {{
+    pub fn new() -> Self {{
+        Self::default()
+    }}
+
+    /// Wraps a payload with the next sequence number of the stream
+    pub fn stamp<T>(&self, payload: T) -> {SEQUENCED_TYPE}<T> {{
+        {SEQUENCED_TYPE} {{
+            seq: self.next.fetch_add(1, Ordering::Relaxed),
+            payload,
+        }}
+    }}
+}}
+
+/// How a received sequence number relates to the ones received before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceCheck {{
+    /// The number the receiver expected next
+    InOrder,
+    /// A number past the expected one; the messages in between are missing or late
+    Gap {{ expected: u64, received: u64 }},
+    /// A number already passed, from a repeated or late message
+    Duplicate,
+}}
+
+/// Tracks the sequence number one receiver expects next
+#[derive(Debug, Default)]
+pub struct SequenceTracker {{
+    expected: u64,
+}}
+
+impl SequenceTracker {{
+    /// Checks a received sequence number, moving past it unless it is a duplicate
+    pub fn check(&mut self, seq: u64) -> SequenceCheck {{
+        if seq < self.expected {{
+            return SequenceCheck::Duplicate;
+        }}
+        let check = if seq == self.expected {{
+            SequenceCheck::InOrder
+        }} else {{
+            SequenceCheck::Gap {{
+                expected: self.expected,
+                received: seq,
+            }}
+        }};
+        self.expected = seq + 1;
+        check
+    }}
+}}
+
+/// Sequence trackers of {actor_name}'s sequenced receivers, owned by the runtime
+#[derive(Debug, Default)]
+pub struct {actor_name}Sequences {{
+{trackers}
+}}
+"#
+    ))
+}
+
+/// Render the checks a sequenced receiver's `select!` arm runs before dispatching
+///
+/// Duplicates are dropped; gaps run the spec's `sequence_gap` code, if any, then dispatch.
+pub fn render_sequence_check(ctx: &RenderCtx, receiver: &MessageReceiver) -> String {
+    let gap_arm = match &ctx.actor.sequence_gap {
+        Some(code) => {
+            let code = code
+                .lines()
+                .map(|line| format!("                                {line}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                r#"                            SequenceCheck::InOrder => {{}}
+                            SequenceCheck::Gap {{ expected, received }} => {{
+{code}
+                            }}"#
+            )
+        }
+        None => {
+            "                            SequenceCheck::InOrder | SequenceCheck::Gap { .. } => {}"
+                .to_string()
+        }
+    };
+
+    format!(
+        r#"                        match sequences.{ident}.check(msg.payload().seq) {{
+{gap_arm}
+                            SequenceCheck::Duplicate => continue,
+                        }}
+"#,
+        ident = receiver.ident
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::message_handlers::MessageReceiver;
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_sequenced_receiver() {
+        let mut actor = create_test_actor()
+            .with_sequence_gap("tracing::warn!(expected, received, \"customargs skipped ahead\");");
+        let receivers = &mut actor.component.message_receivers.receivers;
+        let receiver = receivers.remove(1);
+        receivers.insert(1, receiver.with_sequencing());
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let sequence = generator
+            .generate_sequence()
+            .unwrap()
+            .expect("Sequence module should be generated");
+        assert!(
+            sequence
+                .contains("pub struct Sequenced<T> {\n    pub seq: u64,\n    pub payload: T,\n}")
+        );
+        assert!(
+            sequence.contains(
+                "pub struct ActorSequences {\n    pub customargs_rx: SequenceTracker,\n}"
+            )
+        );
+        assert!(sequence.contains("use std::sync::atomic::AtomicU64;"));
+
+        // The payload is wrapped everywhere the channel's type appears
+        let component = generator.generate_component().unwrap();
+        assert!(
+            component
+                .contains("MessageHandle<Sequenced<CustomArgs>> as MessageSender>::ReceiverType")
+        );
+        assert!(
+            component.contains("pub customargs_handle: TokioMessageHandle<Sequenced<CustomArgs>>")
+        );
+        assert!(component.contains("use crate::actor::sequence::Sequenced;"));
+        let messaging = generator.generate_messaging().unwrap().unwrap();
+        assert!(messaging.contains("CustomValue2(Message<Sequenced<CustomArgs>>)"));
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "            let mut sequences = ActorSequences::default();\n            loop {"
+        ));
+        assert!(runtime.contains(
+            "                        match sequences.customargs_rx.check(msg.payload().seq) {\n                            SequenceCheck::InOrder => {}\n                            SequenceCheck::Gap { expected, received } => {\n                                tracing::warn!("
+        ));
+        assert!(
+            runtime.contains("                            SequenceCheck::Duplicate => continue,")
+        );
+        assert!(!runtime.contains("sequences.standard_rx"));
+        assert!(runtime.contains("use crate::actor::sequence::SequenceCheck;"));
+        assert!(generator.root_modules().contains(&"sequence".to_string()));
+    }
+
+    #[test]
+    fn test_no_sequencing_by_default() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(generator.generate_sequence().unwrap().is_none());
+        assert!(!generator.generate_runtime().unwrap().contains("sequences"));
+        assert!(!MessageReceiver::new("a_rx", "A").sequenced);
+    }
+}
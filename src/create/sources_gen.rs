@@ -0,0 +1,580 @@
+use crate::blox::message_handlers::{Broker, MessageReceiver, Sink};
+use crate::create::{RenderCtx, RenderError, RustVersion, WireFormat};
+
+/// Render the sources module: adapters forwarding external subjects and topics into receivers
+pub fn render_sources_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    ctx.actor
+        .validate_sources()
+        .and_then(|_| ctx.actor.validate_trace_context())
+        .map_err(RenderError::InvalidSpec)?;
+    let imports_section = ctx.imports_section(&format!("{}::sources", ctx.actor_module()))?;
+
+    let receivers = &ctx.actor.component.message_receivers.receivers;
+    let sourced = |broker| {
+        receivers
+            .iter()
+            .filter(move |r| r.source.as_ref().is_some_and(|s| s.broker == broker))
+            .collect::<Vec<_>>()
+    };
+
+    let mut adapters = sourced(Broker::Nats)
+        .into_iter()
+        .map(|receiver| render_nats_adapter(ctx, receiver))
+        .collect::<Vec<_>>();
+    let mqtt = sourced(Broker::Mqtt);
+    if !mqtt.is_empty() {
+        adapters.push(render_mqtt_adapter(ctx, &mqtt));
+    }
+    let kafka = sourced(Broker::Kafka);
+    if !kafka.is_empty() {
+        adapters.push(render_kafka_adapter(ctx, &kafka));
+    }
+
+    // Without any broker feature the whole module, imports included, compiles away
+    let features = [Broker::Nats, Broker::Mqtt, Broker::Kafka]
+        .into_iter()
+        .filter(|broker| !sourced(*broker).is_empty())
+        .map(|broker| format!("feature = \"{}\"", broker.feature()))
+        .collect::<Vec<_>>();
+
+    Ok(format!(
+        "#![cfg(any({}))]\n\n{imports_section}{}\n",
+        features.join(", "),
+        adapters.join("\n\n")
+    ))
+}
+
+/// Render the sinks module: helpers publishing variant payloads to Kafka topics
+pub fn render_sinks_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    ctx.actor
+        .validate_sinks()
+        .and_then(|_| ctx.actor.validate_trace_context())
+        .map_err(RenderError::InvalidSpec)?;
+    let imports_section = ctx.imports_section(&format!("{}::sinks", ctx.actor_module()))?;
+    let variants = ctx
+        .actor
+        .component
+        .message_set
+        .as_ref()
+        .map(|ms| ms.get().variants.as_slice())
+        .unwrap_or_default();
+
+    let helpers = ctx
+        .actor
+        .sinks
+        .iter()
+        .map(|sink| {
+            // Validation guarantees the variant exists and carries one payload
+            let payload = variants
+                .iter()
+                .find(|v| v.ident == sink.variant)
+                .map(|v| v.args[0].to_string())
+                .unwrap_or_default();
+            render_kafka_sink(ctx, sink, &payload)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    Ok(format!(
+        "#![cfg(feature = \"kafka\")]\n\n{imports_section}{helpers}\n"
+    ))
+}
+
+/// Format a source's payloads are decoded from, falling back to the wire format and then JSON
+fn source_format(ctx: &RenderCtx, receiver: &MessageReceiver) -> WireFormat {
+    receiver
+        .source
+        .as_ref()
+        .and_then(|s| s.format)
+        .or(ctx.options.wire_format)
+        .unwrap_or(WireFormat::Json)
+}
+
+/// Chains reading the header pairs of a NATS message and a Kafka record
+const NATS_HEADERS: &[&str] = &[
+    "message",
+    ".headers",
+    ".iter()",
+    ".flat_map(|headers| headers.iter())",
+    ".filter_map(|(name, values)| Some((name.as_ref(), values.first()?.as_str()))),",
+];
+const KAFKA_HEADERS: &[&str] = &[
+    "record",
+    ".headers()",
+    ".into_iter()",
+    ".flat_map(|headers| headers.iter())",
+    ".filter_map(|header| Some((header.key, std::str::from_utf8(header.value?).ok()?))),",
+];
+
+/// Renders decoding `bytes` into the receiver's payload and forwarding it, returning once the actor stops
+///
+/// Sources propagating trace context continue the trace in the message's `headers`, falling
+/// back to the one encoded in the payload.
+fn render_forward(
+    ctx: &RenderCtx,
+    receiver: &MessageReceiver,
+    headers: &[&str],
+    indent: &str,
+) -> String {
+    let (_, decode) = source_format(ctx, receiver).decoder();
+    let traced = receiver.source.as_ref().is_some_and(|s| s.trace_context);
+    let trace_context = if traced {
+        let (first, rest) = headers.split_first().unwrap_or((&"", &[]));
+        let rest = rest
+            .iter()
+            .map(|line| format!("{indent}        {line}\n"))
+            .collect::<String>();
+        format!(
+            r#"{indent}let trace_context = TraceContext::from_headers(
+{indent}    {first}
+{rest}{indent});
+{indent}let payload = if trace_context.is_empty() {{
+{indent}    payload
+{indent}}} else {{
+{indent}    payload.with_trace_context(trace_context)
+{indent}}};
+"#
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        r#"{indent}let decoded: Result<{payload}, _> = {decode};
+{indent}let Ok(payload) = decoded else {{
+{indent}    continue;
+{indent}}};
+{trace_context}{indent}if inbox.{channel}.send(Message::new(0, payload)).await.is_err() {{
+{indent}    return Ok(());
+{indent}}}"#,
+        payload = receiver.message_type,
+        channel = receiver.channel_name(),
+    )
+}
+
+/// Renders the `match` arms forwarding each topic to its receiver
+fn render_topic_arms(ctx: &RenderCtx, receivers: &[&MessageReceiver], headers: &[&str]) -> String {
+    receivers
+        .iter()
+        .filter_map(|receiver| Some((receiver, &receiver.source.as_ref()?.subject)))
+        .map(|(receiver, topic)| {
+            format!(
+                "            \"{topic}\" => {{\n{}\n            }}",
+                render_forward(ctx, receiver, headers, "                ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn topics<'a>(receivers: &[&'a MessageReceiver]) -> Vec<&'a str> {
+    receivers
+        .iter()
+        .filter_map(|r| r.source.as_ref())
+        .map(|s| s.subject.as_str())
+        .collect()
+}
+
+fn render_nats_adapter(ctx: &RenderCtx, receiver: &MessageReceiver) -> String {
+    let actor_name = &ctx.actor.ident;
+    let channel = receiver.channel_name();
+    let subject = receiver
+        .source
+        .as_ref()
+        .map(|s| s.subject.as_str())
+        .unwrap_or_default();
+    let forward = render_forward(ctx, receiver, NATS_HEADERS, "        ");
+    format!(
+        r#"/// Forwards messages published on the `{subject}` NATS subject to {actor_name}'s `{channel}` channel
+///
+/// Messages that fail to decode are skipped; returns once the actor has stopped.
+#[cfg(feature = "nats")]
+pub async fn forward_{channel}_from_nats(
+    client: async_nats::Client,
+    inbox: {actor_name}Inbox,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {{
+    use futures::StreamExt;
+
+    let mut subscriber = client.subscribe("{subject}").await?;
+    while let Some(message) = subscriber.next().await {{
+        let bytes = &message.payload[..];
+{forward}
+    }}
+    Ok(())
+}}"#
+    )
+}
+
+fn render_mqtt_adapter(ctx: &RenderCtx, receivers: &[&MessageReceiver]) -> String {
+    let actor_name = &ctx.actor.ident;
+    let subscriptions = topics(receivers)
+        .iter()
+        .map(|topic| format!("    client.subscribe(\"{topic}\", QoS::AtLeastOnce).await?;"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    // Topics are filters that may hold wildcards, so each is matched in turn rather than
+    // compared; validation keeps trace context off MQTT, which has no headers to carry it
+    let arms = receivers
+        .iter()
+        .filter_map(|receiver| Some((receiver, &receiver.source.as_ref()?.subject)))
+        .map(|(receiver, topic)| {
+            format!(
+                "if rumqttc::matches(topic, \"{topic}\") {{\n{}\n        }}",
+                render_forward(ctx, receiver, &[], "            ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" else ");
+
+    format!(
+        r#"/// Forwards messages published on {actor_name}'s MQTT topics to the matching channels
+///
+/// Drives `event_loop`; a message matching several topic filters goes to the first one's
+/// channel, messages that fail to decode are skipped, and it returns once the actor has stopped.
+#[cfg(feature = "mqtt")]
+pub async fn forward_from_mqtt(
+    client: rumqttc::AsyncClient,
+    mut event_loop: rumqttc::EventLoop,
+    inbox: {actor_name}Inbox,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {{
+    use rumqttc::{{Event, Packet, QoS}};
+
+{subscriptions}
+    loop {{
+        let Event::Incoming(Packet::Publish(publish)) = event_loop.poll().await? else {{
+            continue;
+        }};
+        let (topic, bytes) = (publish.topic.as_str(), &publish.payload[..]);
+        {arms}
+    }}
+}}"#
+    )
+}
+
+fn render_kafka_adapter(ctx: &RenderCtx, receivers: &[&MessageReceiver]) -> String {
+    let actor_name = &ctx.actor.ident;
+    let topics = topics(receivers)
+        .iter()
+        .map(|topic| format!("\"{topic}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let arms = render_topic_arms(ctx, receivers, KAFKA_HEADERS);
+    let traced = receivers
+        .iter()
+        .any(|r| r.source.as_ref().is_some_and(|s| s.trace_context));
+    let use_headers = if traced {
+        "\n    use rdkafka::message::Headers;"
+    } else {
+        ""
+    };
+    let skip_empty = if ctx.options.supports(RustVersion::LET_ELSE) {
+        "let Some(bytes) = record.payload() else {\n            continue;\n        };"
+    } else {
+        "let bytes = match record.payload() {\n            Some(bytes) => bytes,\n            None => continue,\n        };"
+    };
+
+    format!(
+        r#"/// Forwards records consumed from {actor_name}'s Kafka topics to the matching channels
+///
+/// Records without a payload or that fail to decode are skipped; returns once the actor has
+/// stopped.
+#[cfg(feature = "kafka")]
+pub async fn forward_from_kafka(
+    consumer: rdkafka::consumer::StreamConsumer,
+    inbox: {actor_name}Inbox,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {{
+    use rdkafka::Message as _;
+    use rdkafka::consumer::Consumer;{use_headers}
+
+    consumer.subscribe(&[{topics}])?;
+    loop {{
+        let record = consumer.recv().await?;
+        {skip_empty}
+        match record.topic() {{
+{arms}
+            _ => {{}}
+        }}
+    }}
+}}"#
+    )
+}
+
+fn render_kafka_sink(ctx: &RenderCtx, sink: &Sink, payload: &str) -> String {
+    let actor_name = &ctx.actor.ident;
+    let variant = &sink.variant;
+    let topic = &sink.topic;
+    let name = variant.to_lowercase();
+    let (encode_error, encode) = sink
+        .format
+        .or(ctx.options.wire_format)
+        .unwrap_or(WireFormat::Json)
+        .encoder("payload");
+    // Consumers read the trace context from the headers, whatever format the payload is in
+    let (headers, record) = if sink.trace_context {
+        (
+            r#"
+    let mut headers = rdkafka::message::OwnedHeaders::new();
+    for (key, value) in payload.trace_context.headers() {
+        headers = headers.insert(rdkafka::message::Header {
+            key,
+            value: Some(value),
+        });
+    }"#,
+            format!(
+                "rdkafka::producer::FutureRecord::<(), _>::to(\"{topic}\")\n        .payload(&bytes)\n        .headers(headers)"
+            ),
+        )
+    } else {
+        (
+            "",
+            format!("rdkafka::producer::FutureRecord::<(), _>::to(\"{topic}\").payload(&bytes)"),
+        )
+    };
+
+    format!(
+        r#"fn encode_{name}(payload: &{payload}) -> Result<Vec<u8>, {encode_error}> {{
+    {encode}
+}}
+
+/// Publishes the payload of an {actor_name} `{variant}` message to the `{topic}` Kafka topic
+pub async fn publish_{name}(
+    producer: &rdkafka::producer::FutureProducer,
+    payload: &{payload},
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {{
+    let bytes = encode_{name}(payload)?;{headers}
+    let record = {record};
+    producer
+        .send(record, rdkafka::util::Timeout::Never)
+        .await
+        .map_err(|(error, _)| error)?;
+    Ok(())
+}}"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::message_handlers::{Broker, ExternalSource, Sink};
+    use crate::create::{
+        ActorGenerator, Edition, GeneratorOptions, RenderError, RustVersion, WireFormat,
+    };
+    use crate::tests::create_test_actor;
+
+    fn sourced_actor() -> crate::blox::actor::Actor {
+        let mut actor = create_test_actor();
+        let receivers = &mut actor.component.message_receivers.receivers;
+        receivers[0] = receivers[0]
+            .clone()
+            .with_source(ExternalSource::new(Broker::Nats, "values.standard"));
+        receivers[1] = receivers[1]
+            .clone()
+            .with_source(ExternalSource::new(Broker::Mqtt, "values/custom"));
+        actor
+    }
+
+    #[test]
+    fn test_no_sources_by_default() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(generator.generate_sources().unwrap().is_none());
+        assert!(generator.generate_sinks().unwrap().is_none());
+        assert!(!generator.root_modules().contains(&"sources".to_string()));
+        assert!(!generator.root_modules().contains(&"sinks".to_string()));
+    }
+
+    #[test]
+    fn test_external_sources() {
+        let generator =
+            ActorGenerator::new(sourced_actor()).expect("Generator creation should succeed");
+        let sources = generator
+            .generate_sources()
+            .unwrap()
+            .expect("Sources should be generated");
+
+        assert!(
+            sources
+                .contains("#[cfg(feature = \"nats\")]\npub async fn forward_standard_from_nats(")
+        );
+        assert!(
+            sources.contains("let mut subscriber = client.subscribe(\"values.standard\").await?;")
+        );
+        assert!(sources.contains(
+            "        let decoded: Result<StandardPayload, _> = serde_json::from_slice(bytes);"
+        ));
+        assert!(sources.contains("#[cfg(feature = \"mqtt\")]\npub async fn forward_from_mqtt("));
+        assert!(
+            sources.contains("    client.subscribe(\"values/custom\", QoS::AtLeastOnce).await?;")
+        );
+        assert!(sources.contains(
+            "        if rumqttc::matches(topic, \"values/custom\") {\n            let decoded: Result<CustomArgs, _> ="
+        ));
+        assert!(sources.contains("use crate::actor::component::ActorInbox;"));
+        assert!(sources.starts_with("#![cfg(any(feature = \"nats\", feature = \"mqtt\"))]\n"));
+        assert!(generator.root_modules().contains(&"sources".to_string()));
+    }
+
+    #[test]
+    fn test_mqtt_topic_filters() {
+        let mut actor = sourced_actor();
+        let receivers = &mut actor.component.message_receivers.receivers;
+        receivers[0] = receivers[0]
+            .clone()
+            .with_source(ExternalSource::new(Broker::Mqtt, "values/+/standard"));
+        receivers[1] = receivers[1]
+            .clone()
+            .with_source(ExternalSource::new(Broker::Mqtt, "values/#"));
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let sources = generator.generate_sources().unwrap().unwrap();
+        assert!(sources.contains(
+            "        } else if rumqttc::matches(topic, \"values/#\") {\n            let decoded: Result<CustomArgs, _> ="
+        ));
+
+        let sourced = |first: &str, second: &str| {
+            let mut actor = sourced_actor();
+            let receivers = &mut actor.component.message_receivers.receivers;
+            receivers[0] = receivers[0]
+                .clone()
+                .with_source(ExternalSource::new(Broker::Mqtt, first));
+            receivers[1] = receivers[1]
+                .clone()
+                .with_source(ExternalSource::new(Broker::Mqtt, second));
+            actor.validate_sources()
+        };
+        assert_eq!(
+            sourced("values/custom", "values/custom"),
+            Err("'values/custom' on MQTT feeds more than one receiver".to_string())
+        );
+        assert_eq!(
+            sourced("values/#/custom", "values/other"),
+            Err("MQTT topic 'values/#/custom' is not a valid topic filter".to_string())
+        );
+        assert_eq!(
+            sourced("values/a+", "values/other"),
+            Err("MQTT topic 'values/a+' is not a valid topic filter".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sources_decode_with_wire_format() {
+        let options = GeneratorOptions::default().with_wire_format(Some(WireFormat::Postcard));
+        let generator = ActorGenerator::with_options(sourced_actor(), options)
+            .expect("Generator creation should succeed");
+        let sources = generator.generate_sources().unwrap().unwrap();
+        assert!(sources.contains("= postcard::from_bytes(bytes);"));
+    }
+
+    #[test]
+    fn test_kafka_consumer_and_producer() {
+        let mut actor = create_test_actor().with_sinks(vec![
+            Sink::new("CustomValue2", "values.out").with_format(WireFormat::Cbor),
+        ]);
+        let receivers = &mut actor.component.message_receivers.receivers;
+        receivers[1] = receivers[1].clone().with_source(
+            ExternalSource::new(Broker::Kafka, "values.in").with_format(WireFormat::Postcard),
+        );
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+
+        let sources = generator.generate_sources().unwrap().unwrap();
+        assert!(sources.starts_with("#![cfg(any(feature = \"kafka\"))]\n"));
+        assert!(sources.contains("consumer.subscribe(&[\"values.in\"])?;"));
+        assert!(sources.contains(
+            "            \"values.in\" => {\n                let decoded: Result<CustomArgs, _> = postcard::from_bytes(bytes);"
+        ));
+        assert!(sources.contains("        let Some(bytes) = record.payload() else {"));
+
+        let sinks = generator
+            .generate_sinks()
+            .unwrap()
+            .expect("Sinks should be generated");
+        assert!(sinks.starts_with("#![cfg(feature = \"kafka\")]\n"));
+        assert!(sinks.contains(
+            "fn encode_customvalue2(payload: &CustomArgs) -> Result<Vec<u8>, ciborium::ser::Error<std::io::Error>> {"
+        ));
+        assert!(sinks.contains("ciborium::into_writer(payload, &mut bytes)?;"));
+        assert!(sinks.contains("pub async fn publish_customvalue2("));
+        assert!(sinks.contains("FutureRecord::<(), _>::to(\"values.out\")"));
+        assert!(generator.root_modules().contains(&"sinks".to_string()));
+
+        let actor = create_test_actor().with_sinks(vec![Sink::new("Missing", "values.out")]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate_sinks(),
+            Err(RenderError::InvalidSpec(
+                "Sink for topic 'values.out' publishes unknown variant 'Missing'".to_string()
+            ))
+        );
+
+        let actor = create_test_actor().with_sinks(vec![
+            Sink::new("CustomValue2", "values.out"),
+            Sink::new("CustomValue2", "values.audit"),
+        ]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate_sinks(),
+            Err(RenderError::InvalidSpec(
+                "Variant 'CustomValue2' is published to both 'values.out' and 'values.audit'"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_kafka_consumer_without_let_else() {
+        let mut actor = create_test_actor();
+        let receivers = &mut actor.component.message_receivers.receivers;
+        receivers[1] = receivers[1]
+            .clone()
+            .with_source(ExternalSource::new(Broker::Kafka, "values.in"));
+        let options = GeneratorOptions::default()
+            .with_edition(Edition::E2021)
+            .with_msrv(Some(RustVersion::new(1, 64)));
+        let generator = ActorGenerator::with_options(actor, options)
+            .expect("Generator creation should succeed");
+
+        let sources = generator.generate_sources().unwrap().unwrap();
+        assert!(sources.contains(
+            "        let bytes = match record.payload() {\n            Some(bytes) => bytes,\n            None => continue,\n        };"
+        ));
+    }
+
+    #[test]
+    fn test_trace_context_headers() {
+        let mut actor = create_test_actor().with_correlation().with_sinks(vec![
+            Sink::new("CustomValue2", "values.out").with_trace_context(),
+        ]);
+        let receivers = &mut actor.component.message_receivers.receivers;
+        receivers[0] = receivers[0]
+            .clone()
+            .with_source(ExternalSource::new(Broker::Nats, "values.standard").with_trace_context());
+        receivers[1] = receivers[1]
+            .clone()
+            .with_source(ExternalSource::new(Broker::Kafka, "values.in").with_trace_context());
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let sources = generator.generate_sources().unwrap().unwrap();
+        assert!(sources.contains(
+            "        let trace_context = TraceContext::from_headers(\n            message\n                .headers\n"
+        ));
+        assert!(sources.contains(
+            "                        .filter_map(|header| Some((header.key, std::str::from_utf8(header.value?).ok()?))),"
+        ));
+        assert!(sources.contains("            payload.with_trace_context(trace_context)\n"));
+        assert!(sources.contains("    use rdkafka::message::Headers;"));
+        assert!(sources.contains("use crate::actor::correlation::TraceContext;"));
+
+        let sinks = generator.generate_sinks().unwrap().unwrap();
+        assert!(sinks.contains("    for (key, value) in payload.trace_context.headers() {"));
+        assert!(sinks.contains("        .payload(&bytes)\n        .headers(headers);"));
+
+        let mut actor = create_test_actor().with_correlation();
+        let receivers = &mut actor.component.message_receivers.receivers;
+        receivers[1] = receivers[1]
+            .clone()
+            .with_source(ExternalSource::new(Broker::Mqtt, "values/custom").with_trace_context());
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        assert_eq!(
+            generator.generate_sources(),
+            Err(RenderError::InvalidSpec(
+                "MQTT topic 'values/custom' has no headers to read trace context from".to_string()
+            ))
+        );
+    }
+}
@@ -1,574 +1,139 @@
+use crate::blox::constant::ConstantModule;
+use crate::create::constants_gen::render_constants;
+use crate::create::{GeneratorOptions, ImportFallback, RenderCtx, RenderError, ToRust};
 use crate::graph::CodeGenGraph;
-use crate::{actor::Actor, blox::state::State};
+use crate::{
+    actor::Actor,
+    blox::state::{State, StateGroups},
+};
 use std::error::Error;
 
-/// Generate a state implementation for a specific State in the States collection
-pub fn generate_inner_states(actor: &Actor, state: &State) -> Result<String, Box<dyn Error>> {
-    let state_name = &state.ident;
-    let actor_mod = actor.ident.to_lowercase();
-    let component_mod = &actor.component.ident;
-    let component_ident = &actor.component.ident;
-    let message_set = &actor
-        .component
-        .message_set
-        .as_ref()
-        .map(|ms| ms.get().ident.clone())
-        .unwrap_or(format!("<{component_ident} as Components>::MessageSet"));
-
-    let impl_content = format!(
-        r#"use bloxide_tokio::{{components::Components, state_machine::{{StateMachine, State, Transition}}}};
-use crate::{actor_mod}::{{component::{component_mod}, messaging::{message_set}}};
-
-/// State implementation for {state_name} state
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct {state_name};
-
-impl State<{component_ident}> for {state_name} {{
-    fn handle_message(
-        &self,
-        state_machine: &mut StateMachine<{component_ident}>,
-        message: {message_set},
-    ) -> Option<Transition<<{component_ident} as Components>::States, {message_set}>> {{
-        None
-    }}
-}}
-"#,
-    );
-    Ok(impl_content)
+/// Render the module for a single state: its imports followed by the state implementation
+pub fn render_state_module(ctx: &RenderCtx, state: &State) -> Result<String, RenderError> {
+    let actor_mod = ctx.actor_module();
+    let imports_section = ctx.imports_section(&format!(
+        "{actor_mod}::states::{}",
+        state.ident.to_lowercase()
+    ))?;
+
+    let state_code = state.to_source(ctx)?;
+
+    Ok(format!("{imports_section}{state_code}"))
 }
 
-/// Generate a unified StateEnum implementation that contains all states
-pub fn generate_state_enum_impl(actor: &Actor) -> Result<String, Box<dyn Error>> {
-    let states = &actor.component.states;
-    let actor_mod = actor.ident.to_lowercase();
-    let component_ident = &actor.component.ident;
-    let enum_name = states.state_enum.get().ident.clone();
-    let component_mod = &actor.component.ident;
-    let message_set = &actor
-        .component
-        .message_set
-        .as_ref()
-        .map(|ms| ms.get().ident.clone())
-        .unwrap_or(format!("<{component_ident} as Components>::MessageSet"));
-
-    let mut imports = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}use {ident_lowercase}::{ident};\n",
-            ident_lowercase = state.ident.to_lowercase(),
-            ident = state.ident
-        )
-    });
-    imports.push_str(&format!(
-        "use crate::{actor_mod}::{{component::{component_mod}, messaging::{message_set}}};"
-    ));
-
-    let variants = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}    /// {state_name} state\n    {state_name}({state_name}),\n",
-            state_name = state.ident
-        )
-    });
+/// Render the states module body: its imports followed by the StateEnum implementation
+pub fn render_state_enum_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    render_split_state_enum_module(ctx, None)
+}
 
-    let handle_message_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!("{acc}            {enum_name}::{state_name}(state) => state.handle_message(state_machine, message),\n", 
-            state_name = state.ident
-        )
-    });
+/// Render the states module body with the StateEnum dispatching through the submodules of
+/// `groups`, when the states are split into any
+pub(crate) fn render_split_state_enum_module(
+    ctx: &RenderCtx,
+    groups: Option<&StateGroups>,
+) -> Result<String, RenderError> {
+    let actor_mod = ctx.actor_module();
+    let imports_section = ctx.imports_section(&format!("{actor_mod}::states"))?;
 
-    let on_entry_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}            {enum_name}::{state_name}(state) => state.on_entry(state_machine),\n",
-            state_name = state.ident
-        )
-    });
+    let state_enum_code = ctx.actor.component.states.state_enum.render(ctx, groups)?;
 
-    let on_exit_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}            {enum_name}::{state_name}(state) => state.on_exit(state_machine),\n",
-            state_name = state.ident
-        )
-    });
+    let constants = render_constants(ctx, ConstantModule::States);
 
-    let parent_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}            {enum_name}::{state_name}(state) => state.parent(),\n",
-            state_name = state.ident
-        )
-    });
-
-    let impl_content = format!(
-        r#"use bloxide_tokio::{{components::Components, messaging::MessageSet, state_machine::{{StateMachine, State, StateEnum, Transition}}}};
-{imports}
-
-/// Enumeration of all possible states for the actor's state machine
-#[derive(Clone, PartialEq, Debug)]
-pub enum {enum_name} {{
-{variants}}}
-
-impl State<{component_ident}> for {enum_name} {{
-    /// Handles incoming messages and returns a transition to a new state if needed
-    fn handle_message(
-        &self,
-        state_machine: &mut StateMachine<{component_ident}>,
-        message: {message_set},
-    ) -> Option<Transition<<{component_ident} as Components>::States, {message_set}>> {{
-        match self {{
-{handle_message_arms}
-        }}
-    }}
-
-    /// Executes actions when entering a state
-    fn on_entry(&self, state_machine: &mut StateMachine<{component_ident}>) {{
-        match self {{
-{on_entry_arms}
-        }}
-    }}
-
-    /// Executes actions when exiting a state
-    fn on_exit(&self, state_machine: &mut StateMachine<{component_ident}>) {{
-        match self {{
-{on_exit_arms}
-        }}
-    }}
-
-    /// Returns the parent state in the state machine hierarchy
-    fn parent(&self) -> {enum_name} {{
-        match self {{
-{parent_arms}
-        }}
-    }}
-}}
-
-impl StateEnum for {enum_name} {{
-    fn new() -> Self {{
-        Self::default()
-    }}
-}}
-
-impl Default for {enum_name} {{
-    fn default() -> Self {{
-        {enum_name}::Uninit(Uninit)
-    }}
-}}
-"#,
-        message_set = actor.message_set_ident(),
-    );
-
-    Ok(impl_content)
+    Ok(format!("{imports_section}{constants}{state_enum_code}"))
+}
+
+fn analyzed_graph(actor: &Actor) -> Result<CodeGenGraph, Box<dyn Error>> {
+    let mut graph = CodeGenGraph::new();
+    graph.analyze_actor(actor)?;
+    Ok(graph)
+}
+
+fn options_with_fallback(import_fallback: ImportFallback) -> GeneratorOptions {
+    GeneratorOptions::default().with_import_fallback(import_fallback)
+}
+
+/// Generate a state implementation for a specific State in the States collection
+#[deprecated(note = "use `render_state_module` or `ActorGenerator::generate_state_impl`")]
+pub fn generate_inner_states(actor: &Actor, state: &State) -> Result<String, Box<dyn Error>> {
+    let graph = analyzed_graph(actor)?;
+    let options = options_with_fallback(ImportFallback::Defaults);
+    Ok(render_state_module(
+        &RenderCtx::new(actor, &graph, &options),
+        state,
+    )?)
+}
+
+/// Generate a unified StateEnum implementation that contains all states
+#[deprecated(note = "use `render_state_enum_module` or `ActorGenerator::generate_state_enum`")]
+pub fn generate_state_enum_impl(actor: &Actor) -> Result<String, Box<dyn Error>> {
+    let graph = analyzed_graph(actor)?;
+    let options = options_with_fallback(ImportFallback::Defaults);
+    Ok(render_state_enum_module(&RenderCtx::new(
+        actor, &graph, &options,
+    ))?)
 }
 
 /// Generate a state implementation with graph-based import resolution
+#[deprecated(note = "use `render_state_module` with `ImportFallback::Defaults`")]
 pub fn generate_inner_states_with_graph(
     actor: &Actor,
     state: &State,
     graph: &CodeGenGraph,
 ) -> Result<String, Box<dyn Error>> {
-    let state_name = &state.ident;
-    let actor_mod = actor.ident.to_lowercase();
-    let component_ident = &actor.component.ident;
-    let message_set = &actor
-        .component
-        .message_set
-        .as_ref()
-        .map(|ms| ms.get().ident.clone())
-        .unwrap_or(format!("<{component_ident} as Components>::MessageSet"));
-
-    // Find the specific state module in the graph to get its imports
-    let state_module_path = format!("{actor_mod}::states");
-    let imports = if let Some(state_module_idx) = graph
-        .graph
-        .find_module_by_path_hierarchical(&state_module_path)
-    {
-        graph
-            .get_imports_for_module(state_module_idx)
-            .collect::<Vec<_>>()
-    } else {
-        // Fallback to hardcoded imports if graph lookup fails
-        vec![
-            "use bloxide_tokio::components::Components;".to_string(),
-            "use bloxide_tokio::state_machine::{StateMachine, State, Transition};".to_string(),
-            format!("use crate::{actor_mod}::component::{component_ident};"),
-            format!("use crate::{actor_mod}::messaging::{message_set};"),
-        ]
-    };
-
-    let imports_section = if imports.is_empty() {
-        String::new()
-    } else {
-        format!("{}\n\n", imports.join("\n"))
-    };
-
-    let impl_content = format!(
-        r#"{imports_section}/// State implementation for {state_name} state
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct {state_name};
-
-impl State<{component_ident}> for {state_name} {{
-    fn handle_message(
-        &self,
-        _state_machine: &mut StateMachine<{component_ident}>,
-        _message: {message_set},
-    ) -> Option<Transition<<{component_ident} as Components>::States, {message_set}>> {{
-        None
-    }}
-}}
-"#,
-    );
-
-    Ok(impl_content)
+    let options = options_with_fallback(ImportFallback::Defaults);
+    Ok(render_state_module(
+        &RenderCtx::new(actor, graph, &options),
+        state,
+    )?)
 }
 
 /// Generate a unified StateEnum implementation with graph-based import resolution
+#[deprecated(note = "use `render_state_enum_module` with `ImportFallback::Defaults`")]
 pub fn generate_state_enum_impl_with_graph(
     actor: &Actor,
     graph: &CodeGenGraph,
 ) -> Result<String, Box<dyn Error>> {
-    let states = &actor.component.states;
-    let actor_mod = actor.ident.to_lowercase();
-    let component_ident = &actor.component.ident;
-    let enum_name = states.state_enum.get().ident.clone();
-    let message_set = &actor
-        .component
-        .message_set
-        .as_ref()
-        .map(|ms| ms.get().ident.clone())
-        .unwrap_or(format!("<{component_ident} as Components>::MessageSet"));
-
-    // Get imports from graph for the states module
-    let state_module_path = format!("{actor_mod}::states");
-    let mut imports = if let Some(state_module_idx) = graph
-        .graph
-        .find_module_by_path_hierarchical(&state_module_path)
-    {
-        graph
-            .get_imports_for_module(state_module_idx)
-            .collect::<Vec<_>>()
-    } else {
-        // Fallback to hardcoded imports
-        vec![
-            "use bloxide_tokio::components::Components;".to_string(),
-            "use bloxide_tokio::messaging::MessageSet;".to_string(),
-            "use bloxide_tokio::state_machine::{StateMachine, State, StateEnum, Transition};"
-                .to_string(),
-            format!("use crate::{actor_mod}::component::{component_ident};"),
-            format!("use crate::{actor_mod}::messaging::{message_set};"),
-        ]
-    };
-
-    // Add imports for individual state modules (these are local to the states module)
-    for state in &states.states {
-        imports.push(format!(
-            "use {}::{};",
-            state.ident.to_lowercase(),
-            state.ident
-        ));
-    }
-
-    let imports_section = if imports.is_empty() {
-        String::new()
-    } else {
-        format!("{}\n\n", imports.join("\n"))
-    };
-
-    // Generate enum variants
-    let variants = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}    /// {state_name} state\n    {state_name}({state_name}),\n",
-            state_name = state.ident
-        )
-    });
-
-    // Generate match arms for each method
-    let handle_message_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!("{acc}            {enum_name}::{state_name}(state) => state.handle_message(state_machine, message),\n", 
-            state_name = state.ident
-        )
-    });
-
-    let on_entry_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}            {enum_name}::{state_name}(state) => state.on_entry(state_machine),\n",
-            state_name = state.ident
-        )
-    });
-
-    let on_exit_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}            {enum_name}::{state_name}(state) => state.on_exit(state_machine),\n",
-            state_name = state.ident
-        )
-    });
-
-    let parent_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}            {enum_name}::{state_name}(state) => state.parent(),\n",
-            state_name = state.ident
-        )
-    });
-
-    let impl_content = format!(
-        r#"{imports_section}/// Enumeration of all possible states for the actor's state machine
-#[derive(Clone, PartialEq, Debug)]
-pub enum {enum_name} {{
-{variants}}}
-
-impl State<{component_ident}> for {enum_name} {{
-    /// Handles incoming messages and returns a transition to a new state if needed
-    fn handle_message(
-        &self,
-        state_machine: &mut StateMachine<{component_ident}>,
-        message: {message_set},
-    ) -> Option<Transition<<{component_ident} as Components>::States, {message_set}>> {{
-        match self {{
-{handle_message_arms}
-        }}
-    }}
-
-    /// Executes actions when entering a state
-    fn on_entry(&self, state_machine: &mut StateMachine<{component_ident}>) {{
-        match self {{
-{on_entry_arms}
-        }}
-    }}
-
-    /// Executes actions when exiting a state
-    fn on_exit(&self, state_machine: &mut StateMachine<{component_ident}>) {{
-        match self {{
-{on_exit_arms}
-        }}
-    }}
-
-    /// Returns the parent state in the state machine hierarchy
-    fn parent(&self) -> {enum_name} {{
-        match self {{
-{parent_arms}
-        }}
-    }}
-}}
-
-impl StateEnum for {enum_name} {{
-    fn new() -> Self {{
-        Self::default()
-    }}
-}}
-
-impl Default for {enum_name} {{
-    fn default() -> Self {{
-        {enum_name}::Uninit(Uninit)
-    }}
-}}
-"#,
-    );
-
-    Ok(impl_content)
+    let options = options_with_fallback(ImportFallback::Defaults);
+    Ok(render_state_enum_module(&RenderCtx::new(
+        actor, graph, &options,
+    ))?)
 }
 
 /// Generate state implementations with structural analysis for import detection
+#[deprecated(note = "use `render_state_module` with `ImportFallback::Empty`")]
 pub fn generate_inner_states_with_structural_analysis(
     actor: &Actor,
     state: &State,
     graph: &CodeGenGraph,
 ) -> Result<String, Box<dyn Error>> {
-    let state_name = &state.ident;
-    let actor_mod = actor.ident.to_lowercase();
-    let component_ident = &actor.component.ident;
-    let message_set = &actor
-        .component
-        .message_set
-        .as_ref()
-        .map(|ms| ms.get().ident.clone())
-        .unwrap_or(format!("<{component_ident} as Components>::MessageSet"));
-
-    // Find the specific state module in the graph to get its imports
-    let state_module_path = format!("{actor_mod}::states");
-    let imports = if let Some(state_module_idx) = graph
-        .graph
-        .find_module_by_path_hierarchical(&state_module_path)
-    {
-        graph
-            .get_imports_for_module(state_module_idx)
-            .collect::<Vec<_>>()
-    } else {
-        // Fallback if module not found
-        vec![]
-    };
-
-    let imports_section = if imports.is_empty() {
-        String::new()
-    } else {
-        format!("{}\n\n", imports.join("\n"))
-    };
-
-    let impl_content = format!(
-        r#"{imports_section}/// State implementation for {state_name} state
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct {state_name};
-
-impl State<{component_ident}> for {state_name} {{
-    fn handle_message(
-        &self,
-        state_machine: &mut StateMachine<{component_ident}>,
-        message: {message_set},
-    ) -> Option<Transition<<{component_ident} as Components>::States, {message_set}>> {{
-        None
-    }}
-}}
-"#,
-    );
-
-    Ok(impl_content)
+    let options = options_with_fallback(ImportFallback::Empty);
+    Ok(render_state_module(
+        &RenderCtx::new(actor, graph, &options),
+        state,
+    )?)
 }
 
 /// Generate a unified StateEnum implementation with structural analysis
+#[deprecated(note = "use `render_state_enum_module` with `ImportFallback::Empty`")]
 pub fn generate_state_enum_impl_with_structural_analysis(
     actor: &Actor,
     graph: &CodeGenGraph,
 ) -> Result<String, Box<dyn Error>> {
-    let states = &actor.component.states;
-    let actor_mod = actor.ident.to_lowercase();
-    let component_ident = &actor.component.ident;
-    let enum_name = states.state_enum.get().ident.clone();
-    let message_set = &actor
-        .component
-        .message_set
-        .as_ref()
-        .map(|ms| ms.get().ident.clone())
-        .unwrap_or(format!("<{component_ident} as Components>::MessageSet"));
-
-    // Get imports from graph for the states module
-    let state_module_path = format!("{actor_mod}::states");
-    let mut imports = if let Some(state_module_idx) = graph
-        .graph
-        .find_module_by_path_hierarchical(&state_module_path)
-    {
-        graph
-            .get_imports_for_module(state_module_idx)
-            .collect::<Vec<_>>()
-    } else {
-        // Fallback to hardcoded imports
-        vec![
-            "use bloxide_tokio::components::Components;".to_string(),
-            "use bloxide_tokio::messaging::MessageSet;".to_string(),
-            "use bloxide_tokio::state_machine::{StateMachine, State, StateEnum, Transition};"
-                .to_string(),
-            format!("use crate::{actor_mod}::component::{component_ident};"),
-            format!("use crate::{actor_mod}::messaging::{message_set};"),
-        ]
-    };
-
-    // Add imports for individual state modules (these are local to the states module)
-    for state in &states.states {
-        imports.push(format!(
-            "use {}::{};",
-            state.ident.to_lowercase(),
-            state.ident
-        ));
-    }
-
-    let imports_section = if imports.is_empty() {
-        String::new()
-    } else {
-        format!("{}\n\n", imports.join("\n"))
-    };
-
-    // Generate enum variants
-    let variants = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}    /// {state_name} state\n    {state_name}({state_name}),\n",
-            state_name = state.ident
-        )
-    });
-
-    // Generate match arms for each method
-    let handle_message_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!("{acc}            {enum_name}::{state_name}(state) => state.handle_message(state_machine, message),\n", 
-            state_name = state.ident
-        )
-    });
-
-    let on_entry_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}            {enum_name}::{state_name}(state) => state.on_entry(state_machine),\n",
-            state_name = state.ident
-        )
-    });
-
-    let on_exit_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}            {enum_name}::{state_name}(state) => state.on_exit(state_machine),\n",
-            state_name = state.ident
-        )
-    });
-
-    let parent_arms = states.states.iter().fold(String::new(), |acc, state| {
-        format!(
-            "{acc}            {enum_name}::{state_name}(state) => state.parent(),\n",
-            state_name = state.ident
-        )
-    });
-
-    let impl_content = format!(
-        r#"{imports_section}/// Enumeration of all possible states for the actor's state machine
-#[derive(Clone, PartialEq, Debug)]
-pub enum {enum_name} {{
-{variants}}}
-
-impl State<{component_ident}> for {enum_name} {{
-    /// Handles incoming messages and returns a transition to a new state if needed
-    fn handle_message(
-        &self,
-        state_machine: &mut StateMachine<{component_ident}>,
-        message: {message_set},
-    ) -> Option<Transition<<{component_ident} as Components>::States, {message_set}>> {{
-        match self {{
-{handle_message_arms}
-        }}
-    }}
-
-    /// Executes actions when entering a state
-    fn on_entry(&self, state_machine: &mut StateMachine<{component_ident}>) {{
-        match self {{
-{on_entry_arms}
-        }}
-    }}
-
-    /// Executes actions when exiting a state
-    fn on_exit(&self, state_machine: &mut StateMachine<{component_ident}>) {{
-        match self {{
-{on_exit_arms}
-        }}
-    }}
-
-    /// Returns the parent state in the state machine hierarchy
-    fn parent(&self) -> {enum_name} {{
-        match self {{
-{parent_arms}
-        }}
-    }}
-}}
-
-impl StateEnum for {enum_name} {{
-    fn new() -> Self {{
-        Self::default()
-    }}
-}}
-
-impl Default for {enum_name} {{
-    fn default() -> Self {{
-        {enum_name}::Uninit(Uninit)
-    }}
-}}
-"#,
-    );
-
-    Ok(impl_content)
+    let options = options_with_fallback(ImportFallback::Empty);
+    Ok(render_state_enum_module(&RenderCtx::new(
+        actor, graph, &options,
+    ))?)
 }
 
 #[cfg(test)]
 mod tests {
+    #![allow(deprecated)]
+
+    use super::*;
     use crate::{
-        blox::enums::EnumDef,
-        blox::state::{State, StateEnum, States},
+        Field, Link, Method,
+        blox::enums::{EnumDef, EnumVariant},
+        blox::state::{Emit, State, StateEnum, States, Transition},
         create::ActorGenerator,
         tests::create_test_actor,
     };
@@ -628,4 +193,262 @@ mod tests {
         assert!(impl_content.contains("match self {"));
         assert!(impl_content.contains("ActorStates::Create(state) =>"));
     }
+
+    #[test]
+    fn test_state_enum_doc_table() {
+        let mut actor = create_test_actor();
+        actor.component.states.states = vec![
+            State::from("Create").with_transitions(vec![
+                Transition::new("CustomValue1", "Update"),
+                Transition::new("CustomValue2", "Create")
+                    .with_emit(vec![Emit::new("standard_handle", "message.clone()")]),
+            ]),
+            State::new("Update", Some("Create".to_string()), None),
+        ];
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let state_enum = generator.generate_state_enum().unwrap();
+
+        assert!(state_enum.contains(
+            "/// Enumeration of all possible states for the actor's state machine\n///\n/// | State | Parent | Handles | Transitions |\n/// |-------|--------|---------|-------------|\n"
+        ));
+        assert!(state_enum.contains(
+            "/// | [`Create`](Self::Create) | - | `CustomValue1`, `CustomValue2` | `CustomValue1` -> [`Update`](Self::Update)<br>`CustomValue2` -> [`Create`](Self::Create) (emits through `standard_handle`) |\n"
+        ));
+        assert!(state_enum.contains(
+            "/// | [`Update`](Self::Update) | [`Create`](Self::Create) | - | - |\n#[derive(Clone, PartialEq, Debug)]"
+        ));
+    }
+
+    #[test]
+    fn test_state_methods_are_emitted_with_imports() {
+        let mut actor = create_test_actor();
+        actor.component.states.states[0] = State::from("Create").with_methods(vec![Method::new(
+            "payload",
+            &[Field::new("&self", "")],
+            "Option<StandardPayload>",
+            "None",
+        )]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let state = &generator.actor().component.states.states[0];
+        let impl_content = generator
+            .generate_state_impl(state)
+            .expect("Failed to generate state impl");
+
+        assert!(impl_content.contains("impl Create {"));
+        assert!(impl_content.contains("pub fn payload(&self) -> Option<StandardPayload>"));
+        assert!(impl_content.contains("use bloxide_tokio::messaging::StandardPayload;"));
+    }
+
+    #[test]
+    fn test_data_carrying_states() {
+        let mut actor = create_test_actor();
+        actor.component.states.states = vec![
+            State::from("Create").with_fields(vec![
+                Field::new("attempts", "u32"),
+                Field::new("payload", "Option<StandardPayload>"),
+            ]),
+            State::new(
+                "Update",
+                Some("Create".to_string()),
+                Some(vec![
+                    EnumVariant::new("Pending", vec![]),
+                    EnumVariant::new("Retrying", vec![Link::new("u32")]),
+                ]),
+            ),
+        ];
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let states = &generator.actor().component.states.states;
+
+        let create = generator.generate_state_impl(&states[0]).unwrap();
+        assert!(
+            create.contains("#[derive(Debug, Clone, PartialEq, Default)]\npub struct Create {")
+        );
+        assert!(create.contains("    pub attempts: u32,"));
+        assert!(create.contains("use bloxide_tokio::messaging::StandardPayload;"));
+
+        let update = generator.generate_state_impl(&states[1]).unwrap();
+        assert!(update.contains("pub enum Update {\n    Pending,\n    Retrying(u32),\n}"));
+        assert!(update.contains("impl Default for Update"));
+
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains("&ActorStates::Create(Create::default())"));
+        assert!(runtime.contains("&ActorStates::Update(Update::default())"));
+
+        // Without an `Uninit` state the enum defaults to the first one, built like the runtime
+        let state_enum = generator.generate_state_enum().unwrap();
+        assert!(state_enum.contains(
+            "impl Default for ActorStates {\n    fn default() -> Self {\n        ActorStates::Create(Create::default())\n    }\n}"
+        ));
+    }
+
+    #[test]
+    fn test_state_cannot_have_fields_and_variants() {
+        let state = State::new(
+            "Create",
+            None,
+            Some(vec![EnumVariant::new("Pending", vec![])]),
+        )
+        .with_fields(vec![Field::new("attempts", "u32")]);
+        let states = States::new(
+            vec![state],
+            StateEnum::new(EnumDef::new("ActorStates", vec![])),
+        );
+
+        assert!(states.validate().is_err());
+    }
+
+    #[test]
+    fn test_deprecated_paths_match_pipeline() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        let actor = generator.actor();
+        let graph = generator.graph();
+
+        for state in &actor.component.states.states {
+            let expected = generator
+                .generate_state_impl(state)
+                .expect("Pipeline should render state");
+            assert_eq!(generate_inner_states(actor, state).unwrap(), expected);
+            assert_eq!(
+                generate_inner_states_with_graph(actor, state, graph).unwrap(),
+                expected
+            );
+            assert_eq!(
+                generate_inner_states_with_structural_analysis(actor, state, graph).unwrap(),
+                expected
+            );
+        }
+
+        let expected = generator
+            .generate_state_enum()
+            .expect("Pipeline should render state enum");
+        assert_eq!(generate_state_enum_impl(actor).unwrap(), expected);
+        assert_eq!(
+            generate_state_enum_impl_with_graph(actor, graph).unwrap(),
+            expected
+        );
+        assert_eq!(
+            generate_state_enum_impl_with_structural_analysis(actor, graph).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_fallback_strategies_without_analysis() {
+        let actor = create_test_actor();
+        let state = &actor.component.states.states[0];
+        let empty_graph = CodeGenGraph::new();
+
+        let with_defaults = generate_inner_states_with_graph(&actor, state, &empty_graph).unwrap();
+        assert!(with_defaults.contains("use bloxide_tokio::state_machine::State;"));
+        assert!(with_defaults.contains("pub struct Create"));
+
+        let structural =
+            generate_inner_states_with_structural_analysis(&actor, state, &empty_graph).unwrap();
+        assert!(!structural.contains("use "));
+        assert!(structural.contains("pub struct Create"));
+    }
+
+    #[test]
+    fn test_transitions_render_concrete_targets() {
+        let mut actor = create_test_actor();
+        actor.component.states.states = vec![
+            State::from("Create")
+                .with_fields(vec![Field::new("attempts", "u32")])
+                .with_transitions(vec![Transition::new("CustomValue1", "Create")]),
+            State::new("Update", Some("Create".to_string()), None).with_transitions(vec![
+                Transition::new("CustomValue1", "Create"),
+                Transition::new("CustomValue2", "Update"),
+            ]),
+        ];
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let states = &generator.actor().component.states.states;
+
+        let create = generator.generate_state_impl(&states[0]).unwrap();
+        assert!(create.contains("match &message {"));
+        assert!(create.contains(
+            "ActorMessageSet::CustomValue1(..) => Some(Transition::To(ActorStates::Create(Create::default()))),"
+        ));
+        assert!(create.contains("_ => None,"));
+        assert!(create.contains("use crate::actor::states::ActorStates;"));
+
+        // Transition to the parent state, covering every message without a fallthrough
+        let update = generator.generate_state_impl(&states[1]).unwrap();
+        assert!(update.contains(
+            "ActorMessageSet::CustomValue1(..) => Some(Transition::To(ActorStates::Create(Create::default()))),"
+        ));
+        assert!(update.contains(
+            "ActorMessageSet::CustomValue2(..) => Some(Transition::To(ActorStates::Update(Update))),"
+        ));
+        assert!(!update.contains("_ => None,"));
+        assert!(update.contains("use crate::actor::states::create::Create;"));
+    }
+
+    #[test]
+    fn test_transition_targets_are_validated() {
+        let states = States::new(
+            vec![
+                State::from("Create")
+                    .with_transitions(vec![Transition::new("CustomValue1", "Missing")]),
+            ],
+            StateEnum::new(EnumDef::new("ActorStates", vec![])),
+        );
+        assert!(
+            states
+                .validate()
+                .unwrap_err()
+                .contains("unknown state 'Missing'")
+        );
+
+        let states = States::new(
+            vec![State::from("Create").with_transitions(vec![
+                Transition::new("CustomValue1", "Create"),
+                Transition::new("CustomValue1", "Create"),
+            ])],
+            StateEnum::new(EnumDef::new("ActorStates", vec![])),
+        );
+        assert!(states.validate().is_err());
+
+        let mut actor = create_test_actor();
+        actor.component.states.states[0] =
+            State::from("Create").with_transitions(vec![Transition::new("Unknown", "Create")]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let state = &generator.actor().component.states.states[0];
+        assert!(matches!(
+            generator.generate_state_impl(state),
+            Err(RenderError::InvalidSpec(_))
+        ));
+    }
+
+    #[test]
+    fn test_transitions_emit_through_handles() {
+        let mut actor = create_test_actor();
+        actor.component.states.states[0] = State::from("Create").with_transitions(vec![
+            Transition::new("CustomValue2", "Update").with_emit(vec![Emit::new(
+                "customargs_handle",
+                "Message::new(0, CustomArgs::default())",
+            )]),
+        ]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let state = &generator.actor().component.states.states[0];
+        let impl_content = generator.generate_state_impl(state).unwrap();
+
+        assert!(
+            impl_content.contains("        state_machine: &mut StateMachine<ActorComponents>,")
+        );
+        assert!(impl_content.contains(
+            "            ActorMessageSet::CustomValue2(..) => {\n                if state_machine.self_handles.customargs_handle.try_send(Message::new(0, CustomArgs::default())).is_err() {\n                    eprintln!(\"Create dropped a message it emits through `customargs_handle`\");\n                }\n                Some(Transition::To(ActorStates::Update(Update)))\n            }"
+        ));
+
+        let mut actor = create_test_actor();
+        actor.component.states.states[0] = State::from("Create").with_transitions(vec![
+            Transition::new("CustomValue2", "Update").with_emit(vec![Emit::new("missing", "m")]),
+        ]);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let state = &generator.actor().component.states.states[0];
+        assert!(matches!(
+            generator.generate_state_impl(state),
+            Err(RenderError::InvalidSpec(_))
+        ));
+    }
 }
@@ -0,0 +1,189 @@
+use crate::blox::actor::RESTART_SNAPSHOT_FIELD;
+use crate::blox::lifecycle::{RestartPolicy, RestartState};
+use crate::create::{RenderCtx, RenderError};
+
+/// Render the supervisor module: `supervise`, restarting the actor by its restart policy
+pub fn render_supervisor_module(ctx: &RenderCtx) -> Result<String, RenderError> {
+    let actor = ctx.actor;
+    let actor_name = &actor.ident;
+    let lifecycle = &actor.lifecycle;
+    let ext_state_name = actor.component.ext_state.ident();
+    let imports_section = ctx.imports_section(&format!("{}::supervisor", ctx.actor_module()))?;
+
+    let (policy_doc, restart) = match lifecycle.restart {
+        RestartPolicy::Never => {
+            return Err(RenderError::InvalidSpec(format!(
+                "{actor_name} has no restart policy to supervise it by"
+            )));
+        }
+        RestartPolicy::OnPanic => (
+            "whenever it panics".to_string(),
+            r#"        match exit {
+            Err(error) if error.is_panic() => restarts += 1,
+            _ => return,
+        }"#
+            .to_string(),
+        ),
+        RestartPolicy::MaxRestarts { max, backoff_ms } => (
+            format!(
+                "after a panic at most {max} times, waiting {backoff_ms}ms before the first restart and twice as long before each next one; a normal exit ends supervision"
+            ),
+            format!(
+                r#"        if exit.is_ok() || restarts == {max} {{
+            return;
+        }}
+        sleep(Duration::from_millis({backoff_ms}).saturating_mul(1 << restarts.min(16))).await;
+        restarts += 1;"#
+            ),
+        ),
+    };
+
+    let (params, snapshot, spawn_args, arg_types, args_doc, restore) = match lifecycle.restart_state
+    {
+        RestartState::Fresh => (
+            String::new(),
+            "",
+            "restarts",
+            "u32".to_string(),
+            "builds a new instance with fresh extended state from the number of restarts so far; it is called once per run",
+            "",
+        ),
+        RestartState::Persisted => (
+            format!("mut ext_state: {ext_state_name}, "),
+            concat!(
+                "    // The running actor saves its extended state here after every message\n",
+                "    let snapshot = Arc::new(Mutex::new(None));\n",
+            ),
+            "ext_state.clone(), restarts",
+            format!("{ext_state_name}, u32"),
+            "builds a new instance from the extended state and the number of restarts so far; it is called once per run.\n///\n/// The first run starts from `ext_state` and each restart from the extended state the\n/// actor had after the last message it handled in full",
+            r#"
+        if let Some(saved) = snapshot.lock().unwrap_or_else(PoisonError::into_inner).take() {
+            ext_state = saved;
+        }"#,
+        ),
+    };
+    let snapshot = if snapshot.is_empty() {
+        String::new()
+    } else {
+        format!("{snapshot}    ext_state.{RESTART_SNAPSHOT_FIELD} = Arc::downgrade(&snapshot);\n")
+    };
+
+    Ok(format!(
+        r#"{imports_section}/// Runs the {actor_name} actor and restarts it {policy_doc}
+///
+/// `spawn_actor` {args_doc}.
+pub async fn supervise<F>({params}mut spawn_actor: F)
+where
+    F: FnMut({arg_types}) -> Box<Blox<{actor_name}Components>>,
+{{
+{snapshot}    let mut restarts: u32 = 0;
+    loop {{
+        let blox = spawn_actor({spawn_args});
+        let exit = spawn(blox.run()).await;
+{restart}{restore}
+    }}
+}}
+"#
+    ))
+}
+
+/// Render the snapshot of the extended state that follows a dispatch, which a supervised
+/// actor is restarted from
+pub fn render_snapshot_save() -> String {
+    format!(
+        r#"
+                        if let Some(snapshot) = self.state_machine.extended_state.{RESTART_SNAPSHOT_FIELD}.upgrade() {{
+                            let saved = self.state_machine.extended_state.clone();
+                            *snapshot.lock().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(saved);
+                        }}"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blox::lifecycle::{Lifecycle, RestartPolicy, RestartState};
+    use crate::create::ActorGenerator;
+    use crate::tests::create_test_actor;
+
+    #[test]
+    fn test_no_supervisor_by_default() {
+        let generator =
+            ActorGenerator::new(create_test_actor()).expect("Generator creation should succeed");
+        assert!(generator.generate_supervisor().unwrap().is_none());
+        assert!(!generator.root_modules().contains(&"supervisor".to_string()));
+    }
+
+    #[test]
+    fn test_restart_on_panic() {
+        let lifecycle =
+            Lifecycle::default().with_restart(RestartPolicy::OnPanic, RestartState::Fresh);
+        let actor = create_test_actor().with_lifecycle(lifecycle);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let supervisor = generator
+            .generate_supervisor()
+            .unwrap()
+            .expect("Supervisor should be generated");
+
+        assert!(supervisor.contains("pub async fn supervise<F>(mut spawn_actor: F)"));
+        assert!(supervisor.contains("    F: FnMut(u32) -> Box<Blox<ActorComponents>>,"));
+        assert!(supervisor.contains("Err(error) if error.is_panic() => restarts += 1,"));
+        assert!(supervisor.contains("use tokio::spawn;"));
+        assert!(supervisor.contains("use crate::actor::component::ActorComponents;"));
+        assert!(!supervisor.contains("sleep"));
+        assert!(!supervisor.contains("snapshot"));
+        assert!(!generator.generate_runtime().unwrap().contains("snapshot"));
+        assert!(generator.root_modules().contains(&"supervisor".to_string()));
+
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(!ext_state.contains("#[derive(Clone)]"));
+    }
+
+    #[test]
+    fn test_max_restarts_with_persisted_state() {
+        let lifecycle = Lifecycle::default().with_restart(
+            RestartPolicy::MaxRestarts {
+                max: 3,
+                backoff_ms: 100,
+            },
+            RestartState::Persisted,
+        );
+        let actor = create_test_actor().with_lifecycle(lifecycle);
+        let generator = ActorGenerator::new(actor).expect("Generator creation should succeed");
+        let supervisor = generator.generate_supervisor().unwrap().unwrap();
+
+        assert!(supervisor.contains(
+            "pub async fn supervise<F>(mut ext_state: ActorExtState, mut spawn_actor: F)"
+        ));
+        assert!(
+            supervisor.contains("    F: FnMut(ActorExtState, u32) -> Box<Blox<ActorComponents>>,")
+        );
+        assert!(supervisor.contains("let blox = spawn_actor(ext_state.clone(), restarts);"));
+        // A normal exit is not restarted
+        assert!(supervisor.contains(
+            "        if exit.is_ok() || restarts == 3 {\n            return;\n        }"
+        ));
+        assert!(supervisor.contains("sleep(Duration::from_millis(100)"));
+        assert!(supervisor.contains("use std::time::Duration;"));
+        assert!(supervisor.contains("use crate::actor::ext_state::ActorExtState;"));
+        assert!(supervisor.contains("use std::sync::PoisonError;"));
+
+        // Restarts pick up the extended state the actor last saved
+        assert!(supervisor.contains(
+            "    let snapshot = Arc::new(Mutex::new(None));\n    ext_state.restart_snapshot = Arc::downgrade(&snapshot);\n"
+        ));
+        assert!(supervisor.contains(
+            "        restarts += 1;\n        if let Some(saved) = snapshot.lock().unwrap_or_else(PoisonError::into_inner).take() {\n            ext_state = saved;\n        }\n    }"
+        ));
+        let runtime = generator.generate_runtime().unwrap();
+        assert!(runtime.contains(
+            "                        self.state_machine.dispatch(ActorMessageSet::CustomValue1(msg), &current_state);\n                        if let Some(snapshot) = self.state_machine.extended_state.restart_snapshot.upgrade() {\n                            let saved = self.state_machine.extended_state.clone();\n"
+        ));
+
+        let ext_state = generator.generate_ext_state().unwrap();
+        assert!(ext_state.contains("#[derive(Clone)]\npub struct ActorExtState {"));
+        assert!(ext_state.contains("pub restart_snapshot: Weak<Mutex<Option<ActorExtState>>>"));
+        assert!(ext_state.contains("restart_snapshot: Default::default()"));
+        assert!(ext_state.contains("use std::sync::Weak;"));
+    }
+}
@@ -0,0 +1,64 @@
+use core::fmt;
+
+use crate::alloc_prelude::*;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A finding about a spec reported by analysis, without stopping generation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier of the check that produced the diagnostic
+    pub code: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn warning<C, M>(code: C, message: M) -> Self
+    where
+        C: Into<String>,
+        M: Into<String>,
+    {
+        Self {
+            severity: Severity::Warning,
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn error<C, M>(code: C, message: M) -> Self
+    where
+        C: Into<String>,
+        M: Into<String>,
+    {
+        Self {
+            severity: Severity::Error,
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
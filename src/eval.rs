@@ -0,0 +1,780 @@
+//! Sandbox evaluating spec expressions against a mock extended state
+//!
+//! Invariants, guards and simple actions in a spec are Rust expressions reading the extended
+//! state as `ext` and the state's data as `self`. The sandbox interprets the subset a
+//! simulation needs without compiling generated code: literals, arithmetic, comparisons,
+//! boolean logic and field reads, plus assignments to `ext` fields in actions. Anything else,
+//! such as calls and macros, is rejected, and so are byte and C string literals.
+//!
+//! Integers are evaluated as `i128`, but a value stored in an integer field of the extended
+//! state, or a suffixed literal, must fit its type.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::blox::ext_state::ExtState;
+use crate::blox::state::State;
+
+/// Value of a field or an evaluated expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Char(char),
+    Str(String),
+    Tuple(Vec<Value>),
+}
+
+impl Value {
+    /// Value a field of a primitive type starts with when the spec gives it none
+    fn default_for(ty: &str) -> Option<Self> {
+        match ty {
+            "bool" => Some(Value::Bool(false)),
+            "f32" | "f64" => Some(Value::Float(0.0)),
+            "char" => Some(Value::Char('\0')),
+            "String" | "&str" | "&'static str" => Some(Value::Str(String::new())),
+            "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64"
+            | "i128" | "isize" => Some(Value::Int(0)),
+            _ => None,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Char(_) => "char",
+            Value::Str(_) => "string",
+            Value::Tuple(_) => "tuple",
+        }
+    }
+
+    /// Checks the value fits `ty`, when it is an integer type or a tuple of them
+    ///
+    /// Values of any other type are taken as they are.
+    fn check_fits(&self, ty: &str) -> Result<(), EvalError> {
+        match self {
+            Value::Int(value) => match int_range(ty) {
+                Some((min, max)) if *value < min || *value > max => Err(EvalError::Arithmetic(
+                    format!("{value} doesn't fit in {ty}"),
+                )),
+                _ => Ok(()),
+            },
+            Value::Tuple(values) => match tuple_types(ty) {
+                Some(types) if types.len() == values.len() => values
+                    .iter()
+                    .zip(types)
+                    .try_for_each(|(value, ty)| value.check_fits(ty)),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Smallest and largest values of an integer type
+fn int_range(ty: &str) -> Option<(i128, i128)> {
+    let (signed, bits) = match ty {
+        "usize" => (false, 64),
+        "isize" => (true, 64),
+        _ => {
+            let signed = ty.starts_with('i');
+            let bits = ty.strip_prefix(['u', 'i'])?.parse::<u32>().ok()?;
+            (signed, bits)
+        }
+    };
+    match (signed, bits) {
+        // u128 values beyond i128::MAX can't be evaluated anyway
+        (false, 128) => Some((0, i128::MAX)),
+        (false, 8 | 16 | 32 | 64) => Some((0, (1 << bits) - 1)),
+        (true, 8 | 16 | 32 | 64 | 128) => {
+            Some((i128::MIN >> (128 - bits), i128::MAX >> (128 - bits)))
+        }
+        _ => None,
+    }
+}
+
+/// Element types of a tuple type like `(u8, i16)`
+fn tuple_types(ty: &str) -> Option<Vec<&str>> {
+    let inner = ty.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let mut types = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' | '<' | '[' => depth += 1,
+            ')' | '>' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                types.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = inner[start..].trim();
+    if !last.is_empty() {
+        types.push(last);
+    }
+    Some(types)
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Int(value) => write!(f, "{value}"),
+            Value::Float(value) => write!(f, "{value:?}"),
+            Value::Char(value) => write!(f, "{value:?}"),
+            Value::Str(value) => write!(f, "{value:?}"),
+            Value::Tuple(values) => {
+                let values = values.iter().map(Value::to_string).collect::<Vec<_>>();
+                // A one-element tuple keeps its trailing comma, as in Rust
+                match values.as_slice() {
+                    [value] => write!(f, "({value},)"),
+                    values => write!(f, "({})", values.join(", ")),
+                }
+            }
+        }
+    }
+}
+
+/// Errors that can occur while evaluating an expression in the sandbox
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// The expression is not valid Rust
+    Parse(String),
+    /// The expression is valid Rust the sandbox does not interpret
+    Unsupported(String),
+    /// A field read or assigned is not in the mock state
+    UnknownField(String),
+    /// An operator was applied to values of the wrong type
+    Type(String),
+    /// Integer arithmetic overflowed or divided by zero
+    Arithmetic(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Parse(msg) => write!(f, "Invalid expression: {msg}"),
+            EvalError::Unsupported(what) => write!(f, "The sandbox can't evaluate {what}"),
+            EvalError::UnknownField(field) => write!(f, "Unknown field '{field}'"),
+            EvalError::Type(msg) => write!(f, "Type mismatch: {msg}"),
+            EvalError::Arithmetic(msg) => write!(f, "Arithmetic error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Mock extended state and state data expressions are evaluated against
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sandbox {
+    ext: BTreeMap<String, Value>,
+    /// Types of the `ext` fields seeded from a spec, which assignments must fit
+    ext_types: BTreeMap<String, String>,
+    state: BTreeMap<String, Value>,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seeds the mock extended state with the values of the spec's fields
+    ///
+    /// Fields without a value start with their type's default when it is a primitive. Fields
+    /// whose value the sandbox can't evaluate are left out, so reading them is an error, but a
+    /// value that doesn't fit the field's integer type is an error right away.
+    pub fn from_ext_state(ext_state: &ExtState) -> Result<Self, EvalError> {
+        let mut sandbox = Self::new();
+        for field in ext_state.fields() {
+            let ty = field.ty().as_ref();
+            let value = match field.value() {
+                Some(expr) => sandbox.eval(expr).ok(),
+                None => Value::default_for(ty),
+            };
+            if let Some(value) = value {
+                value.check_fits(ty).map_err(|err| match err {
+                    EvalError::Arithmetic(msg) => {
+                        EvalError::Arithmetic(format!("{msg}, the type of ext.{}", field.ident()))
+                    }
+                    err => err,
+                })?;
+                sandbox.ext.insert(field.ident().to_string(), value);
+            }
+            sandbox
+                .ext_types
+                .insert(field.ident().to_string(), ty.to_string());
+        }
+        Ok(sandbox)
+    }
+
+    /// Sets a field of the mock extended state, read as `ext.{ident}`
+    pub fn with_ext<S: Into<String>>(mut self, ident: S, value: Value) -> Self {
+        self.ext.insert(ident.into(), value);
+        self
+    }
+
+    /// Sets a field of the current state's data, read as `self.{ident}`
+    pub fn with_state<S: Into<String>>(mut self, ident: S, value: Value) -> Self {
+        self.state.insert(ident.into(), value);
+        self
+    }
+
+    pub fn ext(&self, ident: &str) -> Option<&Value> {
+        self.ext.get(ident)
+    }
+
+    /// Evaluates an expression
+    pub fn eval(&self, expr: &str) -> Result<Value, EvalError> {
+        let expr =
+            syn::parse_str::<syn::Expr>(expr).map_err(|err| EvalError::Parse(err.to_string()))?;
+        self.eval_expr(&expr)
+    }
+
+    /// Evaluates a guard, which must be a boolean expression
+    pub fn guard(&self, expr: &str) -> Result<bool, EvalError> {
+        match self.eval(expr)? {
+            Value::Bool(value) => Ok(value),
+            other => Err(EvalError::Type(format!(
+                "guard evaluates to {}, not bool",
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// Runs an action: statements assigning `ext` fields with `=` or a compound assignment
+    ///
+    /// The statements run in order and stop at the first error, keeping earlier assignments.
+    pub fn exec(&mut self, action: &str) -> Result<(), EvalError> {
+        let block = syn::parse_str::<syn::Block>(&format!("{{ {action} }}"))
+            .map_err(|err| EvalError::Parse(err.to_string()))?;
+        for stmt in &block.stmts {
+            match stmt {
+                syn::Stmt::Expr(expr, _) => self.exec_expr(expr)?,
+                _ => return Err(EvalError::Unsupported("items and let bindings".to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Invariants of `state` that don't hold in the sandbox
+    pub fn violated_invariants<'a>(&self, state: &'a State) -> Result<Vec<&'a str>, EvalError> {
+        let mut violated = Vec::new();
+        for invariant in &state.invariants {
+            if !self.guard(invariant)? {
+                violated.push(invariant.as_str());
+            }
+        }
+        Ok(violated)
+    }
+
+    fn exec_expr(&mut self, expr: &syn::Expr) -> Result<(), EvalError> {
+        let syn::Expr::Assign(assign) = expr else {
+            let value = match expr {
+                syn::Expr::Binary(binary) => Op::compound(&binary.op).map(|op| (binary, op)),
+                _ => None,
+            };
+            let Some((binary, op)) = value else {
+                return Err(EvalError::Unsupported(
+                    "actions other than assignments".to_string(),
+                ));
+            };
+            let ident = ext_field(&binary.left)?;
+            let current = read(&self.ext, &ident)?.clone();
+            let value = op.apply(current, self.eval_expr(&binary.right)?)?;
+            return self.store(ident, value);
+        };
+        let ident = ext_field(&assign.left)?;
+        // Assignments keep the field's type, unknown fields are typos rather than new state
+        let current = read(&self.ext, &ident)?;
+        let value = self.eval_expr(&assign.right)?;
+        if current.type_name() != value.type_name() {
+            return Err(EvalError::Type(format!(
+                "can't assign {} to ext.{ident} of type {}",
+                value.type_name(),
+                current.type_name()
+            )));
+        }
+        self.store(ident, value)
+    }
+
+    /// Stores an `ext` field, which must still fit its type
+    fn store(&mut self, ident: String, value: Value) -> Result<(), EvalError> {
+        if let Some(ty) = self.ext_types.get(&ident) {
+            value.check_fits(ty).map_err(|_| {
+                EvalError::Arithmetic(format!("{value} doesn't fit ext.{ident} of type {ty}"))
+            })?;
+        }
+        self.ext.insert(ident, value);
+        Ok(())
+    }
+
+    fn eval_expr(&self, expr: &syn::Expr) -> Result<Value, EvalError> {
+        match expr {
+            syn::Expr::Lit(lit) => literal(&lit.lit),
+            syn::Expr::Paren(paren) => self.eval_expr(&paren.expr),
+            syn::Expr::Group(group) => self.eval_expr(&group.expr),
+            syn::Expr::Tuple(tuple) => tuple
+                .elems
+                .iter()
+                .map(|elem| self.eval_expr(elem))
+                .collect::<Result<_, _>>()
+                .map(Value::Tuple),
+            syn::Expr::Field(field) => {
+                let ident = match &field.member {
+                    syn::Member::Named(ident) => ident,
+                    syn::Member::Unnamed(index) => {
+                        return match self.eval_expr(&field.base)? {
+                            Value::Tuple(values) => {
+                                values.get(index.index as usize).cloned().ok_or_else(|| {
+                                    EvalError::Type(format!(
+                                        "a tuple of {} has no field {}",
+                                        values.len(),
+                                        index.index
+                                    ))
+                                })
+                            }
+                            other => Err(EvalError::Type(format!(
+                                "{} has no field {}",
+                                other.type_name(),
+                                index.index
+                            ))),
+                        };
+                    }
+                };
+                let fields = match &*field.base {
+                    syn::Expr::Path(path) if path.path.is_ident("ext") => &self.ext,
+                    syn::Expr::Path(path) if path.path.is_ident("self") => &self.state,
+                    _ => {
+                        return Err(EvalError::Unsupported(
+                            "fields of anything but ext and self".to_string(),
+                        ));
+                    }
+                };
+                read(fields, &ident.to_string()).cloned()
+            }
+            syn::Expr::Unary(unary) => match (&unary.op, self.eval_expr(&unary.expr)?) {
+                (syn::UnOp::Not(_), Value::Bool(value)) => Ok(Value::Bool(!value)),
+                (syn::UnOp::Neg(_), Value::Int(value)) => value
+                    .checked_neg()
+                    .map(Value::Int)
+                    .ok_or_else(|| EvalError::Arithmetic("negation overflowed".to_string())),
+                (syn::UnOp::Neg(_), Value::Float(value)) => Ok(Value::Float(-value)),
+                (syn::UnOp::Not(_), value) => Err(EvalError::Type(format!(
+                    "can't apply '!' to {}",
+                    value.type_name()
+                ))),
+                (syn::UnOp::Neg(_), value) => Err(EvalError::Type(format!(
+                    "can't apply '-' to {}",
+                    value.type_name()
+                ))),
+                _ => Err(EvalError::Unsupported("dereferences".to_string())),
+            },
+            syn::Expr::Binary(binary) => {
+                // Short-circuit like Rust, so a guard can check a field before dividing by it
+                match binary.op {
+                    syn::BinOp::And(_) | syn::BinOp::Or(_) => {
+                        let is_and = matches!(binary.op, syn::BinOp::And(_));
+                        match self.eval_expr(&binary.left)? {
+                            Value::Bool(left) if left != is_and => Ok(Value::Bool(left)),
+                            Value::Bool(_) => match self.eval_expr(&binary.right)? {
+                                Value::Bool(right) => Ok(Value::Bool(right)),
+                                other => Err(EvalError::Type(format!(
+                                    "expected bool, found {}",
+                                    other.type_name()
+                                ))),
+                            },
+                            other => Err(EvalError::Type(format!(
+                                "expected bool, found {}",
+                                other.type_name()
+                            ))),
+                        }
+                    }
+                    ref op => Op::from_syn(op)?.apply(
+                        self.eval_expr(&binary.left)?,
+                        self.eval_expr(&binary.right)?,
+                    ),
+                }
+            }
+            syn::Expr::Path(path) => Err(EvalError::Unsupported(format!(
+                "the bare path '{}', fields are read through ext or self",
+                path.path
+                    .segments
+                    .iter()
+                    .map(|segment| segment.ident.to_string())
+                    .collect::<Vec<_>>()
+                    .join("::")
+            ))),
+            syn::Expr::Call(_) | syn::Expr::MethodCall(_) => {
+                Err(EvalError::Unsupported("calls".to_string()))
+            }
+            syn::Expr::Macro(_) => Err(EvalError::Unsupported("macros".to_string())),
+            _ => Err(EvalError::Unsupported(
+                "this kind of expression".to_string(),
+            )),
+        }
+    }
+}
+
+fn read<'a>(fields: &'a BTreeMap<String, Value>, ident: &str) -> Result<&'a Value, EvalError> {
+    fields
+        .get(ident)
+        .ok_or_else(|| EvalError::UnknownField(ident.to_string()))
+}
+
+/// Name of the `ext` field an action assigns
+fn ext_field(expr: &syn::Expr) -> Result<String, EvalError> {
+    match expr {
+        syn::Expr::Field(syn::ExprField {
+            base,
+            member: syn::Member::Named(ident),
+            ..
+        }) if matches!(&**base, syn::Expr::Path(path) if path.path.is_ident("ext")) => {
+            Ok(ident.to_string())
+        }
+        _ => Err(EvalError::Unsupported(
+            "assignments to anything but ext fields".to_string(),
+        )),
+    }
+}
+
+fn literal(lit: &syn::Lit) -> Result<Value, EvalError> {
+    match lit {
+        syn::Lit::Bool(lit) => Ok(Value::Bool(lit.value)),
+        syn::Lit::Int(lit) => {
+            let value = lit
+                .base10_parse()
+                .map(Value::Int)
+                .map_err(|err| EvalError::Parse(err.to_string()))?;
+            value.check_fits(lit.suffix())?;
+            Ok(value)
+        }
+        syn::Lit::Float(lit) => lit
+            .base10_parse()
+            .map(Value::Float)
+            .map_err(|err| EvalError::Parse(err.to_string())),
+        syn::Lit::Str(lit) => Ok(Value::Str(lit.value())),
+        syn::Lit::Char(lit) => Ok(Value::Char(lit.value())),
+        _ => Err(EvalError::Unsupported(
+            "byte and C string literals".to_string(),
+        )),
+    }
+}
+
+/// Binary operators the sandbox interprets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn from_syn(op: &syn::BinOp) -> Result<Self, EvalError> {
+        use syn::BinOp;
+
+        Ok(match op {
+            BinOp::Add(_) => Op::Add,
+            BinOp::Sub(_) => Op::Sub,
+            BinOp::Mul(_) => Op::Mul,
+            BinOp::Div(_) => Op::Div,
+            BinOp::Rem(_) => Op::Rem,
+            BinOp::Eq(_) => Op::Eq,
+            BinOp::Ne(_) => Op::Ne,
+            BinOp::Lt(_) => Op::Lt,
+            BinOp::Le(_) => Op::Le,
+            BinOp::Gt(_) => Op::Gt,
+            BinOp::Ge(_) => Op::Ge,
+            _ => return Err(EvalError::Unsupported("bitwise operators".to_string())),
+        })
+    }
+
+    /// Operator a compound assignment applies, e.g. `+` for `+=`
+    fn compound(op: &syn::BinOp) -> Option<Self> {
+        use syn::BinOp;
+
+        match op {
+            BinOp::AddAssign(_) => Some(Op::Add),
+            BinOp::SubAssign(_) => Some(Op::Sub),
+            BinOp::MulAssign(_) => Some(Op::Mul),
+            BinOp::DivAssign(_) => Some(Op::Div),
+            BinOp::RemAssign(_) => Some(Op::Rem),
+            _ => None,
+        }
+    }
+
+    fn apply(self, left: Value, right: Value) -> Result<Value, EvalError> {
+        let overflow = || EvalError::Arithmetic(format!("'{self}' overflowed"));
+        match (left, right) {
+            (Value::Int(left), Value::Int(right)) => match self {
+                Op::Add => left.checked_add(right).map(Value::Int).ok_or_else(overflow),
+                Op::Sub => left.checked_sub(right).map(Value::Int).ok_or_else(overflow),
+                Op::Mul => left.checked_mul(right).map(Value::Int).ok_or_else(overflow),
+                Op::Div | Op::Rem if right == 0 => {
+                    Err(EvalError::Arithmetic("division by zero".to_string()))
+                }
+                Op::Div => left.checked_div(right).map(Value::Int).ok_or_else(overflow),
+                Op::Rem => left.checked_rem(right).map(Value::Int).ok_or_else(overflow),
+                op => op.compare(&left, &right),
+            },
+            (Value::Float(left), Value::Float(right)) => match self {
+                Op::Add => Ok(Value::Float(left + right)),
+                Op::Sub => Ok(Value::Float(left - right)),
+                Op::Mul => Ok(Value::Float(left * right)),
+                Op::Div => Ok(Value::Float(left / right)),
+                Op::Rem => Ok(Value::Float(left % right)),
+                op => op.compare(&left, &right),
+            },
+            (Value::Bool(left), Value::Bool(right)) => self.compare(&left, &right),
+            (Value::Char(left), Value::Char(right)) => self.compare(&left, &right),
+            (Value::Str(left), Value::Str(right)) => self.compare(&left, &right),
+            // Tuples are only compared for equality, which needs no order between elements
+            (Value::Tuple(left), Value::Tuple(right)) if matches!(self, Op::Eq | Op::Ne) => {
+                Ok(Value::Bool((left == right) == (self == Op::Eq)))
+            }
+            (left, right) => Err(EvalError::Type(format!(
+                "can't apply '{self}' to {} and {}",
+                left.type_name(),
+                right.type_name()
+            ))),
+        }
+    }
+
+    fn compare<T: PartialOrd>(self, left: &T, right: &T) -> Result<Value, EvalError> {
+        Ok(Value::Bool(match self {
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+            // Bools, chars and strings don't support arithmetic
+            _ => {
+                return Err(EvalError::Type(format!(
+                    "can't apply '{self}' to this type"
+                )));
+            }
+        }))
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "/",
+            Op::Rem => "%",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blox::ext_state::InitArgs;
+    use crate::{Field, Link};
+
+    #[test]
+    fn test_eval() {
+        let sandbox = Sandbox::new()
+            .with_ext("count", Value::Int(3))
+            .with_ext("ratio", Value::Float(0.5))
+            .with_state("retries", Value::Int(0));
+
+        assert_eq!(sandbox.eval("ext.count * 2 + 1"), Ok(Value::Int(7)));
+        assert_eq!(sandbox.eval("-(ext.ratio * 4.0)"), Ok(Value::Float(-2.0)));
+        assert_eq!(sandbox.guard("ext.count > 0 && self.retries < 3"), Ok(true));
+        // The right side is never evaluated, so the division by zero goes unnoticed
+        assert_eq!(
+            sandbox.guard("self.retries != 0 && ext.count / self.retries > 1"),
+            Ok(false)
+        );
+        assert_eq!(
+            sandbox.eval("ext.count / self.retries"),
+            Err(EvalError::Arithmetic("division by zero".to_string()))
+        );
+        assert_eq!(
+            sandbox.eval("ext.count + ext.ratio"),
+            Err(EvalError::Type(
+                "can't apply '+' to integer and float".to_string()
+            ))
+        );
+        assert_eq!(
+            sandbox.eval("ext.missing"),
+            Err(EvalError::UnknownField("missing".to_string()))
+        );
+        assert_eq!(
+            sandbox.guard("ext.count.is_positive()"),
+            Err(EvalError::Unsupported("calls".to_string()))
+        );
+        assert!(matches!(
+            sandbox.eval("ext.count +"),
+            Err(EvalError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_exec() {
+        let ext_state = ExtState::new(
+            "ActorExtState",
+            vec![
+                Field::new("count", Link::new("u32")).with_value("10"),
+                Field::new("active", Link::new("bool")),
+                Field::new("name", Link::new("String")),
+                Field::new("peer", Link::new("PeerId")),
+            ],
+            Vec::new(),
+            InitArgs::default(),
+        );
+        let mut sandbox = Sandbox::from_ext_state(&ext_state).unwrap();
+        assert_eq!(sandbox.ext("count"), Some(&Value::Int(10)));
+        assert_eq!(sandbox.ext("active"), Some(&Value::Bool(false)));
+        assert_eq!(sandbox.ext("name"), Some(&Value::Str(String::new())));
+        assert_eq!(sandbox.ext("peer"), None);
+
+        sandbox
+            .exec("ext.count -= 4; ext.active = ext.count > 5; ext.name = \"left\";")
+            .unwrap();
+        assert_eq!(sandbox.ext("count"), Some(&Value::Int(6)));
+        assert_eq!(sandbox.ext("active"), Some(&Value::Bool(true)));
+        assert_eq!(sandbox.ext("name"), Some(&Value::Str("left".to_string())));
+
+        assert_eq!(
+            sandbox.exec("ext.count = true"),
+            Err(EvalError::Type(
+                "can't assign bool to ext.count of type integer".to_string()
+            ))
+        );
+        assert_eq!(
+            sandbox.exec("self.retries = 1"),
+            Err(EvalError::Unsupported(
+                "assignments to anything but ext fields".to_string()
+            ))
+        );
+
+        let state = State::new("Active", None, None)
+            .with_invariants(vec!["ext.active".to_string(), "ext.count < 5".to_string()]);
+        assert_eq!(
+            sandbox.violated_invariants(&state),
+            Ok(vec!["ext.count < 5"])
+        );
+    }
+
+    #[test]
+    fn test_int_ranges() {
+        let field = |ty: &str, value: &str| {
+            ExtState::new(
+                "ActorExtState",
+                vec![Field::new("level", Link::new(ty)).with_value(value)],
+                Vec::new(),
+                InitArgs::default(),
+            )
+        };
+        assert_eq!(
+            Sandbox::from_ext_state(&field("u8", "300")).err(),
+            Some(EvalError::Arithmetic(
+                "300 doesn't fit in u8, the type of ext.level".to_string()
+            ))
+        );
+        assert_eq!(
+            Sandbox::from_ext_state(&field("(u8, i8)", "(1, -129)")).err(),
+            Some(EvalError::Arithmetic(
+                "-129 doesn't fit in i8, the type of ext.level".to_string()
+            ))
+        );
+
+        let mut sandbox = Sandbox::from_ext_state(&field("u8", "250")).unwrap();
+        sandbox.exec("ext.level += 5").unwrap();
+        assert_eq!(
+            sandbox.exec("ext.level += 1"),
+            Err(EvalError::Arithmetic(
+                "256 doesn't fit ext.level of type u8".to_string()
+            ))
+        );
+        assert_eq!(
+            sandbox.exec("ext.level = -1"),
+            Err(EvalError::Arithmetic(
+                "-1 doesn't fit ext.level of type u8".to_string()
+            ))
+        );
+        // A failed assignment leaves the field as it was
+        assert_eq!(sandbox.ext("level"), Some(&Value::Int(255)));
+
+        assert_eq!(
+            Sandbox::new().eval("300u8"),
+            Err(EvalError::Arithmetic("300 doesn't fit in u8".to_string()))
+        );
+        assert_eq!(Sandbox::new().eval("-1i64"), Ok(Value::Int(-1)));
+    }
+
+    #[test]
+    fn test_chars_and_tuples() {
+        let ext_state = ExtState::new(
+            "ActorExtState",
+            vec![
+                Field::new("separator", Link::new("char")).with_value("'/'"),
+                Field::new("bounds", Link::new("(u8, i16)")).with_value("(0, -1)"),
+            ],
+            Vec::new(),
+            InitArgs::default(),
+        );
+        let mut sandbox = Sandbox::from_ext_state(&ext_state).unwrap();
+        assert_eq!(sandbox.ext("separator"), Some(&Value::Char('/')));
+        assert_eq!(
+            sandbox.ext("bounds"),
+            Some(&Value::Tuple(vec![Value::Int(0), Value::Int(-1)]))
+        );
+
+        assert_eq!(sandbox.guard("ext.separator == '/'"), Ok(true));
+        assert_eq!(sandbox.guard("ext.separator < 'a'"), Ok(true));
+        assert_eq!(sandbox.eval("ext.bounds.1 * 2"), Ok(Value::Int(-2)));
+        assert_eq!(sandbox.guard("ext.bounds != (0, 1)"), Ok(true));
+        assert_eq!(
+            sandbox.eval("ext.bounds < (0, 1)"),
+            Err(EvalError::Type(
+                "can't apply '<' to tuple and tuple".to_string()
+            ))
+        );
+        assert_eq!(
+            sandbox.eval("ext.bounds.2"),
+            Err(EvalError::Type("a tuple of 2 has no field 2".to_string()))
+        );
+
+        sandbox.exec("ext.bounds = (3, 4)").unwrap();
+        assert_eq!(
+            sandbox.eval("ext.bounds"),
+            Ok(Value::Tuple(vec![Value::Int(3), Value::Int(4)]))
+        );
+        assert_eq!(
+            sandbox.exec("ext.bounds = (256, 4)"),
+            Err(EvalError::Arithmetic(
+                "(256, 4) doesn't fit ext.bounds of type (u8, i16)".to_string()
+            ))
+        );
+        assert_eq!(
+            sandbox.eval("b'a'"),
+            Err(EvalError::Unsupported(
+                "byte and C string literals".to_string()
+            ))
+        );
+    }
+}
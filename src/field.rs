@@ -1,14 +1,92 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{
-    Link,
-    create::{ActorGenerator, ToRust},
-};
+use crate::Link;
+use crate::alloc_prelude::*;
+#[cfg(feature = "codegen")]
+use crate::create::{RenderCtx, RenderError, ToRust, deprecated_attr};
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
 pub struct Field {
     ident: String,
     ty: Link,
+    /// Emit the field without `pub`
+    #[serde(default, skip_serializing_if = "is_false")]
+    private: bool,
+    /// Emit a `fn {ident}(&self) -> &T` getter
+    #[serde(default, skip_serializing_if = "is_false")]
+    get: bool,
+    /// Emit a `fn {ident}_mut(&mut self) -> &mut T` getter
+    #[serde(default, skip_serializing_if = "is_false")]
+    get_mut: bool,
+    /// Emit a `fn set_{ident}(&mut self, value: T)` setter
+    #[serde(default, skip_serializing_if = "is_false")]
+    set: bool,
+    /// Wrap the field in `Arc<{lock}<T>>` so it can be shared with background tasks
+    #[serde(default, skip_serializing_if = "is_false")]
+    shared: bool,
+    /// Lock used for shared fields
+    #[serde(default, skip_serializing_if = "LockKind::is_default")]
+    lock: LockKind,
+    /// Expression the field starts from when the init args don't set it, or a
+    /// `${secret:NAME}` reference loaded when the actor starts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    /// Why the field is deprecated, rendered as `#[deprecated(note = ...)]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deprecated: Option<String>,
+}
+
+/// Name of the secret a `${secret:NAME}` reference points to
+pub fn secret_name(value: &str) -> Option<&str> {
+    value
+        .trim()
+        .strip_prefix("${secret:")?
+        .strip_suffix('}')
+        .filter(|name| !name.is_empty())
+}
+
+/// Lock wrapping a shared field
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LockKind {
+    Mutex,
+    #[default]
+    RwLock,
+}
+
+impl LockKind {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Name of the `std::sync` lock type
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            LockKind::Mutex => "Mutex",
+            LockKind::RwLock => "RwLock",
+        }
+    }
+
+    /// `std::sync` paths needed by the generated wrapper and its accessors
+    pub fn import_paths(&self) -> &'static [&'static str] {
+        match self {
+            LockKind::Mutex => &[
+                "std::sync::Arc",
+                "std::sync::Mutex",
+                "std::sync::MutexGuard",
+            ],
+            LockKind::RwLock => &[
+                "std::sync::Arc",
+                "std::sync::RwLock",
+                "std::sync::RwLockReadGuard",
+                "std::sync::RwLockWriteGuard",
+            ],
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
 }
 
 impl Field {
@@ -20,20 +98,171 @@ impl Field {
         Self {
             ident: ident.into(),
             ty: ty.into(),
+            private: false,
+            get: false,
+            get_mut: false,
+            set: false,
+            shared: false,
+            lock: LockKind::default(),
+            value: None,
+            deprecated: None,
+        }
+    }
+
+    /// Shares the field behind `Arc<{lock}<T>>`
+    pub fn shared(mut self, lock: LockKind) -> Self {
+        self.shared = true;
+        self.lock = lock;
+        self
+    }
+
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    pub fn lock(&self) -> LockKind {
+        self.lock
+    }
+
+    /// Type of the field as emitted in the generated struct
+    pub fn rendered_ty(&self) -> String {
+        if self.shared {
+            format!("Arc<{}<{}>>", self.lock.type_name(), self.ty)
+        } else {
+            self.ty.to_string()
         }
     }
 
+    /// Wraps an expression of the declared type into the rendered type
+    pub fn wrap_value(&self, expr: &str) -> String {
+        if self.shared {
+            format!("Arc::new({}::new({expr}))", self.lock.type_name())
+        } else {
+            expr.to_string()
+        }
+    }
+
+    /// Sets the expression or `${secret:NAME}` reference the field starts from
+    pub fn with_value<S: Into<String>>(mut self, value: S) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// Name of the secret the field is loaded from, if its value is a secret reference
+    pub fn secret(&self) -> Option<&str> {
+        self.value.as_deref().and_then(secret_name)
+    }
+
+    /// Deprecates the generated field
+    pub fn deprecated<S: Into<String>>(mut self, note: S) -> Self {
+        self.deprecated = Some(note.into());
+        self
+    }
+
+    pub fn deprecation(&self) -> Option<&str> {
+        self.deprecated.as_deref()
+    }
+
+    /// Makes the field private
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+
+    /// Requests a shared-reference getter
+    pub fn with_getter(mut self) -> Self {
+        self.get = true;
+        self
+    }
+
+    /// Requests a mutable-reference getter
+    pub fn with_getter_mut(mut self) -> Self {
+        self.get_mut = true;
+        self
+    }
+
+    /// Requests a setter
+    pub fn with_setter(mut self) -> Self {
+        self.set = true;
+        self
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
+    /// Renders the accessor methods requested for this field
+    pub fn accessors(&self) -> Vec<String> {
+        let ident = &self.ident;
+        let inner_ty = &self.ty;
+        let ty = &self.rendered_ty();
+        let mut accessors = Vec::new();
+        if self.shared {
+            accessors.push(format!(
+                "pub fn {ident}_handle(&self) -> {ty} {{\n        Arc::clone(&self.{ident})\n    }}"
+            ));
+            match self.lock {
+                LockKind::Mutex => accessors.push(format!(
+                    "pub fn lock_{ident}(&self) -> MutexGuard<'_, {inner_ty}> {{\n        self.{ident}.lock().unwrap_or_else(|e| e.into_inner())\n    }}"
+                )),
+                LockKind::RwLock => {
+                    accessors.push(format!(
+                        "pub fn read_{ident}(&self) -> RwLockReadGuard<'_, {inner_ty}> {{\n        self.{ident}.read().unwrap_or_else(|e| e.into_inner())\n    }}"
+                    ));
+                    accessors.push(format!(
+                        "pub fn write_{ident}(&self) -> RwLockWriteGuard<'_, {inner_ty}> {{\n        self.{ident}.write().unwrap_or_else(|e| e.into_inner())\n    }}"
+                    ));
+                }
+            }
+        }
+        if self.get {
+            accessors.push(format!(
+                "pub fn {ident}(&self) -> &{ty} {{\n        &self.{ident}\n    }}"
+            ));
+        }
+        if self.get_mut {
+            accessors.push(format!(
+                "pub fn {ident}_mut(&mut self) -> &mut {ty} {{\n        &mut self.{ident}\n    }}"
+            ));
+        }
+        if self.set {
+            accessors.push(format!(
+                "pub fn set_{ident}(&mut self, {ident}: {ty}) {{\n        self.{ident} = {ident};\n    }}"
+            ));
+        }
+        accessors
+    }
+
     pub fn ident(&self) -> &str {
         &self.ident
     }
 
+    pub(crate) fn set_ident<S: Into<String>>(&mut self, ident: S) {
+        self.ident = ident.into();
+    }
+
     pub fn ty(&self) -> &Link {
         &self.ty
     }
 }
 
+#[cfg(feature = "codegen")]
 impl ToRust for Field {
-    fn to_rust(&self, _generator: &ActorGenerator) -> String {
-        format!("pub {}: {}", self.ident, self.ty)
+    fn to_source(&self, _ctx: &RenderCtx) -> Result<String, RenderError> {
+        let vis = if self.private { "" } else { "pub " };
+        let deprecated = self
+            .deprecated
+            .as_deref()
+            .map(|note| format!("{}\n\t", deprecated_attr(note)))
+            .unwrap_or_default();
+        Ok(format!(
+            "{deprecated}{vis}{}: {}",
+            self.ident,
+            self.rendered_ty()
+        ))
     }
 }
@@ -2,33 +2,44 @@ mod node;
 mod rgraph;
 mod ty;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::error::Error;
 
+pub use node::{Crate, Entry, Function, Module, Node, RelatedEntry, Relation, Trait, Type};
 use petgraph::graph::NodeIndex;
+pub use rgraph::RustGraph;
+use serde::Serialize;
+use syn::visit::Visit;
 pub use ty::Import;
 
-use crate::blox::actor::Actor;
+use crate::blox::actor::{
+    Actor, CORRELATED_TYPE, HEALTH_CHECK_TYPE, QUEUE_OVERLOAD_TYPE, SEQUENCED_TYPE,
+};
+use crate::blox::bridge::{Bridge, PayloadSource};
 use crate::blox::component::Component;
+use crate::blox::lifecycle::RestartPolicy;
+use crate::blox::message_handlers::{Broker, MessageHandle};
 use crate::blox::message_set::MessageSet;
+use crate::blox::pool::{Pool, Routing, Shard};
 
 use crate::ext_state::ExtState;
-use crate::graph::node::{Module, Node, RelatedEntry, Relation};
-use crate::graph::rgraph::RustGraph;
 use crate::graph::ty::{DiscoveredType, TypeContext, TypeLocation};
+use crate::link::{PRELUDE_TYPES, type_names};
 
 /// Code generation specific wrapper around RustGraph
 ///
 /// This provides additional functionality for code generation including
 /// import tracking, dependency analysis, and module organization.
 pub struct CodeGenGraph {
-    pub graph: RustGraph,
+    pub(crate) graph: RustGraph,
     /// Types discovered during analysis phase
     discovered_types: Vec<DiscoveredType>,
     /// Registry of known framework types
     framework_types: HashMap<String, String>,
     /// Types that have been resolved to their locations
     resolved_types: HashMap<String, TypeLocation>,
+    /// Types no location was found for, with the module using them
+    unresolved_types: Vec<(String, String)>,
 }
 
 impl Default for CodeGenGraph {
@@ -38,10 +49,6 @@ impl Default for CodeGenGraph {
 }
 
 impl CodeGenGraph {
-    const PRELUDE_TYPES: &[&str] = &[
-        "String", "i32", "u32", "i64", "u64", "bool", "Vec", "Option", "Result", "Box", "Arc", "Rc",
-    ];
-
     const RUNTIME_DEFAULT_IMPORTS: &[&str] = &[
         "bloxide_tokio::components::Runnable",
         "bloxide_tokio::components::Blox",
@@ -78,15 +85,206 @@ impl CodeGenGraph {
         "bloxide_tokio::messaging::MessageSet",
     ];
 
+    const HANDLE_HELPER_IMPORTS: &[&str] = &[
+        "bloxide_tokio::messaging::Message",
+        "tokio::sync::mpsc::error::SendError",
+        "tokio::sync::mpsc::error::TrySendError",
+    ];
+
+    const POOL_DEFAULT_IMPORTS: &[&str] = &[
+        "bloxide_tokio::messaging::Message",
+        "tokio::sync::mpsc::error::SendError",
+    ];
+
+    const POOL_ROUND_ROBIN_IMPORTS: &[&str] = &[
+        "std::sync::atomic::AtomicUsize",
+        "std::sync::atomic::Ordering",
+    ];
+
+    const POOL_HASH_IMPORTS: &[&str] = &[
+        "std::collections::hash_map::DefaultHasher",
+        "std::hash::Hash",
+        "std::hash::Hasher",
+    ];
+
+    const SHARD_DEFAULT_IMPORTS: &[&str] = &[
+        "bloxide_tokio::messaging::Message",
+        "std::collections::HashMap",
+        "std::sync::Mutex",
+        "tokio::sync::mpsc::error::SendError",
+    ];
+
+    const BRIDGE_DEFAULT_IMPORTS: &[&str] = &[
+        "bloxide_tokio::messaging::Message",
+        "tokio::sync::mpsc::Sender",
+        "tokio::sync::mpsc::channel",
+    ];
+
+    const GRPC_BRIDGE_IMPORTS: &[&str] = &["tonic::Request", "tonic::Response", "tonic::Status"];
+
+    const HTTP_BRIDGE_IMPORTS: &[&str] = &[
+        "axum::Json",
+        "axum::Router",
+        "axum::extract::State",
+        "axum::http::StatusCode",
+        "axum::response::IntoResponse",
+        "axum::response::Response",
+    ];
+
+    const SOURCES_DEFAULT_IMPORTS: &[&str] = &["bloxide_tokio::messaging::Message"];
+
+    const SCHEDULER_DEFAULT_IMPORTS: &[&str] = &[
+        "bloxide_tokio::messaging::Message",
+        "chrono::Datelike",
+        "chrono::NaiveDateTime",
+        "chrono::TimeDelta",
+        "chrono::Timelike",
+        "chrono::Utc",
+        "tokio::join",
+        "tokio::time::sleep",
+    ];
+
+    const SEQUENCE_DEFAULT_IMPORTS: &[&str] = &[
+        "std::sync::atomic::AtomicU64",
+        "std::sync::atomic::Ordering",
+    ];
+
+    const RATE_LIMIT_DEFAULT_IMPORTS: &[&str] = &[
+        "std::time::Duration",
+        "tokio::time::Instant",
+        "tokio::time::sleep",
+    ];
+
+    const CIRCUIT_BREAKER_DEFAULT_IMPORTS: &[&str] = &[
+        "std::sync::Mutex",
+        "std::sync::MutexGuard",
+        "std::time::Duration",
+        "std::time::Instant",
+    ];
+
+    const PERSISTENCE_DEFAULT_IMPORTS: &[&str] = &["std::sync::Arc", "std::sync::Mutex"];
+
+    const CORRELATION_DEFAULT_IMPORTS: &[&str] = &[
+        "std::sync::atomic::AtomicU64",
+        "std::sync::atomic::Ordering",
+    ];
+
+    const TRACE_CONTEXT_IMPORTS: &[&str] = &[
+        "opentelemetry::global",
+        "opentelemetry::propagation::Extractor",
+        "opentelemetry::propagation::Injector",
+        "std::collections::HashMap",
+        "tracing_opentelemetry::OpenTelemetrySpanExt",
+    ];
+
+    const HEALTH_DEFAULT_IMPORTS: &[&str] = &[
+        "bloxide_tokio::messaging::Message",
+        "tokio::sync::mpsc::Sender",
+        "tokio::sync::mpsc::channel",
+    ];
+
+    const SUPERVISOR_DEFAULT_IMPORTS: &[&str] = &[
+        "bloxide_tokio::components::Blox",
+        "bloxide_tokio::components::Runnable",
+        "tokio::spawn",
+    ];
+
+    const CHILDREN_DEFAULT_IMPORTS: &[&str] = &[
+        "bloxide_tokio::TokioMessageHandle",
+        "bloxide_tokio::components::Blox",
+        "bloxide_tokio::components::Runnable",
+        "bloxide_tokio::state_machine::ExtendedState",
+        "tokio::spawn",
+    ];
+
+    const CHILD_OUTBOX_IMPORTS: &[&str] = &[
+        "bloxide_tokio::TokioRuntime",
+        "bloxide_tokio::components::Runtime",
+        "bloxide_tokio::messaging::MessageSender",
+    ];
+
+    const SUPERVISOR_BACKOFF_IMPORTS: &[&str] = &["std::time::Duration", "tokio::time::sleep"];
+
+    const SUPERVISOR_SNAPSHOT_IMPORTS: &[&str] = &[
+        "std::sync::Arc",
+        "std::sync::Mutex",
+        "std::sync::PoisonError",
+    ];
+
+    /// Modules generated directly inside every actor module
+    const ACTOR_SUBMODULES: &[&str] = &[
+        "component",
+        "states",
+        "ext_state",
+        "runtime",
+        "messaging",
+        "pool",
+        "shard",
+        "bridge",
+        "sources",
+        "sinks",
+        "scheduler",
+        "sequence",
+        "rate_limit",
+        "circuit_breaker",
+        "persistence",
+        "correlation",
+        "health",
+        "supervisor",
+        "children",
+    ];
+
     pub fn new() -> Self {
         Self {
             graph: RustGraph::new(),
             discovered_types: Vec::new(),
             framework_types: HashMap::new(),
             resolved_types: HashMap::new(),
+            unresolved_types: Vec::new(),
         }
     }
 
+    /// The crates, modules and types the graph tracks, for queries such as cycle detection
+    pub fn rust_graph(&self) -> &RustGraph {
+        &self.graph
+    }
+
+    /// Get the framework imports a generated module always needs, without consulting the graph
+    ///
+    /// Used as a fallback when a module has not been analyzed.
+    pub fn default_imports_for_module(module_path: &str) -> Vec<String> {
+        let segments = module_path.split("::").collect::<Vec<_>>();
+        let defaults = match segments.as_slice() {
+            [.., "states", _] => Self::SUB_STATES_DEFAULT_IMPORTS,
+            [.., "states"] => Self::STATES_DEFAULT_IMPORTS,
+            [.., "component"] => Self::COMPONENT_DEFAULT_IMPORTS,
+            [.., "ext_state"] => Self::EXT_STATE_DEFAULT_IMPORTS,
+            [.., "runtime"] => Self::RUNTIME_DEFAULT_IMPORTS,
+            [.., "messaging"] => Self::MESSAGING_DEFAULT_IMPORTS,
+            [.., "pool"] => Self::POOL_DEFAULT_IMPORTS,
+            [.., "shard"] => Self::SHARD_DEFAULT_IMPORTS,
+            [.., "bridge"] => Self::BRIDGE_DEFAULT_IMPORTS,
+            [.., "sources"] => Self::SOURCES_DEFAULT_IMPORTS,
+            [.., "scheduler"] => Self::SCHEDULER_DEFAULT_IMPORTS,
+            [.., "sequence"] => Self::SEQUENCE_DEFAULT_IMPORTS,
+            [.., "rate_limit"] => Self::RATE_LIMIT_DEFAULT_IMPORTS,
+            [.., "circuit_breaker"] => Self::CIRCUIT_BREAKER_DEFAULT_IMPORTS,
+            [.., "persistence"] => Self::PERSISTENCE_DEFAULT_IMPORTS,
+            [.., "correlation"] => Self::CORRELATION_DEFAULT_IMPORTS,
+            [.., "health"] => Self::HEALTH_DEFAULT_IMPORTS,
+            [.., "supervisor"] => Self::SUPERVISOR_DEFAULT_IMPORTS,
+            [.., "children"] => Self::CHILDREN_DEFAULT_IMPORTS,
+            _ => &[],
+        };
+
+        let mut imports = defaults
+            .iter()
+            .map(|path| Import::from(*path))
+            .collect::<Vec<_>>();
+        imports.sort();
+        imports.into_iter().map(|imp| imp.rust_import()).collect()
+    }
+
     /// Phase 1: Bootstrap all known bloxide framework types
     pub fn bootstrap_bloxide_types(&mut self) {
         enum FType {
@@ -135,9 +333,20 @@ impl CodeGenGraph {
         }
     }
 
+    /// Registers a framework type beyond the bloxide ones, such as one from a shared crate
+    pub fn register_framework_type(&mut self, type_name: &str, full_path: &str) {
+        self.framework_types
+            .insert(type_name.to_string(), full_path.to_string());
+        self.graph.add_type_from_path(full_path);
+        self.resolved_types.insert(
+            type_name.into(),
+            TypeLocation::BloxideFramework(full_path.into()),
+        );
+    }
+
     /// Phase 2: Discover all types used in the actor
     pub fn discover_actor_types(&mut self, actor: &Actor) -> Result<(), Box<dyn Error>> {
-        let actor_module_path = actor.ident.to_lowercase();
+        let actor_module_path = actor.module_path();
 
         // Create the main actor module structure
         let _ = self.add_generated_module(&actor_module_path);
@@ -152,46 +361,676 @@ impl CodeGenGraph {
         self.discover_component_types(&actor.component, &actor_module_path)?;
         self.discover_state_types(&actor.component, &actor_module_path)?;
 
-        if let Some(message_set) = &actor.component.message_set {
-            self.discover_message_types(message_set, &actor_module_path)?;
+        if let Some(message_set) = &actor.component.message_set {
+            self.discover_message_types(message_set, &actor_module_path)?;
+        }
+
+        if let Some(pool) = &actor.pool {
+            self.discover_pool_types(actor, pool, &actor_module_path);
+        }
+
+        if let Some(shard) = &actor.shard {
+            self.discover_shard_types(actor, shard, &actor_module_path);
+        }
+
+        if let Some(bridge) = &actor.bridge {
+            self.discover_bridge_types(actor, bridge, &actor_module_path);
+        }
+
+        if actor.component.message_receivers.has_sources() {
+            self.discover_source_types(actor, &actor_module_path);
+        }
+
+        if !actor.sinks.is_empty() {
+            self.discover_sink_types(actor, &actor_module_path);
+        }
+
+        if !actor.schedules.is_empty() {
+            self.discover_scheduler_types(actor, &actor_module_path);
+        }
+
+        if actor.component.message_receivers.has_sequenced() {
+            self.discover_sequence_types(actor, &actor_module_path);
+        }
+
+        if actor.component.message_receivers.has_rate_limits() {
+            self.discover_rate_limit_types(actor, &actor_module_path);
+        }
+
+        if actor.component.message_handles.has_circuit_breakers() {
+            self.discover_circuit_breaker_types(actor, &actor_module_path);
+        }
+
+        if actor.lifecycle.persist_state {
+            self.discover_persistence_types(actor, &actor_module_path);
+        }
+
+        if actor.correlation {
+            self.discover_correlation_types(actor, &actor_module_path);
+        }
+
+        if actor.health_check {
+            self.discover_health_types(actor, &actor_module_path);
+        }
+
+        if actor.notifies_overload() {
+            self.discover_overload_types(&actor_module_path);
+        }
+
+        if !actor.lifecycle.restart.is_never() {
+            self.discover_supervisor_types(actor, &actor_module_path);
+        }
+
+        if !actor.children.is_empty() {
+            self.discover_children_types(actor, &actor_module_path)?;
+        }
+
+        if actor.has_self_queue() {
+            self.add_dependency_by_path(
+                &format!("{actor_module_path}::ext_state"),
+                "tokio::sync::mpsc::UnboundedSender",
+            );
+            self.add_dependency_by_path(
+                &format!("{actor_module_path}::component"),
+                "tokio::sync::mpsc::UnboundedReceiver",
+            );
+        }
+        if actor.relays.iter().any(|relay| relay.transform.is_some()) {
+            self.add_dependency_by_path(
+                &format!("{actor_module_path}::runtime"),
+                "bloxide_tokio::messaging::Message",
+            );
+        }
+        // The self handle and the stash buffers carry the actor's own messages
+        if (actor.has_self_queue() || actor.stash.is_some())
+            && let Some(message_set) = &actor.component.message_set
+        {
+            self.add_dependency_by_path(
+                &format!("{actor_module_path}::ext_state"),
+                &format!(
+                    "crate::{actor_module_path}::messaging::{}",
+                    message_set.get().ident
+                ),
+            );
+        }
+        if actor.stash.is_some() || actor.history.is_some() {
+            self.add_dependency_by_path(
+                &format!("{actor_module_path}::ext_state"),
+                "std::collections::VecDeque",
+            );
+        }
+        if actor.history.is_some() {
+            self.add_dependency_by_path(
+                &format!("{actor_module_path}::ext_state"),
+                "std::time::SystemTime",
+            );
+        }
+        if actor.lifecycle.persists_ext_state() {
+            for import in ["std::sync::Mutex", "std::sync::Weak"] {
+                self.add_dependency_by_path(&format!("{actor_module_path}::ext_state"), import);
+            }
+        }
+
+        for constant in &actor.constants {
+            self.discover_type_usage(
+                constant.ty.as_ref(),
+                &format!("{actor_module_path}::{}", constant.module.name()),
+                TypeContext::Constants,
+            );
+        }
+
+        // Discover runtime dependencies
+        self.discover_runtime_types(&actor_module_path);
+
+        Ok(())
+    }
+
+    /// Discover types used in runtime module
+    fn discover_runtime_types(&mut self, actor_module: &str) {
+        let module_path = format!("{actor_module}::runtime");
+
+        Self::RUNTIME_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+    }
+
+    /// Discover types used in extended state
+    fn discover_extended_state_types(
+        &mut self,
+        ext_state: &ExtState,
+        actor_module: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let module_path = format!("{actor_module}::ext_state");
+        let _ = self.add_generated_module(&module_path);
+
+        // The module imports the traits through the extended state implementing them
+        let ext_state_path = format!("{module_path}::{}", ext_state.ident());
+        Self::EXT_STATE_DEFAULT_IMPORTS
+            .iter()
+            .copied()
+            .chain(ext_state.implements().iter().map(String::as_str))
+            .for_each(|trait_path| {
+                self.add_implementation(&ext_state_path, trait_path);
+            });
+
+        for field in ext_state.fields() {
+            let field_type = field.ty().as_ref();
+            self.discover_type_usage(field_type, &module_path, TypeContext::ExtendedState);
+
+            if field.is_shared() {
+                field
+                    .lock()
+                    .import_paths()
+                    .iter()
+                    .for_each(|import| self.add_dependency_by_path(&module_path, import));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Discover types used in component
+    /// Discover types used by the pool router
+    fn discover_pool_types(&mut self, actor: &Actor, pool: &Pool, actor_module: &str) {
+        let module_path = format!("{actor_module}::pool");
+        let _ = self.add_generated_module(&module_path);
+
+        let routing_imports = match pool.routing {
+            Routing::RoundRobin => Self::POOL_ROUND_ROBIN_IMPORTS,
+            Routing::HashByKey => Self::POOL_HASH_IMPORTS,
+        };
+        Self::POOL_DEFAULT_IMPORTS
+            .iter()
+            .chain(routing_imports)
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        let component_path = format!("crate::{actor_module}::component");
+        let dependencies = [
+            format!("{component_path}::{}Inbox", actor.ident),
+            format!("{component_path}::{}", actor.id_ident()),
+        ];
+        self.add_generated_function(&module_path, "spawn_pool", &dependencies);
+
+        actor
+            .component
+            .message_receivers
+            .receivers
+            .iter()
+            .for_each(|receiver| {
+                self.discover_type_usage(&receiver.message_type, &module_path, TypeContext::Pool);
+            });
+    }
+
+    /// Discover types used by the sharded dispatcher
+    fn discover_shard_types(&mut self, actor: &Actor, shard: &Shard, actor_module: &str) {
+        let module_path = format!("{actor_module}::shard");
+        let _ = self.add_generated_module(&module_path);
+
+        Self::SHARD_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        let inbox_path = format!("crate::{actor_module}::component::{}Inbox", actor.ident);
+        self.add_dependency_by_path(&module_path, &inbox_path);
+
+        self.discover_type_usage(&shard.key_type, &module_path, TypeContext::Shard);
+        actor
+            .component
+            .message_receivers
+            .receivers
+            .iter()
+            .for_each(|receiver| {
+                self.discover_type_usage(&receiver.message_type, &module_path, TypeContext::Shard);
+            });
+    }
+
+    /// Discover types defined and used by the service bridge
+    fn discover_bridge_types(&mut self, actor: &Actor, bridge: &Bridge, actor_module: &str) {
+        let module_path = format!("{actor_module}::bridge");
+        let _ = self.add_generated_module(&module_path);
+
+        Self::BRIDGE_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        let inbox_path = format!("crate::{actor_module}::component::{}Inbox", actor.ident);
+        self.add_dependency_by_path(&module_path, &inbox_path);
+
+        match bridge {
+            Bridge::Grpc(grpc) => {
+                Self::GRPC_BRIDGE_IMPORTS
+                    .iter()
+                    .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+                let service_path = format!("{}::{}", grpc.server_module(), grpc.service);
+                self.add_dependency_by_path(&module_path, &service_path);
+
+                // Variants carrying RPCs name the envelope and the proto messages
+                self.resolved_types.insert(
+                    "RpcRequest".to_string(),
+                    TypeLocation::ActorCustom(format!("crate::{module_path}::RpcRequest")),
+                );
+                for rpc in &grpc.rpcs {
+                    for type_name in [&rpc.request, &rpc.response] {
+                        let type_path = format!("{}::{type_name}", grpc.proto);
+                        self.add_dependency_by_path(&module_path, &type_path);
+                        self.resolved_types
+                            .insert(type_name.clone(), TypeLocation::ActorCustom(type_path));
+                    }
+                }
+            }
+            Bridge::Http(http) => {
+                Self::HTTP_BRIDGE_IMPORTS
+                    .iter()
+                    .for_each(|import| self.add_dependency_by_path(&module_path, import));
+                if http.trace_context {
+                    self.add_dependency_by_path(&module_path, "axum::http::HeaderMap");
+                }
+
+                self.resolved_types.insert(
+                    "HttpRequest".to_string(),
+                    TypeLocation::ActorCustom(format!("crate::{module_path}::HttpRequest")),
+                );
+                self.add_generated_function(&module_path, "router", &[]);
+                for route in &http.routes {
+                    let routing_path = format!("axum::routing::{}", route.method.routing_fn());
+                    self.add_dependency_by_path(&module_path, &routing_path);
+                    if route.payload != PayloadSource::Json {
+                        let extractor_path =
+                            format!("axum::extract::{}", route.payload.extractor());
+                        self.add_dependency_by_path(&module_path, &extractor_path);
+                    }
+                    for ty in [&route.request, &route.response] {
+                        self.discover_type_usage(ty, &module_path, TypeContext::Bridge);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Discover types used by the external source adapters
+    fn discover_source_types(&mut self, actor: &Actor, actor_module: &str) {
+        let module_path = format!("{actor_module}::sources");
+        let _ = self.add_generated_module(&module_path);
+
+        Self::SOURCES_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        // NATS subjects get an adapter each, the other brokers one for all their topics
+        let receivers = &actor.component.message_receivers.receivers;
+        let brokers = |broker| {
+            receivers
+                .iter()
+                .filter(move |r| r.source.as_ref().is_some_and(|s| s.broker == broker))
+        };
+        let adapters = brokers(Broker::Nats)
+            .map(|r| format!("forward_{}_from_nats", r.channel_name()))
+            .chain(
+                [
+                    (Broker::Mqtt, "forward_from_mqtt"),
+                    (Broker::Kafka, "forward_from_kafka"),
+                ]
+                .into_iter()
+                .filter(|(broker, _)| brokers(*broker).next().is_some())
+                .map(|(_, adapter)| adapter.to_string()),
+            )
+            .collect::<Vec<_>>();
+        let inbox_path = format!("crate::{actor_module}::component::{}Inbox", actor.ident);
+        for adapter in adapters {
+            self.add_generated_function(&module_path, &adapter, std::slice::from_ref(&inbox_path));
+        }
+
+        actor
+            .component
+            .message_receivers
+            .receivers
+            .iter()
+            .filter(|receiver| receiver.source.is_some())
+            .for_each(|receiver| {
+                self.discover_type_usage(
+                    &receiver.message_type,
+                    &module_path,
+                    TypeContext::Sources,
+                );
+            });
+    }
+
+    /// Discover types used by the Kafka publishing helpers
+    fn discover_sink_types(&mut self, actor: &Actor, actor_module: &str) {
+        let module_path = format!("{actor_module}::sinks");
+        let _ = self.add_generated_module(&module_path);
+
+        let Some(message_set) = &actor.component.message_set else {
+            return;
+        };
+        for sink in &actor.sinks {
+            let publish = format!("publish_{}", sink.variant.to_lowercase());
+            self.add_generated_function(&module_path, &publish, &[]);
+            message_set
+                .get()
+                .variants
+                .iter()
+                .filter(|variant| variant.ident == sink.variant)
+                .flat_map(|variant| &variant.args)
+                .for_each(|arg| {
+                    self.discover_type_usage(arg.as_ref(), &module_path, TypeContext::Sinks);
+                });
+        }
+    }
+
+    /// Discover types used by the cron scheduler
+    fn discover_scheduler_types(&mut self, actor: &Actor, actor_module: &str) {
+        let module_path = format!("{actor_module}::scheduler");
+        let _ = self.add_generated_module(&module_path);
+
+        Self::SCHEDULER_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        let inbox_path = format!("crate::{actor_module}::component::{}Inbox", actor.ident);
+        self.add_generated_function(&module_path, "run_schedules", &[inbox_path]);
+    }
+
+    /// Discover types defined by the sequence module and the runtime's checks
+    fn discover_sequence_types(&mut self, actor: &Actor, actor_module: &str) {
+        let module_path = format!("{actor_module}::sequence");
+        let _ = self.add_generated_module(&module_path);
+
+        Self::SEQUENCE_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        // Receivers, handles and variants refer to the wrapper by name
+        let sequenced_path = format!("crate::{module_path}::{SEQUENCED_TYPE}");
+        self.resolved_types.insert(
+            SEQUENCED_TYPE.to_string(),
+            TypeLocation::ActorCustom(sequenced_path),
+        );
+
+        let runtime_path = format!("{actor_module}::runtime");
+        for type_name in [
+            "SequenceCheck".to_string(),
+            format!("{}Sequences", actor.ident),
+        ] {
+            self.add_dependency_by_path(
+                &runtime_path,
+                &format!("crate::{module_path}::{type_name}"),
+            );
+        }
+    }
+
+    /// Discover types defined by the rate limit module and used by the runtime's guards
+    fn discover_rate_limit_types(&mut self, actor: &Actor, actor_module: &str) {
+        let module_path = format!("{actor_module}::rate_limit");
+        let _ = self.add_generated_module(&module_path);
+
+        Self::RATE_LIMIT_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        self.add_dependency_by_path(
+            &format!("{actor_module}::runtime"),
+            &format!("crate::{module_path}::{}RateLimits", actor.ident),
+        );
+    }
+
+    /// Discover types defined by the circuit breaker module and used by the send helpers
+    fn discover_circuit_breaker_types(&mut self, actor: &Actor, actor_module: &str) {
+        let module_path = format!("{actor_module}::circuit_breaker");
+        let _ = self.add_generated_module(&module_path);
+
+        Self::CIRCUIT_BREAKER_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        let component_path = format!("{actor_module}::component");
+        for type_name in [
+            "BreakerError".to_string(),
+            format!("{}Breakers", actor.ident),
+        ] {
+            self.add_dependency_by_path(
+                &component_path,
+                &format!("crate::{module_path}::{type_name}"),
+            );
+        }
+        if actor.health_check {
+            self.add_dependency_by_path(
+                &format!("{actor_module}::health"),
+                &format!("crate::{module_path}::CircuitState"),
+            );
+        }
+    }
+
+    /// Discover types defined by the persistence module and the state enum it stores
+    fn discover_persistence_types(&mut self, actor: &Actor, actor_module: &str) {
+        let module_path = format!("{actor_module}::persistence");
+        let _ = self.add_generated_module(&module_path);
+
+        Self::PERSISTENCE_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        let state_enum = &actor.component.states.state_enum.get().ident;
+        self.add_dependency_by_path(
+            &module_path,
+            &format!("crate::{actor_module}::states::{state_enum}"),
+        );
+
+        // The extended state holds the store by this name
+        let store_ref = format!("{}StateStoreRef", actor.ident);
+        self.resolved_types.insert(
+            store_ref.clone(),
+            TypeLocation::ActorCustom(format!("crate::{module_path}::{store_ref}")),
+        );
+    }
+
+    /// Discover types defined by the correlation module and the message set helpers
+    fn discover_correlation_types(&mut self, actor: &Actor, actor_module: &str) {
+        let module_path = format!("{actor_module}::correlation");
+        let _ = self.add_generated_module(&module_path);
+
+        Self::CORRELATION_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+        if actor.traces() {
+            Self::TRACE_CONTEXT_IMPORTS
+                .iter()
+                .for_each(|import| self.add_dependency_by_path(&module_path, import));
+        }
+
+        // Receivers, handles and variants refer to the wrapper by name
+        let correlated_path = format!("crate::{module_path}::{CORRELATED_TYPE}");
+        self.resolved_types.insert(
+            CORRELATED_TYPE.to_string(),
+            TypeLocation::ActorCustom(correlated_path.clone()),
+        );
+
+        let messaging_path = format!("{actor_module}::messaging");
+        self.add_dependency_by_path(&messaging_path, &correlated_path);
+        self.add_dependency_by_path(
+            &messaging_path,
+            &format!("crate::{module_path}::CorrelationId"),
+        );
+
+        // Adapters wrap what they forward and read the trace context it arrived with
+        let trace_context_path = format!("crate::{module_path}::TraceContext");
+        if actor.traces() {
+            self.add_dependency_by_path(&messaging_path, &trace_context_path);
+        }
+        if let Some(bridge) = &actor.bridge {
+            let bridge_path = format!("{actor_module}::bridge");
+            self.add_dependency_by_path(&bridge_path, &correlated_path);
+            if bridge.trace_context() {
+                self.add_dependency_by_path(&bridge_path, &trace_context_path);
+            }
+        }
+        // Schedules start chains of their own, and emits continue the received message's
+        if !actor.schedules.is_empty() {
+            let scheduler_path = format!("{actor_module}::scheduler");
+            self.add_dependency_by_path(&scheduler_path, &correlated_path);
+        }
+        let handles = &actor.component.message_handles;
+        for state in &actor.component.states.states {
+            let correlates = state
+                .transitions
+                .iter()
+                .flat_map(|transition| &transition.emit)
+                .filter_map(|emit| handles.get_handle(&emit.handle))
+                .any(MessageHandle::is_correlated);
+            if correlates {
+                let state_module_path =
+                    format!("{actor_module}::states::{}", state.ident.to_lowercase());
+                self.add_dependency_by_path(
+                    &state_module_path,
+                    "bloxide_tokio::messaging::Message",
+                );
+            }
+        }
+
+        let traced_sources = actor
+            .component
+            .message_receivers
+            .receivers
+            .iter()
+            .any(|receiver| receiver.source.as_ref().is_some_and(|s| s.trace_context));
+        if traced_sources {
+            let sources_path = format!("{actor_module}::sources");
+            self.add_dependency_by_path(&sources_path, &trace_context_path);
+        }
+    }
+
+    /// Discover types defined and used by the health probe
+    fn discover_health_types(&mut self, actor: &Actor, actor_module: &str) {
+        let module_path = format!("{actor_module}::health");
+        let _ = self.add_generated_module(&module_path);
+
+        Self::HEALTH_DEFAULT_IMPORTS
+            .iter()
+            .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        let inbox_path = format!("crate::{actor_module}::component::{}Inbox", actor.ident);
+        self.add_dependency_by_path(&module_path, &inbox_path);
+
+        // Receivers, inboxes and routers refer to the probe payload by name
+        for type_name in [
+            HEALTH_CHECK_TYPE.to_string(),
+            format!("{}Health", actor.ident),
+        ] {
+            let type_path = format!("crate::{module_path}::{type_name}");
+            self.resolved_types
+                .insert(type_name, TypeLocation::ActorCustom(type_path));
         }
 
-        // Discover runtime dependencies
-        self.discover_runtime_types(&actor_module_path);
+        let health_path = format!("crate::{module_path}::{}Health", actor.ident);
+        self.add_dependency_by_path(&format!("{actor_module}::runtime"), &health_path);
+    }
 
-        Ok(())
+    /// Discover the payload of the `Overloaded` notification, which the runtime builds
+    fn discover_overload_types(&mut self, actor_module: &str) {
+        let type_path = format!("crate::{actor_module}::messaging::{QUEUE_OVERLOAD_TYPE}");
+        self.resolved_types.insert(
+            QUEUE_OVERLOAD_TYPE.to_string(),
+            TypeLocation::ActorCustom(type_path.clone()),
+        );
+        let runtime = format!("{actor_module}::runtime");
+        self.add_dependency_by_path(&runtime, &type_path);
+        self.add_dependency_by_path(&runtime, "bloxide_tokio::messaging::Message");
     }
 
-    /// Discover types used in runtime module
-    fn discover_runtime_types(&mut self, actor_module: &str) {
-        let module_path = format!("{actor_module}::runtime");
+    /// Discover types used by the supervisor
+    fn discover_supervisor_types(&mut self, actor: &Actor, actor_module: &str) {
+        let module_path = format!("{actor_module}::supervisor");
+        let _ = self.add_generated_module(&module_path);
 
-        Self::RUNTIME_DEFAULT_IMPORTS
+        let backoff_imports = match actor.lifecycle.restart {
+            RestartPolicy::MaxRestarts { .. } => Self::SUPERVISOR_BACKOFF_IMPORTS,
+            _ => &[],
+        };
+        let snapshot_imports = if actor.lifecycle.persists_ext_state() {
+            Self::SUPERVISOR_SNAPSHOT_IMPORTS
+        } else {
+            &[]
+        };
+        Self::SUPERVISOR_DEFAULT_IMPORTS
             .iter()
+            .chain(backoff_imports)
+            .chain(snapshot_imports)
             .for_each(|import| self.add_dependency_by_path(&module_path, import));
+
+        let mut dependencies = vec![format!(
+            "crate::{actor_module}::component::{}",
+            actor.component.ident
+        )];
+        if actor.lifecycle.persists_ext_state() {
+            dependencies.push(format!(
+                "crate::{actor_module}::ext_state::{}",
+                actor.component.ext_state.ident()
+            ));
+        }
+        self.add_generated_function(&module_path, "supervise", &dependencies);
     }
 
-    /// Discover types used in extended state
-    fn discover_extended_state_types(
+    /// Discover the spawn functions of the children module and the child types they wire
+    fn discover_children_types(
         &mut self,
-        ext_state: &ExtState,
+        actor: &Actor,
         actor_module: &str,
     ) -> Result<(), Box<dyn Error>> {
-        let module_path = format!("{actor_module}::ext_state");
+        let module_path = format!("{actor_module}::children");
+        let _ = self.add_generated_module(&module_path);
 
-        Self::EXT_STATE_DEFAULT_IMPORTS
+        Self::CHILDREN_DEFAULT_IMPORTS
             .iter()
             .for_each(|import| self.add_dependency_by_path(&module_path, import));
 
-        for field in ext_state.fields() {
-            let field_type = field.ty().as_ref();
-            self.discover_type_usage(field_type, &module_path, TypeContext::ExtendedState);
-        }
+        for child in &actor.children {
+            let child_actor = child.actor()?;
+            let child_module = child_actor.module_path();
+            let component = &child_actor.component;
+            let component_path = format!("crate::{child_module}::component");
+            let inbox = format!("{}Inbox", child_actor.ident);
+            let dependencies = [
+                format!("{component_path}::{}", component.ident),
+                format!("{component_path}::{}", child_actor.id_ident()),
+                format!("{component_path}::{inbox}"),
+                format!("{component_path}::{}", component.message_handles.ident),
+                format!("{component_path}::{}", component.message_receivers.ident),
+                format!(
+                    "crate::{child_module}::ext_state::{}",
+                    component.ext_state.ident()
+                ),
+            ];
+            let spawn = format!("spawn_{}", child.ident);
+            self.add_generated_function(&module_path, &spawn, &dependencies);
+
+            // The outbox holds the receiving side of the child's handles
+            if !component.message_handles.handles.is_empty() {
+                Self::CHILD_OUTBOX_IMPORTS
+                    .iter()
+                    .for_each(|import| self.add_dependency_by_path(&module_path, import));
+            }
+            component.message_handles.handles.iter().for_each(|handle| {
+                self.discover_type_usage(
+                    &handle.message_type,
+                    &module_path,
+                    TypeContext::Component,
+                );
+            });
 
+            // The extended state holds the child's inbox, and the runtime spawns it
+            self.resolved_types.insert(
+                inbox.clone(),
+                TypeLocation::ActorCustom(format!("{component_path}::{inbox}")),
+            );
+            self.add_dependency_by_path(
+                &format!("{actor_module}::runtime"),
+                &format!("crate::{module_path}::{spawn}"),
+            );
+        }
         Ok(())
     }
 
-    /// Discover types used in component
     fn discover_component_types(
         &mut self,
         component: &Component,
@@ -228,6 +1067,13 @@ impl CodeGenGraph {
             self.discover_type_usage(&handle.message_type, &module_path, TypeContext::Component);
         });
 
+        // The handles' send helpers take messages and surface channel errors
+        if !component.message_handles.handles.is_empty() {
+            Self::HANDLE_HELPER_IMPORTS
+                .iter()
+                .for_each(|import| self.add_dependency_by_path(&module_path, import));
+        }
+
         // Discover types in message receivers
         component
             .message_receivers
@@ -268,6 +1114,35 @@ impl CodeGenGraph {
                 format!("crate::{actor_module}::component::{}", component.ident);
             self.add_dependency_by_path(&state_module_path, &component_type_path);
 
+            // Discover types carried by data states
+            state
+                .fields
+                .iter()
+                .map(|field| field.ty().as_ref())
+                .chain(
+                    state
+                        .variants
+                        .iter()
+                        .flatten()
+                        .flat_map(|variant| &variant.args)
+                        .map(|arg| arg.as_ref()),
+                )
+                .for_each(|ty| {
+                    self.discover_type_usage(ty, &state_module_path, TypeContext::States)
+                });
+
+            // Discover types used by the state's helper methods
+            for method in &state.methods {
+                method
+                    .args()
+                    .iter()
+                    .map(|arg| arg.ty().as_ref())
+                    .chain(std::iter::once(method.ret().as_ref()))
+                    .for_each(|ty| {
+                        self.discover_type_usage(ty, &state_module_path, TypeContext::States)
+                    });
+            }
+
             // Add message set dependency for individual state modules (if exists)
             if let Some(message_set) = &component.message_set {
                 let message_set_path = format!(
@@ -276,6 +1151,27 @@ impl CodeGenGraph {
                 );
                 self.add_dependency_by_path(&state_module_path, &message_set_path);
             }
+
+            // Transitions construct the state enum and their target states
+            if !state.transitions.is_empty() {
+                let state_enum_path = format!(
+                    "crate::{actor_module}::states::{}",
+                    component.states.state_enum.get().ident
+                );
+                self.add_dependency_by_path(&state_module_path, &state_enum_path);
+            }
+            for target in state
+                .transitions
+                .iter()
+                .map(|t| &t.target)
+                .filter(|target| **target != state.ident)
+            {
+                let target_path = format!(
+                    "crate::{actor_module}::states::{}::{target}",
+                    target.to_lowercase()
+                );
+                self.add_dependency_by_path(&state_module_path, &target_path);
+            }
         }
 
         Self::STATES_DEFAULT_IMPORTS
@@ -368,12 +1264,26 @@ impl CodeGenGraph {
                 });
         }
 
+        // Aliases and newtypes are actor-local types too, named wherever the spec links them
+        let wrapped = message_set
+            .aliases
+            .iter()
+            .map(|alias| (&alias.ident, &alias.ty))
+            .chain(message_set.newtypes.iter().map(|n| (&n.ident, &n.ty)));
+        for (ident, ty) in wrapped {
+            self.resolved_types.insert(
+                ident.clone(),
+                TypeLocation::ActorCustom(format!("crate::{actor_module}::messaging::{ident}")),
+            );
+            self.discover_type_usage(ty.as_ref(), &module_path, TypeContext::MessageSet);
+        }
+
         Ok(())
     }
 
     /// Discover a type usage and add it to the discovered types list
     fn discover_type_usage(&mut self, type_string: &str, module_path: &str, context: TypeContext) {
-        let types = self.extract_types_from_string(type_string);
+        let types = type_names(type_string);
 
         for type_name in types {
             // Skip if already discovered in this context
@@ -394,59 +1304,23 @@ impl CodeGenGraph {
         }
     }
 
-    /// Extract individual type names from a complex type string
-    fn extract_types_from_string(&self, type_string: &str) -> Vec<String> {
-        let mut types = Vec::new();
-        let delimiters = ['<', '>', ',', ' ', '(', ')', '[', ']'];
-
-        let parts = type_string
-            .split(&delimiters[..])
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty());
-
-        for part in parts {
-            // Skip builtin types
-            if Self::PRELUDE_TYPES.contains(&part) {
-                continue;
-            }
-
-            if part.contains("::") {
-                // Extract the final type name from qualified paths
-                if let Some(type_name) = part.split("::").last()
-                    && self.is_valid_type_name(type_name)
-                {
-                    types.push(type_name.to_string());
-                }
-            } else if self.is_valid_type_name(part) {
-                types.push(part.to_string());
-            }
-        }
-
-        types
-    }
-
-    /// Check if a string looks like a valid Rust type name
-    fn is_valid_type_name(&self, name: &str) -> bool {
-        if name.is_empty() || name.starts_with(char::is_numeric) {
-            return false;
-        }
-
-        name.chars().all(|c| c.is_alphanumeric() || c == '_')
-    }
-
     /// Phase 3: Resolve all discovered types to their locations
     pub fn resolve_type_relationships(&mut self) -> Result<(), Box<dyn Error>> {
         // Take ownership of discovered types to avoid borrowing issues
         let discovered_types = std::mem::take(&mut self.discovered_types);
+        self.unresolved_types.clear();
         for discovered_type in discovered_types.iter() {
             let location =
                 self.resolve_type_location(&discovered_type.name, &discovered_type.used_in_module);
 
             if matches!(location, TypeLocation::Unknown) {
-                eprintln!(
-                    "Cannot resolve type '{}' used in module '{}'. Please use qualified paths for external types.",
-                    discovered_type.name, discovered_type.used_in_module
-                );
+                // Types the generator injects are imported by path when they are added
+                if !self.module_imports(&discovered_type.used_in_module, &discovered_type.name) {
+                    self.unresolved_types.push((
+                        discovered_type.name.clone(),
+                        discovered_type.used_in_module.clone(),
+                    ));
+                }
                 continue;
             }
 
@@ -459,10 +1333,37 @@ impl CodeGenGraph {
         Ok(())
     }
 
+    /// Types analysis could not resolve, as `(type, module)` pairs in discovery order
+    pub fn unresolved_types(&self) -> &[(String, String)] {
+        &self.unresolved_types
+    }
+
+    /// Whether `module_path` already imports a type named `type_name`
+    fn module_imports(&self, module_path: &str, type_name: &str) -> bool {
+        self.graph
+            .find_module_by_path_hierarchical(module_path)
+            .is_some_and(|module_idx| {
+                self.graph
+                    .find_connected_nodes(module_idx)
+                    .filter(|entry| entry.relation() == Relation::Uses)
+                    .any(|entry| entry.node().name() == type_name)
+            })
+    }
+
+    /// Actor module a generated module belongs to, e.g. `payments::refund` for
+    /// `payments::refund::states::create`
+    fn actor_module_of(module_path: &str) -> String {
+        module_path
+            .split("::")
+            .take_while(|segment| !Self::ACTOR_SUBMODULES.contains(segment))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
     /// Resolve a type name to its location
     fn resolve_type_location(&self, type_name: &str, used_in_module: &str) -> TypeLocation {
         // Check if it's a builtin type
-        if Self::PRELUDE_TYPES.contains(&type_name) {
+        if PRELUDE_TYPES.contains(&type_name) {
             return TypeLocation::Builtin;
         }
 
@@ -477,7 +1378,7 @@ impl CodeGenGraph {
         }
 
         // Check if it might be an actor-local type
-        let actor_module = used_in_module.split("::").next().unwrap_or_default();
+        let actor_module = Self::actor_module_of(used_in_module);
         if !actor_module.is_empty() {
             // Check if it could be in messaging module
             let messaging_path = format!("crate::{actor_module}::messaging::{type_name}");
@@ -601,22 +1502,31 @@ impl CodeGenGraph {
         self.graph.add_type_from_path(path)
     }
 
-    /// Get all imports needed for a specific module by traversing Uses edges
+    /// Get all imports needed for a specific module by traversing Uses edges, and the
+    /// Implements edges of the types it defines
     pub fn get_imports_for_module(&self, module_idx: NodeIndex) -> impl Iterator<Item = String> {
         let mut imports = Vec::new();
         let module_path = self.graph.get_node_path(module_idx);
 
-        // Find all nodes this module Uses
-        let connected = self.graph.find_connected_nodes(module_idx);
+        // Find all nodes this module Uses, and the traits its types implement
+        let defined = self
+            .graph
+            .find_connected_nodes(module_idx)
+            .filter(|entry| {
+                entry.relation() == Relation::Contains && matches!(entry.node(), Node::Type(_))
+            })
+            .flat_map(|entry| self.graph.find_connected_nodes(entry.index()))
+            .filter(|entry| entry.relation() == Relation::Implements);
+        let connected = self
+            .graph
+            .find_connected_nodes(module_idx)
+            .filter(|entry| entry.relation() == Relation::Uses)
+            .chain(defined);
         for RelatedEntry {
             index: connected_idx,
-            node: _,
-            relation,
+            ..
         } in connected
         {
-            if !matches!(relation, Relation::Uses) {
-                continue;
-            }
             let connected_path = self.graph.get_node_path(connected_idx);
 
             // Safeguard: Skip self-imports
@@ -651,6 +1561,72 @@ impl CodeGenGraph {
         type_idx
     }
 
+    /// Record that a generated type implements a trait, which the type's module then imports
+    ///
+    /// Traits named without a path, such as `Clone`, are in the prelude and need no edge.
+    pub fn add_implementation(&mut self, type_path: &str, trait_path: &str) -> NodeIndex {
+        let (module_path, type_name) = type_path.rsplit_once("::").unwrap_or(("", type_path));
+        let existing = self
+            .graph
+            .find_module_by_path_hierarchical(module_path)
+            .and_then(|module_idx| {
+                self.graph.find_connected_nodes(module_idx).find(|entry| {
+                    entry.relation() == Relation::Contains
+                        && matches!(entry.node(), Node::Type(_))
+                        && entry.node().name() == type_name
+                })
+            })
+            .map(|entry| entry.index());
+        let type_idx = existing.unwrap_or_else(|| self.graph.add_type_from_path(type_path));
+
+        if trait_path.contains("::") {
+            let trait_idx = self.graph.add_trait_from_path(trait_path);
+            self.graph
+                .add_edge(type_idx, trait_idx, Relation::Implements);
+        }
+
+        type_idx
+    }
+
+    /// Add a function generated into a module, with the paths it uses for the module to import
+    pub fn add_generated_function(
+        &mut self,
+        module_path: &str,
+        name: &str,
+        dependencies: &[String],
+    ) -> NodeIndex {
+        let _ = self.add_generated_module(module_path);
+        let function_idx = self
+            .graph
+            .add_function_from_path(&format!("{module_path}::{name}"));
+
+        for dep_path in dependencies {
+            self.add_dependency_by_path(module_path, dep_path);
+            let dep_idx = self.get_or_create_node_by_path(dep_path);
+            self.graph.add_edge(function_idx, dep_idx, Relation::Uses);
+        }
+
+        function_idx
+    }
+
+    /// Get the names of the functions generated into a module, in the order they were added
+    pub fn generated_functions(&self, module_path: &str) -> Vec<String> {
+        let Some(module_idx) = self.graph.find_module_by_path_hierarchical(module_path) else {
+            return Vec::new();
+        };
+
+        let mut functions = self
+            .graph
+            .find_connected_nodes(module_idx)
+            .filter(|entry| {
+                entry.relation() == Relation::Contains && matches!(entry.node(), Node::Function(_))
+            })
+            .map(|entry| (entry.index, entry.node().name()))
+            .collect::<Vec<_>>();
+        functions.sort();
+        functions.into_iter().map(|(_, name)| name).collect()
+    }
+
     /// Add a generated module and track its contents
     pub fn add_generated_module(&mut self, module_path: &str) -> NodeIndex {
         match self.graph.find_module_by_path_hierarchical(module_path) {
@@ -665,6 +1641,93 @@ impl CodeGenGraph {
         }
     }
 
+    /// Get the names of the modules directly contained in a module
+    pub fn child_modules(&self, module_path: &str) -> Vec<String> {
+        let Some(module_idx) = self.graph.find_module_by_path_hierarchical(module_path) else {
+            return Vec::new();
+        };
+
+        self.graph
+            .find_connected_nodes(module_idx)
+            .filter(|entry| {
+                entry.relation() == Relation::Contains && matches!(entry.node(), Node::Module(_))
+            })
+            .map(|entry| entry.node().name())
+            .collect()
+    }
+
+    /// Get the child modules of a module ordered so that dependencies come first
+    ///
+    /// Ordering follows the Uses edges between sibling modules (including
+    /// those of their nested modules). Ties and cycles fall back to
+    /// alphabetical order, so the result is deterministic.
+    pub fn submodules_in_dependency_order(&self, module_path: &str) -> Vec<String> {
+        let children = self
+            .child_modules(module_path)
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+        let sibling_prefix = format!("{module_path}::");
+
+        let mut dependencies = BTreeMap::new();
+        for child in &children {
+            let child_path = format!("{sibling_prefix}{child}");
+            let deps = self
+                .module_uses(&child_path)
+                .into_iter()
+                .filter_map(|used| {
+                    let used = used.strip_prefix("crate::").unwrap_or(&used).to_string();
+                    let sibling = used.strip_prefix(&sibling_prefix)?.split("::").next()?;
+                    (sibling != child && children.contains(sibling)).then(|| sibling.to_string())
+                })
+                .collect::<BTreeSet<_>>();
+            dependencies.insert(child.clone(), deps);
+        }
+
+        let mut ordered = Vec::with_capacity(children.len());
+        while !dependencies.is_empty() {
+            let ready = dependencies
+                .iter()
+                .filter(|(_, deps)| deps.iter().all(|dep| ordered.contains(dep)))
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+
+            // Break cycles by emitting the alphabetically first remaining module
+            let ready = if ready.is_empty() {
+                dependencies.keys().take(1).cloned().collect()
+            } else {
+                ready
+            };
+
+            for name in ready {
+                dependencies.remove(&name);
+                ordered.push(name);
+            }
+        }
+
+        ordered
+    }
+
+    /// Get the paths used by a module and all modules nested inside it
+    fn module_uses(&self, module_path: &str) -> Vec<String> {
+        let Some(module_idx) = self.graph.find_module_by_path_hierarchical(module_path) else {
+            return Vec::new();
+        };
+
+        let mut pending = vec![module_idx];
+        let mut used = Vec::new();
+        while let Some(idx) = pending.pop() {
+            for entry in self.graph.find_connected_nodes(idx) {
+                match (entry.relation(), entry.node()) {
+                    (Relation::Uses, _) => used.push(self.graph.get_node_path(entry.index())),
+                    (Relation::Contains, Node::Module(_)) => pending.push(entry.index()),
+                    _ => {}
+                }
+            }
+        }
+
+        used
+    }
+
     /// Get a visual representation of the dependency graph
     pub fn debug_dependencies(&self) -> String {
         let mut output = String::new();
@@ -683,55 +1746,192 @@ impl CodeGenGraph {
         output
     }
 
-    /// Extract required imports by analyzing the generated code for type usage
-    pub fn extract_required_imports(&self, code: &str) -> Vec<String> {
-        let mut imports = Vec::new();
+    /// Serialize the graph for external viewers and tooling
+    ///
+    /// The schema is versioned so consumers can detect changes to it:
+    ///
+    /// ```json
+    /// {
+    ///   "version": 1,
+    ///   "nodes": [{ "id": 0, "kind": "module", "name": "states", "path": "actor::states" }],
+    ///   "edges": [{ "from": 0, "to": 1, "relation": "uses" }]
+    /// }
+    /// ```
+    ///
+    /// `kind` is one of `crate`, `module`, `type`, `function` or `trait`, and `relation` one
+    /// of `contains`, `implements` or `uses`. Edges refer to nodes by `id`, which is stable
+    /// within one export only.
+    pub fn to_json(&self) -> String {
+        let graph = &self.graph.graph;
+        let export = JsonGraph {
+            version: JsonGraph::VERSION,
+            nodes: graph
+                .node_indices()
+                .map(|index| {
+                    let node = &graph[index];
+                    JsonNode {
+                        id: index.index(),
+                        kind: node.node_str().to_lowercase(),
+                        name: node.name(),
+                        path: self.graph.get_node_path(index),
+                    }
+                })
+                .collect(),
+            edges: graph
+                .raw_edges()
+                .iter()
+                .map(|edge| JsonEdge {
+                    from: edge.source().index(),
+                    to: edge.target().index(),
+                    relation: match edge.weight {
+                        Relation::Contains => "contains",
+                        Relation::Implements => "implements",
+                        Relation::Uses => "uses",
+                    },
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&export)
+            .expect("The graph export only holds strings and numbers")
+    }
 
-        // Map of type patterns to their import paths
-        let type_mappings = [
-            // Core bloxide types
-            ("Components", "bloxide_tokio::components::Components"),
-            ("TokioMessageHandle", "bloxide_tokio::TokioMessageHandle"),
-            ("TokioRuntime", "bloxide_tokio::TokioRuntime"),
-            ("Runtime", "bloxide_tokio::components::Runtime"),
-            ("MessageSender", "bloxide_tokio::messaging::MessageSender"),
-            ("MessageSet", "bloxide_tokio::messaging::MessageSet"),
-            ("Message", "bloxide_tokio::messaging::Message"),
-            // State machine types
-            ("StateMachine", "bloxide_tokio::state_machine::StateMachine"),
-            ("State", "bloxide_tokio::state_machine::State"),
-            ("StateEnum", "bloxide_tokio::state_machine::StateEnum"),
-            ("Transition", "bloxide_tokio::state_machine::Transition"),
-            (
-                "ExtendedState",
-                "bloxide_tokio::state_machine::ExtendedState",
-            ),
-            // Runtime types
-            ("Runnable", "bloxide_tokio::components::Runnable"),
-        ];
+    /// Extract the imports rendered code needs, from the paths its syntax tree uses
+    ///
+    /// Only the first segment of a path has to be in scope, so each is looked up among the
+    /// types the graph resolved; paths it doesn't know, such as fully qualified ones, need no
+    /// import.
+    pub fn extract_required_imports(&self, code: &str) -> Result<Vec<String>, syn::Error> {
+        Ok(self.required_imports(&syn::parse_file(code)?))
+    }
 
-        for (type_name, import_path) in &type_mappings {
-            if self.code_uses_type(code, type_name) {
-                imports.push(import_path.to_string());
-            }
-        }
+    /// Imports rendered code needs that it neither has nor defines itself
+    ///
+    /// Reconciles the imports the graph gave a module with the code rendered for it, so a type
+    /// discovery never linked to the module is still imported.
+    pub fn missing_imports(&self, code: &str) -> Result<Vec<String>, syn::Error> {
+        let file = syn::parse_file(code)?;
+        let mut in_scope = ScopeNames::default();
+        in_scope.visit_file(&file);
+        Ok(self
+            .required_imports(&file)
+            .into_iter()
+            .filter(|path| {
+                let name = path.rsplit("::").next().unwrap_or(path);
+                !in_scope.0.contains(name)
+            })
+            .collect())
+    }
+
+    fn required_imports(&self, file: &syn::File) -> Vec<String> {
+        let mut idents = PathIdents::default();
+        idents.visit_file(file);
 
+        let mut imports = idents
+            .0
+            .iter()
+            .filter_map(|ident| match self.resolved_types.get(ident)? {
+                TypeLocation::BloxideFramework(path) | TypeLocation::ActorCustom(path) => {
+                    Some(path.clone())
+                }
+                TypeLocation::Builtin | TypeLocation::Unknown => None,
+            })
+            .collect::<Vec<_>>();
+        imports.sort();
+        imports.dedup();
         imports
     }
+}
 
-    /// Check if the code uses a specific type
-    pub fn code_uses_type(&self, code: &str, type_name: &str) -> bool {
-        // Look for various usage patterns
-        let patterns = [
-            format!("impl {type_name}"), // trait implementations
-            format!(": {type_name}"),    // type annotations
-            format!("<{type_name}>"),    // generic parameters
-            format!("{type_name}::"),    // qualified paths
-            format!("{type_name}<"),     // generic type usage
-            format!("as {type_name}"),   // type casts
-        ];
+/// The leading identifiers of every relative path in a syntax tree
+/// The graph as [`CodeGenGraph::to_json`] exports it
+#[derive(Serialize)]
+struct JsonGraph {
+    version: u32,
+    nodes: Vec<JsonNode>,
+    edges: Vec<JsonEdge>,
+}
+
+impl JsonGraph {
+    const VERSION: u32 = 1;
+}
+
+#[derive(Serialize)]
+struct JsonNode {
+    id: usize,
+    kind: String,
+    name: String,
+    path: String,
+}
+
+#[derive(Serialize)]
+struct JsonEdge {
+    from: usize,
+    to: usize,
+    relation: &'static str,
+}
+
+#[derive(Default)]
+struct PathIdents(BTreeSet<String>);
+
+impl<'ast> Visit<'ast> for PathIdents {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if path.leading_colon.is_none()
+            && let Some(first) = path.segments.first()
+        {
+            self.0.insert(first.ident.to_string());
+        }
+        // Generic arguments hold paths of their own
+        syn::visit::visit_path(self, path);
+    }
+}
+
+/// Names code brings into scope, by defining or importing them, wherever it does
+#[derive(Default)]
+struct ScopeNames(BTreeSet<String>);
+
+impl ScopeNames {
+    fn visit_use(&mut self, tree: &syn::UseTree) {
+        match tree {
+            syn::UseTree::Path(path) => self.visit_use(&path.tree),
+            syn::UseTree::Name(name) => {
+                self.0.insert(name.ident.to_string());
+            }
+            syn::UseTree::Rename(rename) => {
+                self.0.insert(rename.rename.to_string());
+            }
+            syn::UseTree::Group(group) => group.items.iter().for_each(|tree| self.visit_use(tree)),
+            syn::UseTree::Glob(_) => {}
+        }
+    }
+}
 
-        patterns.iter().any(|pattern| code.contains(pattern))
+impl<'ast> Visit<'ast> for ScopeNames {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        let ident = match item {
+            syn::Item::Const(item) => Some(&item.ident),
+            syn::Item::Enum(item) => Some(&item.ident),
+            syn::Item::Fn(item) => Some(&item.sig.ident),
+            syn::Item::Mod(item) => Some(&item.ident),
+            syn::Item::Static(item) => Some(&item.ident),
+            syn::Item::Struct(item) => Some(&item.ident),
+            syn::Item::Trait(item) => Some(&item.ident),
+            syn::Item::Type(item) => Some(&item.ident),
+            syn::Item::Union(item) => Some(&item.ident),
+            syn::Item::Use(item) => {
+                self.visit_use(&item.tree);
+                None
+            }
+            _ => None,
+        };
+        if let Some(ident) = ident {
+            self.0.insert(ident.to_string());
+        }
+        syn::visit::visit_item(self, item);
+    }
+
+    fn visit_type_param(&mut self, param: &'ast syn::TypeParam) {
+        self.0.insert(param.ident.to_string());
+        syn::visit::visit_type_param(self, param);
     }
 }
 
@@ -1509,6 +2709,110 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_extract_required_imports_from_syntax() {
+        let mut graph = CodeGenGraph::new();
+        graph.bootstrap_bloxide_types();
+
+        let code = r#"
+            // Mentions of Message in comments and strings don't count
+            impl MessageSet for SessionMessageSet {}
+
+            fn step(machine: &mut StateMachine<C>) -> Option<Vec<Transition<S, M>>> {
+                let _ = "Message";
+                let _ = bloxide_tokio::messaging::Message::new(0, ());
+                None
+            }
+        "#;
+        assert_eq!(
+            graph.extract_required_imports(code).unwrap(),
+            vec![
+                "bloxide_tokio::messaging::MessageSet",
+                "bloxide_tokio::state_machine::StateMachine",
+                "bloxide_tokio::state_machine::Transition",
+            ]
+        );
+        assert!(graph.extract_required_imports("impl {").is_err());
+
+        // Types the code imports, defines or takes as generic parameters are in scope already
+        let code = r#"
+            use bloxide_tokio::messaging::{MessageSet as Set, StandardPayload};
+
+            pub struct Transition;
+
+            fn step<StateMachine>(machine: StateMachine) -> Option<(Transition, Message<StandardPayload>)> {
+                None
+            }
+        "#;
+        assert_eq!(
+            graph.missing_imports(code).unwrap(),
+            vec!["bloxide_tokio::messaging::Message"]
+        );
+    }
+
+    #[test]
+    fn test_implements_edges_drive_imports() {
+        let mut graph = CodeGenGraph::new();
+        graph.add_generated_module("session::ext_state");
+        let type_idx =
+            graph.add_implementation("session::ext_state::SessionExtState", "serde::Serialize");
+        assert_eq!(
+            graph.add_implementation("session::ext_state::SessionExtState", "Clone"),
+            type_idx
+        );
+
+        assert!(
+            graph
+                .graph
+                .find_connected_nodes(type_idx)
+                .all(|entry| entry.relation() == Relation::Implements
+                    && entry.node().name() == "Serialize")
+        );
+        assert_eq!(
+            graph.generate_imports_for_module("session::ext_state"),
+            vec!["use serde::Serialize;"]
+        );
+    }
+
+    #[test]
+    fn test_generated_functions_drive_imports() {
+        let mut graph = CodeGenGraph::new();
+        graph.add_generated_function(
+            "session::pool",
+            "spawn_pool",
+            &["crate::session::component::SessionInbox".to_string()],
+        );
+        graph.add_generated_function("session::pool", "drain_pool", &[]);
+
+        assert_eq!(
+            graph.generated_functions("session::pool"),
+            vec!["spawn_pool", "drain_pool"]
+        );
+        assert!(graph.generated_functions("session::missing").is_empty());
+        assert_eq!(
+            graph.generate_imports_for_module("session::pool"),
+            vec!["use crate::session::component::SessionInbox;"]
+        );
+
+        let function = graph
+            .graph
+            .find_by_name("spawn_pool")
+            .into_iter()
+            .next()
+            .expect("Function should be in the graph");
+        assert!(matches!(function.node, Node::Function(_)));
+        let function_idx = function.index;
+        assert!(
+            graph
+                .graph
+                .find_connected_nodes(function_idx)
+                .any(|entry| entry.relation() == Relation::Uses
+                    && entry.node().name() == "SessionInbox")
+        );
+        // Functions are not modules, so they don't change what the parent declares
+        assert_eq!(graph.child_modules("session"), vec!["pool"]);
+    }
+
     #[test]
     fn test_add_dependency_by_path_creates_uses_relationships() {
         let mut graph = CodeGenGraph::new();
@@ -1630,6 +2934,22 @@ mod tests {
         println!("✅ Enhanced discovery methods create expected framework dependencies");
     }
 
+    #[test]
+    fn test_module_imports_without_codegen() {
+        // Resolving imports only needs the `graph` feature, not the generator
+        let mut graph = CodeGenGraph::new();
+        graph
+            .analyze_actor(&crate::tests::create_test_actor())
+            .expect("Analysis should succeed");
+        let imports = graph.generate_imports_for_module("actor::states");
+        assert!(
+            imports
+                .iter()
+                .any(|import| import.contains("crate::actor::component::ActorComponents")),
+            "{imports:?}"
+        );
+    }
+
     #[test]
     fn test_states_imports_actor_component() {
         let mut graph = CodeGenGraph::new();
@@ -1732,6 +3052,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_submodules_in_dependency_order() {
+        let mut graph = CodeGenGraph::new();
+        graph.add_generated_module("app::zeta");
+        graph.add_generated_module("app::alpha");
+        graph.add_generated_module("app::types");
+        graph.add_generated_module("app::nested::inner");
+
+        // alpha depends on zeta through a type, nested depends on alpha through a child module
+        graph.add_dependency_by_path("app::alpha", "crate::app::zeta::Zeta");
+        graph.add_dependency_by_path("app::nested::inner", "crate::app::alpha::Alpha");
+        graph.add_dependency_by_path("app::types", "std::fmt::Debug");
+
+        assert_eq!(
+            graph.submodules_in_dependency_order("app"),
+            vec!["types", "zeta", "alpha", "nested"]
+        );
+    }
+
+    #[test]
+    fn test_submodules_order_breaks_cycles_alphabetically() {
+        let mut graph = CodeGenGraph::new();
+        graph.add_dependency_by_path("app::b", "crate::app::a::A");
+        graph.add_dependency_by_path("app::a", "crate::app::b::B");
+        graph.add_generated_module("app::c");
+
+        assert_eq!(
+            graph.submodules_in_dependency_order("app"),
+            vec!["c", "a", "b"]
+        );
+        assert!(graph.submodules_in_dependency_order("missing").is_empty());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut graph = CodeGenGraph::new();
+        graph.add_dependency_by_path("app::states", "crate::app::messaging::AppMessageSet");
+
+        let export: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+        assert_eq!(export["version"], 1);
+        let nodes = export["nodes"].as_array().unwrap();
+        let id = |path: &str| {
+            nodes
+                .iter()
+                .find(|node| node["path"] == path)
+                .unwrap_or_else(|| panic!("{path} should be exported"))["id"]
+                .clone()
+        };
+        let message_set = nodes
+            .iter()
+            .find(|node| node["name"] == "AppMessageSet")
+            .unwrap();
+        assert_eq!(message_set["kind"], "type");
+        assert_eq!(message_set["path"], "crate::app::messaging::AppMessageSet");
+
+        let edges = export["edges"].as_array().unwrap();
+        assert!(edges.contains(&serde_json::json!({
+            "from": id("app::states"),
+            "to": id("crate::app::messaging::AppMessageSet"),
+            "relation": "uses",
+        })));
+        assert!(edges.contains(&serde_json::json!({
+            "from": id("app"),
+            "to": id("app::states"),
+            "relation": "contains",
+        })));
+    }
+
     #[test]
     fn test_runtime_imports_essential_types() {
         let mut graph = CodeGenGraph::new();
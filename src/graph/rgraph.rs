@@ -14,7 +14,7 @@ use super::{
 
 #[derive(Debug, Clone)]
 pub struct RustGraph {
-    pub graph: Graph<Node, Relation, Directed>,
+    pub(crate) graph: Graph<Node, Relation, Directed>,
 }
 
 impl Default for RustGraph {
@@ -61,7 +61,7 @@ impl RustGraph {
     }
 
     // Find nodes by exact name match (now using graph iteration - simpler!)
-    pub fn find_by_name(&self, name: &str) -> Vec<Entry> {
+    pub fn find_by_name(&self, name: &str) -> Vec<Entry<'_>> {
         self.graph
             .node_indices()
             .filter_map(|idx| {
@@ -76,7 +76,7 @@ impl RustGraph {
     }
 
     // Find nodes by partial name match (now using graph iteration - simpler!)
-    pub fn find_by_partial_name(&self, partial_name: &str) -> Vec<Entry> {
+    pub fn find_by_partial_name(&self, partial_name: &str) -> Vec<Entry<'_>> {
         self.graph
             .node_indices()
             .filter_map(|idx| {
@@ -91,7 +91,7 @@ impl RustGraph {
     }
 
     // Find nodes by type
-    pub fn find_by_type(&self, node_type: &str) -> Vec<Entry> {
+    pub fn find_by_type(&self, node_type: &str) -> Vec<Entry<'_>> {
         self.graph
             .node_indices()
             .filter_map(|idx| {
@@ -106,7 +106,7 @@ impl RustGraph {
     }
 
     // Find nodes by name pattern (case insensitive, now using graph iteration - simpler!)
-    pub fn find_by_pattern(&self, pattern: &str) -> impl Iterator<Item = Entry> {
+    pub fn find_by_pattern(&self, pattern: &str) -> impl Iterator<Item = Entry<'_>> {
         let pattern_lower = pattern.to_lowercase();
         self.graph.node_indices().filter_map(move |idx| {
             let node = &self.graph[idx];
@@ -119,7 +119,10 @@ impl RustGraph {
     }
 
     // Find connected nodes using petgraph's built-in neighbors
-    pub fn find_connected_nodes(&self, node_idx: NodeIndex) -> impl Iterator<Item = RelatedEntry> {
+    pub fn find_connected_nodes(
+        &self,
+        node_idx: NodeIndex,
+    ) -> impl Iterator<Item = RelatedEntry<'_>> {
         self.graph.neighbors(node_idx).map(move |neighbor_idx| {
             // Get the edge weight by finding the edge between these nodes
             let edge_ref = self
@@ -132,7 +135,7 @@ impl RustGraph {
     }
 
     // Find nodes that depend on this node using petgraph's neighbors_directed
-    pub fn find_dependents(&self, node_idx: NodeIndex) -> impl Iterator<Item = RelatedEntry> {
+    pub fn find_dependents(&self, node_idx: NodeIndex) -> impl Iterator<Item = RelatedEntry<'_>> {
         self.graph
             .neighbors_directed(node_idx, Direction::Incoming)
             .map(move |dependent_idx| {
@@ -22,6 +22,12 @@ pub(super) enum TypeContext {
     States,
     MessageSet,
     Runtime,
+    Pool,
+    Shard,
+    Bridge,
+    Sources,
+    Sinks,
+    Constants,
 }
 
 /// Information about a discovered type
@@ -0,0 +1,98 @@
+//! Rust identifiers, checked the way rustc lexes them
+
+use crate::alloc_prelude::*;
+
+/// Keywords of every edition since 2018, including the reserved ones, which can only be used
+/// as raw identifiers
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Keywords that can't be raw identifiers either
+const PATH_KEYWORDS: &[&str] = &["crate", "self", "Self", "super", "_"];
+
+/// Whether `name` is a Rust identifier
+///
+/// Like rustc, it accepts an `XID_Start` character or `_` followed by `XID_Continue`
+/// characters, except `_` alone and keywords. Keywords can be written as raw identifiers,
+/// like `r#type`.
+pub fn is_ident(name: &str) -> bool {
+    let (name, raw) = match name.strip_prefix("r#") {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    let mut chars = name.chars();
+    let starts = chars
+        .next()
+        .is_some_and(|c| c == '_' || unicode_ident::is_xid_start(c));
+    if !starts || !chars.all(unicode_ident::is_xid_continue) || name == "_" {
+        return false;
+    }
+    if raw {
+        !PATH_KEYWORDS.contains(&name)
+    } else {
+        !KEYWORDS.contains(&name)
+    }
+}
+
+/// Whether `name` can name a module declared with `mod`
+///
+/// rustc only accepts non-ASCII module names with a `#[path]` attribute (E0754), so module
+/// names are the ASCII identifiers.
+pub fn is_module_ident(name: &str) -> bool {
+    name.is_ascii() && is_ident(name)
+}
+
+/// Whether `c` can be part of an identifier, for finding whole words in code
+pub(crate) fn is_ident_char(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
+/// `snake_case` form of a `CamelCase` identifier, like `custom_value1` for `CustomValue1`
+pub(crate) fn snake_case(ident: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in ident.char_indices() {
+        if c.is_uppercase() {
+            if i > 0 && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ident() {
+        for name in [
+            "Session", "_private", "_1", "état", "Größe", "数据", "r#type", "ẞ",
+        ] {
+            assert!(is_ident(name), "{name} should be an identifier");
+        }
+        for name in [
+            "", "_", "1st", "a-b", "a b", "²", "x²", "café☕", "type", "dyn", "gen", "r#self",
+            "r#", "r#_",
+        ] {
+            assert!(!is_ident(name), "{name} should not be an identifier");
+        }
+        assert!(is_module_ident("session"));
+        assert!(!is_module_ident("überlauf"));
+        assert!(!is_module_ident("type"));
+    }
+
+    #[test]
+    fn test_snake_case() {
+        assert_eq!(snake_case("CustomValue1"), "custom_value1");
+        assert_eq!(snake_case("Über"), "über");
+    }
+}
@@ -1,15 +1,51 @@
+//! Spec model for bloxide actors and the generator turning specs into Rust modules
+//!
+//! The types most users need are re-exported from [`prelude`].
+//!
+//! The spec model, its validation and lints are always available and only need `alloc`, so
+//! they can be embedded in `no_std` tooling and wasm hosts. The rest is behind features, all
+//! enabled by default through `cli`:
+//!
+//! - `std`: loading specs, mixins and environment overlays from JSON files
+//! - `graph`: the module and type graph imports are resolved from, and the [`eval`] sandbox
+//!   evaluating spec expressions without compiling them
+//! - `codegen`: rendering and writing actor modules
+//! - `cli`: the `bloxml` binary
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "codegen")]
+pub(crate) mod analysis;
 pub mod blox;
+#[cfg(feature = "codegen")]
 pub mod create;
+pub mod diagnostics;
+#[cfg(feature = "graph")]
+pub mod eval;
 pub mod field;
+#[cfg(feature = "graph")]
 pub mod graph;
+pub mod ident;
 pub mod link;
+pub mod lint;
 pub mod method;
-pub use blox::*;
+pub mod prelude;
+pub mod rename;
+pub(crate) use blox::*;
 
 pub use field::Field;
 pub use link::Link;
 pub use method::Method;
 
+/// The `alloc` types the `std` prelude would otherwise bring into scope
+mod alloc_prelude {
+    pub use alloc::format;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use crate::{
@@ -22,11 +58,14 @@ pub(crate) mod tests {
         message_set::MessageSet,
         state::{State, StateEnum, States},
     };
+    #[cfg(feature = "std")]
     use pretty_assertions::assert_eq;
 
+    #[cfg(feature = "std")]
     use std::fs;
 
     const TEST_OUTPUT_DIR: &str = "tests/output";
+    #[cfg(feature = "std")]
     const TEST_FILE: &str = "tests/test_file.json";
 
     pub fn create_test_actor() -> Actor {
@@ -106,6 +145,7 @@ pub(crate) mod tests {
         receivers
     }
 
+    #[cfg(feature = "std")]
     #[expect(dead_code)]
     fn serialize_actor() {
         let test_actor = create_test_actor();
@@ -115,6 +155,16 @@ pub(crate) mod tests {
     }
 
     #[test]
+    fn test_model_without_std() {
+        // Building, validating and linting a spec only needs the always-available model
+        let actor = create_test_actor();
+        assert_eq!(actor.component.validate(), Ok(()));
+        assert_eq!(actor.validate_idents(), Ok(()));
+        assert!(!crate::lint::lint(&actor).iter().any(|d| d.is_error()));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
     fn deserialize_test_file() {
         let contents = fs::read_to_string(TEST_FILE).expect("Failed to read test file");
         let actor: Actor = serde_json::from_str(&contents).expect("Failed to deserialize JSON");
@@ -124,6 +174,7 @@ pub(crate) mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn sanity_test() {
         let expected = create_test_actor();
 
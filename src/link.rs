@@ -1,7 +1,48 @@
-use std::fmt::{self, Display};
+use core::fmt::{self, Display};
 
 use serde::{Deserialize, Serialize};
 
+use crate::alloc_prelude::*;
+use crate::ident::is_ident;
+
+/// Types in scope everywhere, which never need importing
+pub(crate) const PRELUDE_TYPES: &[&str] = &[
+    "String", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128",
+    "usize", "f32", "f64", "bool", "char", "str", "Vec", "Option", "Result", "Box", "Arc", "Rc",
+];
+
+/// Names of the non-prelude types a type string mentions, e.g. `Foo` and `Bar` in
+/// `Vec<a::Foo<Bar>>`
+pub(crate) fn type_names(type_string: &str) -> Vec<String> {
+    let mut types = Vec::new();
+    let delimiters = ['<', '>', ',', ' ', '(', ')', '[', ']'];
+
+    let parts = type_string
+        .split(&delimiters[..])
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty());
+
+    for part in parts {
+        // Skip builtin types
+        if PRELUDE_TYPES.contains(&part) {
+            continue;
+        }
+
+        if part.contains("::") {
+            // Extract the final type name from qualified paths
+            if let Some(type_name) = part.split("::").last()
+                && is_ident(type_name)
+            {
+                types.push(type_name.to_string());
+            }
+        } else if is_ident(part) {
+            types.push(part.to_string());
+        }
+    }
+
+    types
+}
+
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
 #[serde(rename = "link")]
 pub struct Link(String);
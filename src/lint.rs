@@ -0,0 +1,625 @@
+//! Style and consistency lints over the spec, reported by `bloxml check` and fixed by
+//! `bloxml fix`
+
+use serde::{Deserialize, Serialize};
+
+use crate::alloc_prelude::*;
+use crate::blox::actor::Actor;
+use crate::blox::state::State;
+use crate::diagnostics::Diagnostic;
+use crate::ident::is_ident_char;
+use crate::link;
+use crate::rename::{RenameTarget, rename};
+
+/// How findings of a lint are reported
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum LintLevel {
+    /// Not reported
+    Allow,
+    /// Reported as warnings
+    Warn,
+    /// Reported as errors, failing the check
+    Deny,
+}
+
+/// A check over the spec, reported at its default level unless the spec's `lints` overrides it
+pub struct Lint {
+    /// Name used in the `lints` section and as the diagnostic code
+    pub name: &'static str,
+    pub description: &'static str,
+    pub default_level: LintLevel,
+    check: fn(&Actor) -> Vec<String>,
+    /// Rewrites the spec to resolve the lint's findings, describing each change
+    fix: Option<fn(&mut Actor) -> Vec<String>>,
+}
+
+impl Lint {
+    /// Whether `bloxml fix` can resolve the lint's findings
+    pub fn is_fixable(&self) -> bool {
+        self.fix.is_some()
+    }
+}
+
+/// A change `fix` made to the spec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    /// Lint whose finding the change resolves
+    pub lint: &'static str,
+    pub description: String,
+}
+
+/// Every lint `bloxml check` runs
+pub const LINTS: &[Lint] = &[
+    Lint {
+        name: "missing-initial-state",
+        description: "the component should declare a state for the runtime to start in",
+        default_level: LintLevel::Deny,
+        check: missing_initial_state,
+        fix: Some(fix_missing_initial_state),
+    },
+    Lint {
+        name: "state-name-case",
+        description: "state names should be PascalCase",
+        default_level: LintLevel::Warn,
+        check: state_name_case,
+        fix: Some(fix_state_name_case),
+    },
+    Lint {
+        name: "missing-variant-doc",
+        description: "message variants carrying a payload should document it",
+        default_level: LintLevel::Allow,
+        check: missing_variant_doc,
+        fix: None,
+    },
+    Lint {
+        name: "unread-ext-state-field",
+        description: "extended state fields should be read by a declared method",
+        default_level: LintLevel::Warn,
+        check: unread_ext_state_field,
+        fix: None,
+    },
+    Lint {
+        name: "unused-handle",
+        description: "handles should be emitted through by some transition",
+        default_level: LintLevel::Warn,
+        check: unused_handle,
+        fix: Some(fix_unused_handle),
+    },
+    Lint {
+        name: "unhandled-receiver",
+        description: "the variant a receiver delivers should trigger some transition",
+        default_level: LintLevel::Warn,
+        check: unhandled_receiver,
+        fix: None,
+    },
+    Lint {
+        name: "unused-custom-type",
+        description: "custom types should be carried by some variant",
+        default_level: LintLevel::Warn,
+        check: unused_custom_type,
+        fix: None,
+    },
+    Lint {
+        name: "deprecated-reference",
+        description: "transitions should not use deprecated variants or enter deprecated states",
+        default_level: LintLevel::Warn,
+        check: deprecated_reference,
+        fix: None,
+    },
+];
+
+/// Runs every lint over the spec at the levels its `lints` section configures
+pub fn lint(actor: &Actor) -> Vec<Diagnostic> {
+    let mut diagnostics = actor
+        .lints
+        .keys()
+        .filter(|name| !LINTS.iter().any(|lint| lint.name == name.as_str()))
+        .map(|name| Diagnostic::warning("unknown-lint", format!("Unknown lint '{name}'")))
+        .collect::<Vec<_>>();
+
+    for lint in LINTS {
+        let level = actor
+            .lints
+            .get(lint.name)
+            .copied()
+            .unwrap_or(lint.default_level);
+        let report = match level {
+            LintLevel::Allow => continue,
+            LintLevel::Warn => Diagnostic::warning,
+            LintLevel::Deny => Diagnostic::error,
+        };
+        diagnostics.extend(
+            (lint.check)(actor)
+                .into_iter()
+                .map(|message| report(lint.name, message)),
+        );
+    }
+    diagnostics
+}
+
+/// Applies the fixes of every lint the spec does not allow, returning the changes made
+pub fn fix(actor: &mut Actor) -> Vec<Fix> {
+    let mut fixes = Vec::new();
+    for lint in LINTS {
+        let Some(fix) = lint.fix else {
+            continue;
+        };
+        if actor
+            .lints
+            .get(lint.name)
+            .copied()
+            .unwrap_or(lint.default_level)
+            == LintLevel::Allow
+        {
+            continue;
+        }
+        fixes.extend(fix(actor).into_iter().map(|description| Fix {
+            lint: lint.name,
+            description,
+        }));
+    }
+    fixes
+}
+
+/// State the runtime starts in when `fix` adds one to a spec without states
+const INITIAL_STATE: &str = "Idle";
+
+fn missing_initial_state(actor: &Actor) -> Vec<String> {
+    actor
+        .component
+        .states
+        .states
+        .is_empty()
+        .then(|| "The component declares no states, so the runtime has none to start in".into())
+        .into_iter()
+        .collect()
+}
+
+fn fix_missing_initial_state(actor: &mut Actor) -> Vec<String> {
+    let states = &mut actor.component.states.states;
+    states
+        .is_empty()
+        .then(|| {
+            states.push(State::from(INITIAL_STATE));
+            format!("Added initial state '{INITIAL_STATE}'")
+        })
+        .into_iter()
+        .collect()
+}
+
+fn is_pascal_case(ident: &str) -> bool {
+    ident.starts_with(|c: char| c.is_ascii_uppercase())
+        && ident.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// `ident` in PascalCase, like `ShuttingDown` for `shutting_down`
+fn pascal_case(ident: &str) -> String {
+    ident
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
+
+fn state_name_case(actor: &Actor) -> Vec<String> {
+    actor
+        .component
+        .states
+        .states
+        .iter()
+        .filter(|state| !is_pascal_case(&state.ident))
+        .map(|state| format!("State '{}' is not PascalCase", state.ident))
+        .collect()
+}
+
+fn fix_state_name_case(actor: &mut Actor) -> Vec<String> {
+    let misnamed = actor
+        .component
+        .states
+        .states
+        .iter()
+        .map(|state| state.ident.clone())
+        .filter(|ident| !is_pascal_case(ident))
+        .collect::<Vec<_>>();
+    // Names that would clash with another state or still not be PascalCase are left to rename
+    // by hand
+    misnamed
+        .into_iter()
+        .filter_map(|old| {
+            let new = pascal_case(&old);
+            (is_pascal_case(&new) && rename(actor, RenameTarget::State, &old, &new).is_ok())
+                .then(|| format!("Renamed state '{old}' to '{new}'"))
+        })
+        .collect()
+}
+
+fn missing_variant_doc(actor: &Actor) -> Vec<String> {
+    let Some(message_set) = &actor.component.message_set else {
+        return Vec::new();
+    };
+    let message_set = message_set.get();
+    message_set
+        .variants
+        .iter()
+        .filter(|variant| !variant.args.is_empty() && variant.doc.is_none())
+        .map(|variant| {
+            format!(
+                "Variant '{}::{}' carries a payload but has no doc",
+                message_set.ident, variant.ident
+            )
+        })
+        .collect()
+}
+
+fn unread_ext_state_field(actor: &Actor) -> Vec<String> {
+    let ext_state = &actor.component.ext_state;
+    let bodies = ext_state
+        .methods()
+        .iter()
+        .chain(
+            actor
+                .component
+                .states
+                .states
+                .iter()
+                .flat_map(|s| &s.methods),
+        )
+        .map(|method| method.body())
+        .collect::<Vec<_>>();
+
+    ext_state
+        .fields()
+        .iter()
+        .filter(|field| !bodies.iter().any(|body| mentions(body, field.ident())))
+        .map(|field| {
+            format!(
+                "Field '{}' of '{}' is never read by a declared method",
+                field.ident(),
+                ext_state.ident()
+            )
+        })
+        .collect()
+}
+
+/// Handles no transition emits and no relay sends through
+fn unused_handles(actor: &Actor) -> Vec<String> {
+    let component = &actor.component;
+    let emits = component
+        .states
+        .states
+        .iter()
+        .flat_map(|state| &state.transitions)
+        .flat_map(|transition| &transition.emit)
+        .map(|emit| &emit.handle);
+    let used = emits.chain(actor.relays.iter().map(|relay| &relay.handle));
+
+    component
+        .message_handles
+        .handles
+        .iter()
+        .filter(|handle| !used.clone().any(|used| *used == handle.ident))
+        .map(|handle| handle.ident.clone())
+        .collect()
+}
+
+fn unused_handle(actor: &Actor) -> Vec<String> {
+    unused_handles(actor)
+        .into_iter()
+        .map(|handle| format!("Handle '{handle}' is never emitted through"))
+        .collect()
+}
+
+fn fix_unused_handle(actor: &mut Actor) -> Vec<String> {
+    let unused = unused_handles(actor);
+    actor
+        .component
+        .message_handles
+        .handles
+        .retain(|handle| !unused.contains(&handle.ident));
+    unused
+        .into_iter()
+        .map(|handle| format!("Removed handle '{handle}'"))
+        .collect()
+}
+
+fn unhandled_receiver(actor: &Actor) -> Vec<String> {
+    let component = &actor.component;
+    let Some(message_set) = &component.message_set else {
+        return Vec::new();
+    };
+    let transitions = component
+        .states
+        .states
+        .iter()
+        .flat_map(|state| &state.transitions);
+
+    // Receivers deliver the variant at their own position in the message set
+    component
+        .message_receivers
+        .receivers
+        .iter()
+        .zip(&message_set.get().variants)
+        .filter(|(_, variant)| !transitions.clone().any(|t| t.on == variant.ident))
+        .map(|(receiver, variant)| {
+            format!(
+                "Receiver '{}' delivers '{}', which no state transitions on",
+                receiver.ident, variant.ident
+            )
+        })
+        .collect()
+}
+
+fn unused_custom_type(actor: &Actor) -> Vec<String> {
+    let Some(message_set) = &actor.component.message_set else {
+        return Vec::new();
+    };
+    let referenced = |name: &str| {
+        core::iter::once(message_set.get())
+            .chain(message_set.custom_types.iter().filter(|c| c.ident != name))
+            .flat_map(|enum_def| &enum_def.variants)
+            .flat_map(|variant| &variant.args)
+            .any(|arg| link::type_names(arg.as_ref()).iter().any(|t| t == name))
+    };
+
+    message_set
+        .custom_types
+        .iter()
+        .filter(|custom| !referenced(&custom.ident))
+        .map(|custom| {
+            format!(
+                "Custom type '{}' is not carried by any variant",
+                custom.ident
+            )
+        })
+        .collect()
+}
+
+fn deprecated_reference(actor: &Actor) -> Vec<String> {
+    let component = &actor.component;
+    let states = &component.states.states;
+    let variants = component
+        .message_set
+        .iter()
+        .flat_map(|set| &set.get().variants);
+    let deprecated_variant = |ident: &str| {
+        variants
+            .clone()
+            .find(|v| v.ident == ident)
+            .and_then(|v| v.deprecated.as_deref())
+    };
+    let deprecated_state = |ident: &str| {
+        states
+            .iter()
+            .find(|s| s.ident == ident)
+            .and_then(|s| s.deprecated.as_deref())
+    };
+
+    // Deprecated states keep their transitions until they are removed along with them
+    let mut findings = Vec::new();
+    for state in states.iter().filter(|s| s.deprecated.is_none()) {
+        for transition in &state.transitions {
+            if let Some(note) = deprecated_variant(&transition.on) {
+                findings.push(format!(
+                    "State '{}' transitions on deprecated variant '{}': {note}",
+                    state.ident, transition.on
+                ));
+            }
+            if let Some(note) = deprecated_state(&transition.target) {
+                findings.push(format!(
+                    "State '{}' transitions to deprecated state '{}': {note}",
+                    state.ident, transition.target
+                ));
+            }
+        }
+    }
+    findings
+}
+
+/// Whether `code` mentions `ident` as a whole word
+pub(crate) fn mentions(code: &str, ident: &str) -> bool {
+    code.match_indices(ident).any(|(start, _)| {
+        let before = code[..start].chars().next_back();
+        let after = code[start + ident.len()..].chars().next();
+        !before.is_some_and(is_ident_char) && !after.is_some_and(is_ident_char)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blox::enums::{EnumDef, EnumVariant};
+    use crate::blox::ext_state::ExtState;
+    use crate::blox::state::{Emit, State, Transition};
+    use crate::diagnostics::Severity;
+    use crate::tests::create_test_actor;
+    use crate::{Field, Link, Method};
+
+    /// The test actor declares messaging but no behavior, so drift lints would fire everywhere
+    fn without_drift_lints(actor: Actor) -> Actor {
+        ["unused-handle", "unhandled-receiver", "unused-custom-type"]
+            .into_iter()
+            .fold(actor, |actor, name| actor.with_lint(name, LintLevel::Allow))
+    }
+
+    #[test]
+    fn test_default_levels() {
+        let mut actor = without_drift_lints(create_test_actor());
+        actor
+            .component
+            .states
+            .states
+            .push(State::from("shutting_down"));
+        let diagnostics = lint(&actor);
+
+        let codes = diagnostics
+            .iter()
+            .map(|d| d.code.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            vec![
+                "state-name-case",
+                "unread-ext-state-field",
+                "unread-ext-state-field"
+            ]
+        );
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning));
+        assert_eq!(
+            diagnostics[0].message,
+            "State 'shutting_down' is not PascalCase"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "Field 'field1' of 'ActorExtState' is never read by a declared method"
+        );
+    }
+
+    #[test]
+    fn test_configured_levels() {
+        let actor = without_drift_lints(create_test_actor())
+            .with_lint("unread-ext-state-field", LintLevel::Allow)
+            .with_lint("missing-variant-doc", LintLevel::Deny)
+            .with_lint("no-such-lint", LintLevel::Deny);
+        let diagnostics = lint(&actor);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].code, "unknown-lint");
+        assert!(!diagnostics[0].is_error());
+        assert!(
+            diagnostics[1..]
+                .iter()
+                .all(|d| d.code == "missing-variant-doc" && d.is_error())
+        );
+        assert_eq!(
+            diagnostics[2].message,
+            "Variant 'ActorMessageSet::CustomValue2' carries a payload but has no doc"
+        );
+    }
+
+    #[test]
+    fn test_fields_read_by_methods() {
+        let mut actor = without_drift_lints(create_test_actor());
+        actor.component.ext_state = ExtState::new(
+            "ActorExtState",
+            vec![Field::new("count", "u32"), Field::new("count_max", "u32")],
+            vec![Method::new("bump", &[], "", "self.count += 1")],
+            Default::default(),
+        );
+        let diagnostics = lint(&actor);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("'count_max'"));
+        assert!(mentions("self.count += 1", "count"));
+        assert!(!mentions("self.count_max", "count"));
+    }
+
+    #[test]
+    fn test_drift_between_messaging_and_behavior() {
+        let mut actor = create_test_actor().with_lint("unread-ext-state-field", LintLevel::Allow);
+        actor.component.states.states[0] = State::from("Create").with_transitions(vec![
+            Transition::new("CustomValue2", "Update")
+                .with_emit(vec![Emit::new("customargs_handle", "message.clone()")]),
+        ]);
+        let message_set = actor.component.message_set.as_mut().unwrap();
+        message_set.custom_types = vec![
+            EnumDef::new("Orphan", vec![]),
+            EnumDef::new("Nested", vec![]),
+            EnumDef::new(
+                "Carried",
+                vec![EnumVariant::new("A", vec![Link::new("Nested")])],
+            ),
+        ];
+        message_set.def.variants[1].args = vec![Link::new("Option<Carried>")];
+
+        let messages = lint(&actor)
+            .into_iter()
+            .map(|d| d.message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "Handle 'standard_handle' is never emitted through",
+                "Receiver 'standard_rx' delivers 'CustomValue1', which no state transitions on",
+                "Custom type 'Orphan' is not carried by any variant",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fixes() {
+        let mut actor = create_test_actor();
+        let states = &mut actor.component.states.states;
+        states[0] = State::from("Create").with_transitions(vec![
+            Transition::new("CustomValue1", "shutting_down")
+                .with_emit(vec![Emit::new("customargs_handle", "message.clone()")]),
+        ]);
+        states.push(State::from("shutting_down"));
+        states.push(State::from("create"));
+
+        let fixes = fix(&mut actor)
+            .into_iter()
+            .map(|fix| format!("{}: {}", fix.lint, fix.description))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fixes,
+            vec![
+                "state-name-case: Renamed state 'shutting_down' to 'ShuttingDown'",
+                "unused-handle: Removed handle 'standard_handle'",
+            ]
+        );
+        let states = &actor.component.states.states;
+        assert_eq!(states[0].transitions[0].target, "ShuttingDown");
+        // Renaming `create` would clash with `Create`
+        assert_eq!(states[3].ident, "create");
+        assert_eq!(actor.component.message_handles.handles.len(), 1);
+        assert!(fix(&mut actor).is_empty());
+
+        let mut actor = create_test_actor().with_lint("unused-handle", LintLevel::Allow);
+        actor.component.states.states.clear();
+        assert!(
+            lint(&actor)
+                .iter()
+                .any(|d| d.code == "missing-initial-state" && d.is_error())
+        );
+        let fixes = fix(&mut actor);
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].description, "Added initial state 'Idle'");
+        assert_eq!(actor.component.states.states[0].ident, "Idle");
+        assert_eq!(actor.component.message_handles.handles.len(), 2);
+        assert!(LINTS.iter().any(Lint::is_fixable));
+    }
+
+    #[test]
+    fn test_deprecated_references() {
+        let mut actor = without_drift_lints(create_test_actor())
+            .with_lint("unread-ext-state-field", LintLevel::Allow);
+        let states = &mut actor.component.states.states;
+        states[0] =
+            State::from("Create").with_transitions(vec![Transition::new("CustomValue1", "Update")]);
+        states[1] = State::new("Update", Some("Create".to_string()), None)
+            .deprecated("merged into Create")
+            .with_transitions(vec![Transition::new("CustomValue1", "Create")]);
+        let message_set = actor.component.message_set.as_mut().unwrap();
+        message_set.def.variants[0].deprecated = Some("send CustomValue2".to_string());
+
+        let messages = lint(&actor)
+            .into_iter()
+            .map(|d| d.message)
+            .collect::<Vec<_>>();
+        // The deprecated state's own transitions go away with it
+        assert_eq!(
+            messages,
+            vec![
+                "State 'Create' transitions on deprecated variant 'CustomValue1': send CustomValue2",
+                "State 'Create' transitions to deprecated state 'Update': merged into Create",
+            ]
+        );
+    }
+}
@@ -1,10 +1,10 @@
-use crate::{
-    Field, Link,
-    create::{ActorGenerator, ToRust},
-};
+use crate::alloc_prelude::*;
+#[cfg(feature = "codegen")]
+use crate::create::{RenderCtx, RenderError, ToRust};
+use crate::{Field, Link};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
 pub struct Method {
     ident: String,
     #[serde(default)]
@@ -42,10 +42,15 @@ impl Method {
     pub fn body(&self) -> &str {
         &self.body
     }
+
+    pub(crate) fn body_mut(&mut self) -> &mut String {
+        &mut self.body
+    }
 }
 
+#[cfg(feature = "codegen")]
 impl ToRust for Method {
-    fn to_rust(&self, _generator: &ActorGenerator) -> String {
+    fn to_source(&self, _ctx: &RenderCtx) -> Result<String, RenderError> {
         let args = self
             .args
             .iter()
@@ -62,13 +67,13 @@ impl ToRust for Method {
             format!(" -> {}", self.ret)
         };
 
-        format!(
+        Ok(format!(
             r#"pub fn {ident}({args}){ret} {{
         {body}
     }}
     "#,
             ident = self.ident,
             body = self.body,
-        )
+        ))
     }
 }
@@ -0,0 +1,19 @@
+//! The types most users of the crate need, brought into scope with `use bloxml::prelude::*`
+
+pub use crate::blox::actor::Actor;
+pub use crate::blox::component::Component;
+pub use crate::blox::enums::{EnumDef, EnumVariant};
+pub use crate::blox::ext_state::{ExtState, InitArgs};
+pub use crate::blox::message_handlers::{
+    MessageHandle, MessageHandles, MessageReceiver, MessageReceivers, WireFormat,
+};
+pub use crate::blox::message_set::MessageSet;
+pub use crate::blox::state::{Emit, State, StateEnum, States, Transition};
+pub use crate::diagnostics::{Diagnostic, Severity};
+pub use crate::lint::{LintLevel, lint};
+pub use crate::{Field, Link, Method};
+
+#[cfg(feature = "codegen")]
+pub use crate::create::{
+    ActorGenerator, Edition, GenerationReport, GeneratorOptions, RenderError, RustVersion,
+};
@@ -0,0 +1,314 @@
+//! Renaming spec items along with every reference to them, used by `bloxml rename`
+
+use core::fmt;
+
+use crate::alloc_prelude::*;
+use crate::blox::actor::Actor;
+use crate::blox::bridge::Bridge;
+use crate::blox::lifecycle::PanicPolicy;
+use crate::ident::{is_ident, is_ident_char};
+
+/// Kind of spec item being renamed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameTarget {
+    /// A state, with the parents and transition targets naming it
+    State,
+    /// A message set variant, with the transitions, schedules, sinks, relays, child forwards and
+    /// bridge routes sending it
+    Variant,
+    /// An extended state field, with its init arg, the `self.` accesses in ext state methods and
+    /// the `ext.` accesses in state invariants
+    Field,
+    /// A message handle, with the transitions emitting and the relays sending through it
+    Handle,
+}
+
+impl RenameTarget {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenameTarget::State => "state",
+            RenameTarget::Variant => "variant",
+            RenameTarget::Field => "field",
+            RenameTarget::Handle => "handle",
+        }
+    }
+}
+
+impl fmt::Display for RenameTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Renames `old` to `new` throughout the spec, returning how many places changed
+pub fn rename(
+    actor: &mut Actor,
+    target: RenameTarget,
+    old: &str,
+    new: &str,
+) -> Result<usize, String> {
+    if !is_ident(new) {
+        return Err(format!("'{new}' is not a valid identifier"));
+    }
+    let idents = idents(actor, target);
+    if !idents.contains(&old) {
+        return Err(format!("No {target} named '{old}'"));
+    }
+    if old != new && idents.contains(&new) {
+        return Err(format!("A {target} named '{new}' already exists"));
+    }
+
+    let mut renamed = 0;
+    let mut replace = |name: &mut String| {
+        if name == old {
+            *name = new.to_string();
+            renamed += 1;
+        }
+    };
+    let component = &mut actor.component;
+    match target {
+        RenameTarget::State => {
+            for state in &mut component.states.states {
+                replace(&mut state.ident);
+                state.parent.iter_mut().for_each(&mut replace);
+                for transition in &mut state.transitions {
+                    replace(&mut transition.target);
+                }
+            }
+            for variant in &mut component.states.state_enum.0.variants {
+                replace(&mut variant.ident);
+            }
+            if let PanicPolicy::Recover { state } = &mut actor.lifecycle.on_panic {
+                replace(state);
+            }
+        }
+        RenameTarget::Variant => {
+            if let Some(message_set) = &mut component.message_set {
+                for variant in &mut message_set.def.variants {
+                    replace(&mut variant.ident);
+                }
+            }
+            for state in &mut component.states.states {
+                for transition in &mut state.transitions {
+                    replace(&mut transition.on);
+                }
+            }
+            for schedule in &mut actor.schedules {
+                replace(&mut schedule.variant);
+            }
+            for sink in &mut actor.sinks {
+                replace(&mut sink.variant);
+            }
+            for relay in &mut actor.relays {
+                replace(&mut relay.variant);
+            }
+            for forward in actor.children.iter_mut().flat_map(|c| &mut c.forward) {
+                replace(&mut forward.variant);
+            }
+            match &mut actor.bridge {
+                Some(Bridge::Grpc(grpc)) => grpc
+                    .rpcs
+                    .iter_mut()
+                    .for_each(|rpc| replace(&mut rpc.variant)),
+                Some(Bridge::Http(http)) => http
+                    .routes
+                    .iter_mut()
+                    .for_each(|route| replace(&mut route.variant)),
+                None => {}
+            }
+        }
+        RenameTarget::Field => {
+            renamed += component.ext_state.rename_field(old, new);
+            let (old, new) = (format!("ext.{old}"), format!("ext.{new}"));
+            for invariant in component
+                .states
+                .states
+                .iter_mut()
+                .flat_map(|state| &mut state.invariants)
+            {
+                renamed += replace_word(invariant, &old, &new);
+            }
+        }
+        RenameTarget::Handle => {
+            for handle in &mut component.message_handles.handles {
+                replace(&mut handle.ident);
+            }
+            for state in &mut component.states.states {
+                for emit in state.transitions.iter_mut().flat_map(|t| &mut t.emit) {
+                    replace(&mut emit.handle);
+                }
+            }
+            for relay in &mut actor.relays {
+                replace(&mut relay.handle);
+            }
+        }
+    }
+    Ok(renamed)
+}
+
+/// Names already taken by items of the target's kind
+fn idents(actor: &Actor, target: RenameTarget) -> Vec<&str> {
+    let component = &actor.component;
+    match target {
+        RenameTarget::State => component
+            .states
+            .states
+            .iter()
+            .map(|s| s.ident.as_str())
+            .collect(),
+        RenameTarget::Variant => component
+            .message_set
+            .iter()
+            .flat_map(|set| &set.def.variants)
+            .map(|v| v.ident.as_str())
+            .collect(),
+        RenameTarget::Field => component
+            .ext_state
+            .fields()
+            .iter()
+            .map(|f| f.ident())
+            .collect(),
+        RenameTarget::Handle => component
+            .message_handles
+            .handles
+            .iter()
+            .map(|h| h.ident.as_str())
+            .collect(),
+    }
+}
+
+/// Replaces whole-word occurrences of `from` in `code`, returning how many were replaced
+pub(crate) fn replace_word(code: &mut String, from: &str, to: &str) -> usize {
+    let original = core::mem::take(code);
+    let mut replaced = 0;
+    let mut copied = 0;
+    for (start, _) in original.match_indices(from) {
+        let before = original[..start].chars().next_back();
+        let after = original[start + from.len()..].chars().next();
+        if !before.is_some_and(is_ident_char) && !after.is_some_and(is_ident_char) {
+            code.push_str(&original[copied..start]);
+            code.push_str(to);
+            copied = start + from.len();
+            replaced += 1;
+        }
+    }
+    code.push_str(&original[copied..]);
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blox::ext_state::{ExtState, InitArgs};
+    use crate::blox::message_handlers::Relay;
+    use crate::blox::schedule::Schedule;
+    use crate::blox::state::{Emit, State, Transition};
+    use crate::tests::create_test_actor;
+    use crate::{Field, Method};
+
+    fn actor_with_behavior() -> Actor {
+        let mut actor = create_test_actor();
+        actor.component.states.states = vec![
+            State::from("Create").with_transitions(vec![
+                Transition::new("CustomValue1", "Update")
+                    .with_emit(vec![Emit::new("standard_handle", "message.clone()")]),
+            ]),
+            State::new("Update", Some("Create".to_string()), None)
+                .with_transitions(vec![Transition::new("CustomValue2", "Update")]),
+        ];
+        actor
+            .schedules
+            .push(Schedule::new("@hourly", "CustomValue1"));
+        actor
+            .relays
+            .push(Relay::new("CustomValue1", "standard_handle"));
+        actor
+    }
+
+    #[test]
+    fn test_rename_state() {
+        let mut actor = actor_with_behavior();
+        assert_eq!(
+            rename(&mut actor, RenameTarget::State, "Update", "Refresh"),
+            Ok(3)
+        );
+        let states = &actor.component.states.states;
+        assert_eq!(states[0].transitions[0].target, "Refresh");
+        assert_eq!(states[1].ident, "Refresh");
+        assert_eq!(states[1].transitions[0].target, "Refresh");
+
+        assert_eq!(
+            rename(&mut actor, RenameTarget::State, "Create", "Refresh"),
+            Err("A state named 'Refresh' already exists".to_string())
+        );
+        assert_eq!(
+            rename(&mut actor, RenameTarget::State, "Update", "Stale"),
+            Err("No state named 'Update'".to_string())
+        );
+        assert_eq!(
+            rename(&mut actor, RenameTarget::State, "Create", "not valid"),
+            Err("'not valid' is not a valid identifier".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rename_variant_and_handle() {
+        let mut actor = actor_with_behavior();
+        assert_eq!(
+            rename(&mut actor, RenameTarget::Variant, "CustomValue1", "Tick"),
+            Ok(4)
+        );
+        assert_eq!(actor.component.states.states[0].transitions[0].on, "Tick");
+        assert_eq!(actor.schedules[0].variant, "Tick");
+        assert_eq!(actor.relays[0].variant, "Tick");
+
+        assert_eq!(
+            rename(&mut actor, RenameTarget::Handle, "standard_handle", "out"),
+            Ok(3)
+        );
+        assert_eq!(actor.relays[0].handle, "out");
+        assert_eq!(
+            actor.component.states.states[0].transitions[0].emit[0].handle,
+            "out"
+        );
+    }
+
+    #[test]
+    fn test_rename_field() {
+        let mut actor = create_test_actor();
+        actor.component.ext_state = ExtState::new(
+            "ActorExtState",
+            vec![Field::new("field1", "String"), Field::new("count", "u32")],
+            vec![Method::new(
+                "bump",
+                &[],
+                "",
+                "let count = 1; self.count += count; self.count_max = self.count",
+            )],
+            InitArgs::new("ActorInitArgs", vec![Field::new("field1", "String")]),
+        );
+
+        // `field1` is also an init arg
+        assert_eq!(
+            rename(&mut actor, RenameTarget::Field, "field1", "name"),
+            Ok(2)
+        );
+        assert_eq!(
+            rename(&mut actor, RenameTarget::Field, "count", "total"),
+            Ok(3)
+        );
+        let ext_state = &actor.component.ext_state;
+        assert_eq!(
+            ext_state.methods()[0].body(),
+            "let count = 1; self.total += count; self.count_max = self.total"
+        );
+        assert!(ext_state.fields().iter().any(|f| f.ident() == "name"));
+    }
+
+    #[test]
+    fn test_replace_word() {
+        let mut code = "count + counter + count_max + self.count".to_string();
+        assert_eq!(replace_word(&mut code, "count", "n"), 2);
+        assert_eq!(code, "n + counter + count_max + self.n");
+    }
+}